@@ -1,12 +1,42 @@
 #![deny(warnings)]
 
+extern crate system;
+
 use std::env;
 use std::fs::File;
 use std::io::Read;
-use std::process::Command;
+use std::process::{self, Command};
+
+use system::getopt::{Arg, Getopt, Help, Opt};
+
+const OPTS: &'static [Opt] = &[
+    Opt { short: Some('c'), long: "config", value: Some("PATH"), help: "run the commands in PATH" },
+    Opt { short: Some('h'), long: "help", value: None, help: "print this help" },
+];
 
 fn main() {
-    let mut file = File::open("/etc/init.rc").unwrap();
+    let mut path = "/etc/init.rc".to_string();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    for arg in Getopt::new(OPTS, args.iter().map(|arg| arg.as_str())) {
+        match arg {
+            Ok(Arg::Opt("config", Some(config))) => path = config.to_string(),
+            Ok(Arg::Opt("help", _)) => {
+                print!("usage: init [OPTIONS]\n{}", Help(OPTS));
+                process::exit(0);
+            },
+            Ok(arg) => {
+                println!("init: unexpected argument {:?}", arg);
+                process::exit(1);
+            },
+            Err(err) => {
+                println!("init: {}", err);
+                process::exit(1);
+            }
+        }
+    }
+
+    let mut file = File::open(&path).unwrap();
 
     let mut string = String::new();
     file.read_to_string(&mut string).unwrap();
@@ -0,0 +1,53 @@
+#![deny(warnings)]
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::process;
+
+/// Write a swap header to a disk, such as `mkswap disk:/1`
+///
+/// The kernel uses the first disk with a swap header for swap when it boots
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            println!("mkswap: usage: mkswap disk:/N");
+            process::exit(1);
+        }
+    };
+
+    let mut file = match OpenOptions::new().read(true).write(true).open(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            println!("mkswap: failed to open {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+
+    let size = match file.seek(SeekFrom::End(0)) {
+        Ok(size) => size,
+        Err(err) => {
+            println!("mkswap: failed to get size of {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+
+    //The header takes the first page
+    let pages = (size / 4096).saturating_sub(1);
+
+    let mut header = [0; 512];
+    for (h, m) in header.iter_mut().zip(b"REDOXSWP".iter()) {
+        *h = *m;
+    }
+    for i in 0..8 {
+        header[8 + i] = (pages >> (i * 8)) as u8;
+    }
+
+    if let Err(err) = file.seek(SeekFrom::Start(0)).and_then(|_| file.write(&header)) {
+        println!("mkswap: failed to write {}: {}", path, err);
+        process::exit(1);
+    }
+
+    println!("mkswap: {} KB of swap on {}", pages * 4, path);
+}
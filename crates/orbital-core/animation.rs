@@ -0,0 +1,152 @@
+//! Animations of windows, interpolated for each frame the compositor draws
+//!
+//! An animation covers an area of the screen that is redrawn every frame until it ends, with the window drawn
+//! partway between where it started and where it ends.
+
+use std::cmp;
+use std::time::{Duration, Instant};
+
+use super::{Image, Rect};
+
+/// The milliseconds between frames while an animation runs
+pub const FRAME_MS: u32 = 16;
+
+/// What an animation shows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationKind {
+    /// A new window fading in
+    Open,
+    /// A window shrinking to the bottom of its monitor as it is minimized
+    Minimize,
+}
+
+impl AnimationKind {
+    fn duration(&self) -> Duration {
+        match *self {
+            AnimationKind::Open => Duration::from_millis(150),
+            AnimationKind::Minimize => Duration::from_millis(200),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Animation {
+    pub window: usize,
+    pub kind: AnimationKind,
+    start: Instant,
+    /// The area of the window, with its title bar, at the start and at the end
+    from: Rect,
+    to: Rect,
+}
+
+impl Animation {
+    pub fn new(window: usize, kind: AnimationKind, from: Rect, to: Rect) -> Animation {
+        Animation {
+            window: window,
+            kind: kind,
+            start: Instant::now(),
+            from: from,
+            to: to,
+        }
+    }
+
+    /// How far along the animation is, from 0.0 to 1.0, slowing down towards the end
+    pub fn progress(&self, now: Instant) -> f32 {
+        let elapsed = now.duration_since(self.start);
+        let duration = self.kind.duration();
+        let elapsed_ms = elapsed.as_secs() as f32 * 1000.0 + elapsed.subsec_nanos() as f32 / 1000000.0;
+        let duration_ms = duration.as_secs() as f32 * 1000.0 + duration.subsec_nanos() as f32 / 1000000.0;
+        let t = if elapsed_ms >= duration_ms { 1.0 } else { elapsed_ms / duration_ms };
+        1.0 - (1.0 - t) * (1.0 - t)
+    }
+
+    pub fn done(&self, now: Instant) -> bool {
+        now.duration_since(self.start) >= self.kind.duration()
+    }
+
+    /// The area of the window at a point of progress
+    pub fn rect(&self, progress: f32) -> Rect {
+        Rect::new(lerp(self.from.left(), self.to.left(), progress),
+                  lerp(self.from.top(), self.to.top(), progress),
+                  lerp(self.from.width(), self.to.width(), progress),
+                  lerp(self.from.height(), self.to.height(), progress))
+    }
+
+    /// The area covered over the whole animation, which is redrawn every frame
+    pub fn bounds(&self) -> Rect {
+        self.from.container(&self.to)
+    }
+
+    /// The opacity of the window at a point of progress
+    pub fn alpha(&self, progress: f32) -> u8 {
+        let opacity = match self.kind {
+            AnimationKind::Open => progress,
+            AnimationKind::Minimize => 1.0 - progress,
+        };
+        (opacity * 255.0) as u8
+    }
+}
+
+/// Interpolate between two values
+fn lerp(from: i32, to: i32, progress: f32) -> i32 {
+    from + ((to - from) as f32 * progress) as i32
+}
+
+/// Copy the pixels of an area of an image, row by row
+pub fn copy_area(image: &Image, rect: &Rect) -> Vec<u32> {
+    let width = image.width();
+    let data = image.data();
+    let mut area = Vec::with_capacity(rect.area() as usize);
+    for y in rect.top()..rect.bottom() {
+        let row = (y * width) as usize;
+        area.extend_from_slice(&data[row + rect.left() as usize .. row + rect.right() as usize]);
+    }
+    area
+}
+
+/// Mix the pixels drawn over an area with the pixels under them, copied with `copy_area`, by the opacity of what
+/// was drawn
+pub fn fade(image: &mut Image, rect: &Rect, under: &[u32], alpha: u8) {
+    let alpha = alpha as u32;
+    let width = image.width();
+    let data = image.data_mut();
+    let mut i = 0;
+    for y in rect.top()..rect.bottom() {
+        let row = (y * width) as usize;
+        for pixel in data[row + rect.left() as usize .. row + rect.right() as usize].iter_mut() {
+            *pixel = mix(under[i], *pixel, alpha);
+            i += 1;
+        }
+    }
+}
+
+/// Draw an image scaled to an area, with an opacity, onto the part of the area being redrawn
+pub fn draw_scaled(image: &mut Image, clip: &Rect, dest: &Rect, src: &Image, alpha: u8) {
+    let intersect = clip.intersection(dest);
+    if intersect.is_empty() || src.width() <= 0 || src.height() <= 0 {
+        return;
+    }
+
+    let alpha = alpha as u32;
+    let width = image.width();
+    let src_width = src.width();
+    let src_data = src.data();
+    let data = image.data_mut();
+    for y in intersect.top()..intersect.bottom() {
+        let src_y = cmp::min(src.height() - 1, (y - dest.top()) * src.height() / dest.height());
+        for x in intersect.left()..intersect.right() {
+            let src_x = cmp::min(src_width - 1, (x - dest.left()) * src_width / dest.width());
+            let i = (y * width + x) as usize;
+            data[i] = mix(data[i], src_data[(src_y * src_width + src_x) as usize], alpha);
+        }
+    }
+}
+
+/// Mix two pixels, by an opacity of the second from 0 to 255
+fn mix(under: u32, over: u32, alpha: u32) -> u32 {
+    let inverse = 255 - alpha;
+    let channel = |shift: u32| -> u32 {
+        ((((under >> shift) & 0xFF) * inverse + ((over >> shift) & 0xFF) * alpha) / 255) << shift
+    };
+    0xFF000000 | channel(16) | channel(8) | channel(0)
+}
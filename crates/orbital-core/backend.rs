@@ -0,0 +1,99 @@
+use std::io::Result;
+
+use super::{Color, Event, Image, Rect};
+
+/// Where the pixels of a monitor are sent
+pub trait Display: Send {
+    /// Write pixels starting at an offset, in pixels from the top left of the display, continuing on the rows below
+    fn write(&mut self, offset: usize, pixels: &[u32]);
+
+    /// The blitter of the display, if it has one, used for compositing
+    fn accel(&mut self) -> Option<Box<Accel>> {
+        None
+    }
+
+    /// The brightness of the backlight in percent, if the display has one
+    fn brightness(&mut self) -> Option<u8> {
+        None
+    }
+
+    /// Set the brightness of the backlight in percent
+    fn set_brightness(&mut self, _percent: u8) {}
+}
+
+/// Fills and copies done by the blitter of a display, such as the VBE or Bochs blitter or a GPU
+///
+/// Each returns false when the blitter cannot do it, such as for memory it cannot reach, and the software loops of
+/// `ImageRoi` are used instead.
+pub trait Accel: Send {
+    /// Fill a rectangle of an image with an opaque color
+    fn fill(&mut self, image: &mut Image, rect: &Rect, color: Color) -> bool;
+
+    /// Copy the pixels of `src` at `x`, `y` to a rectangle of `dst`, blending them by their alpha if `blend` is set
+    fn copy(&mut self, dst: &mut Image, rect: &Rect, src: &Image, x: i32, y: i32, blend: bool) -> bool;
+}
+
+/// Compositing operations, done by a blitter when there is one, and in software otherwise
+pub struct Blitter {
+    accel: Option<Box<Accel>>,
+}
+
+impl Blitter {
+    pub fn new(accel: Option<Box<Accel>>) -> Blitter {
+        Blitter {
+            accel: accel
+        }
+    }
+
+    /// Check if there is a blitter, instead of only the software loops
+    pub fn accelerated(&self) -> bool {
+        self.accel.is_some()
+    }
+
+    /// Fill a rectangle of an image with a color, blending it if it is translucent
+    pub fn fill(&mut self, image: &mut Image, rect: &Rect, color: Color) {
+        let opaque = color.data >> 24 == 0xFF;
+        if ! (opaque && self.accel.as_mut().map_or(false, |accel| accel.fill(image, rect, color))) {
+            image.roi(rect).set(color);
+        }
+    }
+
+    /// Copy the pixels of `src` at `x`, `y` to a rectangle of `dst`
+    pub fn blit(&mut self, dst: &mut Image, rect: &Rect, src: &mut Image, x: i32, y: i32) {
+        if ! self.accel.as_mut().map_or(false, |accel| accel.copy(dst, rect, src, x, y, false)) {
+            dst.roi(rect).blit(&src.roi(&Rect::new(x, y, rect.width(), rect.height())));
+        }
+    }
+
+    /// Blend the pixels of `src` at `x`, `y` onto a rectangle of `dst`, by their alpha
+    pub fn blend(&mut self, dst: &mut Image, rect: &Rect, src: &mut Image, x: i32, y: i32) {
+        if ! self.accel.as_mut().map_or(false, |accel| accel.copy(dst, rect, src, x, y, true)) {
+            dst.roi(rect).blend(&src.roi(&Rect::new(x, y, rect.width(), rect.height())));
+        }
+    }
+}
+
+/// Where the input events for the compositor come from
+pub trait Input {
+    /// Wait for input, returning the number of events read
+    fn read(&mut self, events: &mut [Event]) -> Result<usize>;
+}
+
+/// A display drawn to memory, for benchmarks and tests
+pub struct MemoryDisplay {
+    pub data: Vec<u32>,
+}
+
+impl MemoryDisplay {
+    pub fn new(width: i32, height: i32) -> MemoryDisplay {
+        MemoryDisplay {
+            data: vec![0; width as usize * height as usize]
+        }
+    }
+}
+
+impl Display for MemoryDisplay {
+    fn write(&mut self, offset: usize, pixels: &[u32]) {
+        self.data[offset .. offset + pixels.len()].copy_from_slice(pixels);
+    }
+}
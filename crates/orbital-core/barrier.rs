@@ -0,0 +1,40 @@
+//! Pointer barriers and edge resistance at the edges of monitors
+//!
+//! The cursor stops at the seam between two monitors until it is pushed past it far enough, and a window being
+//! dragged stops with its edge at the edge of its monitor in the same way, so crossing onto another monitor is never
+//! an accident.
+
+use std::cmp;
+
+use super::Rect;
+
+/// Hold a point leaving a monitor at its edge, until it is `resistance` pixels past the edge
+pub fn barrier(monitor: &Rect, resistance: i32, x: i32, y: i32) -> (i32, i32) {
+    let over_x = cmp::max(monitor.left() - x, x - (monitor.right() - 1));
+    let over_y = cmp::max(monitor.top() - y, y - (monitor.bottom() - 1));
+    let over = cmp::max(over_x, over_y);
+    if over > 0 && over < resistance {
+        (cmp::max(monitor.left(), cmp::min(monitor.right() - 1, x)),
+         cmp::max(monitor.top(), cmp::min(monitor.bottom() - 1, y)))
+    } else {
+        (x, y)
+    }
+}
+
+/// Hold a window, given with its title bar, moving off a monitor with its edge at the edge of the monitor, until it
+/// is `resistance` pixels past it, returning the distance to move it
+pub fn resist(monitor: &Rect, resistance: i32, frame: &Rect, dx: i32, dy: i32) -> (i32, i32) {
+    (resist_axis(monitor.left(), monitor.right(), frame.left(), frame.right(), resistance, dx),
+     resist_axis(monitor.top(), monitor.bottom(), frame.top(), frame.bottom(), resistance, dy))
+}
+
+/// Resist a move along one axis, from `frame_start..frame_end` within `start..end`
+fn resist_axis(start: i32, end: i32, frame_start: i32, frame_end: i32, resistance: i32, delta: i32) -> i32 {
+    if frame_start >= start && frame_start + delta < start && start - (frame_start + delta) < resistance {
+        start - frame_start
+    } else if frame_end <= end && frame_end + delta > end && frame_end + delta - end < resistance {
+        end - frame_end
+    } else {
+        delta
+    }
+}
@@ -0,0 +1,116 @@
+use std::time::{Duration, Instant};
+
+use system::scheme::Scheme;
+
+use super::{Color, Monitor, OrbitalScheme, Rect};
+use super::event::MouseEvent;
+use super::config::Config;
+
+const BENCH_WIDTH: i32 = 1024;
+const BENCH_HEIGHT: i32 = 768;
+const BENCH_WINDOWS: usize = 8;
+const BENCH_WINDOW_WIDTH: i32 = 320;
+const BENCH_WINDOW_HEIGHT: i32 = 240;
+/// Frames between clicks on a window to bring it to the front
+const BENCH_CLICK_FRAMES: usize = 30;
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1000000.0
+}
+
+/// The time spent in one stage of drawing a frame
+struct Stage {
+    name: &'static str,
+    time: Duration,
+}
+
+impl Stage {
+    fn new(name: &'static str) -> Stage {
+        Stage {
+            name: name,
+            time: Duration::new(0, 0)
+        }
+    }
+
+    fn add(&mut self, start: Instant) {
+        self.time = self.time + start.elapsed();
+    }
+}
+
+/// Composite a number of frames to an in-memory display, with windows that redraw every frame and a
+/// moving cursor, then print the time spent in each stage
+pub fn bench(config: &Config, frames: usize) {
+    let mut scheme = OrbitalScheme::new(vec![Monitor::memory(Rect::new(0, 0, BENCH_WIDTH, BENCH_HEIGHT))], config);
+
+    let mut ids = Vec::new();
+    for i in 0..BENCH_WINDOWS {
+        match scheme.open(&format!("orbital:/-1/-1/{}/{}/Bench {}", BENCH_WINDOW_WIDTH, BENCH_WINDOW_HEIGHT, i), 0, 0) {
+            Ok(id) => ids.push(id),
+            Err(err) => {
+                println!("orbital: bench: failed to open window: {}", err);
+                return;
+            }
+        }
+    }
+
+    let mut pixels = vec![0; BENCH_WINDOW_WIDTH as usize * BENCH_WINDOW_HEIGHT as usize];
+    let mut buf = [0; 4096];
+
+    let mut events = Stage::new("events");
+    let mut writes = Stage::new("writes");
+    let mut composite = Stage::new("composite");
+    let mut present = Stage::new("present");
+
+    let total = Instant::now();
+    for frame in 0..frames {
+        //Move the cursor around the screen, clicking on a window now and then
+        let start = Instant::now();
+        let x = (frame as i32 * 7) % BENCH_WIDTH;
+        let y = (frame as i32 * 5) % BENCH_HEIGHT;
+        let click = frame % BENCH_CLICK_FRAMES == 0;
+        let (click_x, click_y) = match ids.get((frame / BENCH_CLICK_FRAMES) % ids.len()).and_then(|id| scheme.windows.get(id)) {
+            Some(window) if click => (window.x + 1, window.y + 1),
+            _ => (x, y)
+        };
+        scheme.event(MouseEvent { x: click_x, y: click_y, left_button: click, middle_button: false, right_button: false }.to_event());
+        scheme.event(MouseEvent { x: click_x, y: click_y, left_button: false, middle_button: false, right_button: false }.to_event());
+        scheme.update_focus();
+        events.add(start);
+
+        //Every window draws a new frame
+        let color = Color::rgb(frame as u8, (frame * 3) as u8, (frame * 7) as u8);
+        for pixel in pixels.iter_mut() {
+            *pixel = color.data;
+        }
+        let data = unsafe { ::std::slice::from_raw_parts(pixels.as_ptr() as *const u8, pixels.len() * 4) };
+        let start = Instant::now();
+        for id in ids.iter() {
+            let _ = scheme.write(*id, data);
+        }
+        writes.add(start);
+
+        //Drop the events sent to the windows, so they do not appear busy
+        for id in ids.iter() {
+            while scheme.read(*id, &mut buf).unwrap_or(0) > 0 {}
+        }
+
+        let mut redraws = Vec::new();
+        ::std::mem::swap(&mut scheme.redraws, &mut redraws);
+
+        let start = Instant::now();
+        scheme.composite(&mut redraws);
+        composite.add(start);
+
+        let start = Instant::now();
+        scheme.present(&redraws);
+        present.add(start);
+    }
+    let total = millis(total.elapsed());
+
+    println!("orbital: bench: {} frames at {}x{} with {} windows", frames, BENCH_WIDTH, BENCH_HEIGHT, BENCH_WINDOWS);
+    for stage in [events, writes, composite, present].iter() {
+        let time = millis(stage.time);
+        println!("    {:<10} {:>10.3} ms {:>8.3} ms/frame", stage.name, time, time / frames as f64);
+    }
+    println!("    {:<10} {:>10.3} ms {:>8.3} ms/frame {:.1} fps", "total", total, total / frames as f64, frames as f64 * 1000.0 / total);
+}
@@ -1,7 +1,7 @@
 use super::{Color, Image};
 
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, Read, Write};
 
 pub struct BmpFile;
 
@@ -15,6 +15,91 @@ impl BmpFile {
         BmpFile::from_data(&data)
     }
 
+    /// Save an image as a bitmap to the given path
+    pub fn save(image: &Image, path: &str) -> io::Result<()> {
+        let mut file = try!(File::create(path));
+        file.write_all(&BmpFile::to_data(image))
+    }
+
+    /// Encode an image as a bitmap
+    ///
+    /// Opaque images are written with 24 bits per pixel. Images with any transparency are written with 32 bits
+    /// and a version 4 header, which carries the alpha mask.
+    pub fn to_data(image: &Image) -> Vec<u8> {
+        let width = image.width() as u32;
+        let height = image.height() as u32;
+        let alpha = image.data().iter().any(|pixel| pixel >> 24 != 0xFF);
+
+        let depth = if alpha { 32 } else { 24 };
+        let header_size = if alpha { 108 } else { 40 };
+        let offset = 14 + header_size;
+        let row_bytes = (depth * width + 31) / 32 * 4;
+        let size = offset + row_bytes * height;
+
+        let mut data = Vec::with_capacity(size as usize);
+
+        let putw = |data: &mut Vec<u8>, value: u16| {
+            data.push(value as u8);
+            data.push((value >> 8) as u8);
+        };
+
+        let putd = |data: &mut Vec<u8>, value: u32| {
+            data.push(value as u8);
+            data.push((value >> 8) as u8);
+            data.push((value >> 16) as u8);
+            data.push((value >> 24) as u8);
+        };
+
+        data.extend_from_slice(b"BM");
+        putd(&mut data, size);
+        putd(&mut data, 0);
+        putd(&mut data, offset);
+
+        putd(&mut data, header_size);
+        putd(&mut data, width);
+        putd(&mut data, height);
+        putw(&mut data, 1);
+        putw(&mut data, depth as u16);
+        putd(&mut data, if alpha { 3 } else { 0 });
+        putd(&mut data, row_bytes * height);
+        // 72 DPI, in pixels per meter
+        putd(&mut data, 2835);
+        putd(&mut data, 2835);
+        putd(&mut data, 0);
+        putd(&mut data, 0);
+
+        if alpha {
+            putd(&mut data, 0xFF0000);
+            putd(&mut data, 0xFF00);
+            putd(&mut data, 0xFF);
+            putd(&mut data, 0xFF000000);
+            // sRGB color space, which leaves the endpoints and gamma unused
+            putd(&mut data, 0x73524742);
+            for _ in 0..12 {
+                putd(&mut data, 0);
+            }
+        }
+
+        let pixels = image.data();
+        for y in (0..height).rev() {
+            let row = &pixels[(y * width) as usize..((y + 1) * width) as usize];
+            let start = data.len();
+            for pixel in row.iter() {
+                data.push(*pixel as u8);
+                data.push((*pixel >> 8) as u8);
+                data.push((*pixel >> 16) as u8);
+                if alpha {
+                    data.push((*pixel >> 24) as u8);
+                }
+            }
+            while data.len() - start < row_bytes as usize {
+                data.push(0);
+            }
+        }
+
+        data
+    }
+
     /// Create a bitmap from some data
     pub fn from_data(file_data: &[u8]) -> Image {
         let get = |i: usize| -> u8 {
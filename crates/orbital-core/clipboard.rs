@@ -0,0 +1,251 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+use super::{Image, ResizeFilter};
+
+/// The entries kept in the history when none is configured
+pub const DEFAULT_HISTORY: usize = 20;
+/// The height of the thumbnails of image entries, in logical pixels
+pub const THUMBNAIL_HEIGHT: i32 = 32;
+/// The largest entry, larger writes are cut off
+const MAX_ENTRY: usize = 64 * 1024 * 1024;
+
+/// What an entry of the clipboard holds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClipboardKind {
+    /// UTF-8 text
+    Text,
+    /// Pixels as 32-bit BGRA, row by row, with the width and height of the image
+    Image(i32, i32),
+}
+
+impl ClipboardKind {
+    /// Parse the kind in the path of a clipboard handle, `text` or `image/width/height`
+    pub fn parse(path: &str) -> Option<ClipboardKind> {
+        let mut parts = path.split('/');
+        match parts.next().unwrap_or("") {
+            "" | "text" => Some(ClipboardKind::Text),
+            "image" => match (parts.next().and_then(|part| part.parse::<i32>().ok()),
+                              parts.next().and_then(|part| part.parse::<i32>().ok())) {
+                (Some(width), Some(height)) if width > 0 && height > 0 => Some(ClipboardKind::Image(width, height)),
+                _ => None
+            },
+            _ => None
+        }
+    }
+
+    /// The path of a clipboard handle with this kind
+    pub fn path(&self) -> String {
+        match *self {
+            ClipboardKind::Text => "orbital:clipboard/text".to_string(),
+            ClipboardKind::Image(width, height) => format!("orbital:clipboard/image/{}/{}", width, height)
+        }
+    }
+}
+
+/// Something that was copied
+pub struct ClipboardEntry {
+    pub kind: ClipboardKind,
+    pub data: Vec<u8>,
+    /// The image of an image entry, made smaller to show in the history
+    pub thumbnail: Option<Image>,
+}
+
+impl ClipboardEntry {
+    pub fn new(kind: ClipboardKind, data: Vec<u8>) -> ClipboardEntry {
+        let thumbnail = match kind {
+            ClipboardKind::Image(width, height) if data.len() >= width as usize * height as usize * size_of::<u32>() => {
+                let pixels: Vec<u32> = data.chunks(4).take(width as usize * height as usize).map(|bgra| {
+                    (bgra[3] as u32) << 24 | (bgra[2] as u32) << 16 | (bgra[1] as u32) << 8 | bgra[0] as u32
+                }).collect();
+                let image = Image::from_data(width, height, pixels.into_boxed_slice());
+                //Keep the aspect ratio, without enlarging small images
+                let thumbnail_height = cmp::min(THUMBNAIL_HEIGHT, height);
+                let thumbnail_width = cmp::max(1, cmp::min(THUMBNAIL_HEIGHT * 4, width * thumbnail_height / height));
+                Some(image.resize(thumbnail_width, thumbnail_height, ResizeFilter::Bilinear))
+            },
+            _ => None
+        };
+
+        ClipboardEntry {
+            kind: kind,
+            data: data,
+            thumbnail: thumbnail
+        }
+    }
+
+    /// The first line of a text entry, to show in the history
+    pub fn summary(&self) -> String {
+        match self.kind {
+            ClipboardKind::Text => String::from_utf8_lossy(&self.data).lines().map(|line| line.trim())
+                                       .find(|line| ! line.is_empty()).unwrap_or("").to_string(),
+            ClipboardKind::Image(width, height) => format!("{}x{} image", width, height)
+        }
+    }
+}
+
+/// The clipboard with its history, the newest entry first, and the primary selection
+///
+/// The primary selection is the text last selected in any window, which a client reports by writing `S,text` to its
+/// window and pastes on a middle click by reading `orbital:clipboard/selection`. It is kept apart from the history,
+/// so selecting text does not replace what was copied.
+pub struct Clipboard {
+    history: VecDeque<ClipboardEntry>,
+    limit: usize,
+    selection: Option<ClipboardEntry>,
+}
+
+impl Clipboard {
+    pub fn new(limit: usize) -> Clipboard {
+        Clipboard {
+            history: VecDeque::new(),
+            limit: limit,
+            selection: None
+        }
+    }
+
+    /// Set the primary selection, empty text clears it
+    pub fn set_selection(&mut self, text: Vec<u8>) {
+        self.selection = if text.is_empty() {
+            None
+        } else {
+            Some(ClipboardEntry::new(ClipboardKind::Text, text))
+        };
+    }
+
+    pub fn selection(&self) -> Option<&ClipboardEntry> {
+        self.selection.as_ref()
+    }
+
+    /// Make an entry the current one, removing an equal older entry and the oldest entries past the limit
+    pub fn push(&mut self, entry: ClipboardEntry) {
+        self.history.retain(|old| old.kind != entry.kind || old.data != entry.data);
+        self.history.push_front(entry);
+        while self.history.len() > self.limit {
+            self.history.pop_back();
+        }
+    }
+
+    /// Make an older entry the current one
+    pub fn select(&mut self, index: usize) {
+        if let Some(entry) = self.history.remove(index) {
+            self.history.push_front(entry);
+        }
+    }
+
+    pub fn current(&self) -> Option<&ClipboardEntry> {
+        self.history.front()
+    }
+
+    pub fn entries(&self) -> &VecDeque<ClipboardEntry> {
+        &self.history
+    }
+}
+
+/// A clipboard handle, opened with `orbital:clipboard`
+///
+/// Reading returns the current entry as it was when the handle was opened, its kind is in the path of the handle,
+/// as `orbital:clipboard/text` or `orbital:clipboard/image/width/height`. Writing copies what was written when the
+/// handle is closed, as text, or as the kind the handle was opened with, such as `orbital:clipboard/image/64/64`.
+/// The primary selection, which is always text, is read and written the same way with `orbital:clipboard/selection`.
+pub struct ClipboardHandle {
+    pub kind: ClipboardKind,
+    /// The handle reads and writes the primary selection, opened with `orbital:clipboard/selection`
+    pub selection: bool,
+    data: Vec<u8>,
+    seek: usize,
+    written: bool,
+    /// The handle was opened with a kind, otherwise writing copies text
+    typed: bool,
+}
+
+impl ClipboardHandle {
+    /// A handle reading the current entry, if there is one, and writing an entry of a kind
+    pub fn new(clipboard: &Clipboard, kind: Option<ClipboardKind>) -> ClipboardHandle {
+        match (clipboard.current(), kind) {
+            (_, Some(kind)) => ClipboardHandle {
+                kind: kind,
+                selection: false,
+                data: Vec::new(),
+                seek: 0,
+                written: false,
+                typed: true
+            },
+            (Some(entry), None) => ClipboardHandle {
+                kind: entry.kind,
+                selection: false,
+                data: entry.data.clone(),
+                seek: 0,
+                written: false,
+                typed: false
+            },
+            (None, None) => ClipboardHandle {
+                kind: ClipboardKind::Text,
+                selection: false,
+                data: Vec::new(),
+                seek: 0,
+                written: false,
+                typed: false
+            }
+        }
+    }
+
+    /// A handle reading the primary selection, and writing a new one
+    pub fn selection(clipboard: &Clipboard) -> ClipboardHandle {
+        ClipboardHandle {
+            kind: ClipboardKind::Text,
+            selection: true,
+            data: clipboard.selection().map_or(Vec::new(), |entry| entry.data.clone()),
+            seek: 0,
+            written: false,
+            typed: true
+        }
+    }
+
+    /// The path of the handle, with the kind of its entry
+    pub fn path(&self) -> String {
+        if self.selection {
+            "orbital:clipboard/selection".to_string()
+        } else {
+            self.kind.path()
+        }
+    }
+
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let mut i = 0;
+        while i < buf.len() && self.seek < self.data.len() {
+            buf[i] = self.data[self.seek];
+            self.seek += 1;
+            i += 1;
+        }
+        i
+    }
+
+    /// Add to the entry, replacing what the handle read
+    pub fn write(&mut self, buf: &[u8]) -> usize {
+        if ! self.written {
+            if ! self.typed {
+                self.kind = ClipboardKind::Text;
+            }
+            self.data.clear();
+            self.written = true;
+        }
+        let count = cmp::min(buf.len(), MAX_ENTRY - self.data.len());
+        self.data.extend_from_slice(&buf[.. count]);
+        count
+    }
+
+    /// The entry written to the handle, an image must be written whole
+    pub fn into_entry(self) -> Option<ClipboardEntry> {
+        if ! self.written {
+            return None;
+        }
+        if let ClipboardKind::Image(width, height) = self.kind {
+            if self.data.len() != width as usize * height as usize * size_of::<u32>() {
+                return None;
+            }
+        }
+        Some(ClipboardEntry::new(self.kind, self.data))
+    }
+}
@@ -0,0 +1,136 @@
+use std::cmp;
+use std::fs::File;
+use std::io::Read;
+
+use system::syscall::{CLOCK_MONOTONIC, TimeSpec};
+use system::timepage::clock_gettime;
+
+const WEEKDAYS: [&'static str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&'static str; 12] = ["January", "February", "March", "April", "May", "June",
+                                    "July", "August", "September", "October", "November", "December"];
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: u8) -> u8 {
+    match month {
+        2 => if is_leap_year(year) { 29 } else { 28 },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+/// The monotonic time in nanoseconds, the clock of the times of events
+pub fn monotonic() -> i64 {
+    let mut time = TimeSpec {
+        tv_sec: 0,
+        tv_nsec: 0
+    };
+    match clock_gettime(CLOCK_MONOTONIC, &mut time) {
+        Ok(_) => time.tv_sec * 1000000000 + time.tv_nsec as i64,
+        Err(_) => 0
+    }
+}
+
+/// The day of the week of a date, where 0 is Sunday
+fn weekday(year: i64, month: u8, day: u8) -> u8 {
+    const OFFSETS: [i64; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let year = if month < 3 { year - 1 } else { year };
+    let weekday = (year + year/4 - year/100 + year/400 + OFFSETS[(month as usize + 11) % 12] + day as i64) % 7;
+    if weekday < 0 { (weekday + 7) as u8 } else { weekday as u8 }
+}
+
+/// The local date and time of day, as read from `clock:`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Date {
+    pub year: i64,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Date {
+    /// Read the local time, or `None` if the clock scheme is not available
+    pub fn now() -> Option<Date> {
+        let mut string = String::new();
+        match File::open("clock:") {
+            Ok(mut file) => match file.read_to_string(&mut string) {
+                Ok(_) => Date::parse(&string),
+                Err(_) => None
+            },
+            Err(_) => None
+        }
+    }
+
+    /// Parse the date and time of an ISO-8601 string, such as `2016-07-12T13:45:01+02:00`
+    pub fn parse(string: &str) -> Option<Date> {
+        let string = string.trim();
+        if string.len() < 19 || ! string.is_char_boundary(19) {
+            return None;
+        }
+
+        let field = |start: usize, end: usize| string[start .. end].parse::<i64>().ok();
+        match (field(0, 4), field(5, 7), field(8, 10), field(11, 13), field(14, 16), field(17, 19)) {
+            (Some(year), Some(month), Some(day), Some(hour), Some(minute), Some(second)) => {
+                if month >= 1 && month <= 12 && day >= 1 && day <= days_in_month(year, month as u8) as i64
+                    && hour < 24 && minute < 60 && second < 61 {
+                    Some(Date {
+                        year: year,
+                        month: month as u8,
+                        day: day as u8,
+                        hour: hour as u8,
+                        minute: minute as u8,
+                        second: second as u8
+                    })
+                } else {
+                    None
+                }
+            },
+            _ => None
+        }
+    }
+
+    /// The text of the panel clock, such as `Tue 12 13:45`
+    pub fn text(&self) -> String {
+        format!("{} {} {:02}:{:02}", WEEKDAYS[weekday(self.year, self.month, self.day) as usize], self.day, self.hour, self.minute)
+    }
+
+    /// Milliseconds until the minute changes
+    pub fn until_next_minute(&self) -> u32 {
+        (60 - cmp::min(self.second as u32, 59)) * 1000
+    }
+
+    /// The lines of a calendar of the month, with the weeks starting on Sunday
+    ///
+    /// The current day is marked with brackets
+    pub fn calendar(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("{} {}", MONTHS[(self.month as usize + 11) % 12], self.year),
+            " Su  Mo  Tu  We  Th  Fr  Sa".to_string()
+        ];
+
+        let mut line = String::new();
+        for _ in 0 .. weekday(self.year, self.month, 1) {
+            line.push_str("    ");
+        }
+        for day in 1 .. days_in_month(self.year, self.month) + 1 {
+            if day == self.day {
+                line.push_str(&format!("[{:2}]", day));
+            } else {
+                line.push_str(&format!(" {:2} ", day));
+            }
+            if weekday(self.year, self.month, day) == 6 {
+                lines.push(line);
+                line = String::new();
+            }
+        }
+        if ! line.is_empty() {
+            lines.push(line);
+        }
+
+        lines
+    }
+}
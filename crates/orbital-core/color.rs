@@ -15,4 +15,17 @@ impl Color {
     pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
         Color { data: ((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32) }
     }
+
+    /// Parse a color written as `#RRGGBB`, or `#AARRGGBB` with alpha
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if ! value.starts_with('#') {
+            return None;
+        }
+        match (value.len(), u32::from_str_radix(&value[1..], 16)) {
+            (7, Ok(data)) => Some(Color { data: 0xFF000000 | data }),
+            (9, Ok(data)) => Some(Color { data: data }),
+            _ => None
+        }
+    }
 }
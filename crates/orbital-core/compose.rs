@@ -0,0 +1,138 @@
+//! Dead keys and the compose key, which combine the characters of two keys into one
+//!
+//! A dead key types nothing, and the next key types its character with the accent of the dead key, such as `´` then
+//! `e` for `é`. The dead key followed by space types its accent, and followed by a key it does not combine with
+//! types that key alone. The compose key does the same for the next two characters, such as compose, `"`, `a` for
+//! `ä`, or compose, `s`, `s` for `ß`.
+
+use super::event::{K_EXTENDED, K_ESC, K_MENU, K_RIGHT_SUPER};
+
+/// The scroll lock key
+const K_SCROLL: u8 = 0x46;
+
+/// Accents, with the letters they combine with and the combined letters
+const ACCENTS: [(&'static str, &'static str, &'static str); 8] = [
+    ("´'", "aceinorsuyzACEINORSUYZ", "áćéíńóŕśúýźÁĆÉÍŃÓŔŚÚÝŹ"),
+    ("`", "aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+    ("^", "aeioucghjswyAEIOUCGHJSWY", "âêîôûĉĝĥĵŝŵŷÂÊÎÔÛĈĜĤĴŜŴŶ"),
+    ("¨\"", "aeiouyAEIOUY", "äëïöüÿÄËÏÖÜŸ"),
+    ("~", "anoANO", "ãñõÃÑÕ"),
+    ("°o", "auAU", "åůÅŮ"),
+    ("¸,", "cgklnrstCGKLNRST", "çģķļņŗşţÇĢĶĻŅŖŞŢ"),
+    ("ˇv", "cdenrszCDENRSZ", "čďěňřšžČĎĚŇŘŠŽ"),
+];
+
+/// Pairs of characters that are not an accent and a letter, each in the order typed after the compose key
+const PAIRS: [(&'static str, char); 24] = [
+    ("ss", 'ß'), ("ae", 'æ'), ("AE", 'Æ'), ("oe", 'œ'), ("OE", 'Œ'), ("o/", 'ø'), ("O/", 'Ø'),
+    ("=e", '€'), ("C=", '€'), ("L-", '£'), ("Y=", '¥'), ("c/", '¢'), ("<<", '«'), (">>", '»'),
+    ("??", '¿'), ("!!", '¡'), ("oo", '°'), ("+-", '±'), ("co", '©'), ("ro", '®'), ("tm", '™'),
+    ("12", '½'), ("14", '¼'), ("34", '¾'),
+];
+
+/// Combine an accent with a letter, or two characters typed after the compose key
+///
+/// An accent combines with a letter typed before or after it.
+pub fn combine(first: char, second: char) -> Option<char> {
+    for &(accents, letters, combined) in ACCENTS.iter() {
+        let (accent, letter) = if accents.contains(first) {
+            (first, second)
+        } else if accents.contains(second) {
+            (second, first)
+        } else {
+            continue;
+        };
+        if let Some(i) = letters.chars().position(|c| c == letter) {
+            return combined.chars().nth(i);
+        }
+    }
+
+    for &(pair, combined) in PAIRS.iter() {
+        let mut chars = pair.chars();
+        if chars.next() == Some(first) && chars.next() == Some(second) {
+            return Some(combined);
+        }
+    }
+
+    None
+}
+
+/// Parse the key used to compose, `menu`, `right_super`, or `scroll_lock`, or `off` for none
+pub fn parse_compose_key(value: &str) -> Option<Option<u8>> {
+    match value {
+        "menu" => Some(Some(K_MENU | K_EXTENDED)),
+        "right_super" => Some(Some(K_RIGHT_SUPER | K_EXTENDED)),
+        "scroll_lock" => Some(Some(K_SCROLL)),
+        "off" => Some(None),
+        _ => None
+    }
+}
+
+/// What was typed so far of a combination
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    None,
+    /// A dead key with its accent
+    Dead(char),
+    /// The compose key, with the first character once it is typed
+    Compose(Option<char>),
+}
+
+/// Combines the characters of the keys pressed, see the module documentation
+pub struct Composer {
+    /// The scancode of the compose key, with `K_EXTENDED` for extended keys
+    pub compose_key: Option<u8>,
+    state: State,
+}
+
+impl Composer {
+    pub fn new(compose_key: Option<u8>) -> Composer {
+        Composer {
+            compose_key: compose_key,
+            state: State::None
+        }
+    }
+
+    /// Follow a key press, given its scancode with `K_EXTENDED`, its character, and if it is a dead key
+    ///
+    /// Returns the character to type, `'\0'` while a combination is being typed
+    pub fn press(&mut self, scancode: u8, character: char, dead: bool) -> char {
+        if Some(scancode) == self.compose_key {
+            self.state = State::Compose(None);
+            return '\0';
+        }
+        if scancode == K_ESC && self.state != State::None {
+            self.state = State::None;
+            return '\0';
+        }
+        //Keys without a character, such as shift, are pressed in the middle of a combination
+        if character == '\0' {
+            return character;
+        }
+
+        match self.state {
+            State::None => if dead {
+                self.state = State::Dead(character);
+                '\0'
+            } else {
+                character
+            },
+            State::Dead(accent) => {
+                self.state = State::None;
+                if character == ' ' || character == accent {
+                    accent
+                } else {
+                    combine(accent, character).unwrap_or(character)
+                }
+            },
+            State::Compose(None) => {
+                self.state = State::Compose(Some(character));
+                '\0'
+            },
+            State::Compose(Some(first)) => {
+                self.state = State::None;
+                combine(first, character).unwrap_or('\0')
+            }
+        }
+    }
+}
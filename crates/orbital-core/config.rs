@@ -0,0 +1,304 @@
+use std::fs::File;
+use std::io::Read;
+
+use super::clipboard::DEFAULT_HISTORY;
+use super::compose::parse_compose_key;
+use super::corner::{Corner, CornerAction};
+use super::event::{K_EXTENDED, K_MENU};
+use super::focus::FocusMode;
+use super::gamma::{Calibration, NightLight};
+use super::keymap::DEFAULT_KEYMAP;
+use super::monitor::Rotation;
+use super::repeat::KeyRepeat;
+use super::wallpaper::WallpaperMode;
+
+pub struct Config {
+    /// Animate windows opening and being minimized
+    pub animations: bool,
+    pub background: String,
+    pub background_mode: WallpaperMode,
+    /// The entries kept in the history of the clipboard
+    pub clipboard_history: usize,
+    /// The scancode of the compose key, with `K_EXTENDED` for extended keys
+    pub compose_key: Option<u8>,
+    pub cursor: String,
+    pub cursor_theme: String,
+    /// The pixels a dragged window is pushed past the edge of a monitor before it leaves it, 0 for none
+    pub edge_resistance: i32,
+    pub font: String,
+    pub font_fallbacks: Vec<String>,
+    pub focus_mode: FocusMode,
+    pub scale: i32,
+    pub display_scales: Vec<(usize, i32)>,
+    pub display_rotations: Vec<(usize, Rotation)>,
+    pub display_gammas: Vec<(usize, f32)>,
+    pub display_temperatures: Vec<(usize, u32)>,
+    pub night_light: Option<NightLight>,
+    /// The pixels the cursor is pushed past the seam between monitors before it crosses it, 0 for none
+    pub pointer_barrier: i32,
+    /// How held keys repeat, `None` leaves repeating to the keyboard
+    pub key_repeat: Option<KeyRepeat>,
+    /// The name of the keymap in `KEYMAP_DIR`
+    pub keymap: String,
+    pub hot_corners: Vec<(Corner, CornerAction)>,
+    pub clock: Option<Corner>,
+    /// Seconds without input before the displays are blanked
+    pub blank: Option<u32>,
+    pub screensaver: String,
+    /// The path of the theme of window decorations, the default theme if empty
+    pub theme: String,
+}
+
+impl Config {
+    pub fn from_path(path: &str) -> Config {
+        let mut string = String::new();
+
+        match File::open(path) {
+            Ok(mut file) => match file.read_to_string(&mut string) {
+                Ok(_) => (),
+                Err(err) => println!("orbital: failed to read config '{}': {}", path, err)
+            },
+            Err(err) => println!("orbital: failed to open config '{}': {}", path, err)
+        }
+
+        Config::from_str(&string)
+    }
+
+    pub fn from_str(string: &str) -> Config {
+        let mut config = Config {
+            animations: true,
+            background: String::new(),
+            background_mode: WallpaperMode::Center,
+            clipboard_history: DEFAULT_HISTORY,
+            compose_key: Some(K_MENU | K_EXTENDED),
+            cursor: String::new(),
+            cursor_theme: String::new(),
+            edge_resistance: 0,
+            font: String::new(),
+            font_fallbacks: Vec::new(),
+            focus_mode: FocusMode::Click,
+            scale: 1,
+            display_scales: Vec::new(),
+            display_rotations: Vec::new(),
+            display_gammas: Vec::new(),
+            display_temperatures: Vec::new(),
+            night_light: None,
+            pointer_barrier: 0,
+            key_repeat: Some(KeyRepeat::new()),
+            keymap: DEFAULT_KEYMAP.to_string(),
+            hot_corners: Vec::new(),
+            clock: None,
+            blank: None,
+            screensaver: String::new(),
+            theme: String::new(),
+        };
+
+        for line_original in string.lines() {
+            let line = line_original.trim();
+            if line.starts_with("animations=") {
+                //Animate windows opening and being minimized, `on` or `off`
+                match &line[11..] {
+                    "on" => config.animations = true,
+                    "off" => config.animations = false,
+                    value => println!("orbital: invalid animations '{}'", value)
+                }
+            }
+            if line.starts_with("background=") {
+                config.background = line[11..].to_string();
+            }
+            if line.starts_with("background_mode=") {
+                //How the background fits each display, `center`, `scale`, or `tile`
+                match WallpaperMode::parse(&line[16..]) {
+                    Some(mode) => config.background_mode = mode,
+                    None => println!("orbital: invalid background mode '{}'", &line[16..])
+                }
+            }
+            if line.starts_with("blank=") {
+                //Seconds without input before blanking the displays, 0 never blanks them
+                match line[6..].parse::<u32>() {
+                    Ok(0) => config.blank = None,
+                    Ok(seconds) => config.blank = Some(seconds),
+                    Err(_) => println!("orbital: invalid blank timeout '{}'", &line[6..])
+                }
+            }
+            if line.starts_with("clipboard_history=") {
+                //The entries kept in the history of the clipboard, which Super+V shows, such as `clipboard_history=20`
+                match line[18..].parse::<usize>() {
+                    Ok(history) if history > 0 => config.clipboard_history = history,
+                    _ => println!("orbital: invalid clipboard history '{}'", &line[18..])
+                }
+            }
+            if line.starts_with("clock=") {
+                //The corner of the first display to show the clock in, such as `clock=top_right`
+                config.clock = Corner::parse(&line[6..]);
+            }
+            if line.starts_with("compose_key=") {
+                //The key that starts a compose sequence, `menu`, `right_super`, or `scroll_lock`, or `off`
+                match parse_compose_key(&line[12..]) {
+                    Some(compose_key) => config.compose_key = compose_key,
+                    None => println!("orbital: invalid compose key '{}'", &line[12..])
+                }
+            }
+            if line.starts_with("cursor=") {
+                config.cursor = line[7..].to_string();
+            }
+            if line.starts_with("cursor_theme=") {
+                config.cursor_theme = line[13..].to_string();
+            }
+            if line.starts_with("edge_resistance=") {
+                //The pixels a dragged window is pushed past the edge of a display before it leaves it, such as
+                //`edge_resistance=32`
+                match line[16..].parse::<i32>() {
+                    Ok(resistance) if resistance >= 0 => config.edge_resistance = resistance,
+                    _ => println!("orbital: invalid edge resistance '{}'", &line[16..])
+                }
+            }
+            if line.starts_with("font=") {
+                config.font = line[5..].to_string();
+            }
+            if line.starts_with("font_fallback=") {
+                config.font_fallbacks.push(line[14..].to_string());
+            }
+            if line.starts_with("focus_mode=") {
+                //How windows take focus, `click`, `mouse`, or `mouse_raise`
+                match FocusMode::parse(&line[11..]) {
+                    Some(focus_mode) => config.focus_mode = focus_mode,
+                    None => println!("orbital: invalid focus mode '{}'", &line[11..])
+                }
+            }
+            if line.starts_with("gamma=") {
+                //The gamma of a display, such as `gamma=0/1.2`
+                let value = &line[6..];
+                if let Some(slash) = value.find('/') {
+                    match (value[.. slash].parse::<usize>(), Calibration::parse_gamma(&value[slash + 1 ..])) {
+                        (Ok(display), Some(gamma)) => config.display_gammas.push((display, gamma)),
+                        _ => println!("orbital: invalid gamma '{}'", value)
+                    }
+                }
+            }
+            if line.starts_with("hot_corner=") {
+                //A corner and its action, such as `hot_corner=top_left/switcher`
+                let value = &line[11..];
+                if let Some(slash) = value.find('/') {
+                    match (Corner::parse(&value[.. slash]), CornerAction::parse(&value[slash + 1 ..])) {
+                        (Some(corner), Some(action)) => config.hot_corners.push((corner, action)),
+                        _ => println!("orbital: invalid hot corner '{}'", value)
+                    }
+                }
+            }
+            if line.starts_with("key_repeat=") {
+                //The milliseconds before a held key repeats and the repeats per second, such as `key_repeat=500/30`,
+                //or `key_repeat=off`
+                let value = &line[11..];
+                if value == "off" {
+                    config.key_repeat = None;
+                } else {
+                    match KeyRepeat::parse(value) {
+                        Some(key_repeat) => config.key_repeat = Some(key_repeat),
+                        None => println!("orbital: invalid key repeat '{}'", value)
+                    }
+                }
+            }
+            if line.starts_with("keymap=") {
+                //The keyboard layout, such as `keymap=de`
+                config.keymap = line[7..].to_string();
+            }
+            if line.starts_with("night_light=") {
+                //When to warm the colors of all displays and the color temperature, such as `night_light=21:00/07:00/3400`
+                match NightLight::parse(&line[12..]) {
+                    Some(night_light) => config.night_light = Some(night_light),
+                    None => println!("orbital: invalid night light '{}'", &line[12..])
+                }
+            }
+            if line.starts_with("pointer_barrier=") {
+                //The pixels the cursor is pushed past the seam between displays before it crosses it, such as
+                //`pointer_barrier=24`
+                match line[16..].parse::<i32>() {
+                    Ok(resistance) if resistance >= 0 => config.pointer_barrier = resistance,
+                    _ => println!("orbital: invalid pointer barrier '{}'", &line[16..])
+                }
+            }
+            if line.starts_with("rotate=") {
+                //The degrees to turn a display clockwise, such as `rotate=1/90`
+                let value = &line[7..];
+                if let Some(slash) = value.find('/') {
+                    match (value[.. slash].parse::<usize>(), Rotation::parse(&value[slash + 1 ..])) {
+                        (Ok(display), Some(rotation)) => config.display_rotations.push((display, rotation)),
+                        _ => println!("orbital: invalid rotation '{}'", value)
+                    }
+                }
+            }
+            if line.starts_with("screensaver=") {
+                //An image shown in the middle of each display while they are blanked
+                config.screensaver = line[12..].to_string();
+            }
+            if line.starts_with("scale=") {
+                //Either `scale=factor` for all displays, or `scale=display/factor`
+                let value = &line[6..];
+                if let Some(slash) = value.find('/') {
+                    if let (Ok(display), Ok(scale)) = (value[.. slash].parse::<usize>(), value[slash + 1 ..].parse::<i32>()) {
+                        if scale > 0 {
+                            config.display_scales.push((display, scale));
+                        }
+                    }
+                } else if let Ok(scale) = value.parse::<i32>() {
+                    if scale > 0 {
+                        config.scale = scale;
+                    }
+                }
+            }
+            if line.starts_with("theme=") {
+                //The theme of window decorations, such as `theme=/ui/themes/default.theme`
+                config.theme = line[6..].to_string();
+            }
+            if line.starts_with("white_point=") {
+                //The color temperature of white on a display in kelvin, such as `white_point=0/5500`
+                let value = &line[12..];
+                if let Some(slash) = value.find('/') {
+                    match (value[.. slash].parse::<usize>(), Calibration::parse_temperature(&value[slash + 1 ..])) {
+                        (Ok(display), Some(temperature)) => config.display_temperatures.push((display, temperature)),
+                        _ => println!("orbital: invalid white point '{}'", value)
+                    }
+                }
+            }
+        }
+
+        config
+    }
+
+    /// The scale factor of a display, by its index
+    pub fn display_scale(&self, display: usize) -> i32 {
+        for &(i, scale) in self.display_scales.iter() {
+            if i == display {
+                return scale;
+            }
+        }
+        self.scale
+    }
+
+    /// The rotation of a display, by its index
+    pub fn display_rotation(&self, display: usize) -> Rotation {
+        for &(i, rotation) in self.display_rotations.iter() {
+            if i == display {
+                return rotation;
+            }
+        }
+        Rotation::None
+    }
+
+    /// The gamma and white point of a display, by its index
+    pub fn display_calibration(&self, display: usize) -> Calibration {
+        let mut calibration = Calibration::new();
+        for &(i, gamma) in self.display_gammas.iter() {
+            if i == display {
+                calibration.gamma = gamma;
+            }
+        }
+        for &(i, temperature) in self.display_temperatures.iter() {
+            if i == display {
+                calibration.temperature = temperature;
+            }
+        }
+        calibration
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::VecDeque;
+
+use super::WallpaperMode;
+use super::focus::FocusMode;
+use super::gamma::{Calibration, NightLight};
+use super::repeat::KeyRepeat;
+
+/// A command written to a control handle
+#[derive(Clone, Debug)]
+pub enum ControlCommand {
+    /// Switch to the given workspace
+    Switch(usize),
+    /// Move the window with the given id to a workspace
+    Move(usize, usize),
+    /// Restore, raise, and switch to the workspace of the window with the given id
+    Activate(usize),
+    /// Hide the window with the given id until it is activated
+    Minimize(usize),
+    /// Change the wallpaper to the image at a path, fit to the displays with a mode
+    Wallpaper(WallpaperMode, String),
+    /// Set the gamma of a display
+    Gamma(usize, f32),
+    /// Set the color temperature of white on a display
+    WhitePoint(usize, u32),
+    /// Set when the night light warms the colors, or turn it off
+    NightLight(Option<NightLight>),
+    /// Set how held keys repeat, or leave repeating to the keyboard
+    KeyRepeat(Option<KeyRepeat>),
+    /// Type with the keymap of a name, such as `de`
+    Keymap(String),
+    /// Change how windows take focus
+    FocusMode(FocusMode),
+}
+
+impl ControlCommand {
+    /// Parse a command line, such as `switch/1`, `move/4/2`, `activate/4`, `minimize/4`,
+    /// `wallpaper/tile//ui/background.png`, `gamma/0/1.2`, `white_point/0/5500`, `night_light/21:00/07:00/3400`,
+    /// `night_light/off`, `key_repeat/500/30`, `key_repeat/off`, `keymap/de`, or `focus_mode/mouse`
+    pub fn parse(line: &str) -> Option<ControlCommand> {
+        let mut parts = line.trim().split("/");
+        match parts.next().unwrap_or("") {
+            "switch" => {
+                let workspace = parts.next().unwrap_or("").parse::<usize>().ok();
+                workspace.map(|workspace| ControlCommand::Switch(workspace))
+            },
+            "move" => {
+                let id = parts.next().unwrap_or("").parse::<usize>().ok();
+                let workspace = parts.next().unwrap_or("").parse::<usize>().ok();
+                match (id, workspace) {
+                    (Some(id), Some(workspace)) => Some(ControlCommand::Move(id, workspace)),
+                    _ => None
+                }
+            },
+            "activate" => parts.next().unwrap_or("").parse::<usize>().ok().map(|id| ControlCommand::Activate(id)),
+            "minimize" => parts.next().unwrap_or("").parse::<usize>().ok().map(|id| ControlCommand::Minimize(id)),
+            "wallpaper" => {
+                let mode = WallpaperMode::parse(parts.next().unwrap_or(""));
+                let path = parts.collect::<Vec<&str>>().join("/");
+                match mode {
+                    Some(mode) if ! path.is_empty() => Some(ControlCommand::Wallpaper(mode, path)),
+                    _ => None
+                }
+            },
+            "gamma" => {
+                let display = parts.next().unwrap_or("").parse::<usize>().ok();
+                let gamma = Calibration::parse_gamma(parts.next().unwrap_or(""));
+                match (display, gamma) {
+                    (Some(display), Some(gamma)) => Some(ControlCommand::Gamma(display, gamma)),
+                    _ => None
+                }
+            },
+            "white_point" => {
+                let display = parts.next().unwrap_or("").parse::<usize>().ok();
+                let temperature = Calibration::parse_temperature(parts.next().unwrap_or(""));
+                match (display, temperature) {
+                    (Some(display), Some(temperature)) => Some(ControlCommand::WhitePoint(display, temperature)),
+                    _ => None
+                }
+            },
+            "night_light" => {
+                let value = parts.collect::<Vec<&str>>().join("/");
+                if value == "off" {
+                    Some(ControlCommand::NightLight(None))
+                } else {
+                    NightLight::parse(&value).map(|night_light| ControlCommand::NightLight(Some(night_light)))
+                }
+            },
+            "key_repeat" => {
+                let value = parts.collect::<Vec<&str>>().join("/");
+                if value == "off" {
+                    Some(ControlCommand::KeyRepeat(None))
+                } else {
+                    KeyRepeat::parse(&value).map(|key_repeat| ControlCommand::KeyRepeat(Some(key_repeat)))
+                }
+            },
+            "keymap" => match parts.next() {
+                Some(name) if ! name.is_empty() => Some(ControlCommand::Keymap(name.to_string())),
+                _ => None
+            },
+            "focus_mode" => FocusMode::parse(parts.next().unwrap_or("")).map(|focus_mode| ControlCommand::FocusMode(focus_mode)),
+            _ => None
+        }
+    }
+}
+
+/// A control handle, opened with `orbital:control`
+///
+/// Reading returns one line per window, writing accepts one command per line
+pub struct Control {
+    data: Vec<u8>,
+    seek: usize,
+}
+
+impl Control {
+    pub fn new() -> Control {
+        Control {
+            data: Vec::new(),
+            seek: 0
+        }
+    }
+
+    /// Read from a snapshot of the window list
+    ///
+    /// The snapshot is taken on the first read, and the read after it is consumed returns 0
+    pub fn read(&mut self, buf: &mut [u8], list: &str) -> usize {
+        if self.data.is_empty() {
+            self.data = list.as_bytes().to_vec();
+            self.seek = 0;
+        } else if self.seek >= self.data.len() {
+            self.data.clear();
+            self.seek = 0;
+            return 0;
+        }
+
+        let mut i = 0;
+        while i < buf.len() && self.seek < self.data.len() {
+            buf[i] = self.data[self.seek];
+            self.seek += 1;
+            i += 1;
+        }
+        i
+    }
+}
+
+/// A window list handle, opened with `orbital:windows`, for implementing a taskbar
+///
+/// Reading returns lines describing changes to the windows, blocking until there is one:
+/// `open/id/workspace/state/title` when a window is opened or on opening the handle, where the state
+/// contains `f` if the window is focused, `m` if it is minimized, `p` if it is a popup, and `k` if it is an input
+/// panel, `close/id`, `focus/id`, `minimize/id`, `restore/id`, `move/id/workspace`, and `title/id/title`. Titles are
+/// escaped with `system::path::escape`, so they stay one segment of one line. Writing accepts the same commands as
+/// `Control`.
+pub struct WindowList {
+    data: VecDeque<u8>,
+}
+
+impl WindowList {
+    pub fn new() -> WindowList {
+        WindowList {
+            data: VecDeque::new()
+        }
+    }
+
+    /// Queue a line to be read
+    pub fn push(&mut self, line: &str) {
+        self.data.extend(line.bytes());
+        self.data.push_back(b'\n');
+    }
+
+    /// Read queued lines, returning 0 if there are none
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let mut i = 0;
+        while i < buf.len() {
+            match self.data.pop_front() {
+                Some(b) => buf[i] = b,
+                None => break
+            }
+            i += 1;
+        }
+        i
+    }
+}
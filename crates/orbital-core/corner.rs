@@ -0,0 +1,77 @@
+use super::Rect;
+
+/// A corner of a monitor
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    /// Parse a corner name, such as `top_left`
+    pub fn parse(name: &str) -> Option<Corner> {
+        match name.trim() {
+            "top_left" => Some(Corner::TopLeft),
+            "top_right" => Some(Corner::TopRight),
+            "bottom_left" => Some(Corner::BottomLeft),
+            "bottom_right" => Some(Corner::BottomRight),
+            _ => None
+        }
+    }
+
+    /// Find the corner of a rectangle a point is in, if any
+    pub fn at(rect: &Rect, x: i32, y: i32) -> Option<Corner> {
+        let left = x <= rect.left();
+        let right = x >= rect.right() - 1;
+        let top = y <= rect.top();
+        let bottom = y >= rect.bottom() - 1;
+        if top && left {
+            Some(Corner::TopLeft)
+        } else if top && right {
+            Some(Corner::TopRight)
+        } else if bottom && left {
+            Some(Corner::BottomLeft)
+        } else if bottom && right {
+            Some(Corner::BottomRight)
+        } else {
+            None
+        }
+    }
+}
+
+/// An action run when the cursor enters a hot corner
+#[derive(Clone, Debug)]
+pub enum CornerAction {
+    /// Show or hide the window switcher
+    Switcher,
+    /// Show or hide the desktop, by hiding the windows of the workspace
+    Desktop,
+    /// Switch to the given workspace
+    Workspace(usize),
+    /// Run a command
+    Exec(String),
+}
+
+impl CornerAction {
+    /// Parse an action, such as `switcher`, `desktop`, `workspace/1`, or `exec/launcher`
+    pub fn parse(line: &str) -> Option<CornerAction> {
+        let line = line.trim();
+        let (name, arg) = match line.find('/') {
+            Some(slash) => (&line[.. slash], &line[slash + 1 ..]),
+            None => (line, "")
+        };
+        match name {
+            "switcher" => Some(CornerAction::Switcher),
+            "desktop" => Some(CornerAction::Desktop),
+            "workspace" => arg.parse::<usize>().ok().map(|workspace| CornerAction::Workspace(workspace)),
+            "exec" => if arg.is_empty() {
+                None
+            } else {
+                Some(CornerAction::Exec(arg.to_string()))
+            },
+            _ => None
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::cmp;
+use std::fs::File;
+use std::io::Read;
+
+use super::Image;
+
+/// Milliseconds each frame of an animated cursor is shown
+const FRAME_TIME: u64 = 100;
+
+/// The shapes a cursor theme provides
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CursorKind {
+    Arrow,
+    Text,
+    ResizeHorizontal,
+    ResizeVertical,
+    Busy,
+    Crosshair,
+}
+
+impl CursorKind {
+    /// Parse the name of a shape a window can choose, such as `text`
+    pub fn parse(name: &str) -> Option<CursorKind> {
+        match name.trim() {
+            "arrow" => Some(CursorKind::Arrow),
+            "text" => Some(CursorKind::Text),
+            "resize_horizontal" => Some(CursorKind::ResizeHorizontal),
+            "resize_vertical" => Some(CursorKind::ResizeVertical),
+            "crosshair" => Some(CursorKind::Crosshair),
+            _ => None
+        }
+    }
+
+    /// The name of the shape, which is also the name of its image
+    pub fn name(&self) -> &'static str {
+        match *self {
+            CursorKind::Arrow => "arrow",
+            CursorKind::Text => "text",
+            CursorKind::ResizeHorizontal => "resize_horizontal",
+            CursorKind::ResizeVertical => "resize_vertical",
+            CursorKind::Busy => "busy",
+            CursorKind::Crosshair => "crosshair",
+        }
+    }
+}
+
+/// A set of cursor shapes, loaded from a directory of images
+///
+/// The directory contains `arrow.bmp`, `text.bmp`, `resize_horizontal.bmp`, `resize_vertical.bmp`,
+/// `crosshair.bmp`, and the frames of the busy cursor as `busy_0.bmp`, `busy_1.bmp`, and so on.
+/// Missing shapes are drawn with the arrow.
+///
+/// The point of each shape that is placed at the pointer, its hotspot, is read from `hotspots`, as lines such as
+/// `text=4,8` in pixels from the top left of the image. Shapes without one have it at their middle, and the arrow at
+/// its top left.
+pub struct CursorTheme {
+    arrow: Image,
+    text: Option<Image>,
+    resize_horizontal: Option<Image>,
+    resize_vertical: Option<Image>,
+    busy: Vec<Image>,
+    crosshair: Option<Image>,
+    hotspots: Vec<(CursorKind, i32, i32)>,
+}
+
+impl CursorTheme {
+    /// Load a theme from a directory, using the image at `default` if the theme has no arrow
+    pub fn from_path(path: &str, default: &str) -> CursorTheme {
+        let load = |name: &str| -> Option<Image> {
+            if path.is_empty() {
+                return None;
+            }
+            let image = Image::from_path(&format!("{}/{}", path.trim_right_matches('/'), name));
+            if image.width() > 0 && image.height() > 0 {
+                Some(image)
+            } else {
+                None
+            }
+        };
+
+        let mut busy = Vec::new();
+        while let Some(image) = load(&format!("busy_{}.bmp", busy.len())) {
+            busy.push(image);
+        }
+
+        CursorTheme {
+            arrow: load("arrow.bmp").unwrap_or_else(|| Image::from_path(default)),
+            text: load("text.bmp"),
+            resize_horizontal: load("resize_horizontal.bmp"),
+            resize_vertical: load("resize_vertical.bmp"),
+            busy: busy,
+            crosshair: load("crosshair.bmp"),
+            hotspots: if path.is_empty() { Vec::new() } else { load_hotspots(path) }
+        }
+    }
+
+    /// The number of frames of a cursor shape
+    pub fn frames(&self, kind: CursorKind) -> usize {
+        if kind == CursorKind::Busy && ! self.busy.is_empty() {
+            self.busy.len()
+        } else {
+            1
+        }
+    }
+
+    /// The frame of a cursor shape to show after some time, in milliseconds
+    pub fn frame(&self, kind: CursorKind, time: u64) -> usize {
+        (time / FRAME_TIME) as usize % self.frames(kind)
+    }
+
+    /// The milliseconds after some time until the frame of a cursor shape changes, `None` if it is not animated
+    pub fn next_frame(&self, kind: CursorKind, time: u64) -> Option<u32> {
+        if self.frames(kind) > 1 {
+            Some((FRAME_TIME - time % FRAME_TIME) as u32)
+        } else {
+            None
+        }
+    }
+
+    /// The image of a cursor shape, if the theme has it
+    fn shape(&self, kind: CursorKind, frame: usize) -> Option<&Image> {
+        match kind {
+            CursorKind::Arrow => None,
+            CursorKind::Text => self.text.as_ref(),
+            CursorKind::ResizeHorizontal => self.resize_horizontal.as_ref(),
+            CursorKind::ResizeVertical => self.resize_vertical.as_ref(),
+            CursorKind::Busy => self.busy.get(frame % self.frames(kind)),
+            CursorKind::Crosshair => self.crosshair.as_ref()
+        }
+    }
+
+    /// Get the image of a cursor shape
+    pub fn image(&self, kind: CursorKind, frame: usize) -> &Image {
+        self.shape(kind, frame).unwrap_or(&self.arrow)
+    }
+
+    /// The hotspot of a cursor shape, in pixels from the top left of its image
+    pub fn hotspot(&self, kind: CursorKind, frame: usize) -> (i32, i32) {
+        let (kind, image) = match self.shape(kind, frame) {
+            Some(image) => (kind, image),
+            None => (CursorKind::Arrow, &self.arrow)
+        };
+
+        let (x, y) = match self.hotspots.iter().find(|&&(hotspot_kind, _, _)| hotspot_kind == kind) {
+            Some(&(_, x, y)) => (x, y),
+            None if kind == CursorKind::Arrow => (0, 0),
+            None => (image.width()/2, image.height()/2)
+        };
+        (cmp::max(0, cmp::min(image.width() - 1, x)), cmp::max(0, cmp::min(image.height() - 1, y)))
+    }
+}
+
+/// Read the hotspots of a theme, from lines such as `text=4,8`
+fn load_hotspots(path: &str) -> Vec<(CursorKind, i32, i32)> {
+    let mut string = String::new();
+    if let Ok(mut file) = File::open(&format!("{}/hotspots", path.trim_right_matches('/'))) {
+        let _ = file.read_to_string(&mut string);
+    }
+
+    let kinds = [CursorKind::Arrow, CursorKind::Text, CursorKind::ResizeHorizontal, CursorKind::ResizeVertical,
+                 CursorKind::Busy, CursorKind::Crosshair];
+    let mut hotspots = Vec::new();
+    for line in string.lines() {
+        let mut parts = line.trim().splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        let mut point = parts.next().unwrap_or("").split(',').map(|part| part.trim().parse::<i32>());
+        match (kinds.iter().find(|kind| kind.name() == name), point.next(), point.next(), point.next()) {
+            (Some(&kind), Some(Ok(x)), Some(Ok(y)), None) => hotspots.push((kind, x, y)),
+            _ => if ! line.trim().is_empty() {
+                println!("orbital: invalid cursor hotspot '{}'", line);
+            }
+        }
+    }
+    hotspots
+}
@@ -0,0 +1,23 @@
+/// How windows take keyboard focus
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FocusMode {
+    /// Clicking a window focuses and raises it
+    Click,
+    /// The window under the cursor is focused, and stays focused while the cursor is over the desktop,
+    /// clicking still raises it
+    Mouse,
+    /// The window under the cursor is focused and raised
+    MouseRaise,
+}
+
+impl FocusMode {
+    /// Parse a focus mode, `click`, `mouse`, or `mouse_raise`
+    pub fn parse(name: &str) -> Option<FocusMode> {
+        match name.trim() {
+            "click" => Some(FocusMode::Click),
+            "mouse" => Some(FocusMode::Mouse),
+            "mouse_raise" => Some(FocusMode::MouseRaise),
+            _ => None
+        }
+    }
+}
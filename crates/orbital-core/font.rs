@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+
+use super::Color;
+use super::Image;
+use super::ttf::{GlyphBitmap, TtfFile};
+
+static FONT: &'static [u8] = include_bytes!("../../filesystem/ui/unifont.font");
+
+pub struct Font;
+
+impl Font {
+    /// Check if the bitmap font has a glyph for a character
+    pub fn contains(character: char) -> bool {
+        16 * (character as usize) + 16 <= FONT.len()
+    }
+
+    /// Render a character, characters outside of the bitmap font are rendered as `?`
+    pub fn render(character: char, color: Color) -> Image {
+        let mut data = Box::new([0; 8*16]);
+
+        let character = if Font::contains(character) { character } else { '?' };
+        let font_i = 16 * (character as usize);
+        if font_i + 16 <= FONT.len() {
+            for row in 0..16 {
+                let row_data = FONT[font_i + row];
+                let row_i = row * 8;
+                for col in 0..8 {
+                    if (row_data >> (7 - col)) & 1 == 1 {
+                        data[row_i + col] = color.data;
+                    }
+                }
+            }
+        }
+
+        Image::from_data(8, 16, data)
+    }
+}
+
+/// How a line of text is placed across its rectangle, see `ImageRoi::draw_text`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// A font to draw text with, see `ImageRoi::draw_text`
+pub enum TextFont<'a> {
+    /// The bitmap font, enlarged by a scale factor
+    Bitmap(i32),
+    /// A vector font at a size in pixels, with the bitmap font enlarged by a scale factor for characters that only
+    /// it has
+    Vector(&'a mut VectorFont, u32, i32),
+}
+
+impl<'a> TextFont<'a> {
+    /// Render a character into an image one line high and one advance wide, for blending
+    pub fn render(&mut self, character: char, color: Color) -> Image {
+        match *self {
+            TextFont::Vector(ref mut font, size, scale) => if font.contains(character) || ! Font::contains(character) {
+                font.render(character, size, color)
+            } else {
+                Font::render(character, color).scale(scale)
+            },
+            TextFont::Bitmap(scale) => Font::render(character, color).scale(scale)
+        }
+    }
+
+    /// The width of a character
+    pub fn advance(&mut self, character: char) -> i32 {
+        match *self {
+            TextFont::Vector(ref mut font, size, scale) => if font.contains(character) || ! Font::contains(character) {
+                font.glyph(character, size).advance
+            } else {
+                8 * scale
+            },
+            TextFont::Bitmap(scale) => 8 * scale
+        }
+    }
+
+    /// The width of a line of text
+    pub fn width(&mut self, text: &str) -> i32 {
+        let mut width = 0;
+        for character in text.chars() {
+            width += self.advance(character);
+        }
+        width
+    }
+}
+
+/// The maximum number of glyphs kept by a vector font before its cache is cleared
+const GLYPH_CACHE_SIZE: usize = 1024;
+
+/// A scalable font, rasterizing glyphs from a TrueType file on demand
+pub struct VectorFont {
+    ttf: TtfFile,
+    fallbacks: Vec<TtfFile>,
+    cache: BTreeMap<(char, u32), GlyphBitmap>,
+}
+
+impl VectorFont {
+    /// Load a TrueType font, such as `/ui/fonts/DroidSans-Regular.ttf`
+    pub fn from_path(path: &str) -> Option<VectorFont> {
+        TtfFile::from_path(path).map(|ttf| VectorFont {
+            ttf: ttf,
+            fallbacks: Vec::new(),
+            cache: BTreeMap::new()
+        })
+    }
+
+    /// Add a font to search for characters missing from this font, in the order they are added
+    pub fn add_fallback(&mut self, path: &str) -> bool {
+        match TtfFile::from_path(path) {
+            Some(ttf) => {
+                self.fallbacks.push(ttf);
+                self.cache.clear();
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Check if this font or one of its fallbacks has a glyph for a character
+    pub fn contains(&self, character: char) -> bool {
+        self.ttf.glyph_index(character) != 0 || self.fallbacks.iter().any(|ttf| ttf.glyph_index(character) != 0)
+    }
+
+    /// Distance from the top of a line to the baseline, in pixels
+    pub fn ascent(&self, size: u32) -> i32 {
+        (self.ttf.ascent() as f32 * size as f32 / self.ttf.units_per_em() as f32).ceil() as i32
+    }
+
+    /// Height of a line, in pixels
+    pub fn line_height(&self, size: u32) -> i32 {
+        let units = self.ttf.ascent() as i32 - self.ttf.descent() as i32;
+        (units as f32 * size as f32 / self.ttf.units_per_em() as f32).ceil() as i32
+    }
+
+    /// Get a rasterized glyph at a size in pixels, from the cache if possible
+    pub fn glyph(&mut self, character: char, size: u32) -> &GlyphBitmap {
+        if ! self.cache.contains_key(&(character, size)) {
+            if self.cache.len() >= GLYPH_CACHE_SIZE {
+                self.cache.clear();
+            }
+            //Use the first font with the character, or the missing glyph of the primary font
+            let glyph = match self.fallbacks.iter().find(|ttf| ttf.glyph_index(character) != 0) {
+                Some(ttf) if self.ttf.glyph_index(character) == 0 => ttf.rasterize(character, size as f32),
+                _ => self.ttf.rasterize(character, size as f32)
+            };
+            self.cache.insert((character, size), glyph);
+        }
+        &self.cache[&(character, size)]
+    }
+
+    /// Render a character into an image one line high and one advance wide, for blending
+    pub fn render(&mut self, character: char, size: u32, color: Color) -> Image {
+        let ascent = self.ascent(size);
+        let height = self.line_height(size);
+
+        let glyph = self.glyph(character, size);
+        let width = glyph.advance;
+        let mut data = vec![0; width as usize * height as usize].into_boxed_slice();
+
+        let alpha = (color.data >> 24) & 0xFF;
+        for y in 0..glyph.height {
+            let image_y = ascent - glyph.top + y;
+            if image_y < 0 || image_y >= height {
+                continue;
+            }
+            for x in 0..glyph.width {
+                let image_x = glyph.left + x;
+                if image_x < 0 || image_x >= width {
+                    continue;
+                }
+                let coverage = glyph.coverage[(y * glyph.width + x) as usize] as u32;
+                if coverage > 0 {
+                    let a = alpha * coverage / 255;
+                    data[(image_y * width + image_x) as usize] = (a << 24) | (color.data & 0xFFFFFF);
+                }
+            }
+        }
+
+        Image::from_data(width, height, data)
+    }
+}
@@ -0,0 +1,161 @@
+//! Color calibration of displays, applied to the pixels as they are sent to a display
+//!
+//! Each display has a gamma and a white point, which become a lookup table for each channel. The night light shifts
+//! the white point of every display to a warmer color temperature between two times of the day.
+
+use super::clock::Date;
+
+/// The color temperature of daylight, which leaves the colors as they are
+pub const NEUTRAL_TEMPERATURE: u32 = 6500;
+/// The range of color temperatures that can be set, in kelvin
+pub const MIN_TEMPERATURE: u32 = 1000;
+pub const MAX_TEMPERATURE: u32 = 10000;
+
+/// The gamma and white point of a display
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Calibration {
+    /// The exponent of each channel is divided by this, 1.0 leaves them as they are
+    pub gamma: f32,
+    /// The color temperature of white, in kelvin
+    pub temperature: u32,
+}
+
+impl Calibration {
+    pub fn new() -> Calibration {
+        Calibration {
+            gamma: 1.0,
+            temperature: NEUTRAL_TEMPERATURE
+        }
+    }
+
+    /// Parse a gamma, such as `1.2`
+    pub fn parse_gamma(value: &str) -> Option<f32> {
+        match value.parse::<f32>() {
+            Ok(gamma) if gamma >= 0.1 && gamma <= 10.0 => Some(gamma),
+            _ => None
+        }
+    }
+
+    /// Parse a color temperature in kelvin, such as `5000`
+    pub fn parse_temperature(value: &str) -> Option<u32> {
+        match value.parse::<u32>() {
+            Ok(temperature) if temperature >= MIN_TEMPERATURE && temperature <= MAX_TEMPERATURE => Some(temperature),
+            _ => None
+        }
+    }
+
+    /// Check if the calibration leaves the colors as they are, so no lookup is needed
+    pub fn is_identity(&self) -> bool {
+        self.gamma == 1.0 && self.temperature == NEUTRAL_TEMPERATURE
+    }
+}
+
+/// The color of a black body at a temperature, from the approximation by Tanner Helland, as 0 to 255 for each channel
+fn black_body(temperature: u32) -> (f32, f32, f32) {
+    let t = temperature as f32 / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698727446 * (t - 60.0).powf(-0.1332047592)
+    };
+    let green = if t <= 66.0 {
+        99.4708025861 * t.ln() - 161.1195681661
+    } else {
+        288.1221695283 * (t - 60.0).powf(-0.0755148492)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (t - 10.0).ln() - 305.0447927307
+    };
+    (red, green, blue)
+}
+
+/// The factor of each channel for a white point, relative to daylight so that it is neutral
+fn white_point(temperature: u32) -> (f32, f32, f32) {
+    let clamp = |value: f32| if value < 0.0 { 0.0 } else if value > 1.0 { 1.0 } else { value };
+    let (red, green, blue) = black_body(temperature);
+    let (neutral_red, neutral_green, neutral_blue) = black_body(NEUTRAL_TEMPERATURE);
+    (clamp(red / neutral_red), clamp(green / neutral_green), clamp(blue / neutral_blue))
+}
+
+/// Lookup tables for the red, green, and blue channels of a calibration
+pub struct Ramp {
+    red: [u8; 256],
+    green: [u8; 256],
+    blue: [u8; 256],
+}
+
+impl Ramp {
+    pub fn new(calibration: &Calibration) -> Ramp {
+        let (red, green, blue) = white_point(calibration.temperature);
+        let mut ramp = Ramp {
+            red: [0; 256],
+            green: [0; 256],
+            blue: [0; 256]
+        };
+        for i in 0..256 {
+            let value = (i as f32 / 255.0).powf(1.0 / calibration.gamma) * 255.0;
+            ramp.red[i] = (value * red + 0.5) as u8;
+            ramp.green[i] = (value * green + 0.5) as u8;
+            ramp.blue[i] = (value * blue + 0.5) as u8;
+        }
+        ramp
+    }
+
+    /// Look up the channels of a pixel, keeping its alpha
+    pub fn apply(&self, pixel: u32) -> u32 {
+        (pixel & 0xFF000000)
+            | (self.red[((pixel >> 16) & 0xFF) as usize] as u32) << 16
+            | (self.green[((pixel >> 8) & 0xFF) as usize] as u32) << 8
+            | self.blue[(pixel & 0xFF) as usize] as u32
+    }
+}
+
+/// Warmer colors for every display during the night
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NightLight {
+    /// The minute of the day the night light turns on
+    pub start: u32,
+    /// The minute of the day the night light turns off, which is before `start` if it is on over midnight
+    pub end: u32,
+    /// The color temperature of white while it is on, in kelvin
+    pub temperature: u32,
+}
+
+impl NightLight {
+    /// Parse the times it is on and off and its temperature, such as `21:00/07:00/3400`
+    pub fn parse(value: &str) -> Option<NightLight> {
+        let mut parts = value.split('/');
+        match (parts.next().and_then(parse_time), parts.next().and_then(parse_time),
+               parts.next().and_then(Calibration::parse_temperature)) {
+            (Some(start), Some(end), Some(temperature)) => Some(NightLight {
+                start: start,
+                end: end,
+                temperature: temperature
+            }),
+            _ => None
+        }
+    }
+
+    /// Check if the night light is on at the time of a date
+    pub fn active(&self, date: &Date) -> bool {
+        let minute = date.hour as u32 * 60 + date.minute as u32;
+        if self.start <= self.end {
+            minute >= self.start && minute < self.end
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+/// Parse a time of day, such as `07:30`, to the minute of the day
+fn parse_time(value: &str) -> Option<u32> {
+    let mut parts = value.split(':');
+    match (parts.next().and_then(|part| part.parse::<u32>().ok()), parts.next().and_then(|part| part.parse::<u32>().ok())) {
+        (Some(hour), Some(minute)) if hour < 24 && minute < 60 => Some(hour * 60 + minute),
+        _ => None
+    }
+}
@@ -0,0 +1,704 @@
+use std::{cmp, mem};
+use std::fs::File;
+use std::io::Read;
+
+use system::graphics::{fast_copy, fast_set};
+
+use super::{pixel, Color, Rect, Rotation};
+use super::font::{TextAlign, TextFont};
+use super::bmp::BmpFile;
+use super::jpeg::JpegFile;
+use super::png::PngFile;
+
+/// How the pixels of a scaled image are picked from the original
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ResizeFilter {
+    /// The nearest pixel, keeping edges sharp
+    Nearest,
+    /// A mix of the four nearest pixels, by distance
+    Bilinear,
+}
+
+/// Mix two pixels, by a weight of the second from 0 to 256
+fn lerp(a: u32, b: u32, weight: u32) -> u32 {
+    let inverse = 256 - weight;
+    let channel = |shift: u32| -> u32 {
+        ((((a >> shift) & 0xFF) * inverse + ((b >> shift) & 0xFF) * weight) >> 8) << shift
+    };
+    channel(24) | channel(16) | channel(8) | channel(0)
+}
+
+/// The position in the source of the center of a pixel in the destination, in 1/256ths of a pixel, from the
+/// center of the first source pixel
+fn sample(i: i32, src: i32, dst: i32) -> i64 {
+    (2 * i as i64 + 1) * src as i64 * 256 / (2 * dst as i64) - 128
+}
+
+/// Scale an area of one image to fill an area of another, both areas being inside their images
+fn scale(src: &Image, src_rect: Rect, dst: &mut Image, dst_rect: Rect, filter: ResizeFilter) {
+    let sw = src_rect.width();
+    let sh = src_rect.height();
+    let dw = dst_rect.width();
+    let dh = dst_rect.height();
+    if sw <= 0 || sh <= 0 || dw <= 0 || dh <= 0 {
+        return;
+    }
+
+    let src_width = src.width();
+    let dst_width = dst.width();
+    let pixel = |x: i32, y: i32| -> u32 {
+        src.data[((src_rect.top() + y) * src_width + src_rect.left() + x) as usize]
+    };
+
+    for y in 0..dh {
+        let row = ((dst_rect.top() + y) * dst_width + dst_rect.left()) as usize;
+        match filter {
+            ResizeFilter::Nearest => {
+                let src_y = cmp::min(sh - 1, ((2 * y as i64 + 1) * sh as i64 / (2 * dh as i64)) as i32);
+                for x in 0..dw {
+                    let src_x = cmp::min(sw - 1, ((2 * x as i64 + 1) * sw as i64 / (2 * dw as i64)) as i32);
+                    dst.data[row + x as usize] = pixel(src_x, src_y);
+                }
+            },
+            ResizeFilter::Bilinear => {
+                let fy = cmp::min((sh as i64 - 1) * 256, cmp::max(0, sample(y, sh, dh)));
+                let y0 = (fy >> 8) as i32;
+                let y1 = cmp::min(sh - 1, y0 + 1);
+                let wy = (fy & 0xFF) as u32;
+                for x in 0..dw {
+                    let fx = cmp::min((sw as i64 - 1) * 256, cmp::max(0, sample(x, sw, dw)));
+                    let x0 = (fx >> 8) as i32;
+                    let x1 = cmp::min(sw - 1, x0 + 1);
+                    let wx = (fx & 0xFF) as u32;
+                    let top = lerp(pixel(x0, y0), pixel(x1, y0), wx);
+                    let bottom = lerp(pixel(x0, y1), pixel(x1, y1), wx);
+                    dst.data[row + x as usize] = lerp(top, bottom, wy);
+                }
+            }
+        }
+    }
+}
+
+pub struct ImageRoiRows<'a> {
+    rect: Rect,
+    image: &'a Image,
+    i: i32,
+}
+
+impl<'a> Iterator for ImageRoiRows<'a> {
+    type Item = &'a [u32];
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.i < self.rect.height() {
+            let start = (self.rect.top() + self.i) * self.image.width() + self.rect.left();
+            let end = start + self.rect.width();
+            self.i += 1;
+            Some(& self.image.data[start as usize .. end as usize])
+        } else {
+            None
+        }
+    }
+}
+
+pub struct ImageRoiRowsMut<'a> {
+    rect: Rect,
+    image: &'a mut Image,
+    i: i32,
+}
+
+impl<'a> Iterator for ImageRoiRowsMut<'a> {
+    type Item = &'a mut [u32];
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.i < self.rect.height() {
+            let start = (self.rect.top() + self.i) * self.image.width() + self.rect.left();
+            let end = start + self.rect.width();
+            self.i += 1;
+            // it does not appear to be possible to do this in safe rust
+            Some(unsafe { mem::transmute(&mut self.image.data[start as usize .. end as usize]) })
+        } else {
+            None
+        }
+    }
+}
+
+pub struct ImageRoi<'a> {
+    rect: Rect,
+    image: &'a mut Image
+}
+
+impl<'a> ImageRoi<'a> {
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    pub fn left(&self) -> i32 {
+        self.rect.left()
+    }
+
+    pub fn right(&self) -> i32 {
+        self.rect.right()
+    }
+
+    pub fn top(&self) -> i32 {
+        self.rect.top()
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.rect.bottom()
+    }
+
+    pub fn width(&self) -> i32 {
+        self.rect.width()
+    }
+
+    pub fn height(&self) -> i32 {
+        self.rect.height()
+    }
+
+    pub fn rows(&'a self) -> ImageRoiRows<'a> {
+        ImageRoiRows {
+            rect: self.rect,
+            image: self.image,
+            i: 0
+        }
+    }
+
+    pub fn rows_mut(&'a mut self) -> ImageRoiRowsMut<'a> {
+        ImageRoiRowsMut {
+            rect: self.rect,
+            image: self.image,
+            i: 0
+        }
+    }
+
+    pub fn blend(&'a mut self, other: &ImageRoi) {
+        for (self_row, other_row) in self.rows_mut().zip(other.rows()) {
+            pixel::blend(self_row, other_row);
+        }
+    }
+
+    pub fn blit(&'a mut self, other: &ImageRoi) {
+        for (mut self_row, other_row) in self.rows_mut().zip(other.rows()) {
+            let len = cmp::min(self_row.len(), other_row.len());
+            unsafe { fast_copy(self_row.as_mut_ptr() as *mut u32, other_row.as_ptr() as *const u32, len); }
+        }
+    }
+
+    /// Scale another area to fill this one
+    pub fn scaled_blit(&'a mut self, other: &ImageRoi, filter: ResizeFilter) {
+        scale(&*other.image, other.rect, &mut *self.image, self.rect, filter);
+    }
+
+    /// Blend a color onto a pixel, relative to the area, if it is inside it
+    fn point(&mut self, x: i32, y: i32, color: u32) {
+        if x >= 0 && y >= 0 && x < self.rect.width() && y < self.rect.height() {
+            let i = ((self.rect.top() + y) * self.image.w + self.rect.left() + x) as usize;
+            pixel::blend_color(&mut self.image.data[i .. i + 1], color);
+        }
+    }
+
+    /// Blend a color onto the pixels of a row from `x1` up to `x2`, relative to the area, clipped to it
+    fn span(&mut self, x1: i32, x2: i32, y: i32, color: u32) {
+        let x1 = cmp::max(0, x1);
+        let x2 = cmp::min(self.rect.width(), x2);
+        if x1 < x2 && y >= 0 && y < self.rect.height() {
+            let start = ((self.rect.top() + y) * self.image.w + self.rect.left()) as usize;
+            pixel::blend_color(&mut self.image.data[start + x1 as usize .. start + x2 as usize], color);
+        }
+    }
+
+    /// Draw a line between two points, including both, relative to the area
+    pub fn line(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, color: Color) {
+        let dx = (x2 - x1).abs();
+        let dy = -(y2 - y1).abs();
+        let sx = if x1 < x2 { 1 } else { -1 };
+        let sy = if y1 < y2 { 1 } else { -1 };
+        let mut err = dx + dy;
+        let mut x = x1;
+        let mut y = y1;
+        loop {
+            self.point(x, y, color.data);
+            if x == x2 && y == y2 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Draw the edge of a rectangle, one pixel wide and inside it, relative to the area
+    pub fn outline(&mut self, rect: &Rect, color: Color) {
+        if rect.is_empty() {
+            return;
+        }
+
+        self.span(rect.left(), rect.right(), rect.top(), color.data);
+        if rect.height() > 1 {
+            self.span(rect.left(), rect.right(), rect.bottom() - 1, color.data);
+        }
+        for y in rect.top() + 1 .. rect.bottom() - 1 {
+            self.point(rect.left(), y, color.data);
+            if rect.width() > 1 {
+                self.point(rect.right() - 1, y, color.data);
+            }
+        }
+    }
+
+    /// Fill a circle around the center of a pixel, relative to the area, blending its edge by how much of each
+    /// pixel it covers
+    pub fn circle(&mut self, x: i32, y: i32, radius: i32, color: Color) {
+        if radius <= 0 {
+            return;
+        }
+
+        let alpha = (color.data >> 24) as f32;
+        let rgb = color.data & 0xFFFFFF;
+        let r = radius as f32;
+        for dy in -radius .. radius + 1 {
+            for dx in -radius .. radius + 1 {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                let coverage = (r + 0.5 - distance).max(0.0).min(1.0);
+                if coverage > 0.0 {
+                    self.point(x + dx, y + dy, ((alpha * coverage) as u32) << 24 | rgb);
+                }
+            }
+        }
+    }
+
+    /// Fill a polygon, relative to the area, with the pixels whose centers are inside it by the even-odd rule
+    pub fn polygon(&mut self, points: &[(i32, i32)], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let top = cmp::max(0, points.iter().map(|point| point.1).min().unwrap_or(0));
+        let bottom = cmp::min(self.rect.height(), points.iter().map(|point| point.1).max().unwrap_or(0) + 1);
+
+        let mut crossings: Vec<f32> = Vec::new();
+        for y in top..bottom {
+            let center = y as f32 + 0.5;
+            crossings.clear();
+            for i in 0..points.len() {
+                let (x1, y1) = points[i];
+                let (x2, y2) = points[(i + 1) % points.len()];
+                let (y1, y2) = (y1 as f32, y2 as f32);
+                if (y1 <= center) != (y2 <= center) {
+                    crossings.push(x1 as f32 + (center - y1) * (x2 - x1) as f32 / (y2 - y1));
+                }
+            }
+            crossings.sort_by(|a, b| a.partial_cmp(b).unwrap_or(cmp::Ordering::Equal));
+
+            for pair in crossings.chunks(2) {
+                if pair.len() == 2 {
+                    //The pixels whose centers are between the crossings
+                    let x1 = (pair[0] - 0.5).ceil() as i32;
+                    let x2 = (pair[1] - 0.5).ceil() as i32;
+                    self.span(x1, x2, y, color.data);
+                }
+            }
+        }
+    }
+
+    /// Draw a line of text with its top at the top of a rectangle relative to the area, aligned across it and clipped
+    /// to it and to the area, returning the width of the whole line
+    pub fn draw_text(&mut self, font: &mut TextFont, text: &str, color: Color, layout: &Rect, align: TextAlign) -> i32 {
+        let width = font.width(text);
+        let mut x = match align {
+            TextAlign::Left => layout.left(),
+            TextAlign::Center => layout.left() + (layout.width() - width) / 2,
+            TextAlign::Right => layout.right() - width,
+        };
+
+        let clip = layout.intersection(&Rect::new(0, 0, self.rect.width(), self.rect.height()));
+        if clip.is_empty() {
+            return width;
+        }
+
+        for c in text.chars() {
+            if x >= clip.right() {
+                break;
+            }
+            let advance = font.advance(c);
+            if x + advance > clip.left() {
+                let mut glyph = font.render(c, color);
+                let glyph_rect = Rect::new(x, layout.top(), glyph.width(), glyph.height());
+                let intersect = clip.intersection(&glyph_rect);
+                if ! intersect.is_empty() {
+                    self.image.roi(&intersect.offset(self.rect.left(), self.rect.top()))
+                              .blend(&glyph.roi(&intersect.offset(-glyph_rect.left(), -glyph_rect.top())));
+                }
+            }
+            x += advance;
+        }
+
+        width
+    }
+
+    pub fn set(&'a mut self, color: Color) {
+        let new = color.data;
+
+        let alpha = (new >> 24) & 0xFF;
+        if alpha >= 255 {
+            for mut self_row in self.rows_mut() {
+                unsafe { fast_set(self_row.as_mut_ptr() as *mut u32, new, self_row.len()); }
+            }
+        } else if alpha > 0 {
+            for self_row in self.rows_mut() {
+                pixel::blend_color(self_row, new);
+            }
+        }
+    }
+}
+
+pub struct Image {
+    w: i32,
+    h: i32,
+    data: Box<[u32]>
+}
+
+impl Image {
+    pub fn new(width: i32, height: i32) -> Image {
+        Image::from_color(width, height, Color::rgb(0, 0, 0))
+    }
+
+    pub fn from_color(width: i32, height: i32, color: Color) -> Image {
+        Image::from_data(width, height, vec![color.data; width as usize * height as usize].into_boxed_slice())
+    }
+
+    pub fn from_data(width: i32, height: i32, data: Box<[u32]>) -> Image {
+        Image {
+            w: width,
+            h: height,
+            data: data
+        }
+    }
+
+    /// Load a BMP, PNG, or JPEG image, detecting the format from the file contents
+    pub fn from_path(path: &str) -> Image {
+        let mut data: Vec<u8> = Vec::new();
+        if let Ok(mut file) = File::open(path) {
+            let _ = file.read_to_end(&mut data);
+        }
+        Image::decode(&data)
+    }
+
+    /// Decode a BMP, PNG, or JPEG image, unknown formats result in an empty image
+    pub fn decode(file_data: &[u8]) -> Image {
+        if file_data.starts_with(b"BM") {
+            BmpFile::from_data(file_data)
+        } else if file_data.starts_with(b"\x89PNG") {
+            PngFile::from_data(file_data)
+        } else if file_data.starts_with(b"\xFF\xD8") {
+            JpegFile::from_data(file_data)
+        } else {
+            Image::new(0, 0)
+        }
+    }
+
+    /// Enlarge the image by an integer factor, repeating each pixel
+    pub fn scale(&self, factor: i32) -> Image {
+        if factor <= 1 {
+            return Image::from_data(self.w, self.h, self.data.clone());
+        }
+
+        let w = self.w * factor;
+        let h = self.h * factor;
+        let mut data = Vec::with_capacity(w as usize * h as usize);
+        for y in 0..h {
+            let row = (y / factor) * self.w;
+            for x in 0..w {
+                data.push(self.data[(row + x / factor) as usize]);
+            }
+        }
+        Image::from_data(w, h, data.into_boxed_slice())
+    }
+
+    /// Scale the image to the given size, an empty size resulting in an empty image
+    pub fn resize(&self, w: i32, h: i32, filter: ResizeFilter) -> Image {
+        if w <= 0 || h <= 0 || self.w <= 0 || self.h <= 0 {
+            return Image::new(0, 0);
+        }
+
+        let mut image = Image::new(w, h);
+        scale(self, Rect::new(0, 0, self.w, self.h), &mut image, Rect::new(0, 0, w, h), filter);
+        image
+    }
+
+    /// Turn the image clockwise
+    pub fn rotate(&self, rotation: Rotation) -> Image {
+        let w = self.w;
+        let h = self.h;
+        let (width, height) = if rotation.swaps_axes() { (h, w) } else { (w, h) };
+        let mut data = vec![0; self.data.len()];
+        for y in 0..h {
+            for x in 0..w {
+                let (dx, dy) = match rotation {
+                    Rotation::None => (x, y),
+                    Rotation::Quarter => (h - 1 - y, x),
+                    Rotation::Half => (w - 1 - x, h - 1 - y),
+                    Rotation::ThreeQuarters => (y, w - 1 - x),
+                };
+                data[(dy * width + dx) as usize] = self.data[(y * w + x) as usize];
+            }
+        }
+        Image::from_data(width, height, data.into_boxed_slice())
+    }
+
+    /// Mirror the image, swapping its left and right
+    pub fn flip_horizontal(&self) -> Image {
+        let mut data = self.data.clone();
+        if self.w > 0 {
+            for row in data.chunks_mut(self.w as usize) {
+                row.reverse();
+            }
+        }
+        Image::from_data(self.w, self.h, data)
+    }
+
+    /// Mirror the image, swapping its top and bottom
+    pub fn flip_vertical(&self) -> Image {
+        let mut data = Vec::with_capacity(self.data.len());
+        if self.w > 0 {
+            for row in self.data.chunks(self.w as usize).rev() {
+                data.extend_from_slice(row);
+            }
+        }
+        Image::from_data(self.w, self.h, data.into_boxed_slice())
+    }
+
+    pub fn width(&self) -> i32 {
+        self.w
+    }
+
+    pub fn height(&self) -> i32 {
+        self.h
+    }
+
+    pub fn data(&self) -> &[u32] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u32] {
+        &mut self.data
+    }
+
+    pub fn as_roi(&mut self) -> ImageRoi {
+        ImageRoi {
+            rect: Rect::new(0, 0, self.w, self.h),
+            image: self
+        }
+    }
+
+    pub fn roi(&mut self, rect: &Rect) -> ImageRoi {
+        ImageRoi {
+            rect: *rect,
+            image: self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Image, ResizeFilter};
+    use super::super::{Color, Rect, Rotation, TextAlign, TextFont};
+
+    const RED: u32 = 0xFFFF0000;
+    const BLUE: u32 = 0xFF0000FF;
+
+    #[test]
+    fn resize_empty() {
+        let image = Image::from_data(2, 2, vec![RED; 4].into_boxed_slice());
+        for &filter in [ResizeFilter::Nearest, ResizeFilter::Bilinear].iter() {
+            for &(w, h) in [(0, 0), (0, 3), (3, 0), (-1, 2)].iter() {
+                let resized = image.resize(w, h, filter);
+                assert_eq!((resized.width(), resized.height()), (0, 0));
+            }
+            let resized = Image::new(0, 0).resize(4, 4, filter);
+            assert_eq!((resized.width(), resized.height()), (0, 0));
+        }
+    }
+
+    #[test]
+    fn resize_single_pixel() {
+        let image = Image::from_data(1, 1, vec![RED].into_boxed_slice());
+        for &filter in [ResizeFilter::Nearest, ResizeFilter::Bilinear].iter() {
+            let resized = image.resize(3, 5, filter);
+            assert_eq!((resized.width(), resized.height()), (3, 5));
+            assert!(resized.data().iter().all(|&pixel| pixel == RED));
+        }
+    }
+
+    #[test]
+    fn resize_same_size() {
+        let data = vec![RED, BLUE, BLUE, RED, 0, 0x80808080];
+        let image = Image::from_data(3, 2, data.clone().into_boxed_slice());
+        for &filter in [ResizeFilter::Nearest, ResizeFilter::Bilinear].iter() {
+            assert_eq!(image.resize(3, 2, filter).data(), &data[..]);
+        }
+    }
+
+    #[test]
+    fn resize_nearest() {
+        let image = Image::from_data(2, 1, vec![RED, BLUE].into_boxed_slice());
+        assert_eq!(image.resize(4, 1, ResizeFilter::Nearest).data(), &[RED, RED, BLUE, BLUE]);
+        assert_eq!(image.resize(1, 1, ResizeFilter::Nearest).data().len(), 1);
+    }
+
+    #[test]
+    fn resize_bilinear() {
+        let image = Image::from_data(2, 1, vec![RED, BLUE].into_boxed_slice());
+        let resized = image.resize(4, 1, ResizeFilter::Bilinear);
+        let data = resized.data();
+        //The edges are clamped to the edge pixels, the middle is mixed
+        assert_eq!(data[0], RED);
+        assert_eq!(data[3], BLUE);
+        assert!((data[1] >> 16) & 0xFF > data[1] & 0xFF);
+        assert!((data[2] >> 16) & 0xFF < data[2] & 0xFF);
+        assert!(data.iter().all(|&pixel| pixel >> 24 == 0xFF));
+    }
+
+    #[test]
+    fn rotate() {
+        //1 2 3
+        //4 5 6
+        let image = Image::from_data(3, 2, vec![1, 2, 3, 4, 5, 6].into_boxed_slice());
+
+        let rotated = image.rotate(Rotation::Quarter);
+        assert_eq!((rotated.width(), rotated.height()), (2, 3));
+        assert_eq!(rotated.data(), &[4, 1, 5, 2, 6, 3]);
+
+        let rotated = image.rotate(Rotation::Half);
+        assert_eq!((rotated.width(), rotated.height()), (3, 2));
+        assert_eq!(rotated.data(), &[6, 5, 4, 3, 2, 1]);
+
+        let rotated = image.rotate(Rotation::ThreeQuarters);
+        assert_eq!((rotated.width(), rotated.height()), (2, 3));
+        assert_eq!(rotated.data(), &[3, 6, 2, 5, 1, 4]);
+
+        assert_eq!(image.rotate(Rotation::None).data(), image.data());
+        assert_eq!(rotated.rotate(Rotation::Quarter).data(), image.data());
+        assert_eq!(Image::new(0, 0).rotate(Rotation::Quarter).data().len(), 0);
+    }
+
+    #[test]
+    fn flip() {
+        let image = Image::from_data(3, 2, vec![1, 2, 3, 4, 5, 6].into_boxed_slice());
+        assert_eq!(image.flip_horizontal().data(), &[3, 2, 1, 6, 5, 4]);
+        assert_eq!(image.flip_vertical().data(), &[4, 5, 6, 1, 2, 3]);
+        assert_eq!(image.flip_horizontal().flip_horizontal().data(), image.data());
+        assert_eq!(Image::new(0, 0).flip_vertical().data().len(), 0);
+    }
+
+    #[test]
+    fn line() {
+        let mut image = Image::from_data(4, 4, vec![0; 16].into_boxed_slice());
+        image.as_roi().line(0, 0, 3, 3, Color::rgb(255, 0, 0));
+        image.as_roi().line(3, 0, 3, 1, Color::rgb(255, 0, 0));
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = if x == y || (x == 3 && y < 2) { RED } else { 0 };
+                assert_eq!(image.data()[y * 4 + x], expected);
+            }
+        }
+
+        //Lines are clipped to the area
+        image.roi(&Rect::new(1, 1, 2, 2)).line(-5, 0, 10, 0, Color::rgb(0, 0, 255));
+        assert_eq!(&image.data()[4 .. 8], &[0, BLUE, BLUE, RED]);
+    }
+
+    #[test]
+    fn outline() {
+        let mut image = Image::from_data(4, 4, vec![0; 16].into_boxed_slice());
+        image.as_roi().outline(&Rect::new(0, 0, 4, 4), Color::rgb(255, 0, 0));
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = if x == 0 || y == 0 || x == 3 || y == 3 { RED } else { 0 };
+                assert_eq!(image.data()[y * 4 + x], expected);
+            }
+        }
+
+        //Each pixel is drawn once, so translucent edges are even
+        let mut image = Image::from_data(3, 3, vec![0xFF000000; 9].into_boxed_slice());
+        image.as_roi().outline(&Rect::new(0, 0, 3, 3), Color::rgba(255, 255, 255, 128));
+        let edge = image.data()[0];
+        assert!(image.data().iter().enumerate().all(|(i, &pixel)| i == 4 || pixel == edge));
+    }
+
+    #[test]
+    fn circle() {
+        let mut image = Image::from_data(9, 9, vec![0xFF000000; 81].into_boxed_slice());
+        image.as_roi().circle(4, 4, 3, Color::rgb(255, 255, 255));
+        let data = image.data();
+        assert_eq!(data[4 * 9 + 4], 0xFFFFFFFF);
+        assert_eq!(data[4 * 9 + 2], 0xFFFFFFFF);
+        assert_eq!(data[0], 0xFF000000);
+        //The edge is partly covered
+        let edge = data[4 * 9 + 7] & 0xFF;
+        assert!(edge > 0 && edge < 0xFF);
+
+        //Circles are clipped to the area
+        image.as_roi().circle(0, 0, 20, Color::rgb(255, 0, 0));
+        image.as_roi().circle(4, 4, 0, Color::rgb(0, 0, 255));
+    }
+
+    #[test]
+    fn polygon() {
+        let mut image = Image::from_data(4, 4, vec![0; 16].into_boxed_slice());
+        image.as_roi().polygon(&[(0, 0), (4, 0), (4, 4), (0, 4)], Color::rgb(255, 0, 0));
+        assert!(image.data().iter().all(|&pixel| pixel == RED));
+
+        let mut image = Image::from_data(4, 4, vec![0; 16].into_boxed_slice());
+        image.as_roi().polygon(&[(0, 0), (4, 0), (0, 4)], Color::rgb(255, 0, 0));
+        //Only the pixels with their centers inside are filled
+        let count = image.data().iter().filter(|&&pixel| pixel == RED).count();
+        assert_eq!(count, 3 + 2 + 1);
+
+        image.as_roi().polygon(&[(-10, -10), (10, -10), (0, 10)], Color::rgb(0, 0, 255));
+        image.as_roi().polygon(&[(0, 0), (1, 1)], Color::rgb(0, 0, 255));
+    }
+
+    #[test]
+    fn draw_text() {
+        let white = Color::rgb(255, 255, 255);
+        let drawn = |image: &Image, left: i32, right: i32| -> bool {
+            (0..16).any(|y| (left..right).any(|x| image.data()[(y * image.width() + x) as usize] != 0))
+        };
+
+        for &(align, left) in [(TextAlign::Left, 0), (TextAlign::Center, 8), (TextAlign::Right, 16)].iter() {
+            let mut image = Image::from_data(24, 16, vec![0; 24 * 16].into_boxed_slice());
+            let width = image.as_roi().draw_text(&mut TextFont::Bitmap(1), "H", white, &Rect::new(0, 0, 24, 16), align);
+            assert_eq!(width, 8);
+            assert!(drawn(&image, left, left + 8));
+            assert!(! drawn(&image, 0, left) && ! drawn(&image, left + 8, 24));
+        }
+
+        //Text is clipped to its rectangle and to the area
+        let mut image = Image::from_data(24, 16, vec![0; 24 * 16].into_boxed_slice());
+        let width = image.roi(&Rect::new(0, 0, 20, 16)).draw_text(&mut TextFont::Bitmap(1), "HHHH", white,
+                                                                  &Rect::new(4, 0, 100, 16), TextAlign::Left);
+        assert_eq!(width, 32);
+        assert!(drawn(&image, 4, 20));
+        assert!(! drawn(&image, 0, 4) && ! drawn(&image, 20, 24));
+
+        image.as_roi().draw_text(&mut TextFont::Bitmap(2), "HH", white, &Rect::new(-100, -100, 8, 8), TextAlign::Center);
+    }
+
+    #[test]
+    fn scaled_blit_stays_inside() {
+        let mut source = Image::from_data(1, 1, vec![BLUE].into_boxed_slice());
+        let mut image = Image::from_data(4, 4, vec![RED; 16].into_boxed_slice());
+        image.roi(&Rect::new(1, 1, 2, 2)).scaled_blit(&source.as_roi(), ResizeFilter::Bilinear);
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = if x >= 1 && x < 3 && y >= 1 && y < 3 { BLUE } else { RED };
+                assert_eq!(image.data()[y * 4 + x], expected);
+            }
+        }
+    }
+}
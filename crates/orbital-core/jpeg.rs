@@ -0,0 +1,483 @@
+use super::{Color, Image};
+
+use std::cmp::{max, min};
+use std::fs::File;
+use std::io::Read;
+
+macro_rules! try_some {
+    ($expr:expr) => (match $expr {
+        Some(value) => value,
+        None => return None
+    })
+}
+
+/// The order of coefficients in an encoded block
+const ZIGZAG: [usize; 64] = [
+     0,  1,  8, 16,  9,  2,  3, 10,
+    17, 24, 32, 25, 18, 11,  4,  5,
+    12, 19, 26, 33, 40, 48, 41, 34,
+    27, 20, 13,  6,  7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36,
+    29, 22, 15, 23, 30, 37, 44, 51,
+    58, 59, 52, 45, 38, 31, 39, 46,
+    53, 60, 61, 54, 47, 55, 62, 63
+];
+
+/// A Huffman table, as lookup of (length, code) to value
+#[derive(Clone)]
+struct Huffman {
+    /// The first code of each length
+    first: [i32; 17],
+    /// The number of codes of each length
+    counts: [i32; 17],
+    /// The index into values of the first code of each length
+    offsets: [i32; 17],
+    values: Vec<u8>,
+}
+
+impl Huffman {
+    fn new(counts: &[u8], values: &[u8]) -> Huffman {
+        let mut huffman = Huffman {
+            first: [0; 17],
+            counts: [0; 17],
+            offsets: [0; 17],
+            values: values.to_vec()
+        };
+
+        let mut code = 0;
+        let mut offset = 0;
+        for length in 1..17 {
+            let count = counts[length - 1] as i32;
+            huffman.first[length] = code;
+            huffman.counts[length] = count;
+            huffman.offsets[length] = offset;
+            code = (code + count) << 1;
+            offset += count;
+        }
+
+        huffman
+    }
+}
+
+/// Reads bits from entropy coded data, most significant bit first, removing stuffed bytes
+struct BitReader<'a> {
+    data: &'a [u8],
+    i: usize,
+    bits: u32,
+    count: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn bit(&mut self) -> Option<u32> {
+        if self.count == 0 {
+            let byte = *try_some!(self.data.get(self.i));
+            if byte == 0xFF {
+                match self.data.get(self.i + 1) {
+                    Some(&0) => self.i += 2,
+                    //A marker ends the data, pad with ones
+                    _ => return Some(1)
+                }
+            } else {
+                self.i += 1;
+            }
+            self.bits = byte as u32;
+            self.count = 8;
+        }
+        self.count -= 1;
+        Some((self.bits >> self.count) & 1)
+    }
+
+    fn bits(&mut self, count: usize) -> Option<u32> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | try_some!(self.bit());
+        }
+        Some(value)
+    }
+
+    /// Read a value with the given number of bits, where values with a leading 0 are negative
+    fn signed(&mut self, count: usize) -> Option<i32> {
+        if count == 0 {
+            return Some(0);
+        }
+        let value = try_some!(self.bits(count)) as i32;
+        if value < 1 << (count - 1) {
+            Some(value - (1 << count) + 1)
+        } else {
+            Some(value)
+        }
+    }
+
+    fn decode(&mut self, huffman: &Huffman) -> Option<u8> {
+        let mut code = 0;
+        for length in 1..17 {
+            code = (code << 1) | try_some!(self.bit()) as i32;
+            if code - huffman.first[length] < huffman.counts[length] {
+                let index = huffman.offsets[length] + code - huffman.first[length];
+                return huffman.values.get(index as usize).map(|value| *value);
+            }
+        }
+        None
+    }
+
+    /// Skip to a restart marker and reset the bit buffer
+    fn restart(&mut self) {
+        self.count = 0;
+        while self.i + 1 < self.data.len() && ! (self.data[self.i] == 0xFF && self.data[self.i + 1] >= 0xD0 && self.data[self.i + 1] <= 0xD7) {
+            self.i += 1;
+        }
+        self.i += 2;
+    }
+}
+
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    quant: usize,
+    dc_table: usize,
+    ac_table: usize,
+    prediction: i32,
+    /// Samples, with the size of the component rounded up to whole MCUs
+    data: Vec<u8>,
+    stride: usize,
+}
+
+/// Inverse discrete cosine transform of a block, storing the samples with level shift
+fn idct(coefficients: &[i32; 64], output: &mut [u8], stride: usize) {
+    let mut cos = [[0.0f32; 8]; 8];
+    for x in 0..8 {
+        for u in 0..8 {
+            let c = if u == 0 { 0.70710678 } else { 1.0 };
+            cos[x][u] = c * (((2 * x + 1) * u) as f32 * 3.14159265 / 16.0).cos();
+        }
+    }
+
+    let mut temp = [0.0f32; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            let mut sum = 0.0;
+            for v in 0..8 {
+                sum += cos[y][v] * coefficients[v * 8 + u] as f32;
+            }
+            temp[y * 8 + u] = sum;
+        }
+    }
+
+    for y in 0..8 {
+        for x in 0..8 {
+            let mut sum = 0.0;
+            for u in 0..8 {
+                sum += cos[x][u] * temp[y * 8 + u];
+            }
+            let value = sum / 4.0 + 128.0;
+            output[y * stride + x] = if value < 0.0 { 0 } else if value > 255.0 { 255 } else { (value + 0.5) as u8 };
+        }
+    }
+}
+
+fn clamp(value: f32) -> u8 {
+    if value < 0.0 {
+        0
+    } else if value > 255.0 {
+        255
+    } else {
+        (value + 0.5) as u8
+    }
+}
+
+pub struct JpegFile;
+
+impl JpegFile {
+    /// Load a JPEG image from given path
+    pub fn from_path(path: &str) -> Image {
+        let mut data: Vec<u8> = Vec::new();
+        if let Ok(mut file) = File::open(path) {
+            let _ = file.read_to_end(&mut data);
+        }
+        JpegFile::from_data(&data)
+    }
+
+    /// Decode a baseline JPEG image, unsupported or corrupt images result in an empty image
+    ///
+    /// Progressive and arithmetic coded images are not supported
+    pub fn from_data(file_data: &[u8]) -> Image {
+        JpegFile::decode(file_data).unwrap_or(Image::new(0, 0))
+    }
+
+    fn decode(file_data: &[u8]) -> Option<Image> {
+        let get = |i: usize| -> u8 {
+            match file_data.get(i) {
+                Some(byte) => *byte,
+                None => 0,
+            }
+        };
+
+        let getw = |i: usize| -> usize { ((get(i) as usize) << 8) + get(i + 1) as usize };
+
+        if get(0) != 0xFF || get(1) != 0xD8 {
+            return None;
+        }
+
+        let mut quant = [[0i32; 64]; 4];
+        let mut dc_tables: Vec<Option<Huffman>> = vec![None, None, None, None];
+        let mut ac_tables: Vec<Option<Huffman>> = vec![None, None, None, None];
+        let mut components: Vec<Component> = Vec::new();
+        let mut width = 0;
+        let mut height = 0;
+        let mut restart_interval = 0;
+        let mut adobe_transform = None;
+
+        let mut i = 2;
+        while i + 4 <= file_data.len() {
+            if get(i) != 0xFF {
+                return None;
+            }
+            let marker = get(i + 1);
+            if marker == 0xFF {
+                i += 1;
+                continue;
+            }
+
+            let len = getw(i + 2);
+            let start = i + 4;
+            let end = i + 2 + len;
+            if end > file_data.len() || len < 2 {
+                return None;
+            }
+
+            match marker {
+                //Baseline and extended sequential frames
+                0xC0 | 0xC1 => {
+                    height = getw(start + 1);
+                    width = getw(start + 3);
+                    let count = get(start + 5) as usize;
+                    for c in 0..count {
+                        let offset = start + 6 + c * 3;
+                        let sampling = get(offset + 1);
+                        components.push(Component {
+                            id: get(offset),
+                            h: max(1, (sampling >> 4) as usize),
+                            v: max(1, (sampling & 0xF) as usize),
+                            quant: (get(offset + 2) & 3) as usize,
+                            dc_table: 0,
+                            ac_table: 0,
+                            prediction: 0,
+                            data: Vec::new(),
+                            stride: 0
+                        });
+                    }
+                },
+                //Progressive, lossless, and arithmetic frames
+                0xC2 ... 0xC3 | 0xC5 ... 0xC7 | 0xC9 ... 0xCB | 0xCD ... 0xCF => return None,
+                //Huffman tables
+                0xC4 => {
+                    let mut offset = start;
+                    while offset < end {
+                        let info = get(offset);
+                        let counts = &file_data[offset + 1 .. min(offset + 17, end)];
+                        let total = counts.iter().fold(0, |total, count| total + *count as usize);
+                        let values = &file_data[min(offset + 17, end) .. min(offset + 17 + total, end)];
+                        if counts.len() < 16 {
+                            return None;
+                        }
+                        let huffman = Huffman::new(counts, values);
+                        if info >> 4 == 0 {
+                            dc_tables[(info & 3) as usize] = Some(huffman);
+                        } else {
+                            ac_tables[(info & 3) as usize] = Some(huffman);
+                        }
+                        offset += 17 + total;
+                    }
+                },
+                //Quantization tables
+                0xDB => {
+                    let mut offset = start;
+                    while offset < end {
+                        let info = get(offset);
+                        let table = &mut quant[(info & 3) as usize];
+                        if info >> 4 == 0 {
+                            for k in 0..64 {
+                                table[ZIGZAG[k]] = get(offset + 1 + k) as i32;
+                            }
+                            offset += 65;
+                        } else {
+                            for k in 0..64 {
+                                table[ZIGZAG[k]] = getw(offset + 1 + k * 2) as i32;
+                            }
+                            offset += 129;
+                        }
+                    }
+                },
+                //Restart interval
+                0xDD => restart_interval = getw(start),
+                //Adobe application segment, which says if the components are YCbCr
+                0xEE => if len >= 12 && &file_data[start .. start + 5] == b"Adobe" {
+                    adobe_transform = Some(get(start + 11));
+                },
+                //Start of scan, the entropy coded data follows the header
+                0xDA => {
+                    if components.is_empty() || width == 0 || height == 0 {
+                        return None;
+                    }
+
+                    let count = get(start) as usize;
+                    let mut scan = Vec::new();
+                    for c in 0..count {
+                        let id = get(start + 1 + c * 2);
+                        let tables = get(start + 2 + c * 2);
+                        let index = try_some!(components.iter().position(|component| component.id == id));
+                        components[index].dc_table = (tables >> 4) as usize & 3;
+                        components[index].ac_table = (tables & 0xF) as usize & 3;
+                        scan.push(index);
+                    }
+
+                    let h_max = components.iter().map(|component| component.h).max().unwrap_or(1);
+                    let v_max = components.iter().map(|component| component.v).max().unwrap_or(1);
+                    let mcus_x = (width + 8 * h_max - 1) / (8 * h_max);
+                    let mcus_y = (height + 8 * v_max - 1) / (8 * v_max);
+
+                    for component in components.iter_mut() {
+                        component.stride = mcus_x * component.h * 8;
+                        component.data = vec![0; component.stride * mcus_y * component.v * 8];
+                        component.prediction = 0;
+                    }
+
+                    let mut reader = BitReader {
+                        data: &file_data[end ..],
+                        i: 0,
+                        bits: 0,
+                        count: 0
+                    };
+
+                    let single = scan.len() == 1;
+                    let (blocks_x, blocks_y) = if single {
+                        //A scan of a single component is not interleaved, and covers only its own blocks
+                        let component = &components[scan[0]];
+                        ((width * component.h / h_max + 7) / 8, (height * component.v / v_max + 7) / 8)
+                    } else {
+                        (mcus_x, mcus_y)
+                    };
+
+                    let mut mcu = 0;
+                    for mcu_y in 0..blocks_y {
+                        for mcu_x in 0..blocks_x {
+                            if restart_interval > 0 && mcu > 0 && mcu % restart_interval == 0 {
+                                reader.restart();
+                                for component in components.iter_mut() {
+                                    component.prediction = 0;
+                                }
+                            }
+                            mcu += 1;
+
+                            for &index in scan.iter() {
+                                let (h, v) = if single { (1, 1) } else { (components[index].h, components[index].v) };
+                                for block_y in 0..v {
+                                    for block_x in 0..h {
+                                        let mut coefficients = [0i32; 64];
+
+                                        let dc = try_some!(dc_tables[components[index].dc_table].as_ref());
+                                        let ac = try_some!(ac_tables[components[index].ac_table].as_ref());
+                                        let table = &quant[components[index].quant];
+
+                                        let size = try_some!(reader.decode(dc)) as usize;
+                                        let diff = try_some!(reader.signed(size));
+                                        components[index].prediction += diff;
+                                        coefficients[0] = components[index].prediction * table[0];
+
+                                        let mut k = 1;
+                                        while k < 64 {
+                                            let symbol = try_some!(reader.decode(ac));
+                                            let run = (symbol >> 4) as usize;
+                                            let size = (symbol & 0xF) as usize;
+                                            if size == 0 {
+                                                if run == 15 {
+                                                    k += 16;
+                                                    continue;
+                                                }
+                                                break;
+                                            }
+                                            k += run;
+                                            if k >= 64 {
+                                                break;
+                                            }
+                                            coefficients[ZIGZAG[k]] = try_some!(reader.signed(size)) * table[ZIGZAG[k]];
+                                            k += 1;
+                                        }
+
+                                        let component = &mut components[index];
+                                        let x = (mcu_x * h + block_x) * 8;
+                                        let y = (mcu_y * v + block_y) * 8;
+                                        let stride = component.stride;
+                                        if x + 8 <= stride && (y + 8) * stride <= component.data.len() {
+                                            idct(&coefficients, &mut component.data[y * stride + x ..], stride);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    //Continue after the entropy coded data
+                    let mut next = end + reader.i;
+                    while next + 1 < file_data.len() && ! (file_data[next] == 0xFF && file_data[next + 1] != 0 && (file_data[next + 1] < 0xD0 || file_data[next + 1] > 0xD7)) {
+                        next += 1;
+                    }
+                    i = next;
+                    continue;
+                },
+                //End of image
+                0xD9 => break,
+                _ => ()
+            }
+
+            i = end;
+        }
+
+        if components.is_empty() || width == 0 || height == 0 {
+            return None;
+        }
+
+        let h_max = components.iter().map(|component| component.h).max().unwrap_or(1);
+        let v_max = components.iter().map(|component| component.v).max().unwrap_or(1);
+
+        //Upsample each component to the full size, nearest neighbor
+        let sample = |component: &Component, x: usize, y: usize| -> u8 {
+            let cx = x * component.h / h_max;
+            let cy = y * component.v / v_max;
+            match component.data.get(cy * component.stride + cx) {
+                Some(value) => *value,
+                None => 0
+            }
+        };
+
+        let ycbcr = match adobe_transform {
+            Some(transform) => transform != 0,
+            None => components.len() == 3
+        };
+
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let color = if components.len() >= 3 {
+                    let c0 = sample(&components[0], x, y) as f32;
+                    let c1 = sample(&components[1], x, y) as f32;
+                    let c2 = sample(&components[2], x, y) as f32;
+                    if ycbcr {
+                        Color::rgb(clamp(c0 + 1.402 * (c2 - 128.0)),
+                                   clamp(c0 - 0.344136 * (c1 - 128.0) - 0.714136 * (c2 - 128.0)),
+                                   clamp(c0 + 1.772 * (c1 - 128.0)))
+                    } else {
+                        Color::rgb(c0 as u8, c1 as u8, c2 as u8)
+                    }
+                } else {
+                    let v = sample(&components[0], x, y);
+                    Color::rgb(v, v, v)
+                };
+                data.push(color.data);
+            }
+        }
+
+        Some(Image::from_data(width as i32, height as i32, data.into_boxed_slice()))
+    }
+}
@@ -0,0 +1,173 @@
+//! Keyboard layouts, which give the characters typed by the keys of the kernel in raw mode
+//!
+//! A keymap is a text file in `KEYMAP_DIR`, such as `/ui/keymaps/de.keymap`, with a line for each key:
+//! the scancode, then the character typed alone, with shift, and with AltGr, such as `0x10 q Q @`. A character is
+//! written as itself, or as `U+XXXX` for a space or a character that is hard to type. A missing character is the one
+//! typed alone. A line starting with `dead` lists the characters typed by dead keys, which combine with the next
+//! key, such as `dead ´ ^`, see the `compose` module. Lines starting with `#` are comments.
+
+use std::fs::File;
+use std::io::Read;
+
+use super::event::{K_EXTENDED, K_ENTER, K_ESC, K_TAB};
+
+/// The directory keymaps are loaded from by name
+pub const KEYMAP_DIR: &'static str = "/ui/keymaps";
+/// The keymap used when none is configured, or the configured one cannot be loaded
+pub const DEFAULT_KEYMAP: &'static str = "us";
+
+/// The US keymap, built in so there is always a keymap to fall back to
+const US_KEYMAP: &'static str = include_str!("../../filesystem/ui/keymaps/us.keymap");
+
+/// The space bar
+const K_SPACE: u8 = 0x39;
+/// The slash of the keypad, without `K_EXTENDED`
+const K_KEYPAD_SLASH: u8 = 0x35;
+
+/// The characters of the keys of a layout
+pub struct Keymap {
+    name: String,
+    /// The characters typed alone, with shift, and with AltGr, by scancode with `K_EXTENDED` for extended keys
+    keys: Vec<[char; 3]>,
+    /// The characters of dead keys
+    dead: Vec<char>,
+}
+
+impl Keymap {
+    /// Parse a keymap, returning `None` if a line is invalid
+    ///
+    /// The keys that are the same on every layout, such as enter and the space bar, do not have to be listed
+    pub fn from_str(name: &str, string: &str) -> Option<Keymap> {
+        let mut keymap = Keymap {
+            name: name.to_string(),
+            keys: vec![['\0'; 3]; 256],
+            dead: Vec::new()
+        };
+        keymap.keys[K_ESC as usize] = ['\x1B'; 3];
+        keymap.keys[K_TAB as usize] = ['\t'; 3];
+        keymap.keys[K_ENTER as usize] = ['\n'; 3];
+        keymap.keys[(K_ENTER | K_EXTENDED) as usize] = ['\n'; 3];
+        keymap.keys[K_SPACE as usize] = [' '; 3];
+        keymap.keys[(K_KEYPAD_SLASH | K_EXTENDED) as usize] = ['/'; 3];
+
+        for line in string.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            if line.starts_with("dead") {
+                parts.next();
+                for part in parts {
+                    match parse_char(part) {
+                        Some(c) => keymap.dead.push(c),
+                        None => return None
+                    }
+                }
+                continue;
+            }
+
+            let scancode = match parts.next().and_then(parse_scancode) {
+                Some(scancode) => scancode,
+                None => return None
+            };
+            let normal = match parts.next().and_then(parse_char) {
+                Some(c) => c,
+                None => return None
+            };
+            let shift = match parts.next() {
+                Some(part) => match parse_char(part) {
+                    Some(c) => c,
+                    None => return None
+                },
+                None => normal
+            };
+            let altgr = match parts.next() {
+                Some(part) => match parse_char(part) {
+                    Some(c) => c,
+                    None => return None
+                },
+                None => normal
+            };
+            keymap.keys[scancode as usize] = [normal, shift, altgr];
+        }
+
+        Some(keymap)
+    }
+
+    /// The built in US keymap
+    pub fn us() -> Keymap {
+        Keymap::from_str(DEFAULT_KEYMAP, US_KEYMAP).expect("orbital: invalid built in keymap")
+    }
+
+    /// Load a keymap from `KEYMAP_DIR` by its name, such as `de`
+    pub fn from_name(name: &str) -> Option<Keymap> {
+        if name.is_empty() || ! name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+            return None;
+        }
+
+        let path = format!("{}/{}.keymap", KEYMAP_DIR, name);
+        let mut string = String::new();
+        match File::open(&path).and_then(|mut file| file.read_to_string(&mut string)) {
+            Ok(_) => {
+                let keymap = Keymap::from_str(name, &string);
+                if keymap.is_none() {
+                    println!("orbital: invalid keymap '{}'", path);
+                }
+                keymap
+            },
+            Err(err) => {
+                println!("orbital: failed to read keymap '{}': {}", path, err);
+                None
+            }
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Check if a character is typed by a dead key
+    pub fn is_dead(&self, character: char) -> bool {
+        self.dead.contains(&character)
+    }
+
+    /// The character typed by a key, `'\0'` if it types none
+    ///
+    /// Caps lock works like shift for keys that type a lowercase and an uppercase letter, so it leaves the digits
+    /// and punctuation as they are
+    pub fn character(&self, scancode: u8, shift: bool, altgr: bool, caps_lock: bool) -> char {
+        let characters = self.keys[scancode as usize];
+        let letter = characters[0].is_lowercase() && characters[1].is_uppercase();
+        if altgr {
+            characters[2]
+        } else if shift != (caps_lock && letter) {
+            characters[1]
+        } else {
+            characters[0]
+        }
+    }
+}
+
+/// Parse a scancode, in hexadecimal such as `0x10`
+fn parse_scancode(part: &str) -> Option<u8> {
+    if part.starts_with("0x") {
+        u8::from_str_radix(&part[2..], 16).ok()
+    } else {
+        None
+    }
+}
+
+/// Parse a character, written as itself or as `U+XXXX`
+fn parse_char(part: &str) -> Option<char> {
+    let mut chars = part.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => if part.starts_with("U+") {
+            u32::from_str_radix(&part[2..], 16).ok().and_then(::std::char::from_u32)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,2830 @@
+#![crate_name="orbital_core"]
+#![crate_type="lib"]
+#![feature(asm)]
+#![feature(const_fn)]
+
+//! The compositor of Orbital, the display manager, without the hardware it runs on
+//!
+//! Monitors draw to a `Display` and input comes from an `Input`, so the same window management and compositing
+//! can run on the displays of the kernel, in memory for benchmarks, or on another backend.
+
+extern crate core;
+extern crate system;
+
+use std::char;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::mem;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use system::error::{Error, Result, EBADF, EINVAL, ENOENT};
+use system::path;
+use system::scheme::{Packet, Scheme};
+use system::syscall::{O_NONBLOCK, SYS_READ};
+
+pub use self::backend::{Accel, Blitter, Display, Input, MemoryDisplay};
+pub use self::color::Color;
+pub use self::config::Config;
+pub use self::corner::{Corner, CornerAction};
+pub use self::cursor::{CursorKind, CursorTheme};
+pub use self::event::{Event, EventOption};
+pub use self::focus::FocusMode;
+pub use self::font::{Font, TextAlign, TextFont, VectorFont};
+pub use self::gamma::{Calibration, NightLight};
+pub use self::image::{Image, ImageRoi, ResizeFilter};
+pub use self::keymap::Keymap;
+pub use self::monitor::{Monitor, Rotation};
+pub use self::rect::Rect;
+pub use self::theme::{Theme, TitleButton};
+pub use self::repeat::KeyRepeat;
+pub use self::wallpaper::WallpaperMode;
+pub use self::window::{Window, WindowQuery};
+
+use self::animation::{Animation, AnimationKind, FRAME_MS};
+use self::barrier::{barrier, resist};
+use self::clipboard::{Clipboard, ClipboardHandle, ClipboardKind, THUMBNAIL_HEIGHT};
+use self::clock::Date;
+use self::compose::Composer;
+use self::control::{Control, ControlCommand, WindowList};
+use self::record::Recorder;
+use self::repeat::{is_modifier, HeldKey};
+use self::screenshot::Screenshot;
+use self::shadow::Shadow;
+use self::wallpaper::{Wallpaper, BACKGROUND_COLOR};
+use self::event::{EVENT_DISPLAY, EVENT_KEY, EVENT_MOUSE, EVENT_MOUSE_RELATIVE, EVENT_SCROLL, EVENT_TOUCH, DisplayEvent, MouseEvent, QuitEvent, TouchEvent, TouchPhase};
+
+pub mod animation;
+pub mod backend;
+pub mod barrier;
+pub mod bench;
+pub mod bmp;
+pub mod clipboard;
+pub mod clock;
+pub mod color;
+pub mod compose;
+pub mod config;
+pub mod control;
+pub mod corner;
+pub mod cursor;
+#[path="../../kernel/common/event.rs"]
+pub mod event;
+pub mod focus;
+pub mod font;
+pub mod gamma;
+pub mod image;
+pub mod jpeg;
+pub mod keymap;
+pub mod monitor;
+pub mod pixel;
+pub mod png;
+pub mod rect;
+pub mod record;
+pub mod repeat;
+pub mod screenshot;
+pub mod shadow;
+pub mod stream;
+pub mod surface;
+pub mod theme;
+pub mod ttf;
+pub mod vnc;
+pub mod wallpaper;
+pub mod window;
+
+/// Number of virtual desktops
+const WORKSPACES: usize = 4;
+/// Percent of brightness changed by the brightness keys, which also never dim the backlight below it
+const BRIGHTNESS_STEP: i32 = 10;
+
+const SWITCHER_COLOR: Color = Color::rgb(40, 45, 57);
+const SWITCHER_HIGHLIGHT_COLOR: Color = Color::rgb(80, 86, 102);
+const SWITCHER_TEXT_COLOR: Color = Color::rgb(235, 241, 255);
+const SWITCHER_WIDTH: i32 = 400;
+const SWITCHER_ROW_HEIGHT: i32 = 20;
+
+/// The V key, which shows the clipboard history with Super
+const K_V: u8 = 0x2F;
+
+const CLOCK_COLOR: Color = SWITCHER_COLOR;
+const CLOCK_TEXT_COLOR: Color = SWITCHER_TEXT_COLOR;
+/// Space around the text of the clock and calendar
+const CLOCK_PADDING: i32 = 4;
+/// Milliseconds to wait before reading the clock again if it could not be read
+const CLOCK_RETRY: u32 = 60000;
+/// Milliseconds between pings of the clients of windows
+const PING_INTERVAL: u32 = 2000;
+/// Seconds a client has to answer a ping before its window is shown as not responding
+const PING_TIMEOUT: u64 = 5;
+/// Dims the windows of clients that are not responding
+const NOT_RESPONDING_COLOR: Color = Color::rgba(0, 0, 0, 96);
+
+/// Height of the tray of minimized windows
+const TRAY_HEIGHT: i32 = 20;
+/// Width of each window in the tray
+const TRAY_ENTRY_WIDTH: i32 = 160;
+
+/// Draw a line of text with the bitmap font, enlarged by a scale factor and clipped to a rectangle
+fn draw_text(image: &mut Image, clip: &Rect, x: i32, y: i32, text: &str, color: Color, scale: i32) {
+    let layout = Rect::new(x - clip.left(), y - clip.top(), clip.right() - x, clip.bottom() - y);
+    image.roi(clip).draw_text(&mut TextFont::Bitmap(scale), text, color, &layout, TextAlign::Left);
+}
+
+/// Place the monitors side by side on the desktop, as they are rotated, returning the size of the desktop
+fn arrange(monitors: &mut [Monitor]) -> (i32, i32) {
+    let mut width = 0;
+    let mut height = 0;
+    for monitor in monitors.iter_mut() {
+        //Displays stay side by side when they are rotated
+        let rotation = monitor.rotation;
+        monitor.set_rotation(rotation, width);
+        width = cmp::max(width, monitor.rect.right());
+        height = cmp::max(height, monitor.rect.bottom());
+    }
+    (width, height)
+}
+
+/// Escape an argument for a path, such as a title, see `system::path::escape`
+fn escape_arg(arg: &str) -> String {
+    let mut buf = vec![0; arg.len() * 3];
+    let len = path::escape(arg, &mut buf).unwrap_or(0);
+    String::from_utf8_lossy(&buf[.. len]).into_owned()
+}
+
+/// Decode an argument from a path, keeping it as it is if it is not escaped correctly
+fn unescape_arg(arg: &str) -> String {
+    let mut buf = vec![0; arg.len()];
+    match path::unescape(arg, &mut buf) {
+        Ok(len) => String::from_utf8_lossy(&buf[.. len]).into_owned(),
+        Err(_) => arg.to_string()
+    }
+}
+
+fn schedule(redraws: &mut Vec<Rect>, request: Rect) {
+    if request.is_empty() {
+        return;
+    }
+
+    let mut push = true;
+    for mut rect in redraws.iter_mut() {
+        //If contained, ignore new redraw request
+        let container = rect.container(&request);
+        if container.area() <= rect.area() + request.area() {
+            *rect = container;
+            push = false;
+            break;
+        }
+    }
+
+    if push {
+        redraws.push(request);
+    }
+}
+
+/// The state of the display manager, which is also the scheme windows are opened with
+pub struct OrbitalScheme {
+    start: Instant,
+    monitors: Vec<Monitor>,
+    image: Image,
+    /// Fills and copies for compositing, using the blitter of the first display that has one
+    blitter: Blitter,
+    wallpaper: Wallpaper,
+    cursors: CursorTheme,
+    cursor_kind: CursorKind,
+    cursor_frame: usize,
+    /// The cursor, enlarged for the display it is on
+    cursor_scaled: Image,
+    /// The point of the cursor image at the pointer, enlarged with it
+    cursor_hotspot: (i32, i32),
+    cursor_scale: i32,
+    cursor_hidden: bool,
+    font: Option<VectorFont>,
+    /// The look of window decorations
+    theme: Theme,
+    shadow: Shadow,
+    /// The area of the shadow of the focused window, as last drawn
+    shadow_rect: Rect,
+    /// The strips of the tray of minimized windows, with the windows in them, as last drawn
+    tray: Vec<(Rect, Vec<(usize, Rect)>)>,
+    cursor_x: i32,
+    cursor_y: i32,
+    dragging: bool,
+    hot_corners: Vec<(Corner, CornerAction)>,
+    /// The hot corner the cursor is in, so its action runs once when it is entered
+    corner: Option<Corner>,
+    /// The monitor the window switcher is shown on
+    switcher: Option<Rect>,
+    /// The monitor the clipboard history is shown on
+    clipboard_popup: Option<Rect>,
+    /// Hide the windows of the active workspace
+    show_desktop: bool,
+    /// The corner of the first display the clock is shown in
+    clock: Option<Corner>,
+    clock_date: Option<Date>,
+    /// Show the calendar of the month below the clock
+    calendar: bool,
+    night_light: Option<NightLight>,
+    /// The color temperature of the night light while it is on
+    night: Option<u32>,
+    /// How long without input before the displays are blanked
+    blank_timeout: Option<Duration>,
+    last_input: Instant,
+    blanked: bool,
+    /// The image shown while the displays are blanked
+    screensaver: Wallpaper,
+    /// The left button was down at the previous mouse event
+    mouse_down: bool,
+    /// The window receiving each touch contact, or `None` if it is emulating the pointer
+    touches: BTreeMap<u32, Option<usize>>,
+    /// The contact emulating the pointer
+    touch_pointer: Option<u32>,
+    drag_x: i32,
+    drag_y: i32,
+    /// How far the window being dragged is held back from the cursor by edge resistance
+    drag_held_x: i32,
+    drag_held_y: i32,
+    /// The pixels a dragged window is pushed past the edge of a monitor before it leaves it
+    edge_resistance: i32,
+    /// The pixels the cursor is pushed past the seam between monitors before it crosses it
+    pointer_barrier: i32,
+    /// Animate windows opening and being minimized
+    animate: bool,
+    /// The animations running
+    animations: Vec<Animation>,
+    /// A key repeat, animation, stream frame, or animated cursor was added since the timers last looked, see
+    /// `take_deadline_added`
+    deadline_added: bool,
+    ctrl: bool,
+    shift: bool,
+    super_key: bool,
+    /// The right alt key is held, which types the third character of keys
+    altgr: bool,
+    caps_lock: bool,
+    /// The characters of keys from the kernel in raw mode
+    keymap: Keymap,
+    /// Combines dead keys and compose sequences into characters
+    composer: Composer,
+    /// Repeat held keys, `None` leaves repeating to the keyboard
+    key_repeat: Option<KeyRepeat>,
+    held_key: Option<HeldKey>,
+    workspace: usize,
+    next_id: isize,
+    /// The serial of the next ping sent to clients
+    next_ping: u32,
+    /// The offset of the next automatically placed window, in logical pixels
+    next_x: i32,
+    next_y: i32,
+    order: VecDeque<usize>,
+    windows: BTreeMap<usize, Window>,
+    controls: BTreeMap<usize, Control>,
+    window_lists: BTreeMap<usize, WindowList>,
+    clipboard: Clipboard,
+    clipboard_handles: BTreeMap<usize, ClipboardHandle>,
+    screenshots: BTreeMap<usize, Screenshot>,
+    recorders: BTreeMap<usize, Recorder>,
+    /// The windows each wait handle is waiting on, see `OrbitalScheme::ready_windows`
+    waits: BTreeMap<usize, Vec<usize>>,
+    /// The focused window, as last sent to the window lists
+    last_focus: Option<usize>,
+    focus_mode: FocusMode,
+    /// The window focused by the cursor when focus follows the mouse, which may be behind the frontmost window
+    pointer_focus: Option<usize>,
+    redraws: Vec<Rect>,
+    todo: Vec<Packet>
+}
+
+impl OrbitalScheme {
+    pub fn new(mut monitors: Vec<Monitor>, config: &Config) -> OrbitalScheme {
+        for (i, monitor) in monitors.iter_mut().enumerate() {
+            monitor.scale = config.display_scale(i);
+            monitor.calibration = config.display_calibration(i);
+            monitor.update_ramp(None);
+            monitor.rotation = config.display_rotation(i);
+        }
+        let (width, height) = arrange(&mut monitors);
+
+        let mut font = VectorFont::from_path(&config.font);
+        if let Some(ref mut font) = font {
+            for path in config.font_fallbacks.iter() {
+                if ! font.add_fallback(path) {
+                    println!("orbital: failed to load fallback font '{}'", path);
+                }
+            }
+        }
+
+        let accel = monitors.iter_mut().filter_map(|monitor| monitor.display.accel()).next();
+
+        let cursors = CursorTheme::from_path(&config.cursor_theme, &config.cursor);
+        let cursor_scale = monitors.get(0).map_or(1, |monitor| monitor.scale);
+        let cursor_scaled = cursors.image(CursorKind::Arrow, 0).scale(cursor_scale);
+        let (hotspot_x, hotspot_y) = cursors.hotspot(CursorKind::Arrow, 0);
+
+        OrbitalScheme {
+            start: Instant::now(),
+            monitors: monitors,
+            image: Image::new(width, height),
+            blitter: Blitter::new(accel),
+            wallpaper: Wallpaper::new(Image::from_path(&config.background), config.background_mode),
+            cursors: cursors,
+            cursor_kind: CursorKind::Arrow,
+            cursor_frame: 0,
+            cursor_scaled: cursor_scaled,
+            cursor_hotspot: (hotspot_x * cursor_scale, hotspot_y * cursor_scale),
+            cursor_scale: cursor_scale,
+            cursor_hidden: false,
+            font: font,
+            theme: if config.theme.is_empty() { Theme::new() } else { Theme::from_path(&config.theme) },
+            shadow: Shadow::new(),
+            shadow_rect: Rect::default(),
+            tray: Vec::new(),
+            cursor_x: 0,
+            cursor_y: 0,
+            dragging: false,
+            hot_corners: config.hot_corners.clone(),
+            corner: None,
+            switcher: None,
+            clipboard_popup: None,
+            show_desktop: false,
+            clock: config.clock,
+            clock_date: None,
+            calendar: false,
+            night_light: config.night_light,
+            night: None,
+            blank_timeout: config.blank.map(|seconds| Duration::new(seconds as u64, 0)),
+            last_input: Instant::now(),
+            blanked: false,
+            screensaver: Wallpaper::new(Image::from_path(&config.screensaver), WallpaperMode::Center),
+            mouse_down: false,
+            touches: BTreeMap::new(),
+            touch_pointer: None,
+            drag_x: 0,
+            drag_y: 0,
+            drag_held_x: 0,
+            drag_held_y: 0,
+            edge_resistance: config.edge_resistance,
+            pointer_barrier: config.pointer_barrier,
+            animate: config.animations,
+            animations: Vec::new(),
+            deadline_added: false,
+            ctrl: false,
+            shift: false,
+            super_key: false,
+            altgr: false,
+            caps_lock: false,
+            keymap: Keymap::from_name(&config.keymap).unwrap_or_else(Keymap::us),
+            composer: Composer::new(config.compose_key),
+            key_repeat: config.key_repeat,
+            held_key: None,
+            workspace: 0,
+            next_id: 1,
+            next_ping: 0,
+            next_x: 20,
+            next_y: 20,
+            order: VecDeque::new(),
+            windows: BTreeMap::new(),
+            controls: BTreeMap::new(),
+            window_lists: BTreeMap::new(),
+            clipboard: Clipboard::new(config.clipboard_history),
+            clipboard_handles: BTreeMap::new(),
+            screenshots: BTreeMap::new(),
+            recorders: BTreeMap::new(),
+            waits: BTreeMap::new(),
+            last_focus: None,
+            focus_mode: config.focus_mode,
+            pointer_focus: None,
+            redraws: vec![Rect::new(0, 0, width, height)],
+            todo: Vec::new()
+        }
+    }
+
+    fn cursor_rect(&self) -> Rect {
+        if self.cursor_hidden {
+            Rect::new(self.cursor_x, self.cursor_y, 0, 0)
+        } else {
+            //The image is placed with its hotspot at the pointer, which is the point windows are hit tested at
+            let (hotspot_x, hotspot_y) = self.cursor_hotspot;
+            Rect::new(self.cursor_x - hotspot_x, self.cursor_y - hotspot_y, self.cursor_scaled.width(), self.cursor_scaled.height())
+        }
+    }
+
+    /// The focused window, if it has grabbed the pointer
+    fn grabbed(&self) -> Option<usize> {
+        if self.show_desktop {
+            return None;
+        }
+        self.focused().and_then(|id| match self.windows.get(&id) {
+            Some(window) if window.grab => Some(id),
+            _ => None
+        })
+    }
+
+    /// Window ids from front to back, with input panels above popups, and popups above other windows
+    fn stacking(&self) -> Vec<usize> {
+        let mut panels = Vec::new();
+        let mut popups = Vec::new();
+        let mut windows = Vec::new();
+        for id in self.order.iter() {
+            match self.windows.get(&id) {
+                Some(window) if window.panel => panels.push(*id),
+                Some(window) if window.popup => popups.push(*id),
+                _ => windows.push(*id)
+            }
+        }
+        panels.extend(popups);
+        panels.extend(windows);
+        panels
+    }
+
+    /// The frontmost window on the active workspace with contents at a point
+    fn window_at(&self, x: i32, y: i32) -> Option<usize> {
+        if ! self.show_desktop {
+            for id in self.stacking().iter() {
+                if let Some(window) = self.windows.get(&id) {
+                    if window.workspace == self.workspace && ! window.minimized && window.rect().contains(x, y) {
+                        return Some(*id);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// The input panel at a point
+    fn panel_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.window_at(x, y).and_then(|id| match self.windows.get(&id) {
+            Some(window) if window.panel => Some(id),
+            _ => None
+        })
+    }
+
+    /// Place an input panel across the bottom of the display it is on, resizing it to the width of the display
+    fn dock_panel(&mut self, id: usize) {
+        let monitor_rect = match self.windows.get(&id) {
+            Some(window) => self.monitor_rect(window.x, window.y),
+            None => return
+        };
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            schedule(&mut self.redraws, window.rect());
+            let height = window.height();
+            window.resize(monitor_rect.width(), height);
+            window.x = monitor_rect.left();
+            window.y = monitor_rect.bottom() - window.height();
+            schedule(&mut self.redraws, window.rect());
+        }
+    }
+
+    /// The frontmost popup on the active workspace at a point
+    fn popup_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.window_at(x, y).and_then(|id| match self.windows.get(&id) {
+            Some(window) if window.popup => Some(id),
+            _ => None
+        })
+    }
+
+    /// Ask the popups on the active workspace to close, returning true if there were any
+    fn close_popups(&mut self) -> bool {
+        let mut closed = false;
+        for (_, window) in self.windows.iter_mut() {
+            if window.popup && window.workspace == self.workspace && ! window.minimized {
+                window.event(QuitEvent.to_event());
+                closed = true;
+            }
+        }
+        closed
+    }
+
+    /// The cursor is hidden by the window that grabbed the pointer, or the window under it
+    fn cursor_hidden(&self) -> bool {
+        let id = self.grabbed().or(self.window_at(self.cursor_x, self.cursor_y));
+        id.and_then(|id| self.windows.get(&id)).map_or(false, |window| window.hide_cursor)
+    }
+
+    /// The cursor shape for what is under the pointer
+    fn cursor_kind(&self) -> CursorKind {
+        if self.dragging {
+            //Show where the window will snap when it is released
+            let screen_rect = self.monitor_rect(self.cursor_x, self.cursor_y);
+            return if self.cursor_y <= screen_rect.top() {
+                CursorKind::ResizeVertical
+            } else if self.cursor_x <= screen_rect.left() || self.cursor_x >= screen_rect.right() - 1 {
+                CursorKind::ResizeHorizontal
+            } else {
+                CursorKind::Arrow
+            };
+        }
+
+        for id in self.stacking().iter() {
+            if let Some(window) = self.windows.get(&id) {
+                if window.workspace != self.workspace || window.minimized || self.show_desktop {
+                    //Ignore windows on other workspaces
+                } else if window.rect().contains(self.cursor_x, self.cursor_y) {
+                    return if window.busy() { CursorKind::Busy } else { window.cursor };
+                } else if window.title_rect().contains(self.cursor_x, self.cursor_y) {
+                    return CursorKind::Arrow;
+                }
+            }
+        }
+
+        CursorKind::Arrow
+    }
+
+    /// Change the cursor for what is under it, animating it and enlarging it for the display it is on
+    ///
+    /// Animated cursors advance as events are received, and with `update_cursor_animation` while the pointer is still
+    fn update_cursor(&mut self) {
+        let kind = self.cursor_kind();
+        let elapsed = self.start.elapsed();
+        let time = elapsed.as_secs() * 1000 + (elapsed.subsec_nanos() / 1000000) as u64;
+        let frame = self.cursors.frame(kind, time);
+        if kind != self.cursor_kind && self.cursors.next_frame(kind, time).is_some() {
+            self.deadline_added = true;
+        }
+        let scale = self.monitor_scale(self.cursor_x, self.cursor_y);
+        let hidden = self.cursor_hidden();
+        if kind != self.cursor_kind || frame != self.cursor_frame || scale != self.cursor_scale || hidden != self.cursor_hidden {
+            let cursor_rect = self.cursor_rect();
+            schedule(&mut self.redraws, cursor_rect);
+
+            self.cursor_hidden = hidden;
+            self.cursor_kind = kind;
+            self.cursor_frame = frame;
+            self.cursor_scale = scale;
+            self.cursor_scaled = self.cursors.image(kind, frame).scale(scale);
+            let (hotspot_x, hotspot_y) = self.cursors.hotspot(kind, frame);
+            self.cursor_hotspot = (hotspot_x * scale, hotspot_y * scale);
+
+            let cursor_rect = self.cursor_rect();
+            schedule(&mut self.redraws, cursor_rect);
+        }
+    }
+
+    fn screen_rect(&self) -> Rect {
+        Rect::new(0, 0, self.image.width(), self.image.height())
+    }
+
+    /// The rectangle of the monitor containing a point, or of the first monitor
+    fn monitor_rect(&self, x: i32, y: i32) -> Rect {
+        for monitor in self.monitors.iter() {
+            if monitor.rect.contains(x, y) {
+                return monitor.rect;
+            }
+        }
+        self.monitors.get(0).map_or(self.screen_rect(), |monitor| monitor.rect)
+    }
+
+    /// The scale factor of the monitor containing a point, or of the first monitor
+    fn monitor_scale(&self, x: i32, y: i32) -> i32 {
+        for monitor in self.monitors.iter() {
+            if monitor.rect.contains(x, y) {
+                return monitor.scale;
+            }
+        }
+        self.monitors.get(0).map_or(1, |monitor| monitor.scale)
+    }
+
+    /// Update the scale factor of a window to that of the monitor it was moved to
+    fn update_window_scale(&mut self, id: usize) {
+        let scale = match self.windows.get(&id) {
+            Some(window) => self.monitor_scale(window.x, window.y),
+            None => return
+        };
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            schedule(&mut self.redraws, window.title_rect());
+            schedule(&mut self.redraws, window.rect());
+            window.set_scale(scale);
+            schedule(&mut self.redraws, window.title_rect());
+            schedule(&mut self.redraws, window.rect());
+        }
+    }
+
+    /// Keep a point on a monitor, so the cursor can move between monitors of different sizes
+    fn clamp_point(&self, x: i32, y: i32) -> (i32, i32) {
+        let mut closest: Option<Rect> = None;
+        for monitor in self.monitors.iter() {
+            if monitor.rect.contains(x, y) {
+                return (x, y);
+            }
+            if x >= monitor.rect.left() && x <= monitor.rect.right() {
+                closest = Some(monitor.rect);
+            }
+        }
+
+        if let Some(rect) = closest {
+            (x, cmp::max(rect.top(), cmp::min(rect.bottom(), y)))
+        } else {
+            (x, y)
+        }
+    }
+
+    /// Check if a window can take focus, popups and input panels do not
+    fn focusable(&self, window: &Window) -> bool {
+        window.workspace == self.workspace && ! window.minimized && ! window.popup && ! window.panel
+    }
+
+    /// The focused window is the frontmost window on the active workspace, or the window the cursor focused when
+    /// focus follows the mouse
+    fn focused(&self) -> Option<usize> {
+        if let Some(id) = self.pointer_focus {
+            if self.windows.get(&id).map_or(false, |window| self.focusable(window)) {
+                return Some(id);
+            }
+        }
+        for id in self.order.iter() {
+            if let Some(window) = self.windows.get(&id) {
+                if self.focusable(window) {
+                    return Some(*id);
+                }
+            }
+        }
+        None
+    }
+
+    /// Focus the window under the cursor when focus follows the mouse, raising it if the mode does
+    fn focus_pointer(&mut self) {
+        if self.focus_mode == FocusMode::Click {
+            return;
+        }
+
+        let id = match self.window_at(self.cursor_x, self.cursor_y) {
+            Some(id) if self.windows.get(&id).map_or(false, |window| self.focusable(window)) && ! self.blocked(id) => id,
+            _ => return
+        };
+        let focused = self.focused();
+        if focused == Some(id) {
+            return;
+        }
+
+        if self.focus_mode == FocusMode::MouseRaise {
+            self.raise(id);
+        } else {
+            for focus_id in focused.iter().chain(Some(id).iter()) {
+                if let Some(window) = self.windows.get(focus_id) {
+                    schedule(&mut self.redraws, window.title_rect());
+                }
+            }
+        }
+        self.pointer_focus = Some(id);
+    }
+
+    /// Change the brightness of the backlight by a number of percent
+    fn step_brightness(&mut self, step: i32) {
+        for monitor in self.monitors.iter_mut() {
+            if let Some(percent) = monitor.display.brightness() {
+                let percent = cmp::max(BRIGHTNESS_STEP, cmp::min(100, percent as i32 + step));
+                monitor.display.set_brightness(percent as u8);
+                //There is one backlight, the panel of a laptop
+                break;
+            }
+        }
+    }
+
+    fn switch_workspace(&mut self, workspace: usize) {
+        if workspace < WORKSPACES && workspace != self.workspace {
+            self.workspace = workspace;
+            self.show_desktop = false;
+            //Input panels are on every workspace
+            for (_, window) in self.windows.iter_mut() {
+                if window.panel {
+                    window.workspace = workspace;
+                }
+            }
+            let screen_rect = self.screen_rect();
+            schedule(&mut self.redraws, screen_rect);
+        }
+    }
+
+    /// Bring a window to the front and focus it, redrawing the window that loses focus
+    fn raise(&mut self, id: usize) {
+        if let Some(focused) = self.focused() {
+            if let Some(window) = self.windows.get(&focused) {
+                schedule(&mut self.redraws, window.title_rect());
+                schedule(&mut self.redraws, window.rect());
+            }
+        }
+        self.pointer_focus = None;
+        self.order.retain(|&e| e != id);
+        self.order.push_front(id);
+        if let Some(window) = self.windows.get(&id) {
+            schedule(&mut self.redraws, window.title_rect());
+            schedule(&mut self.redraws, window.rect());
+        }
+
+        //Modal children stay above their parent
+        for child in self.modal_children(id) {
+            self.raise(child);
+        }
+    }
+
+    /// The modal windows whose parent is a window
+    fn modal_children(&self, id: usize) -> Vec<usize> {
+        let mut children = Vec::new();
+        for (child, window) in self.windows.iter() {
+            if window.modal && window.parent == Some(id) {
+                children.push(*child);
+            }
+        }
+        children
+    }
+
+    /// Input to a window is blocked while it has a modal child
+    fn blocked(&self, id: usize) -> bool {
+        ! self.modal_children(id).is_empty()
+    }
+
+    /// Make a window the child of another, refusing a parent that would make a cycle
+    fn set_parent(&mut self, id: usize, parent: usize) -> Result<usize> {
+        let mut ancestor = Some(parent);
+        while let Some(ancestor_id) = ancestor {
+            if ancestor_id == id {
+                return Err(Error::new(EINVAL));
+            }
+            ancestor = match self.windows.get(&ancestor_id) {
+                Some(window) => window.parent,
+                None => return Err(Error::new(EBADF))
+            };
+        }
+
+        let modal = match self.windows.get_mut(&id) {
+            Some(mut window) => {
+                window.parent = Some(parent);
+                window.modal
+            },
+            None => return Err(Error::new(EBADF))
+        };
+        if modal {
+            self.raise(id);
+        }
+        Ok(0)
+    }
+
+    /// Change the flags of a window from a flags write, see `Window::set_flags` and `Window::is_flags_write`
+    ///
+    /// The `f` flag makes the window cover its display without decorations, and leaving it out restores the window.
+    /// The flags can be followed by a command: `F,/icon` makes the next write to the window set its icon, from BMP,
+    /// PNG, or JPEG data. `F,/geometry` makes the next read return where the window was placed, see
+    /// `Window::geometry`, `F,/scale` makes it return the scale factor of the display, to render at its resolution,
+    /// and `F,/id` makes it return the id of the window. `F,m/parent/3` makes the window a child of the window with
+    /// the id 3, so with the `m` flag it is a modal dialog for it.
+    fn set_window_flags(&mut self, id: usize, control: &str) -> Result<()> {
+        let mut parts = control.split("/");
+        let flags = parts.next().unwrap_or("");
+        let command = parts.next().unwrap_or("");
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            let translucent = window.translucent;
+            window.set_flags(flags);
+            if window.translucent != translucent && window.workspace == self.workspace {
+                schedule(&mut self.redraws, window.rect());
+            }
+            match command {
+                "icon" => window.icon_pending = true,
+                "geometry" => window.query = Some(WindowQuery::Geometry),
+                "scale" => window.query = Some(WindowQuery::Scale),
+                "id" => window.query = Some(WindowQuery::Id(id)),
+                _ => ()
+            }
+        } else {
+            return Err(Error::new(EBADF));
+        }
+        self.set_fullscreen(id, flags.contains('f'));
+        if command == "parent" {
+            match parts.next().unwrap_or("").parse::<usize>() {
+                Ok(parent) => {
+                    try!(self.set_parent(id, parent));
+                },
+                Err(_) => return Err(Error::new(EINVAL))
+            }
+        }
+        self.update_cursor();
+        Ok(())
+    }
+
+    /// Restore a window, switch to its workspace, and bring it to the front
+    fn activate(&mut self, id: usize) -> Result<usize> {
+        let (workspace, restored) = match self.windows.get_mut(&id) {
+            Some(mut window) => {
+                let restored = window.minimized;
+                window.minimized = false;
+                (window.workspace, restored)
+            },
+            None => return Err(Error::new(EBADF))
+        };
+        if restored {
+            self.animations.retain(|animation| animation.window != id);
+            self.notify(&format!("restore/{}", id));
+        }
+
+        self.switch_workspace(workspace);
+        if self.show_desktop {
+            self.show_desktop = false;
+            let screen_rect = self.screen_rect();
+            schedule(&mut self.redraws, screen_rect);
+        }
+        self.raise(id);
+        Ok(0)
+    }
+
+    /// Make a window cover the display it is on, or restore it
+    fn set_fullscreen(&mut self, id: usize, fullscreen: bool) {
+        let display = match self.windows.get(&id) {
+            Some(window) if window.fullscreen() != fullscreen => if fullscreen {
+                let rect = window.rect();
+                Some(self.monitor_rect(rect.left() + rect.width()/2, rect.top() + rect.height()/2))
+            } else {
+                None
+            },
+            _ => return
+        };
+
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            schedule(&mut self.redraws, window.title_rect());
+            schedule(&mut self.redraws, window.rect());
+            window.set_fullscreen(display);
+            schedule(&mut self.redraws, window.title_rect());
+            schedule(&mut self.redraws, window.rect());
+        }
+        if fullscreen {
+            self.raise(id);
+        }
+    }
+
+    /// Hide a window until it is activated
+    fn minimize(&mut self, id: usize) -> Result<usize> {
+        let frame = match self.windows.get_mut(&id) {
+            Some(mut window) => if ! window.minimized {
+                schedule(&mut self.redraws, window.title_rect());
+                schedule(&mut self.redraws, window.rect());
+                window.minimized = true;
+                window.frame_rect()
+            } else {
+                return Ok(0);
+            },
+            None => return Err(Error::new(EBADF))
+        };
+        if self.animate {
+            //Shrink the window to its place in the tray, or the middle of the bottom of its monitor if it is full
+            let mut to = None;
+            for (_, entries) in self.tray() {
+                for (entry_id, rect) in entries {
+                    if entry_id == id {
+                        to = Some(rect);
+                    }
+                }
+            }
+            let to = to.unwrap_or_else(|| {
+                let monitor_rect = self.monitor_rect(frame.left() + frame.width()/2, frame.top() + frame.height()/2);
+                let width = cmp::max(1, frame.width()/8);
+                let height = cmp::max(1, frame.height()/8);
+                Rect::new(monitor_rect.left() + (monitor_rect.width() - width)/2, monitor_rect.bottom() - height,
+                          width, height)
+            });
+            self.animations.push(Animation::new(id, AnimationKind::Minimize, frame, to));
+            self.deadline_added = true;
+        }
+        self.notify(&format!("minimize/{}", id));
+
+        //Redraw the title of the window that gains focus
+        if let Some(id) = self.focused() {
+            if let Some(window) = self.windows.get(&id) {
+                schedule(&mut self.redraws, window.title_rect());
+            }
+        }
+
+        Ok(0)
+    }
+
+    /// Run a command written to a control or window list handle
+    fn command(&mut self, command: ControlCommand) -> Result<usize> {
+        match command {
+            ControlCommand::Switch(workspace) => if workspace < WORKSPACES {
+                self.switch_workspace(workspace);
+                Ok(0)
+            } else {
+                Err(Error::new(EINVAL))
+            },
+            ControlCommand::Move(id, workspace) => self.move_window(id, workspace),
+            ControlCommand::Activate(id) => self.activate(id),
+            ControlCommand::Minimize(id) => self.minimize(id),
+            ControlCommand::Wallpaper(mode, path) => self.set_wallpaper(&path, mode),
+            ControlCommand::Gamma(display, gamma) => match self.monitors.get_mut(display) {
+                Some(monitor) => {
+                    monitor.calibration.gamma = gamma;
+                    self.update_ramps();
+                    Ok(0)
+                },
+                None => Err(Error::new(ENOENT))
+            },
+            ControlCommand::WhitePoint(display, temperature) => match self.monitors.get_mut(display) {
+                Some(monitor) => {
+                    monitor.calibration.temperature = temperature;
+                    self.update_ramps();
+                    Ok(0)
+                },
+                None => Err(Error::new(ENOENT))
+            },
+            ControlCommand::NightLight(night_light) => {
+                self.night_light = night_light;
+                self.update_night_light(Date::now());
+                Ok(0)
+            },
+            ControlCommand::KeyRepeat(key_repeat) => {
+                self.key_repeat = key_repeat;
+                self.held_key = None;
+                Ok(0)
+            },
+            ControlCommand::Keymap(name) => match Keymap::from_name(&name) {
+                Some(keymap) => {
+                    println!("orbital: using keymap {}", keymap.name());
+                    self.keymap = keymap;
+                    Ok(0)
+                },
+                None => Err(Error::new(ENOENT))
+            },
+            ControlCommand::FocusMode(focus_mode) => {
+                self.focus_mode = focus_mode;
+                //The frontmost window takes focus back from the window under the cursor
+                if let Some(id) = self.pointer_focus.take() {
+                    if let Some(window) = self.windows.get(&id) {
+                        schedule(&mut self.redraws, window.title_rect());
+                    }
+                    if let Some(id) = self.focused() {
+                        if let Some(window) = self.windows.get(&id) {
+                            schedule(&mut self.redraws, window.title_rect());
+                        }
+                    }
+                }
+                Ok(0)
+            }
+        }
+    }
+
+    /// Apply the calibration of every monitor, with the night light if it is on, and redraw them
+    fn update_ramps(&mut self) {
+        for monitor in self.monitors.iter_mut() {
+            monitor.update_ramp(self.night);
+        }
+        let screen_rect = self.screen_rect();
+        schedule(&mut self.redraws, screen_rect);
+    }
+
+    /// Turn the night light on or off for the time of a date
+    fn update_night_light(&mut self, date: Option<Date>) {
+        let night = match (self.night_light, date) {
+            (Some(night_light), Some(date)) if night_light.active(&date) => Some(night_light.temperature),
+            _ => None
+        };
+        if night != self.night {
+            self.night = night;
+            self.update_ramps();
+        }
+    }
+
+    /// Load a new wallpaper, returning `ENOENT` if the image cannot be read
+    fn set_wallpaper(&mut self, path: &str, mode: WallpaperMode) -> Result<usize> {
+        let image = Image::from_path(path);
+        if image.width() <= 0 || image.height() <= 0 {
+            return Err(Error::new(ENOENT));
+        }
+
+        self.wallpaper = Wallpaper::new(image, mode);
+        let screen_rect = self.screen_rect();
+        schedule(&mut self.redraws, screen_rect);
+        Ok(0)
+    }
+
+    /// Send a line to every window list
+    fn notify(&mut self, line: &str) {
+        for (_, window_list) in self.window_lists.iter_mut() {
+            window_list.push(line);
+        }
+    }
+
+    /// The line sent to window lists when a window is opened
+    fn window_open_line(&self, id: usize) -> String {
+        match self.windows.get(&id) {
+            Some(window) => format!("open/{}/{}/{}{}{}{}/{}", id, window.workspace,
+                                    if self.focused() == Some(id) { "f" } else { "" },
+                                    if window.minimized { "m" } else { "" },
+                                    if window.popup { "p" } else { "" },
+                                    if window.panel { "k" } else { "" },
+                                    escape_arg(window.title())),
+            None => String::new()
+        }
+    }
+
+    /// Tell the window lists if the focused window changed
+    pub fn update_focus(&mut self) {
+        let focused = self.focused();
+        if focused != self.last_focus {
+            self.last_focus = focused;
+            if let Some(id) = focused {
+                self.notify(&format!("focus/{}", id));
+            }
+        }
+    }
+
+    /// The rows of the window switcher, one per window on the active workspace, front to back
+    fn switcher_rows(&self) -> Vec<(usize, Rect)> {
+        let mut rows = Vec::new();
+        if let Some(monitor_rect) = self.switcher {
+            let mut ids = Vec::new();
+            for id in self.order.iter() {
+                if let Some(window) = self.windows.get(&id) {
+                    if window.workspace == self.workspace && ! window.popup && ! window.panel {
+                        ids.push(*id);
+                    }
+                }
+            }
+
+            let scale = self.monitor_scale(monitor_rect.left(), monitor_rect.top());
+            let width = cmp::min(SWITCHER_WIDTH * scale, monitor_rect.width());
+            let row_height = SWITCHER_ROW_HEIGHT * scale;
+            let x = monitor_rect.left() + (monitor_rect.width() - width)/2;
+            let mut y = monitor_rect.top() + (monitor_rect.height() - row_height * ids.len() as i32)/2;
+            for id in ids {
+                rows.push((id, Rect::new(x, y, width, row_height)));
+                y += row_height;
+            }
+        }
+        rows
+    }
+
+    /// The strips of the tray across the bottom of each monitor with minimized windows, with the windows in them
+    fn tray(&self) -> Vec<(Rect, Vec<(usize, Rect)>)> {
+        let mut tray = Vec::new();
+        for monitor in self.monitors.iter() {
+            let height = TRAY_HEIGHT * monitor.scale;
+            let width = TRAY_ENTRY_WIDTH * monitor.scale;
+            let strip = Rect::new(monitor.rect.left(), monitor.rect.bottom() - height, monitor.rect.width(), height);
+
+            let mut entries = Vec::new();
+            let mut x = strip.left();
+            for id in self.order.iter() {
+                if let Some(window) = self.windows.get(&id) {
+                    let frame = window.frame_rect();
+                    let center_rect = self.monitor_rect(frame.left() + frame.width()/2, frame.top() + frame.height()/2);
+                    if window.workspace == self.workspace && window.minimized && center_rect == monitor.rect
+                        && x + width <= strip.right() {
+                        entries.push((*id, Rect::new(x, strip.top(), width, height)));
+                        x += width;
+                    }
+                }
+            }
+
+            if ! entries.is_empty() {
+                tray.push((strip, entries));
+            }
+        }
+        tray
+    }
+
+    /// The window in the tray at a point
+    fn tray_at(&self, x: i32, y: i32) -> Option<usize> {
+        for (_, entries) in self.tray() {
+            for (id, rect) in entries {
+                if rect.contains(x, y) {
+                    return Some(id);
+                }
+            }
+        }
+        None
+    }
+
+    /// Show the window switcher on a monitor, or hide it
+    fn set_switcher(&mut self, switcher: Option<Rect>) {
+        for (_, rect) in self.switcher_rows() {
+            schedule(&mut self.redraws, rect);
+        }
+        self.switcher = switcher;
+        for (_, rect) in self.switcher_rows() {
+            schedule(&mut self.redraws, rect);
+        }
+    }
+
+    /// The rows of the clipboard history, one per entry, newest first, with the index of the entry
+    ///
+    /// Text entries show their first line, and image entries show a thumbnail in a taller row.
+    fn clipboard_rows(&self) -> Vec<(usize, Rect)> {
+        let mut rows = Vec::new();
+        if let Some(monitor_rect) = self.clipboard_popup {
+            let scale = self.monitor_scale(monitor_rect.left(), monitor_rect.top());
+            let width = cmp::min(SWITCHER_WIDTH * scale, monitor_rect.width());
+            let heights: Vec<i32> = self.clipboard.entries().iter().map(|entry| match entry.thumbnail {
+                Some(_) => (THUMBNAIL_HEIGHT + 4) * scale,
+                None => SWITCHER_ROW_HEIGHT * scale
+            }).collect();
+
+            let x = monitor_rect.left() + (monitor_rect.width() - width)/2;
+            let mut y = monitor_rect.top() + cmp::max(0, (monitor_rect.height() - heights.iter().fold(0, |total, height| total + height))/2);
+            for (i, height) in heights.into_iter().enumerate() {
+                rows.push((i, Rect::new(x, y, width, height)));
+                y += height;
+            }
+        }
+        rows
+    }
+
+    /// Show the clipboard history on a monitor, or hide it
+    fn set_clipboard_popup(&mut self, clipboard_popup: Option<Rect>) {
+        for (_, rect) in self.clipboard_rows() {
+            schedule(&mut self.redraws, rect);
+        }
+        self.clipboard_popup = clipboard_popup;
+        for (_, rect) in self.clipboard_rows() {
+            schedule(&mut self.redraws, rect);
+        }
+    }
+
+    /// Run the action bound to a hot corner
+    fn hot_corner(&mut self, corner: Corner) {
+        let action = match self.hot_corners.iter().find(|&&(c, _)| c == corner) {
+            Some(&(_, ref action)) => action.clone(),
+            None => return
+        };
+
+        match action {
+            CornerAction::Switcher => {
+                let switcher = if self.switcher.is_some() {
+                    None
+                } else {
+                    Some(self.monitor_rect(self.cursor_x, self.cursor_y))
+                };
+                self.set_switcher(switcher);
+            },
+            CornerAction::Desktop => {
+                self.show_desktop = ! self.show_desktop;
+                let screen_rect = self.screen_rect();
+                schedule(&mut self.redraws, screen_rect);
+            },
+            CornerAction::Workspace(workspace) => self.switch_workspace(workspace),
+            CornerAction::Exec(command) => {
+                let mut args = command.split(' ').filter(|arg| ! arg.is_empty());
+                if let Some(program) = args.next() {
+                    if let Err(err) = Command::new(program).args(&args.collect::<Vec<&str>>()).spawn() {
+                        println!("orbital: failed to run '{}': {}", command, err);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The clock, with the text enlarged for the first display
+    fn clock_rect(&self) -> Rect {
+        let (corner, date, monitor) = match (self.clock, self.clock_date, self.monitors.get(0)) {
+            (Some(corner), Some(date), Some(monitor)) => (corner, date, monitor),
+            _ => return Rect::new(0, 0, 0, 0)
+        };
+
+        let scale = monitor.scale;
+        let w = (date.text().chars().count() as i32 * 8 + 2 * CLOCK_PADDING) * scale;
+        let h = (16 + 2 * CLOCK_PADDING) * scale;
+        let x = match corner {
+            Corner::TopLeft | Corner::BottomLeft => monitor.rect.left(),
+            Corner::TopRight | Corner::BottomRight => monitor.rect.right() - w
+        };
+        let y = match corner {
+            Corner::TopLeft | Corner::TopRight => monitor.rect.top(),
+            Corner::BottomLeft | Corner::BottomRight => monitor.rect.bottom() - h
+        };
+        Rect::new(x, y, w, h)
+    }
+
+    /// The calendar, next to the clock toward the center of the display
+    fn calendar_rect(&self) -> Rect {
+        let (corner, date) = match (self.clock, self.clock_date) {
+            (Some(corner), Some(date)) if self.calendar => (corner, date),
+            _ => return Rect::new(0, 0, 0, 0)
+        };
+
+        let scale = self.monitors.get(0).map_or(1, |monitor| monitor.scale);
+        let lines = date.calendar();
+        let columns = lines.iter().map(|line| line.chars().count() as i32).max().unwrap_or(0);
+        let w = (columns * 8 + 2 * CLOCK_PADDING) * scale;
+        let h = (lines.len() as i32 * 16 + 2 * CLOCK_PADDING) * scale;
+        let clock_rect = self.clock_rect();
+        let x = match corner {
+            Corner::TopLeft | Corner::BottomLeft => clock_rect.left(),
+            Corner::TopRight | Corner::BottomRight => clock_rect.right() - w
+        };
+        let y = match corner {
+            Corner::TopLeft | Corner::TopRight => clock_rect.bottom(),
+            Corner::BottomLeft | Corner::BottomRight => clock_rect.top() - h
+        };
+        Rect::new(x, y, w, h)
+    }
+
+    /// Show or hide the calendar
+    fn set_calendar(&mut self, calendar: bool) {
+        let calendar_rect = self.calendar_rect();
+        schedule(&mut self.redraws, calendar_rect);
+        self.calendar = calendar;
+        let calendar_rect = self.calendar_rect();
+        schedule(&mut self.redraws, calendar_rect);
+    }
+
+    /// Follow the key sent to a window, so it is repeated while it is held
+    ///
+    /// Returns false for repeats made by the keyboard, which are left out in favor of those of `update_repeat`
+    fn hold_key(&mut self, id: usize, event: Event) -> bool {
+        let repeat = match self.key_repeat {
+            Some(repeat) => repeat,
+            None => return true
+        };
+
+        if event.c > 0 {
+            if let Some(held) = self.held_key {
+                if held.event.b == event.b && held.window == id {
+                    return false;
+                }
+            }
+            if ! is_modifier(event.b as u8) {
+                self.held_key = Some(HeldKey {
+                    event: event,
+                    window: id,
+                    next: Instant::now() + repeat.delay()
+                });
+                self.deadline_added = true;
+            }
+        } else if self.held_key.map_or(false, |held| held.event.b == event.b) {
+            self.held_key = None;
+        }
+        true
+    }
+
+    /// Send the held key to its window again if it is time to repeat it, stopping if the window lost focus
+    ///
+    /// Returns the milliseconds to wait before the next update, `None` if no key is held
+    pub fn update_repeat(&mut self) -> Option<u32> {
+        let interval = match self.key_repeat {
+            Some(repeat) => repeat.interval(),
+            None => return None
+        };
+
+        let focused = self.focused();
+        let now = Instant::now();
+        let mut held = match self.held_key {
+            Some(held) if Some(held.window) == focused => held,
+            _ => {
+                self.held_key = None;
+                return None;
+            }
+        };
+
+        if now >= held.next {
+            if let Some(mut window) = self.windows.get_mut(&held.window) {
+                let mut event = held.event;
+                event.time = 0;
+                window.event(event);
+            }
+            //Skip the repeats that were missed, instead of sending them all at once
+            held.next = held.next + interval;
+            if held.next < now {
+                held.next = now + interval;
+            }
+            self.held_key = Some(held);
+        }
+
+        let left = held.next - now;
+        Some(left.as_secs() as u32 * 1000 + left.subsec_nanos() / 1000000 + 1)
+    }
+
+    /// Blank the displays if there was no input for the timeout
+    ///
+    /// Returns the milliseconds to wait before checking again
+    pub fn update_blank(&mut self) -> u32 {
+        let timeout = match self.blank_timeout {
+            Some(timeout) => timeout,
+            None => return CLOCK_RETRY
+        };
+        let timeout_ms = timeout.as_secs() as u32 * 1000;
+
+        let idle = self.last_input.elapsed();
+        if self.blanked {
+            timeout_ms
+        } else if idle >= timeout {
+            self.blanked = true;
+            let screen_rect = self.screen_rect();
+            schedule(&mut self.redraws, screen_rect);
+            timeout_ms
+        } else {
+            let left = timeout - idle;
+            left.as_secs() as u32 * 1000 + left.subsec_nanos() / 1000000 + 1
+        }
+    }
+
+    /// Read the clock, redrawing it if the minute changed, and turn the night light on or off
+    ///
+    /// Returns the milliseconds to wait before the next update
+    pub fn update_clock(&mut self) -> u32 {
+        let date = Date::now();
+        if date.map(|date| date.text()) != self.clock_date.map(|date| date.text()) {
+            let clock_rect = self.clock_rect();
+            schedule(&mut self.redraws, clock_rect);
+            let calendar_rect = self.calendar_rect();
+            schedule(&mut self.redraws, calendar_rect);
+            self.clock_date = date;
+            let clock_rect = self.clock_rect();
+            schedule(&mut self.redraws, clock_rect);
+            let calendar_rect = self.calendar_rect();
+            schedule(&mut self.redraws, calendar_rect);
+        }
+        self.update_night_light(date);
+        date.map_or(CLOCK_RETRY, |date| date.until_next_minute())
+    }
+
+    /// Redraw the areas of the animations running, and end those that finished
+    ///
+    /// Returns the milliseconds to wait before the next frame, `None` if no animation is running
+    pub fn update_animations(&mut self) -> Option<u32> {
+        let now = Instant::now();
+        for animation in self.animations.iter() {
+            //A window fading in also fades in its shadow
+            let scale = self.windows.get(&animation.window).map_or(1, |window| window.scale());
+            schedule(&mut self.redraws, Shadow::rect(&animation.bounds(), scale));
+        }
+        self.animations.retain(|animation| ! animation.done(now));
+
+        if self.animations.is_empty() {
+            None
+        } else {
+            Some(FRAME_MS)
+        }
+    }
+
+    /// Show the frames of streaming windows that are due, dropping the frames they replace
+    ///
+    /// Returns the milliseconds to wait before the next flip, `None` if no window is streaming
+    pub fn update_streams(&mut self) -> Option<u32> {
+        let now = Instant::now();
+        let mut streaming = false;
+        let mut next: Option<Instant> = None;
+        for (_, window) in self.windows.iter_mut() {
+            if ! window.streaming() {
+                continue;
+            }
+            streaming = true;
+            if window.present(now) && window.workspace == self.workspace && ! window.minimized {
+                schedule(&mut self.redraws, window.rect());
+            }
+            if let Some(time) = window.next_frame() {
+                next = Some(next.map_or(time, |next| cmp::min(next, time)));
+            }
+        }
+
+        if ! streaming {
+            return None;
+        }
+
+        //Flip at the display rate, or sooner if a frame is due sooner
+        Some(match next {
+            Some(next) if next > now => {
+                let wait = next - now;
+                cmp::min(FRAME_MS, wait.as_secs() as u32 * 1000 + wait.subsec_nanos() / 1000000 + 1)
+            },
+            _ => FRAME_MS
+        })
+    }
+
+    /// Show the next frame of an animated cursor, such as the busy cursor, when it is due
+    ///
+    /// Returns the milliseconds to wait before the next frame, `None` if the cursor is not animated
+    pub fn update_cursor_animation(&mut self) -> Option<u32> {
+        self.update_cursor();
+        let elapsed = self.start.elapsed();
+        self.cursors.next_frame(self.cursor_kind, elapsed.as_secs() * 1000 + (elapsed.subsec_nanos() / 1000000) as u64)
+    }
+
+    /// Whether a key repeat, animation, stream frame, or animated cursor was added since the last call, so the timers
+    /// have to look at `update_repeat`, `update_animations`, `update_streams`, and `update_cursor_animation` again
+    /// before they sleep
+    pub fn take_deadline_added(&mut self) -> bool {
+        mem::replace(&mut self.deadline_added, false)
+    }
+
+    /// Ping the clients of windows, showing the windows of clients that did not answer in time as not responding
+    ///
+    /// Returns the milliseconds to wait before the next ping
+    pub fn update_watchdog(&mut self) -> u32 {
+        let serial = self.next_ping;
+        self.next_ping = self.next_ping.wrapping_add(1);
+
+        let mut stopped = Vec::new();
+        for (id, window) in self.windows.iter_mut() {
+            if window.ping(serial, Duration::new(PING_TIMEOUT, 0)) {
+                stopped.push(*id);
+            }
+        }
+        for id in stopped {
+            if let Some(window) = self.windows.get(&id) {
+                if window.workspace == self.workspace {
+                    schedule(&mut self.redraws, window.title_rect());
+                    schedule(&mut self.redraws, window.rect());
+                }
+            }
+            self.notify(&format!("unresponsive/{}", id));
+        }
+
+        PING_INTERVAL
+    }
+
+    fn move_window(&mut self, id: usize, workspace: usize) -> Result<usize> {
+        if workspace >= WORKSPACES {
+            return Err(Error::new(EINVAL));
+        }
+
+        let focused = self.focused();
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            schedule(&mut self.redraws, window.title_rect());
+            schedule(&mut self.redraws, window.rect());
+            window.workspace = workspace;
+        } else {
+            return Err(Error::new(EBADF));
+        }
+        self.notify(&format!("move/{}/{}", id, workspace));
+
+        //Redraw the title of the window that gains focus
+        if focused == Some(id) {
+            if let Some(id) = self.focused() {
+                if let Some(window) = self.windows.get(&id) {
+                    schedule(&mut self.redraws, window.title_rect());
+                }
+            }
+        }
+
+        Ok(0)
+    }
+
+    /// Snap the dragged window when it is released at a screen edge
+    fn snap(&mut self) {
+        let screen_rect = self.monitor_rect(self.cursor_x, self.cursor_y);
+        let half = screen_rect.width()/2;
+        let rect = if self.cursor_y <= screen_rect.top() {
+            screen_rect
+        } else if self.cursor_x <= screen_rect.left() {
+            Rect::new(screen_rect.left(), screen_rect.top(), half, screen_rect.height())
+        } else if self.cursor_x >= screen_rect.right() - 1 {
+            Rect::new(screen_rect.left() + half, screen_rect.top(), screen_rect.width() - half, screen_rect.height())
+        } else {
+            return;
+        };
+
+        if let Some(id) = self.order.front() {
+            if let Some(mut window) = self.windows.get_mut(&id) {
+                schedule(&mut self.redraws, window.title_rect());
+                schedule(&mut self.redraws, window.rect());
+                window.snap(rect);
+                schedule(&mut self.redraws, window.title_rect());
+                schedule(&mut self.redraws, window.rect());
+            }
+        }
+    }
+
+    /// List windows, one per line, as `id/workspace/x/y/width/height/title`, with the title escaped
+    fn window_list(&self) -> String {
+        let mut list = String::new();
+        for id in self.order.iter() {
+            if let Some(window) = self.windows.get(&id) {
+                let rect = window.rect();
+                list.push_str(&format!("{}/{}/{}/{}/{}/{}/{}\n", id, window.workspace, rect.left(), rect.top(), rect.width(),
+                                       rect.height(), escape_arg(window.title())));
+            }
+        }
+        list
+    }
+
+    /// Draw the scheduled areas of the screen and send them to the displays
+    pub fn redraw(&mut self){
+        //Redraw where the shadow was and where it is, when the focused window moved, resized, or changed
+        let shadow_rect = self.shadow_frame().map_or(Rect::default(), |(frame, scale)| Shadow::rect(&frame, scale));
+        if shadow_rect != self.shadow_rect {
+            let old_rect = self.shadow_rect;
+            schedule(&mut self.redraws, old_rect);
+            schedule(&mut self.redraws, shadow_rect);
+            self.shadow_rect = shadow_rect;
+        }
+
+        //Redraw the tray when windows were minimized or restored
+        let tray = self.tray();
+        if tray != self.tray {
+            for &(strip, _) in self.tray.iter().chain(tray.iter()) {
+                schedule(&mut self.redraws, strip);
+            }
+            self.tray = tray;
+        }
+
+        let mut redraws = Vec::new();
+        mem::swap(&mut self.redraws, &mut redraws);
+
+        self.composite(&mut redraws);
+        self.present(&redraws);
+
+        for (_, recorder) in self.recorders.iter_mut() {
+            for rect in redraws.iter() {
+                recorder.damage(*rect);
+            }
+        }
+    }
+
+    /// The focused window with its title bar and its scale, if it casts a shadow
+    fn shadow_frame(&self) -> Option<(Rect, i32)> {
+        if self.show_desktop {
+            return None;
+        }
+        match self.focused().and_then(|id| self.windows.get(&id)) {
+            Some(window) if window.workspace == self.workspace && ! window.minimized && ! window.fullscreen() => {
+                Some((window.frame_rect(), window.scale()))
+            },
+            _ => None
+        }
+    }
+
+    /// Draw the scheduled areas of the screen, clipping them to it
+    fn composite(&mut self, redraws: &mut Vec<Rect>) {
+        let screen_rect = self.screen_rect();
+
+        //The focused window covers the background when it is fullscreen
+        let fullscreen_rect = match self.focused().and_then(|id| self.windows.get(&id)) {
+            Some(window) if window.fullscreen() && ! window.translucent && ! self.show_desktop => window.rect(),
+            _ => Rect::default()
+        };
+
+        for mut rect in redraws.iter_mut() {
+            *rect = rect.intersection(&screen_rect);
+
+            if ! rect.is_empty() {
+                if self.blanked {
+                    self.image.roi(&rect).set(Color::rgb(0, 0, 0));
+                    for i in 0..self.monitors.len() {
+                        let monitor_rect = self.monitors[i].rect;
+                        self.screensaver.draw(&mut self.image, &rect, &monitor_rect);
+                    }
+                    continue;
+                }
+
+                if ! fullscreen_rect.contains_rect(&rect) {
+                    //TODO: only clear area not covered by the wallpaper
+                    self.blitter.fill(&mut self.image, &rect, BACKGROUND_COLOR);
+
+                    for i in 0..self.monitors.len() {
+                        let monitor_rect = self.monitors[i].rect;
+                        self.wallpaper.draw(&mut self.image, &rect, &monitor_rect);
+                    }
+                }
+
+                let focused = self.focused();
+                let shadow_frame = self.shadow_frame();
+                let now = Instant::now();
+                for id in self.stacking().iter().rev() {
+                    if let Some(mut window) = self.windows.get_mut(&id) {
+                        if window.workspace == self.workspace && ! window.minimized && ! self.show_desktop {
+                            //Keep what is under a window fading in, with its shadow, to mix it with the window
+                            let fade = self.animations.iter()
+                                           .find(|animation| animation.window == *id && animation.kind == AnimationKind::Open)
+                                           .map(|animation| animation.alpha(animation.progress(now)));
+                            let fade_rect = rect.intersection(&Shadow::rect(&window.frame_rect(), window.scale()));
+                            let under = if fade.is_some() {
+                                Some(animation::copy_area(&self.image, &fade_rect))
+                            } else {
+                                None
+                            };
+
+                            if let (true, Some((frame, scale))) = (Some(*id) == focused, shadow_frame) {
+                                self.shadow.draw(&mut self.image, &rect, &frame, scale);
+                            }
+                            window.draw_title(&mut self.image, &rect, Some(*id) == focused, self.font.as_mut());
+                            window.draw(&mut self.image, &rect, &mut self.blitter);
+                            if window.not_responding {
+                                self.image.roi(&rect.intersection(&window.frame_rect())).set(NOT_RESPONDING_COLOR);
+                            }
+
+                            if let (Some(alpha), Some(under)) = (fade, under) {
+                                animation::fade(&mut self.image, &fade_rect, &under, alpha);
+                            }
+                        }
+                    }
+                }
+
+                for (strip, entries) in self.tray() {
+                    let strip_intersect = rect.intersection(&strip);
+                    if strip_intersect.is_empty() {
+                        continue;
+                    }
+                    self.image.roi(&strip_intersect).set(SWITCHER_COLOR);
+
+                    let scale = strip.height() / TRAY_HEIGHT;
+                    for (id, entry_rect) in entries {
+                        let entry_intersect = rect.intersection(&entry_rect);
+                        if ! entry_intersect.is_empty() {
+                            if entry_rect.contains(self.cursor_x, self.cursor_y) {
+                                self.image.roi(&entry_intersect).set(SWITCHER_HIGHLIGHT_COLOR);
+                            }
+                            if let Some(mut window) = self.windows.get_mut(&id) {
+                                let mut x = entry_rect.left() + 2 * scale;
+                                let icon_width = window.draw_icon(&mut self.image, &entry_intersect, x, entry_rect.top() + 2 * scale);
+                                if icon_width > 0 {
+                                    x += icon_width + 2 * scale;
+                                }
+                                draw_text(&mut self.image, &entry_intersect, x, entry_rect.top() + 2 * scale, window.title(), SWITCHER_TEXT_COLOR, scale);
+                            }
+                        }
+                    }
+                }
+
+                //Windows being minimized are drawn shrinking above the others
+                for animation in self.animations.iter() {
+                    if animation.kind == AnimationKind::Minimize {
+                        if let Some(window) = self.windows.get(&animation.window) {
+                            if window.workspace == self.workspace && ! self.show_desktop {
+                                let progress = animation.progress(now);
+                                window.draw_animated(&mut self.image, &rect, &animation.rect(progress), animation.alpha(progress));
+                            }
+                        }
+                    }
+                }
+
+                let scale = self.switcher.map_or(1, |monitor_rect| self.monitor_scale(monitor_rect.left(), monitor_rect.top()));
+                for (id, row_rect) in self.switcher_rows() {
+                    let row_intersect = rect.intersection(&row_rect);
+                    if ! row_intersect.is_empty() {
+                        if row_rect.contains(self.cursor_x, self.cursor_y) {
+                            self.image.roi(&row_intersect).set(SWITCHER_HIGHLIGHT_COLOR);
+                        } else {
+                            self.image.roi(&row_intersect).set(SWITCHER_COLOR);
+                        }
+                        if let Some(mut window) = self.windows.get_mut(&id) {
+                            let mut x = row_rect.left() + 2 * scale;
+                            let icon_width = window.draw_icon(&mut self.image, &row_intersect, x, row_rect.top() + 2 * scale);
+                            if icon_width > 0 {
+                                x += icon_width + 2 * scale;
+                            }
+                            draw_text(&mut self.image, &row_intersect, x, row_rect.top() + 2 * scale, window.title(), SWITCHER_TEXT_COLOR, scale);
+                        }
+                    }
+                }
+
+                let scale = self.clipboard_popup.map_or(1, |monitor_rect| self.monitor_scale(monitor_rect.left(), monitor_rect.top()));
+                for (i, row_rect) in self.clipboard_rows() {
+                    let row_intersect = rect.intersection(&row_rect);
+                    if ! row_intersect.is_empty() {
+                        if row_rect.contains(self.cursor_x, self.cursor_y) {
+                            self.image.roi(&row_intersect).set(SWITCHER_HIGHLIGHT_COLOR);
+                        } else {
+                            self.image.roi(&row_intersect).set(SWITCHER_COLOR);
+                        }
+                        if let Some(entry) = self.clipboard.entries().get(i) {
+                            let mut x = row_rect.left() + 2 * scale;
+                            if let Some(ref thumbnail) = entry.thumbnail {
+                                let mut thumbnail = thumbnail.scale(scale);
+                                let thumbnail_rect = Rect::new(x, row_rect.top() + 2 * scale, thumbnail.width(), thumbnail.height());
+                                let thumbnail_intersect = row_intersect.intersection(&thumbnail_rect);
+                                if ! thumbnail_intersect.is_empty() {
+                                    self.image.roi(&thumbnail_intersect).blend(&thumbnail.roi(&thumbnail_intersect.offset(-thumbnail_rect.left(), -thumbnail_rect.top())));
+                                }
+                                x += thumbnail.width() + 4 * scale;
+                            }
+                            let y = row_rect.top() + (row_rect.height() - 16 * scale)/2;
+                            draw_text(&mut self.image, &row_intersect, x, y, &entry.summary(), SWITCHER_TEXT_COLOR, scale);
+                        }
+                    }
+                }
+
+                let scale = self.monitors.get(0).map_or(1, |monitor| monitor.scale);
+                let clock_rect = self.clock_rect();
+                let clock_intersect = rect.intersection(&clock_rect);
+                if ! clock_intersect.is_empty() {
+                    self.image.roi(&clock_intersect).set(CLOCK_COLOR);
+                    if let Some(date) = self.clock_date {
+                        let padding = CLOCK_PADDING * scale;
+                        draw_text(&mut self.image, &clock_intersect, clock_rect.left() + padding, clock_rect.top() + padding, &date.text(), CLOCK_TEXT_COLOR, scale);
+                    }
+                }
+
+                let calendar_rect = self.calendar_rect();
+                let calendar_intersect = rect.intersection(&calendar_rect);
+                if ! calendar_intersect.is_empty() {
+                    self.image.roi(&calendar_intersect).set(CLOCK_COLOR);
+                    if let Some(date) = self.clock_date {
+                        let padding = CLOCK_PADDING * scale;
+                        let mut y = calendar_rect.top() + padding;
+                        for line in date.calendar() {
+                            draw_text(&mut self.image, &calendar_intersect, calendar_rect.left() + padding, y, &line, CLOCK_TEXT_COLOR, scale);
+                            y += 16 * scale;
+                        }
+                    }
+                }
+
+                let cursor_rect = self.cursor_rect();
+                let cursor_intersect = rect.intersection(&cursor_rect);
+                if ! cursor_intersect.is_empty() {
+                    self.blitter.blend(&mut self.image, &cursor_intersect, &mut self.cursor_scaled,
+                                       cursor_intersect.left() - cursor_rect.left(), cursor_intersect.top() - cursor_rect.top());
+                }
+            }
+        }
+
+    }
+
+    /// Send the composited areas to the displays
+    fn present(&mut self, redraws: &Vec<Rect>) {
+        for rect in redraws.iter() {
+            if ! rect.is_empty() {
+                let data = self.image.data();
+                for monitor in self.monitors.iter_mut() {
+                    let monitor_rect = rect.intersection(&monitor.rect);
+                    if ! monitor_rect.is_empty() {
+                        for row in monitor_rect.top()..monitor_rect.bottom() {
+                            let off1 = row * self.image.width() + monitor_rect.left();
+                            let off2 = row * self.image.width() + monitor_rect.right();
+                            monitor.write(monitor_rect.left(), row, &data[off1 as usize .. off2 as usize]);
+                        }
+                        monitor.flush(&monitor_rect);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Convert a pointer or touch position from the kernel, on the displays before rotation, to a point on the desktop
+    fn desktop_point(&self, x: i32, y: i32, touch: bool) -> (i32, i32) {
+        for monitor in self.monitors.iter() {
+            if monitor.physical.contains(x, y) {
+                return if touch { monitor.touch_point(x, y) } else { monitor.pointer_point(x, y) };
+            }
+        }
+        (x, y)
+    }
+
+    /// Give a key from the kernel in raw mode its character from the keymap, and clear `K_EXTENDED` so clients get
+    /// the scancodes they know
+    ///
+    /// Keys that already have a character, from the kernel layout or another backend, keep it. Dead keys and the
+    /// compose key type nothing, and the key that completes a combination types the combined character.
+    fn translate_key(&mut self, event: &mut Event) {
+        let scancode = event.b as u8;
+        let pressed = event.c > 0;
+        if scancode == event::K_ALT | event::K_EXTENDED {
+            self.altgr = pressed;
+        } else if scancode == event::K_CAPS && pressed {
+            self.caps_lock = ! self.caps_lock;
+        }
+
+        let mut dead = false;
+        if event.a == 0 {
+            let character = self.keymap.character(scancode, self.shift, self.altgr, self.caps_lock);
+            dead = self.keymap.is_dead(character);
+            event.a = character as i64;
+        }
+        if pressed {
+            let character = char::from_u32(event.a as u32).unwrap_or('\0');
+            event.a = self.composer.press(scancode, character, dead) as i64;
+        } else if dead {
+            event.a = 0;
+        }
+        event.b = (scancode & ! event::K_EXTENDED) as i64;
+    }
+
+    /// Follow a display that changed resolution, placing the displays side by side again and redrawing the desktop
+    fn resize_display(&mut self, index: usize, width: i32, height: i32) {
+        match self.monitors.get_mut(index) {
+            Some(monitor) => {
+                let (x, y) = (monitor.physical.left(), monitor.physical.top());
+                monitor.physical = Rect::new(x, y, width, height);
+            },
+            None => return
+        }
+
+        //The kernel places the displays left to right, the pointer positions it sends follow that
+        let mut x = 0;
+        for monitor in self.monitors.iter_mut() {
+            monitor.physical = Rect::new(x, monitor.physical.top(), monitor.physical.width(), monitor.physical.height());
+            x += monitor.physical.width();
+        }
+
+        let (width, height) = arrange(&mut self.monitors);
+        if width != self.image.width() || height != self.image.height() {
+            self.image = Image::new(width, height);
+        }
+
+        //Bring back the windows and the cursor that are no longer on the desktop
+        for (_, window) in self.windows.iter_mut() {
+            let rect = window.rect();
+            if rect.left() >= width {
+                window.x = cmp::max(0, width - rect.width());
+            }
+            if rect.top() >= height {
+                window.y = cmp::max(0, height - rect.height());
+            }
+        }
+        let panels: Vec<usize> = self.windows.iter().filter(|&(_, window)| window.panel).map(|(id, _)| *id).collect();
+        for id in panels {
+            self.dock_panel(id);
+        }
+        let cursor_x = cmp::max(0, cmp::min(width - 1, self.cursor_x));
+        let (cursor_x, cursor_y) = self.clamp_point(cursor_x, self.cursor_y);
+        self.cursor_x = cursor_x;
+        self.cursor_y = cursor_y;
+
+        let screen_rect = self.screen_rect();
+        schedule(&mut self.redraws, screen_rect);
+    }
+
+    /// Handle an event from the input backend
+    pub fn input(&mut self, mut event: Event) {
+        if event.code == EVENT_DISPLAY {
+            let display = DisplayEvent::from_event(event);
+            self.resize_display(display.index as usize, display.width as i32, display.height as i32);
+            return;
+        }
+
+        //Any input wakes blanked displays, and is handled as usual
+        self.last_input = Instant::now();
+        if self.blanked {
+            self.blanked = false;
+            let screen_rect = self.screen_rect();
+            schedule(&mut self.redraws, screen_rect);
+        }
+
+        //Events from the kernel have the time they were generated, others are stamped when they arrive
+        if event.time == 0 {
+            event.time = clock::monotonic();
+        }
+
+        if event.code == EVENT_MOUSE {
+            let (x, y) = self.desktop_point(event.a as i32, event.b as i32, false);
+            event.a = x as i64;
+            event.b = y as i64;
+        } else if event.code == EVENT_TOUCH {
+            let mut touch = TouchEvent::from_event(event);
+            let (x, y) = self.desktop_point(touch.x, touch.y, true);
+            touch.x = x;
+            touch.y = y;
+            event = Event {
+                time: event.time,
+                ..touch.to_event()
+            };
+        }
+        self.event(event);
+    }
+
+    fn event(&mut self, mut event: Event){
+        if event.code == EVENT_KEY {
+            self.translate_key(&mut event);
+            let scancode = event.b as u8;
+            if scancode == event::K_CTRL {
+                self.ctrl = event.c > 0;
+            } else if scancode == event::K_LEFT_SHIFT || scancode == event::K_RIGHT_SHIFT {
+                self.shift = event.c > 0;
+            } else if scancode == event::K_SUPER || scancode == event::K_RIGHT_SUPER {
+                self.super_key = event.c > 0;
+            }
+
+            if scancode == event::K_ESC && self.switcher.is_some() {
+                if event.c > 0 {
+                    self.set_switcher(None);
+                }
+                return;
+            }
+
+            if scancode == event::K_ESC && self.clipboard_popup.is_some() {
+                if event.c > 0 {
+                    self.set_clipboard_popup(None);
+                }
+                return;
+            }
+
+            //Super+V shows the clipboard history, to choose an older entry to paste
+            if self.super_key && scancode == K_V {
+                if event.c > 0 {
+                    let clipboard_popup = if self.clipboard_popup.is_some() {
+                        None
+                    } else {
+                        Some(self.monitor_rect(self.cursor_x, self.cursor_y))
+                    };
+                    self.set_clipboard_popup(clipboard_popup);
+                }
+                return;
+            }
+
+            //Ctrl+N switches to workspace N, Ctrl+Shift+N moves the focused window there
+            if self.ctrl && scancode >= event::K_1 && scancode < event::K_1 + WORKSPACES as u8 {
+                if event.c > 0 {
+                    let workspace = (scancode - event::K_1) as usize;
+                    if self.shift {
+                        if let Some(id) = self.focused() {
+                            let _ = self.move_window(id, workspace);
+                        }
+                    } else {
+                        self.switch_workspace(workspace);
+                    }
+                }
+                return;
+            }
+
+            //Ctrl+F5 dims the backlight and Ctrl+F6 brightens it
+            if self.ctrl && (scancode == event::K_F5 || scancode == event::K_F6) {
+                if event.c > 0 {
+                    self.step_brightness(if scancode == event::K_F5 { -BRIGHTNESS_STEP } else { BRIGHTNESS_STEP });
+                }
+                return;
+            }
+
+            if event.c > 0 {
+                if event.b as u8 == event::K_F1 {
+                    let cursor_rect = self.cursor_rect();
+                    schedule(&mut self.redraws, cursor_rect);
+
+                    self.cursor_x = 0;
+                    self.cursor_y = 0;
+
+                    let cursor_rect = self.cursor_rect();
+                    schedule(&mut self.redraws, cursor_rect);
+                } else if event.b as u8 == event::K_F2 {
+                    let cursor_rect = self.cursor_rect();
+                    schedule(&mut self.redraws, cursor_rect);
+
+                    self.cursor_x = self.screen_rect().width();
+                    self.cursor_y = self.screen_rect().height();
+
+                    let cursor_rect = self.cursor_rect();
+                    schedule(&mut self.redraws, cursor_rect);
+                }
+                self.update_cursor();
+            }
+            if let Some(id) = self.focused() {
+                if ! self.hold_key(id, event) {
+                    return;
+                }
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    window.event(event);
+                }
+            }
+        } else if event.code == EVENT_MOUSE {
+            let (mut x, mut y) = self.clamp_point(event.a as i32, event.b as i32);
+
+            //Hold the cursor at the seam between monitors until it is pushed past it
+            if self.pointer_barrier > 0 {
+                let monitor_rect = self.monitor_rect(self.cursor_x, self.cursor_y);
+                if ! monitor_rect.contains(x, y) && self.monitors.iter().any(|monitor| monitor.rect.contains(x, y)) {
+                    let (held_x, held_y) = barrier(&monitor_rect, self.pointer_barrier, x, y);
+                    x = held_x;
+                    y = held_y;
+                }
+            }
+
+            //Keep the cursor inside of a window that grabbed the pointer, or pin it for relative motion
+            let grabbed = self.grabbed();
+            if let Some(id) = grabbed {
+                if let Some(window) = self.windows.get(&id) {
+                    if window.relative {
+                        x = self.cursor_x;
+                        y = self.cursor_y;
+                    } else {
+                        let rect = window.rect();
+                        x = cmp::max(rect.left(), cmp::min(rect.right() - 1, x));
+                        y = cmp::max(rect.top(), cmp::min(rect.bottom() - 1, y));
+                    }
+                }
+            }
+
+            event.a = x as i64;
+            event.b = y as i64;
+
+            let pressed = event.c & 1 == 1 && ! self.mouse_down;
+            self.mouse_down = event.c & 1 == 1;
+
+            if event.a as i32 != self.cursor_x || event.b as i32 != self.cursor_y {
+                let cursor_rect = self.cursor_rect();
+                schedule(&mut self.redraws, cursor_rect);
+
+                self.cursor_x = event.a as i32;
+                self.cursor_y = event.b as i32;
+
+                let cursor_rect = self.cursor_rect();
+                schedule(&mut self.redraws, cursor_rect);
+
+                //Redraw the switcher to highlight the row under the cursor
+                if self.switcher.is_some() {
+                    for (_, rect) in self.switcher_rows() {
+                        schedule(&mut self.redraws, rect);
+                    }
+                }
+                if self.clipboard_popup.is_some() {
+                    for (_, rect) in self.clipboard_rows() {
+                        schedule(&mut self.redraws, rect);
+                    }
+                }
+                for (_, entries) in self.tray() {
+                    for (_, rect) in entries {
+                        schedule(&mut self.redraws, rect);
+                    }
+                }
+            }
+
+            if let Some(id) = grabbed {
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    window.mouse_event(event);
+                }
+                self.update_cursor();
+                return;
+            }
+
+            if ! self.dragging {
+                let monitor_rect = self.monitor_rect(self.cursor_x, self.cursor_y);
+                let corner = Corner::at(&monitor_rect, self.cursor_x, self.cursor_y);
+                if corner != self.corner {
+                    self.corner = corner;
+                    if let Some(corner) = corner {
+                        self.hot_corner(corner);
+                    }
+                }
+
+                if self.switcher.is_none() && self.clipboard_popup.is_none() {
+                    self.focus_pointer();
+                }
+            }
+
+            if self.dragging {
+                if event.c > 0 {
+                    if let Some(&id) = self.order.front() {
+                        //Stop the window at the edges of its monitor, keeping how far it is held back from the cursor
+                        let mut dx = self.cursor_x - self.drag_x + self.drag_held_x;
+                        let mut dy = self.cursor_y - self.drag_y + self.drag_held_y;
+                        if self.edge_resistance > 0 {
+                            let frame = self.windows.get(&id).map(|window| window.frame_rect());
+                            if let Some(frame) = frame {
+                                let monitor_rect = self.monitor_rect(frame.left() + frame.width()/2,
+                                                                     frame.top() + frame.height()/2);
+                                let (resisted_x, resisted_y) = resist(&monitor_rect, self.edge_resistance, &frame,
+                                                                      dx, dy);
+                                self.drag_held_x = dx - resisted_x;
+                                self.drag_held_y = dy - resisted_y;
+                                dx = resisted_x;
+                                dy = resisted_y;
+                            }
+                        }
+
+                        if let Some(mut window) = self.windows.get_mut(&id) {
+                            if self.drag_x != self.cursor_x || self.drag_y != self.cursor_y {
+                                schedule(&mut self.redraws, window.title_rect());
+                                schedule(&mut self.redraws, window.rect());
+                                window.x += dx;
+                                window.y += dy;
+                                if window.unsnap() {
+                                    //Keep the restored window under the cursor
+                                    window.x = self.cursor_x - window.width()/2;
+                                    self.drag_held_x = 0;
+                                    self.drag_held_y = 0;
+                                }
+                                self.drag_x = self.cursor_x;
+                                self.drag_y = self.cursor_y;
+                                schedule(&mut self.redraws, window.title_rect());
+                                schedule(&mut self.redraws, window.rect());
+                            }
+                        } else {
+                            self.dragging = false;
+                        }
+                    } else {
+                        self.dragging = false;
+                    }
+                } else {
+                    self.dragging = false;
+                    if let Some(&id) = self.order.front() {
+                        self.update_window_scale(id);
+                    }
+                    self.snap();
+                }
+            } else if self.switcher.is_some() {
+                //Clicking a row of the switcher raises its window, clicking anywhere hides the switcher
+                if event.c > 0 {
+                    let mut raise = None;
+                    for (id, rect) in self.switcher_rows() {
+                        if rect.contains(self.cursor_x, self.cursor_y) {
+                            raise = Some(id);
+                        }
+                    }
+                    self.set_switcher(None);
+                    if let Some(id) = raise {
+                        let _ = self.activate(id);
+                    }
+                }
+            } else if self.clipboard_popup.is_some() {
+                //Clicking a row of the clipboard history makes its entry the current one, clicking anywhere hides it
+                if pressed {
+                    let mut select = None;
+                    for (i, rect) in self.clipboard_rows() {
+                        if rect.contains(self.cursor_x, self.cursor_y) {
+                            select = Some(i);
+                        }
+                    }
+                    self.set_clipboard_popup(None);
+                    if let Some(i) = select {
+                        self.clipboard.select(i);
+                    }
+                }
+            } else if self.tray().iter().any(|&(strip, _)| strip.contains(self.cursor_x, self.cursor_y)) {
+                //Clicking a window in the tray restores it, and the tray is kept from the windows under it
+                if pressed {
+                    if let Some(id) = self.tray_at(self.cursor_x, self.cursor_y) {
+                        let _ = self.activate(id);
+                    }
+                }
+            } else if pressed && (self.calendar || self.clock_rect().contains(self.cursor_x, self.cursor_y)) {
+                //Clicking the clock shows the calendar, clicking anywhere hides it
+                let calendar = ! self.calendar;
+                self.set_calendar(calendar);
+            } else if let Some(id) = self.panel_at(self.cursor_x, self.cursor_y) {
+                //Input panels get clicks without taking focus from the window they type into
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    window.mouse_event(event);
+                }
+            } else if let Some(id) = self.popup_at(self.cursor_x, self.cursor_y) {
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    window.mouse_event(event);
+                }
+            } else if pressed && self.close_popups() {
+                //The click that closes popups is not sent to the window under it
+            } else {
+                let blocked: Vec<usize> = self.order.iter().map(|id| *id).filter(|id| self.blocked(*id)).collect();
+                let monitor_rect = self.monitor_rect(self.cursor_x, self.cursor_y);
+                let mut minimize = None;
+                let mut force_close = None;
+                let mut focus = 0;
+                let mut i = 0;
+                for id in self.order.iter() {
+                    if let Some(mut window) = self.windows.get_mut(&id) {
+                        if window.workspace != self.workspace || window.minimized || window.popup || self.show_desktop {
+                            //Ignore windows on other workspaces, and popups which were handled above
+                        } else if blocked.contains(id) && (window.rect().contains(event.a as i32, event.b as i32)
+                                                           || window.title_rect().contains(event.a as i32, event.b as i32)) {
+                            //Clicking a window with a modal child only raises it, bringing the child to the front
+                            if event.c > 0 {
+                                focus = i;
+                            }
+                            break;
+                        } else if window.rect().contains(event.a as i32, event.b as i32) {
+                            window.mouse_event(event);
+                            if event.c > 0 {
+                                focus = i;
+                            }
+                            break;
+                        } else if window.title_rect().contains(event.a as i32, event.b as i32) {
+                            if event.c > 0 {
+                                focus = i;
+                                match window.button_at(event.a as i32, event.b as i32) {
+                                    //A client that is not responding would not read the quit event
+                                    Some(TitleButton::Close) => if window.not_responding {
+                                        force_close = Some(*id);
+                                    } else {
+                                        window.event(QuitEvent.to_event());
+                                    },
+                                    Some(TitleButton::Minimize) => minimize = Some(*id),
+                                    Some(TitleButton::Maximize) => {
+                                        schedule(&mut self.redraws, window.title_rect());
+                                        schedule(&mut self.redraws, window.rect());
+                                        window.toggle_maximize(monitor_rect);
+                                        schedule(&mut self.redraws, window.title_rect());
+                                        schedule(&mut self.redraws, window.rect());
+                                    },
+                                    Some(TitleButton::Zoom) => {
+                                        schedule(&mut self.redraws, window.title_rect());
+                                        schedule(&mut self.redraws, window.rect());
+                                        window.cycle_zoom();
+                                        schedule(&mut self.redraws, window.title_rect());
+                                        schedule(&mut self.redraws, window.rect());
+                                    },
+                                    None => {
+                                        self.dragging = true;
+                                        self.drag_x = self.cursor_x;
+                                        self.drag_y = self.cursor_y;
+                                        self.drag_held_x = 0;
+                                        self.drag_held_y = 0;
+                                    }
+                                }
+                            }
+                            break;
+                        }
+                    }
+                    i += 1;
+                }
+                if let Some(id) = force_close {
+                    let _ = self.close(id);
+                } else if let Some(id) = minimize {
+                    let _ = self.minimize(id);
+                } else if focus > 0 {
+                    if let Some(&id) = self.order.get(focus) {
+                        self.raise(id);
+                    }
+                }
+            }
+
+            self.update_cursor();
+        } else if event.code == EVENT_TOUCH {
+            let mut touch = TouchEvent::from_event(event);
+            let (x, y) = self.clamp_point(touch.x, touch.y);
+            touch.x = x;
+            touch.y = y;
+
+            //A contact goes to the touch aware window it started on, or emulates the pointer if it is the first contact
+            if touch.phase == TouchPhase::Down {
+                let target = match self.window_at(x, y) {
+                    Some(id) if self.windows.get(&id).map_or(false, |window| window.touch) && ! self.blocked(id) => Some(id),
+                    _ => None
+                };
+                if target.is_none() && self.touch_pointer.is_none() {
+                    self.touch_pointer = Some(touch.id);
+                }
+                self.touches.insert(touch.id, target);
+            }
+
+            match self.touches.get(&touch.id).map(|target| *target) {
+                Some(Some(id)) => if let Some(mut window) = self.windows.get_mut(&id) {
+                    window.mouse_event(Event {
+                        time: event.time,
+                        ..touch.to_event()
+                    });
+                },
+                Some(None) => if self.touch_pointer == Some(touch.id) {
+                    self.event(Event {
+                        time: event.time,
+                        ..MouseEvent {
+                            x: x,
+                            y: y,
+                            left_button: touch.phase != TouchPhase::Up,
+                            middle_button: false,
+                            right_button: false
+                        }.to_event()
+                    });
+                },
+                None => ()
+            }
+
+            if touch.phase == TouchPhase::Up {
+                self.touches.remove(&touch.id);
+                if self.touch_pointer == Some(touch.id) {
+                    self.touch_pointer = None;
+                }
+            }
+        } else if event.code == EVENT_SCROLL {
+            //Scrolling goes to the window that grabbed the pointer, or the window under the cursor
+            if let Some(id) = self.grabbed().or(self.window_at(self.cursor_x, self.cursor_y)) {
+                if ! self.blocked(id) {
+                    if let Some(mut window) = self.windows.get_mut(&id) {
+                        window.event(event);
+                    }
+                }
+            }
+        } else if event.code == EVENT_MOUSE_RELATIVE {
+            //Relative motion is only sent to a window that grabbed the pointer in relative mode
+            if let Some(id) = self.grabbed() {
+                if let Some(mut window) = self.windows.get_mut(&id) {
+                    if window.relative {
+                        window.event(event);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Read the ids of the windows of a wait handle that have data, one per line, such as `3\n7\n`
+    ///
+    /// A window that was closed is also ready, so its client is not left waiting. Only whole lines are read, and 0
+    /// is returned if no window is ready, which keeps the read waiting until one is.
+    fn ready_windows(&self, id: usize, buf: &mut [u8]) -> Result<usize> {
+        let ids = match self.waits.get(&id) {
+            Some(ids) => ids,
+            None => return Err(Error::new(EBADF))
+        };
+
+        let mut i = 0;
+        for window_id in ids.iter() {
+            if self.windows.get(window_id).map_or(true, |window| window.ready()) {
+                let line = format!("{}\n", window_id);
+                if i + line.len() > buf.len() {
+                    break;
+                }
+                for b in line.bytes() {
+                    buf[i] = b;
+                    i += 1;
+                }
+            }
+        }
+        Ok(i)
+    }
+
+    /// Whether a read would wait for data instead of returning 0, as reads from windows that are not async do
+    fn blocking(&self, packet: &Packet) -> bool {
+        if packet.a == SYS_READ {
+            //The packet is packed, so its fields are copied rather than borrowed
+            let id = packet.b;
+            if let Some(window) = self.windows.get(&id) {
+                window.async == false
+            } else {
+                ! self.controls.contains_key(&id) && ! self.screenshots.contains_key(&id)
+            }
+        } else {
+            false
+        }
+    }
+
+    /// Handle requests from clients, returning the responses
+    ///
+    /// Reads that would wait for data are kept until `retry` finishes them
+    pub fn requests(&mut self, packets: &mut [Packet]) -> Vec<Packet> {
+        let mut responses = Vec::new();
+        for mut packet in packets.iter_mut() {
+            let blocking = self.blocking(packet);
+
+            self.handle(packet);
+
+            if blocking && packet.a == 0 {
+                self.todo.push(*packet);
+            } else {
+                responses.push(*packet);
+            }
+        }
+        responses
+    }
+
+    /// Try the reads kept by `requests` again, returning the responses to those that finished
+    pub fn retry(&mut self) -> Vec<Packet> {
+        let mut packets = Vec::new();
+        mem::swap(&mut self.todo, &mut packets);
+        self.requests(&mut packets)
+    }
+}
+
+impl Scheme for OrbitalScheme {
+    /// Opening `orbital:wait/3/7` makes a wait handle for the windows with the ids 3 and 7, as read after writing `F,/id` to them
+    ///
+    /// A client with async windows, or windows opened with `O_NONBLOCK`, can block on reading the wait handle until
+    /// one of them has events, instead of reading each window in a loop, see `OrbitalScheme::ready_windows`.
+    fn open(&mut self, url: &str, flags: usize, _mode: usize) -> Result<usize> {
+        let path = path::split(url).map_or(url, |(_, reference)| reference);
+        if path == "control" {
+            let id = self.next_id as usize;
+            self.next_id += 1;
+            if self.next_id < 0 {
+                self.next_id = 1;
+            }
+
+            self.controls.insert(id, Control::new());
+
+            return Ok(id);
+        } else if path == "windows" {
+            let id = self.next_id as usize;
+            self.next_id += 1;
+            if self.next_id < 0 {
+                self.next_id = 1;
+            }
+
+            let mut window_list = WindowList::new();
+            for window_id in self.order.iter() {
+                window_list.push(&self.window_open_line(*window_id));
+            }
+            self.window_lists.insert(id, window_list);
+
+            return Ok(id);
+        } else if path == "screenshot" {
+            let id = self.next_id as usize;
+            self.next_id += 1;
+            if self.next_id < 0 {
+                self.next_id = 1;
+            }
+
+            //Draw anything still scheduled, so the copy matches what is on the displays
+            self.redraw();
+            self.screenshots.insert(id, Screenshot::new(&self.image));
+
+            return Ok(id);
+        } else if path == "clipboard" || path.starts_with("clipboard/") {
+            //Reading gets the current entry, writing copies a new one of the kind in the path when closed, and
+            //`clipboard/selection` does the same with the primary selection
+            let kind = if path == "clipboard" || path == "clipboard/selection" {
+                None
+            } else {
+                match ClipboardKind::parse(&path[10..]) {
+                    Some(kind) => Some(kind),
+                    None => return Err(Error::new(EINVAL))
+                }
+            };
+
+            let id = self.next_id as usize;
+            self.next_id += 1;
+            if self.next_id < 0 {
+                self.next_id = 1;
+            }
+
+            let clipboard_handle = if path == "clipboard/selection" {
+                ClipboardHandle::selection(&self.clipboard)
+            } else {
+                ClipboardHandle::new(&self.clipboard, kind)
+            };
+            self.clipboard_handles.insert(id, clipboard_handle);
+
+            return Ok(id);
+        } else if path == "record" || path.starts_with("record/") {
+            let fps = path.split("/").nth(1).unwrap_or("").parse::<u32>().unwrap_or(record::DEFAULT_FPS);
+
+            let id = self.next_id as usize;
+            self.next_id += 1;
+            if self.next_id < 0 {
+                self.next_id = 1;
+            }
+
+            let screen_rect = self.screen_rect();
+            self.recorders.insert(id, Recorder::new(fps, screen_rect));
+
+            return Ok(id);
+        } else if path == "wait" || path.starts_with("wait/") {
+            let mut ids = Vec::new();
+            for part in path.split("/").skip(1).filter(|part| ! part.is_empty()) {
+                match part.parse::<usize>() {
+                    Ok(window_id) => ids.push(window_id),
+                    Err(_) => return Err(Error::new(EINVAL))
+                }
+            }
+
+            let id = self.next_id as usize;
+            self.next_id += 1;
+            if self.next_id < 0 {
+                self.next_id = 1;
+            }
+
+            self.waits.insert(id, ids);
+
+            return Ok(id);
+        }
+
+        //Windows opened without blocking are async, whatever their flags
+        let nonblock = flags & O_NONBLOCK == O_NONBLOCK;
+
+        let mut parts = path.split("/");
+
+        let flags = parts.next().unwrap_or("");
+
+        let mut async = nonblock;
+        let mut scale_aware = false;
+        let mut popup = false;
+        let mut panel = false;
+        for flag in flags.chars() {
+            match flag {
+                'a' => async = true,
+                's' => scale_aware = true,
+                'p' => popup = true,
+                'k' => panel = true,
+                _ => ()
+            }
+        }
+
+        let mut x = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
+        let mut y = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
+        let width = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
+        let height = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
+
+        //The title is escaped, but older clients may leave slashes in it
+        let mut title = parts.next().unwrap_or("").to_string();
+        for part in parts {
+            title.push('/');
+            title.push_str(part);
+        }
+        let title = unescape_arg(&title);
+
+        let id = self.next_id as usize;
+        self.next_id += 1;
+        if self.next_id < 0 {
+            self.next_id = 1;
+        }
+
+        if x < 0 && y < 0 {
+            //Place new windows on the monitor containing the cursor
+            let monitor_rect = self.monitor_rect(self.cursor_x, self.cursor_y);
+            let scale = self.monitor_scale(self.cursor_x, self.cursor_y);
+            x = monitor_rect.left() + self.next_x * scale;
+            y = monitor_rect.top() + self.next_y * scale;
+
+            self.next_x += 20;
+            if (self.next_x + 20) * scale >= monitor_rect.width() {
+                self.next_x = 20;
+            }
+            self.next_y += 20;
+            if (self.next_y + 20) * scale >= monitor_rect.height() {
+                self.next_y = 20;
+            }
+        }
+
+        if let Some(id) = self.focused() {
+            if let Some(window) = self.windows.get(&id){
+                schedule(&mut self.redraws, window.title_rect());
+                schedule(&mut self.redraws, window.rect());
+            }
+        }
+
+        //A new window is shown even if the desktop is
+        if self.show_desktop {
+            self.show_desktop = false;
+            let screen_rect = self.screen_rect();
+            schedule(&mut self.redraws, screen_rect);
+        }
+
+        let scale = self.monitor_scale(x, y);
+        let mut window = Window::new(x, y, width, height, title, async, scale_aware, popup, scale, self.workspace);
+        window.set_flags(flags);
+        window.panel = panel;
+        window.theme = self.theme;
+        schedule(&mut self.redraws, window.title_rect());
+        schedule(&mut self.redraws, window.rect());
+        if self.animate && ! panel {
+            let frame = window.frame_rect();
+            self.animations.push(Animation::new(id, AnimationKind::Open, frame, frame));
+            self.deadline_added = true;
+        }
+        self.order.push_front(id);
+        self.windows.insert(id, window);
+        if panel {
+            self.dock_panel(id);
+        } else {
+            //A new window takes focus from the window under the cursor
+            self.pointer_focus = None;
+        }
+
+        let line = self.window_open_line(id);
+        self.notify(&line);
+
+        if flags.contains('f') {
+            self.set_fullscreen(id, true);
+        }
+
+        Ok(id)
+    }
+
+    fn read(&mut self, id: usize, buf: &mut [u8]) -> Result<usize> {
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            return window.read(buf);
+        }
+
+        if let Some(mut window_list) = self.window_lists.get_mut(&id) {
+            return Ok(window_list.read(buf));
+        }
+
+        if let Some(mut clipboard_handle) = self.clipboard_handles.get_mut(&id) {
+            return Ok(clipboard_handle.read(buf));
+        }
+
+        if let Some(mut screenshot) = self.screenshots.get_mut(&id) {
+            return Ok(screenshot.read(buf));
+        }
+
+        if let Some(mut recorder) = self.recorders.get_mut(&id) {
+            return Ok(recorder.read(buf, &self.image));
+        }
+
+        if self.waits.contains_key(&id) {
+            return self.ready_windows(id, buf);
+        }
+
+        let list = self.window_list();
+        if let Some(mut control) = self.controls.get_mut(&id) {
+            Ok(control.read(buf, &list))
+        } else {
+            Err(Error::new(EBADF))
+        }
+    }
+
+    fn write(&mut self, id: usize, buf: &[u8]) -> Result<usize> {
+        //Input panels type into the focused window
+        let key = match self.windows.get(&id) {
+            Some(window) if window.panel && window.is_key_write(buf) => Some(try!(window.key(buf))),
+            _ => None
+        };
+        if let Some(event) = key {
+            if let Some(focused) = self.focused() {
+                if self.hold_key(focused, event) {
+                    if let Some(mut window) = self.windows.get_mut(&focused) {
+                        window.event(event);
+                    }
+                }
+            }
+            return Ok(buf.len());
+        }
+
+        if self.windows.get(&id).map_or(false, |window| window.is_selection_write(buf)) {
+            self.clipboard.set_selection(buf[2..].to_vec());
+            return Ok(buf.len());
+        }
+
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            if window.is_pong_write(buf) {
+                if try!(window.pong(buf)) && window.workspace == self.workspace {
+                    schedule(&mut self.redraws, window.title_rect());
+                    schedule(&mut self.redraws, window.rect());
+                }
+                return Ok(buf.len());
+            }
+        }
+
+        if self.windows.get(&id).map_or(false, |window| window.is_flags_write(buf)) {
+            try!(self.set_window_flags(id, String::from_utf8_lossy(&buf[2..]).trim_right()));
+            return Ok(buf.len());
+        }
+
+        //Show the cursor the window chose now, if it is over the window
+        if self.windows.get(&id).map_or(false, |window| window.is_cursor_write(buf)) {
+            let result = match self.windows.get_mut(&id) {
+                Some(mut window) => window.set_cursor(buf),
+                None => Err(Error::new(EBADF))
+            };
+            self.update_cursor();
+            return result;
+        }
+
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            if window.icon_pending {
+                window.icon_pending = false;
+                if window.workspace == self.workspace {
+                    schedule(&mut self.redraws, window.title_rect());
+                }
+                return window.set_icon(buf);
+            }
+            if window.is_queue_write(buf) {
+                self.deadline_added = true;
+                return window.queue(buf);
+            }
+            if window.is_title_write(buf) {
+                let visible = window.workspace == self.workspace;
+                if visible {
+                    schedule(&mut self.redraws, window.title_rect());
+                }
+                let result = window.write(buf);
+                if visible {
+                    schedule(&mut self.redraws, window.title_rect());
+                }
+                let line = format!("title/{}/{}", id, escape_arg(window.title()));
+                for (_, window_list) in self.window_lists.iter_mut() {
+                    window_list.push(&line);
+                }
+                return result;
+            }
+            if window.is_damage_write(buf) {
+                let rect = try!(window.damage(buf));
+                if window.workspace == self.workspace {
+                    schedule(&mut self.redraws, rect);
+                }
+                return Ok(buf.len());
+            }
+            if window.workspace == self.workspace {
+                schedule(&mut self.redraws, window.rect());
+            }
+            return window.write(buf);
+        }
+
+        if let Some(mut clipboard_handle) = self.clipboard_handles.get_mut(&id) {
+            return Ok(clipboard_handle.write(buf));
+        }
+
+        if self.controls.contains_key(&id) || self.window_lists.contains_key(&id) {
+            let string = String::from_utf8_lossy(buf).into_owned();
+            for line in string.lines() {
+                match ControlCommand::parse(line) {
+                    Some(command) => {
+                        try!(self.command(command));
+                    },
+                    None => return Err(Error::new(EINVAL))
+                }
+            }
+            Ok(buf.len())
+        } else {
+            Err(Error::new(EBADF))
+        }
+    }
+
+    /// Mapping a window shares its contents with the client, which draws into them without writing every frame
+    /// through the scheme, and writes `D,x,y,width,height` to redraw the rectangle it changed, see `Window::map`
+    fn fmap(&mut self, id: usize, offset: usize, size: usize) -> Result<usize> {
+        if let Some(mut window) = self.windows.get_mut(&id) {
+            window.map(offset, size)
+        } else {
+            Err(Error::new(EBADF))
+        }
+    }
+
+    /// The path of a window has its flags and geometry, as `orbital:flags/x/y/width/height/title`
+    ///
+    /// The path of a screenshot has the size of the frame, as `orbital:screenshot/width/height`, and the path of a
+    /// recording has its frame rate and the size of the desktop, as `orbital:record/fps/width/height`. The path of a wait
+    /// handle has the ids of its windows, as `orbital:wait/3/7`, and the path of a clipboard handle has the kind of its
+    /// entry, as `orbital:clipboard/text` or `orbital:clipboard/image/width/height`, or `orbital:clipboard/selection`
+    fn fpath(&self, id: usize, buf: &mut [u8]) -> Result<usize> {
+        if let Some(window) = self.windows.get(&id) {
+            window.path(buf)
+        } else if let Some(clipboard_handle) = self.clipboard_handles.get(&id) {
+            let path = clipboard_handle.path();
+            let mut i = 0;
+            for b in path.bytes() {
+                if i < buf.len() {
+                    buf[i] = b;
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            Ok(i)
+        } else if let Some(screenshot) = self.screenshots.get(&id) {
+            let path = format!("orbital:screenshot/{}/{}", screenshot.width(), screenshot.height());
+            let mut i = 0;
+            for b in path.bytes() {
+                if i < buf.len() {
+                    buf[i] = b;
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            Ok(i)
+        } else if let Some(recorder) = self.recorders.get(&id) {
+            let path = format!("orbital:record/{}/{}/{}", recorder.fps(), self.image.width(), self.image.height());
+            let mut i = 0;
+            for b in path.bytes() {
+                if i < buf.len() {
+                    buf[i] = b;
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            Ok(i)
+        } else if let Some(ids) = self.waits.get(&id) {
+            let mut path = "orbital:wait".to_string();
+            for window_id in ids.iter() {
+                path.push_str(&format!("/{}", window_id));
+            }
+            let mut i = 0;
+            for b in path.bytes() {
+                if i < buf.len() {
+                    buf[i] = b;
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            Ok(i)
+        } else if self.controls.contains_key(&id) || self.window_lists.contains_key(&id) {
+            let mut i = 0;
+            let path = if self.controls.contains_key(&id) { &b"orbital:control"[..] } else { &b"orbital:windows"[..] };
+            while i < buf.len() && i < path.len() {
+                buf[i] = path[i];
+                i += 1;
+            }
+            Ok(i)
+        } else {
+            Err(Error::new(EBADF))
+        }
+    }
+
+    fn close(&mut self, id: usize) -> Result<usize> {
+        if let Some(clipboard_handle) = self.clipboard_handles.remove(&id) {
+            if clipboard_handle.selection {
+                if let Some(entry) = clipboard_handle.into_entry() {
+                    self.clipboard.set_selection(entry.data);
+                }
+            } else if let Some(entry) = clipboard_handle.into_entry() {
+                for (_, rect) in self.clipboard_rows() {
+                    schedule(&mut self.redraws, rect);
+                }
+                self.clipboard.push(entry);
+                for (_, rect) in self.clipboard_rows() {
+                    schedule(&mut self.redraws, rect);
+                }
+            }
+            return Ok(0);
+        }
+
+        if self.controls.remove(&id).is_some() || self.window_lists.remove(&id).is_some() || self.screenshots.remove(&id).is_some()
+            || self.recorders.remove(&id).is_some() || self.waits.remove(&id).is_some() {
+            return Ok(0);
+        }
+
+        self.order.retain(|&e| e != id);
+        self.animations.retain(|animation| animation.window != id);
+
+        if let Some(id) = self.focused() {
+            if let Some(window) = self.windows.get(&id){
+                schedule(&mut self.redraws, window.title_rect());
+                schedule(&mut self.redraws, window.rect());
+            }
+        }
+
+        for (_, child) in self.windows.iter_mut() {
+            if child.parent == Some(id) {
+                child.parent = None;
+            }
+        }
+
+        if let Some(window) = self.windows.remove(&id) {
+            schedule(&mut self.redraws, window.title_rect());
+            schedule(&mut self.redraws, window.rect());
+            self.notify(&format!("close/{}", id));
+            Ok(0)
+        } else {
+            Err(Error::new(EBADF))
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::{cmp, mem};
+
+use super::{Display, MemoryDisplay, Rect};
+use super::gamma::{Calibration, Ramp};
+
+/// How far a display is turned clockwise, for portrait monitors and tablets
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl Rotation {
+    /// Parse a rotation in degrees, `0`, `90`, `180`, or `270`
+    pub fn parse(degrees: &str) -> Option<Rotation> {
+        match degrees {
+            "0" => Some(Rotation::None),
+            "90" => Some(Rotation::Quarter),
+            "180" => Some(Rotation::Half),
+            "270" => Some(Rotation::ThreeQuarters),
+            _ => None
+        }
+    }
+
+    /// Displays turned a quarter either way swap their width and height
+    pub fn swaps_axes(&self) -> bool {
+        *self == Rotation::Quarter || *self == Rotation::ThreeQuarters
+    }
+}
+
+/// A display, positioned on the desktop
+pub struct Monitor {
+    /// The area of the desktop shown on the display, after rotation
+    pub rect: Rect,
+    /// The area of the display in the coordinates used by the kernel for the pointer, before rotation
+    pub physical: Rect,
+    pub rotation: Rotation,
+    /// The number of pixels per logical pixel, used for decorations and windows that are not scale aware
+    pub scale: i32,
+    pub display: Box<Display>,
+    /// The pixels of a rotated display before they are sent
+    pub data: Vec<u32>,
+    /// The gamma and white point set for the display, before the night light
+    pub calibration: Calibration,
+    /// The lookup of the current calibration, `None` if it leaves the colors as they are
+    ramp: Option<Ramp>,
+    /// The calibrated pixels of a row, kept to avoid allocating for every row
+    calibrated: Vec<u32>,
+}
+
+impl Monitor {
+    /// Create a monitor showing an area of the desktop on a display
+    pub fn new(rect: Rect, display: Box<Display>) -> Monitor {
+        Monitor {
+            rect: rect,
+            physical: rect,
+            rotation: Rotation::None,
+            scale: 1,
+            display: display,
+            data: Vec::new(),
+            calibration: Calibration::new(),
+            ramp: None,
+            calibrated: Vec::new()
+        }
+    }
+
+    /// Create a monitor that is drawn to memory, for benchmarks
+    pub fn memory(rect: Rect) -> Monitor {
+        Monitor::new(rect, Box::new(MemoryDisplay::new(rect.width(), rect.height())))
+    }
+
+    /// Turn the display, placing its left edge at `x` on the desktop
+    pub fn set_rotation(&mut self, rotation: Rotation, x: i32) {
+        let (width, height) = if rotation.swaps_axes() {
+            (self.physical.height(), self.physical.width())
+        } else {
+            (self.physical.width(), self.physical.height())
+        };
+        self.rotation = rotation;
+        self.rect = Rect::new(x, self.physical.top(), width, height);
+        let size = self.physical.width() as usize * self.physical.height() as usize;
+        if rotation != Rotation::None && self.data.len() != size {
+            self.data = vec![0; size];
+        }
+    }
+
+    /// Build the lookup of the calibration, with the white point of the night light if it is on
+    ///
+    /// The whole monitor must be redrawn to show it
+    pub fn update_ramp(&mut self, night_temperature: Option<u32>) {
+        let mut calibration = self.calibration;
+        if let Some(temperature) = night_temperature {
+            calibration.temperature = cmp::min(calibration.temperature, temperature);
+        }
+        self.ramp = if calibration.is_identity() {
+            None
+        } else {
+            Some(Ramp::new(&calibration))
+        };
+    }
+
+    /// Convert a point on the desktop, which must be on the monitor, to a point on the display
+    fn to_display(&self, x: i32, y: i32) -> (i32, i32) {
+        let x = x - self.rect.left();
+        let y = y - self.rect.top();
+        let w = self.physical.width();
+        let h = self.physical.height();
+        match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Quarter => (w - 1 - y, x),
+            Rotation::Half => (w - 1 - x, h - 1 - y),
+            Rotation::ThreeQuarters => (y, h - 1 - x),
+        }
+    }
+
+    /// Convert an absolute point from a touch screen, in kernel coordinates on the display, to a point on the desktop
+    pub fn touch_point(&self, x: i32, y: i32) -> (i32, i32) {
+        let x = x - self.physical.left();
+        let y = y - self.physical.top();
+        let w = self.physical.width();
+        let h = self.physical.height();
+        let (x, y) = match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Quarter => (y, w - 1 - x),
+            Rotation::Half => (w - 1 - x, h - 1 - y),
+            Rotation::ThreeQuarters => (h - 1 - y, x),
+        };
+        (self.rect.left() + x, self.rect.top() + y)
+    }
+
+    /// Convert a pointer position, in kernel coordinates on the display, to a point on the desktop
+    ///
+    /// The axes are stretched instead of turned, so moving the mouse right still moves the cursor right
+    pub fn pointer_point(&self, x: i32, y: i32) -> (i32, i32) {
+        if self.rotation == Rotation::None || self.physical.width() == 0 || self.physical.height() == 0 {
+            return (x - self.physical.left() + self.rect.left(), y - self.physical.top() + self.rect.top());
+        }
+        let x = (x - self.physical.left()) * self.rect.width() / self.physical.width();
+        let y = (y - self.physical.top()) * self.rect.height() / self.physical.height();
+        (self.rect.left() + x, self.rect.top() + y)
+    }
+
+    /// Write a row of pixels starting at a point on the desktop, which must be on the monitor
+    ///
+    /// Rows of a rotated display are kept until `flush`
+    pub fn write(&mut self, x: i32, y: i32, pixels: &[u32]) {
+        let calibrated = match self.ramp {
+            Some(ref ramp) => {
+                let mut calibrated = mem::replace(&mut self.calibrated, Vec::new());
+                calibrated.clear();
+                calibrated.extend(pixels.iter().map(|pixel| ramp.apply(*pixel)));
+                Some(calibrated)
+            },
+            None => None
+        };
+
+        match calibrated {
+            Some(calibrated) => {
+                self.write_raw(x, y, &calibrated);
+                self.calibrated = calibrated;
+            },
+            None => self.write_raw(x, y, pixels)
+        }
+    }
+
+    fn write_raw(&mut self, x: i32, y: i32, pixels: &[u32]) {
+        if self.rotation != Rotation::None {
+            let width = self.physical.width();
+            for (i, pixel) in pixels.iter().enumerate() {
+                let (px, py) = self.to_display(x + i as i32, y);
+                self.data[(py * width + px) as usize] = *pixel;
+            }
+            return;
+        }
+
+        let offset = ((y - self.rect.top()) * self.rect.width() + x - self.rect.left()) as usize;
+        self.display.write(offset, pixels);
+    }
+
+    /// Send the pixels written to an area of the desktop on a rotated display
+    pub fn flush(&mut self, rect: &Rect) {
+        if self.rotation == Rotation::None || rect.is_empty() {
+            return;
+        }
+
+        //The corners of the area on the display
+        let (x1, y1) = self.to_display(rect.left(), rect.top());
+        let (x2, y2) = self.to_display(rect.right() - 1, rect.bottom() - 1);
+        let left = if x1 < x2 { x1 } else { x2 };
+        let right = if x1 < x2 { x2 } else { x1 } + 1;
+        let top = if y1 < y2 { y1 } else { y2 };
+        let bottom = if y1 < y2 { y2 } else { y1 } + 1;
+
+        let width = self.physical.width();
+        for row in top..bottom {
+            let offset = (row * width + left) as usize;
+            self.display.write(offset, &self.data[offset .. offset + (right - left) as usize]);
+        }
+    }
+}
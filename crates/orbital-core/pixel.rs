@@ -0,0 +1,237 @@
+//! Alpha blending of rows of pixels, used by `ImageRoi`
+//!
+//! On x86_64, where SSE2 is always available, four pixels are blended at a time, and the scalar loops finish the
+//! rest of a row. Both give exactly the same results, the alpha of blended pixels is cleared.
+
+use std::cmp;
+
+/// The value 255 in each of eight words
+#[cfg(target_arch = "x86_64")]
+static WORDS_255: [u16; 8] = [255; 8];
+/// The alpha of each of four pixels
+#[cfg(target_arch = "x86_64")]
+static ALPHA_MASK: [u32; 4] = [0xFF000000; 4];
+/// The color of each of four pixels
+#[cfg(target_arch = "x86_64")]
+static COLOR_MASK: [u32; 4] = [0x00FFFFFF; 4];
+
+/// Blend the pixels of `new` onto `old` by their alpha
+pub fn blend(old: &mut [u32], new: &[u32]) {
+    let len = cmp::min(old.len(), new.len());
+    let done = blend_fast(&mut old[.. len], &new[.. len]);
+    blend_scalar(&mut old[done .. len], &new[done .. len]);
+}
+
+/// Blend a color onto every pixel of `old` by its alpha
+pub fn blend_color(old: &mut [u32], color: u32) {
+    let done = blend_color_fast(old, color);
+    blend_color_scalar(&mut old[done ..], color);
+}
+
+pub fn blend_scalar(old: &mut [u32], new: &[u32]) {
+    for (mut old, new) in old.iter_mut().zip(new.iter()) {
+        let alpha = (*new >> 24) & 0xFF;
+        if alpha >= 255 {
+            *old = *new;
+        } else if alpha > 0 {
+            let n_r = (((*new >> 16) & 0xFF) * alpha) >> 8;
+            let n_g = (((*new >> 8) & 0xFF) * alpha) >> 8;
+            let n_b = ((*new & 0xFF) * alpha) >> 8;
+
+            let n_alpha = 255 - alpha;
+
+            let o_r = (((*old >> 16) & 0xFF) * n_alpha) >> 8;
+            let o_g = (((*old >> 8) & 0xFF) * n_alpha) >> 8;
+            let o_b = ((*old & 0xFF) * n_alpha) >> 8;
+
+            *old = ((o_r << 16) | (o_g << 8) | o_b) + ((n_r << 16) | (n_g << 8) | n_b);
+        }
+    }
+}
+
+pub fn blend_color_scalar(old: &mut [u32], color: u32) {
+    let alpha = (color >> 24) & 0xFF;
+    if alpha >= 255 {
+        for mut old in old.iter_mut() {
+            *old = color;
+        }
+    } else if alpha > 0 {
+        let n_r = (((color >> 16) & 0xFF) * alpha) >> 8;
+        let n_g = (((color >> 8) & 0xFF) * alpha) >> 8;
+        let n_b = ((color & 0xFF) * alpha) >> 8;
+
+        let n_alpha = 255 - alpha;
+
+        for mut old in old.iter_mut() {
+            let o_r = (((*old >> 16) & 0xFF) * n_alpha) >> 8;
+            let o_g = (((*old >> 8) & 0xFF) * n_alpha) >> 8;
+            let o_b = ((*old & 0xFF) * n_alpha) >> 8;
+
+            *old = ((o_r << 16) | (o_g << 8) | o_b) + ((n_r << 16) | (n_g << 8) | n_b);
+        }
+    }
+}
+
+/// Blend groups of four pixels with SSE2, returning the number of pixels blended
+#[cfg(target_arch = "x86_64")]
+fn blend_fast(old: &mut [u32], new: &[u32]) -> usize {
+    let groups = old.len() / 4;
+    for i in 0..groups {
+        unsafe {
+            asm!("movdqu xmm5, [$2]
+                pxor xmm7, xmm7
+                movdqu xmm0, [$1]
+                movdqu xmm1, [$0]
+
+                movdqa xmm2, xmm0
+                punpcklbw xmm2, xmm7
+                pshuflw xmm3, xmm2, 0xFF
+                pshufhw xmm3, xmm3, 0xFF
+                movdqa xmm4, xmm1
+                punpcklbw xmm4, xmm7
+                pmullw xmm2, xmm3
+                psrlw xmm2, 8
+                movdqa xmm8, xmm5
+                psubw xmm8, xmm3
+                pmullw xmm4, xmm8
+                psrlw xmm4, 8
+                paddw xmm2, xmm4
+
+                movdqa xmm3, xmm0
+                punpckhbw xmm3, xmm7
+                pshuflw xmm4, xmm3, 0xFF
+                pshufhw xmm4, xmm4, 0xFF
+                movdqa xmm8, xmm1
+                punpckhbw xmm8, xmm7
+                pmullw xmm3, xmm4
+                psrlw xmm3, 8
+                movdqa xmm9, xmm5
+                psubw xmm9, xmm4
+                pmullw xmm8, xmm9
+                psrlw xmm8, 8
+                paddw xmm3, xmm8
+
+                packuswb xmm2, xmm3
+                movdqu xmm6, [$3]
+                movdqa xmm3, xmm6
+                pandn xmm3, xmm2
+
+                movdqa xmm4, xmm0
+                pand xmm4, xmm6
+                movdqa xmm8, xmm4
+                pcmpeqd xmm8, xmm6
+                pcmpeqd xmm4, xmm7
+                pand xmm0, xmm8
+                pand xmm1, xmm4
+                por xmm8, xmm4
+                pandn xmm8, xmm3
+                por xmm8, xmm0
+                por xmm8, xmm1
+                movdqu [$0], xmm8"
+                :
+                : "r"(old[i * 4 ..].as_mut_ptr()), "r"(new[i * 4 ..].as_ptr()), "r"(WORDS_255.as_ptr()), "r"(ALPHA_MASK.as_ptr())
+                : "memory", "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7", "xmm8", "xmm9"
+                : "intel", "volatile");
+        }
+    }
+    groups * 4
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn blend_fast(_old: &mut [u32], _new: &[u32]) -> usize {
+    0
+}
+
+/// Blend a translucent color onto groups of four pixels with SSE2, returning the number of pixels blended
+#[cfg(target_arch = "x86_64")]
+fn blend_color_fast(old: &mut [u32], color: u32) -> usize {
+    let alpha = (color >> 24) & 0xFF;
+    if alpha == 0 || alpha >= 255 {
+        return 0;
+    }
+
+    let n_r = (((color >> 16) & 0xFF) * alpha) >> 8;
+    let n_g = (((color >> 8) & 0xFF) * alpha) >> 8;
+    let n_b = ((color & 0xFF) * alpha) >> 8;
+    let new = [(n_r << 16) | (n_g << 8) | n_b; 4];
+    let n_alpha = [(255 - alpha) as u16; 8];
+
+    let groups = old.len() / 4;
+    for i in 0..groups {
+        unsafe {
+            asm!("movdqu xmm5, [$1]
+                movdqu xmm6, [$2]
+                movdqu xmm4, [$3]
+                pxor xmm7, xmm7
+                movdqu xmm1, [$0]
+
+                movdqa xmm2, xmm1
+                punpcklbw xmm2, xmm7
+                pmullw xmm2, xmm5
+                psrlw xmm2, 8
+                movdqa xmm3, xmm1
+                punpckhbw xmm3, xmm7
+                pmullw xmm3, xmm5
+                psrlw xmm3, 8
+
+                packuswb xmm2, xmm3
+                pand xmm2, xmm4
+                paddd xmm2, xmm6
+                movdqu [$0], xmm2"
+                :
+                : "r"(old[i * 4 ..].as_mut_ptr()), "r"(n_alpha.as_ptr()), "r"(new.as_ptr()), "r"(COLOR_MASK.as_ptr())
+                : "memory", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7"
+                : "intel", "volatile");
+        }
+    }
+    groups * 4
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn blend_color_fast(_old: &mut [u32], _color: u32) -> usize {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blend, blend_color, blend_color_scalar, blend_scalar};
+
+    /// Pixels with every kind of alpha, from a linear congruential generator
+    fn pixels(len: usize, mut seed: u32) -> Vec<u32> {
+        let mut pixels = Vec::new();
+        for i in 0..len {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            pixels.push(match i % 4 {
+                0 => seed | 0xFF000000,
+                1 => seed & 0x00FFFFFF,
+                _ => seed
+            });
+        }
+        pixels
+    }
+
+    #[test]
+    fn blend_matches_scalar() {
+        for len in 0..19 {
+            let new = pixels(len, len as u32 + 1);
+            let mut expected = pixels(len, len as u32 + 100);
+            let mut actual = expected.clone();
+            blend_scalar(&mut expected, &new);
+            blend(&mut actual, &new);
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn blend_color_matches_scalar() {
+        for &color in [0x00FF0000, 0x01000000, 0x80FF8040, 0xFEFFFFFF, 0xFF123456].iter() {
+            for len in 0..19 {
+                let mut expected = pixels(len, len as u32 + 7);
+                let mut actual = expected.clone();
+                blend_color_scalar(&mut expected, color);
+                blend_color(&mut actual, color);
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+}
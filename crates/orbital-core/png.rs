@@ -0,0 +1,411 @@
+use super::{Color, Image};
+
+use std::fs::File;
+use std::io::Read;
+
+macro_rules! try_some {
+    ($expr:expr) => (match $expr {
+        Some(value) => value,
+        None => return None
+    })
+}
+
+/// Reads bits from a deflate stream, least significant bit first
+struct BitReader<'a> {
+    data: &'a [u8],
+    i: usize,
+    bit: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader {
+            data: data,
+            i: 0,
+            bit: 0
+        }
+    }
+
+    fn bits(&mut self, count: usize) -> Option<u32> {
+        let mut value = 0;
+        for n in 0..count {
+            let byte = match self.data.get(self.i) {
+                Some(byte) => *byte,
+                None => return None
+            };
+            value |= (((byte >> self.bit) & 1) as u32) << n;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.i += 1;
+            }
+        }
+        Some(value)
+    }
+
+    fn align(&mut self) {
+        if self.bit > 0 {
+            self.bit = 0;
+            self.i += 1;
+        }
+    }
+}
+
+/// A canonical Huffman code, stored as symbol counts per length and symbols sorted by code
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0; 16];
+        for &length in lengths.iter() {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0; 16];
+        for i in 1..16 {
+            offsets[i] = offsets[i - 1] + counts[i - 1];
+        }
+
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length > 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Huffman {
+            counts: counts,
+            symbols: symbols
+        }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Option<u16> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for length in 1..16 {
+            code |= match reader.bits(1) {
+                Some(bit) => bit as i32,
+                None => return None
+            };
+            let count = self.counts[length] as i32;
+            if code - count < first {
+                return self.symbols.get((index + code - first) as usize).map(|symbol| *symbol);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        None
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Decompress a block compressed with Huffman codes
+fn inflate_block(reader: &mut BitReader, output: &mut Vec<u8>, lengths: &Huffman, distances: &Huffman) -> Option<()> {
+    loop {
+        let symbol = try_some!(lengths.decode(reader));
+        if symbol < 256 {
+            output.push(symbol as u8);
+        } else if symbol == 256 {
+            return Some(());
+        } else {
+            let i = symbol as usize - 257;
+            if i >= LENGTH_BASE.len() {
+                return None;
+            }
+            let length = LENGTH_BASE[i] as usize + try_some!(reader.bits(LENGTH_EXTRA[i] as usize)) as usize;
+
+            let d = try_some!(distances.decode(reader)) as usize;
+            if d >= DIST_BASE.len() {
+                return None;
+            }
+            let distance = DIST_BASE[d] as usize + try_some!(reader.bits(DIST_EXTRA[d] as usize)) as usize;
+            if distance > output.len() {
+                return None;
+            }
+
+            let start = output.len() - distance;
+            for n in 0..length {
+                let byte = output[start + n];
+                output.push(byte);
+            }
+        }
+    }
+}
+
+/// Decompress a zlib stream
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < 2 || data[0] & 0x0F != 8 {
+        return None;
+    }
+
+    let mut reader = BitReader::new(&data[2..]);
+    let mut output = Vec::new();
+    loop {
+        let last = try_some!(reader.bits(1));
+        match try_some!(reader.bits(2)) {
+            0 => {
+                reader.align();
+                let i = reader.i;
+                if i + 4 > reader.data.len() {
+                    return None;
+                }
+                let len = reader.data[i] as usize | (reader.data[i + 1] as usize) << 8;
+                if i + 4 + len > reader.data.len() {
+                    return None;
+                }
+                output.extend_from_slice(&reader.data[i + 4 .. i + 4 + len]);
+                reader.i = i + 4 + len;
+            },
+            1 => {
+                let mut lengths = [0; 288];
+                for i in 0..288 {
+                    lengths[i] = if i < 144 { 8 } else if i < 256 { 9 } else if i < 280 { 7 } else { 8 };
+                }
+                let distances = [5; 30];
+                try_some!(inflate_block(&mut reader, &mut output, &Huffman::new(&lengths), &Huffman::new(&distances)));
+            },
+            2 => {
+                let hlit = try_some!(reader.bits(5)) as usize + 257;
+                let hdist = try_some!(reader.bits(5)) as usize + 1;
+                let hclen = try_some!(reader.bits(4)) as usize + 4;
+
+                let mut code_lengths = [0; 19];
+                for i in 0..hclen {
+                    code_lengths[CODE_LENGTH_ORDER[i]] = try_some!(reader.bits(3)) as u8;
+                }
+                let code_huffman = Huffman::new(&code_lengths);
+
+                let mut lengths = Vec::with_capacity(hlit + hdist);
+                while lengths.len() < hlit + hdist {
+                    let symbol = try_some!(code_huffman.decode(&mut reader));
+                    let (value, repeat) = match symbol {
+                        0 ... 15 => (symbol as u8, 1),
+                        16 => (try_some!(lengths.last().map(|length| *length)), 3 + try_some!(reader.bits(2))),
+                        17 => (0, 3 + try_some!(reader.bits(3))),
+                        18 => (0, 11 + try_some!(reader.bits(7))),
+                        _ => return None
+                    };
+                    for _ in 0..repeat {
+                        lengths.push(value);
+                    }
+                }
+                if lengths.len() > hlit + hdist {
+                    return None;
+                }
+
+                let literal_huffman = Huffman::new(&lengths[.. hlit]);
+                let distance_huffman = Huffman::new(&lengths[hlit ..]);
+                try_some!(inflate_block(&mut reader, &mut output, &literal_huffman, &distance_huffman));
+            },
+            _ => return None
+        }
+
+        if last == 1 {
+            return Some(output);
+        }
+    }
+}
+
+/// The Paeth predictor used by PNG filters
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+pub struct PngFile;
+
+impl PngFile {
+    /// Load a PNG image from given path
+    pub fn from_path(path: &str) -> Image {
+        let mut data: Vec<u8> = Vec::new();
+        if let Ok(mut file) = File::open(path) {
+            let _ = file.read_to_end(&mut data);
+        }
+        PngFile::from_data(&data)
+    }
+
+    /// Decode a PNG image, unsupported or corrupt images result in an empty image
+    ///
+    /// All color types and bit depths are supported, interlaced images are not
+    pub fn from_data(file_data: &[u8]) -> Image {
+        PngFile::decode(file_data).unwrap_or(Image::new(0, 0))
+    }
+
+    fn decode(file_data: &[u8]) -> Option<Image> {
+        let get = |i: usize| -> u8 {
+            match file_data.get(i) {
+                Some(byte) => *byte,
+                None => 0,
+            }
+        };
+
+        let getd = |i: usize| -> u32 {
+            ((get(i) as u32) << 24) + ((get(i + 1) as u32) << 16) + ((get(i + 2) as u32) << 8) +
+            (get(i + 3) as u32)
+        };
+
+        if file_data.len() < 8 || &file_data[.. 8] != b"\x89PNG\r\n\x1A\n" {
+            return None;
+        }
+
+        let mut width = 0;
+        let mut height = 0;
+        let mut depth = 0;
+        let mut color_type = 0;
+        let mut interlace = 0;
+        let mut palette: Vec<u32> = Vec::new();
+        let mut transparency: Vec<u8> = Vec::new();
+        let mut compressed: Vec<u8> = Vec::new();
+
+        let mut i = 8;
+        while i + 8 <= file_data.len() {
+            let len = getd(i) as usize;
+            let kind = &file_data[i + 4 .. i + 8];
+            let start = i + 8;
+            let end = try_some!(start.checked_add(len));
+            if end > file_data.len() {
+                return None;
+            }
+            let chunk = &file_data[start .. end];
+
+            if kind == b"IHDR" {
+                width = getd(start);
+                height = getd(start + 4);
+                depth = get(start + 8);
+                color_type = get(start + 9);
+                interlace = get(start + 12);
+            } else if kind == b"PLTE" {
+                for rgb in chunk.chunks(3) {
+                    if rgb.len() == 3 {
+                        palette.push(Color::rgb(rgb[0], rgb[1], rgb[2]).data);
+                    }
+                }
+            } else if kind == b"tRNS" {
+                transparency.extend_from_slice(chunk);
+            } else if kind == b"IDAT" {
+                compressed.extend_from_slice(chunk);
+            } else if kind == b"IEND" {
+                break;
+            }
+
+            //Skip the CRC
+            i = end + 4;
+        }
+
+        if interlace != 0 || width == 0 || height == 0 || width > i32::max_value() as u32 ||
+           height > i32::max_value() as u32 {
+            return None;
+        }
+
+        let channels = match color_type {
+            0 => 1,
+            2 => 3,
+            3 => 1,
+            4 => 2,
+            6 => 4,
+            _ => return None
+        };
+        match depth {
+            1 | 2 | 4 | 8 | 16 => (),
+            _ => return None
+        }
+
+        let bits = channels * depth as usize;
+        let stride = try_some!((width as usize).checked_mul(bits).and_then(|bits| bits.checked_add(7))) / 8;
+        let bpp = (bits + 7) / 8;
+        let size = try_some!((stride + 1).checked_mul(height as usize));
+
+        let raw = try_some!(inflate(&compressed));
+        if raw.len() < size {
+            return None;
+        }
+
+        //Undo the filter of each scanline
+        let mut pixels = vec![0; stride * height as usize];
+        for y in 0..height as usize {
+            let filter = raw[y * (stride + 1)];
+            let line = &raw[y * (stride + 1) + 1 .. (y + 1) * (stride + 1)];
+            for x in 0..stride {
+                let a = if x >= bpp { pixels[y * stride + x - bpp] } else { 0 };
+                let b = if y > 0 { pixels[(y - 1) * stride + x] } else { 0 };
+                let c = if x >= bpp && y > 0 { pixels[(y - 1) * stride + x - bpp] } else { 0 };
+                pixels[y * stride + x] = match filter {
+                    0 => line[x],
+                    1 => line[x].wrapping_add(a),
+                    2 => line[x].wrapping_add(b),
+                    3 => line[x].wrapping_add(((a as u16 + b as u16) / 2) as u8),
+                    4 => line[x].wrapping_add(paeth(a, b, c)),
+                    _ => return None
+                };
+            }
+        }
+
+        //Read a sample as 8 bits, sub-byte samples are scaled up
+        let sample = |row: &[u8], index: usize| -> u8 {
+            match depth {
+                16 => row[index * 2],
+                8 => row[index],
+                _ => {
+                    let bit = index * depth as usize;
+                    let max = (1u16 << depth) - 1;
+                    let value = (row[bit / 8] >> (8 - depth as usize - bit % 8)) as u16 & max;
+                    if color_type == 3 {
+                        value as u8
+                    } else {
+                        (value * 255 / max) as u8
+                    }
+                }
+            }
+        };
+
+        let mut data = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height as usize {
+            let row = &pixels[y * stride .. (y + 1) * stride];
+            for x in 0..width as usize {
+                let index = x * channels;
+                let color = match color_type {
+                    0 => {
+                        let v = sample(row, index);
+                        Color::rgb(v, v, v).data
+                    },
+                    2 => Color::rgb(sample(row, index), sample(row, index + 1), sample(row, index + 2)).data,
+                    3 => {
+                        let entry = sample(row, index) as usize;
+                        let alpha = transparency.get(entry).map(|alpha| *alpha).unwrap_or(255) as u32;
+                        (palette.get(entry).map(|color| *color).unwrap_or(0) & 0xFFFFFF) | (alpha << 24)
+                    },
+                    4 => {
+                        let v = sample(row, index);
+                        Color::rgba(v, v, v, sample(row, index + 1)).data
+                    },
+                    _ => Color::rgba(sample(row, index), sample(row, index + 1), sample(row, index + 2), sample(row, index + 3)).data
+                };
+                data.push(color);
+            }
+        }
+
+        Some(Image::from_data(width as i32, height as i32, data.into_boxed_slice()))
+    }
+}
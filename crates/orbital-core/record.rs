@@ -0,0 +1,109 @@
+use std::mem::size_of;
+use std::slice;
+use std::time::{Duration, Instant};
+
+use super::{schedule, Image, Rect};
+
+/// The frame rate of a recording opened without one
+pub const DEFAULT_FPS: u32 = 30;
+
+/// The highest frame rate a recording can ask for
+pub const MAX_FPS: u32 = 120;
+
+/// A recording handle, opened with `orbital:record` or `orbital:record/fps`
+///
+/// Reading returns composited frames no faster than the frame rate, waiting until the desktop changes. Only the
+/// areas drawn since the last frame are copied, so each frame starts with a header of 32-bit integers: the
+/// milliseconds since the recording started, the width and height of the desktop, and the number of areas,
+/// followed by the x, y, width, and height of each area. The pixels of the areas follow, as 32-bit BGRA, row by row,
+/// in the same order. The first frame has the whole desktop.
+pub struct Recorder {
+    fps: u32,
+    interval: Duration,
+    start: Instant,
+    last: Option<Instant>,
+    /// The areas drawn since the last frame
+    damage: Vec<Rect>,
+    /// The frame being read
+    data: Vec<u32>,
+    seek: usize,
+}
+
+impl Recorder {
+    pub fn new(fps: u32, screen_rect: Rect) -> Recorder {
+        let fps = if fps == 0 || fps > MAX_FPS { DEFAULT_FPS } else { fps };
+        Recorder {
+            fps: fps,
+            interval: Duration::new(0, 1000000000 / fps),
+            start: Instant::now(),
+            last: None,
+            damage: vec![screen_rect],
+            data: Vec::new(),
+            seek: 0
+        }
+    }
+
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    /// Add an area that was drawn to the next frame
+    pub fn damage(&mut self, rect: Rect) {
+        schedule(&mut self.damage, rect);
+    }
+
+    /// Take a frame from the desktop, if it changed and the last frame was long enough ago
+    fn capture(&mut self, image: &Image) -> bool {
+        if self.damage.is_empty() || self.last.map_or(false, |last| last.elapsed() < self.interval) {
+            return false;
+        }
+
+        let elapsed = self.start.elapsed();
+        let millis = elapsed.as_secs() as u32 * 1000 + elapsed.subsec_nanos() / 1000000;
+
+        self.data.clear();
+        self.data.push(millis);
+        self.data.push(image.width() as u32);
+        self.data.push(image.height() as u32);
+        self.data.push(self.damage.len() as u32);
+        for rect in self.damage.iter() {
+            self.data.push(rect.left() as u32);
+            self.data.push(rect.top() as u32);
+            self.data.push(rect.width() as u32);
+            self.data.push(rect.height() as u32);
+        }
+
+        let pixels = image.data();
+        for rect in self.damage.iter() {
+            for row in rect.top()..rect.bottom() {
+                let offset = (row * image.width() + rect.left()) as usize;
+                self.data.extend_from_slice(&pixels[offset .. offset + rect.width() as usize]);
+            }
+        }
+
+        self.damage.clear();
+        self.seek = 0;
+        self.last = Some(Instant::now());
+        true
+    }
+
+    /// Read the current frame, taking the next one from the desktop once it has been read
+    ///
+    /// Returns 0 if there is no new frame yet
+    pub fn read(&mut self, buf: &mut [u8], image: &Image) -> usize {
+        let len = self.data.len() * size_of::<u32>();
+        if self.seek >= len && ! self.capture(image) {
+            return 0;
+        }
+
+        let bytes = unsafe { slice::from_raw_parts(self.data.as_ptr() as *const u8, self.data.len() * size_of::<u32>()) };
+
+        let mut i = 0;
+        while i < buf.len() && self.seek < bytes.len() {
+            buf[i] = bytes[self.seek];
+            self.seek += 1;
+            i += 1;
+        }
+        i
+    }
+}
@@ -1,6 +1,6 @@
 use std::cmp::{min, max};
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Rect {
     x: i32,
     y: i32,
@@ -0,0 +1,58 @@
+//! Key repeat, which sends a held key to the focused window again, first after a delay and then at a rate
+
+use std::time::{Duration, Instant};
+
+use super::event::{self, Event};
+
+/// How long a key is held before it repeats, and how often it repeats after that
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyRepeat {
+    /// Milliseconds before the first repeat
+    pub delay: u32,
+    /// Repeats per second
+    pub rate: u32,
+}
+
+impl KeyRepeat {
+    pub fn new() -> KeyRepeat {
+        KeyRepeat {
+            delay: 500,
+            rate: 30
+        }
+    }
+
+    /// Parse a delay in milliseconds and a rate in repeats per second, such as `500/30`
+    pub fn parse(value: &str) -> Option<KeyRepeat> {
+        let mut parts = value.split('/');
+        match (parts.next().and_then(|part| part.parse::<u32>().ok()), parts.next().and_then(|part| part.parse::<u32>().ok()), parts.next()) {
+            (Some(delay), Some(rate), None) if delay <= 10000 && rate > 0 && rate <= 1000 => Some(KeyRepeat {
+                delay: delay,
+                rate: rate
+            }),
+            _ => None
+        }
+    }
+
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay as u64)
+    }
+
+    /// The time between repeats
+    pub fn interval(&self) -> Duration {
+        Duration::new(0, 1000000000 / self.rate)
+    }
+}
+
+/// A key held down in a window, which is sent to it again at `next`
+#[derive(Clone, Copy, Debug)]
+pub struct HeldKey {
+    pub event: Event,
+    pub window: usize,
+    pub next: Instant,
+}
+
+/// Modifiers only change other keys, so they do not repeat
+pub fn is_modifier(scancode: u8) -> bool {
+    scancode == event::K_CTRL || scancode == event::K_ALT || scancode == event::K_LEFT_SHIFT
+        || scancode == event::K_RIGHT_SHIFT || scancode == event::K_CAPS
+}
@@ -0,0 +1,48 @@
+use std::mem::size_of;
+use std::slice;
+
+use super::Image;
+
+/// A screenshot handle, opened with `orbital:screenshot`
+///
+/// The composited desktop is copied when the handle is opened, so reads see one consistent frame. Reading
+/// returns its pixels as 32-bit BGRA, row by row, and the read after they are consumed returns 0. The size of the
+/// frame is in the path of the handle, as `orbital:screenshot/width/height`.
+pub struct Screenshot {
+    width: i32,
+    height: i32,
+    data: Vec<u32>,
+    seek: usize,
+}
+
+impl Screenshot {
+    pub fn new(image: &Image) -> Screenshot {
+        Screenshot {
+            width: image.width(),
+            height: image.height(),
+            data: image.data().to_vec(),
+            seek: 0
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// Read the pixels of the frame, continuing where the last read stopped
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let bytes = unsafe { slice::from_raw_parts(self.data.as_ptr() as *const u8, self.data.len() * size_of::<u32>()) };
+
+        let mut i = 0;
+        while i < buf.len() && self.seek < bytes.len() {
+            buf[i] = bytes[self.seek];
+            self.seek += 1;
+            i += 1;
+        }
+        i
+    }
+}
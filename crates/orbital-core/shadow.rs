@@ -0,0 +1,105 @@
+//! The shadow around the focused window
+//!
+//! The shadow is an alpha mask that fades out with the distance from the window, rounded at the corners. It is drawn
+//! as four strips around the window, which are computed again only when the size of the window or its scale changes.
+
+use super::{Color, Image, Rect};
+
+/// The width of the shadow in logical pixels
+const SHADOW_SIZE: i32 = 12;
+/// The alpha of the shadow next to the window
+const SHADOW_ALPHA: f32 = 96.0;
+
+pub struct Shadow {
+    /// The size of the window and the scale the strips were computed for
+    width: i32,
+    height: i32,
+    scale: i32,
+    /// The strips above and below the window, including the corners
+    top: Image,
+    bottom: Image,
+    /// The strips left and right of the window
+    left: Image,
+    right: Image,
+}
+
+impl Shadow {
+    pub fn new() -> Shadow {
+        Shadow {
+            width: 0,
+            height: 0,
+            scale: 0,
+            top: Image::new(0, 0),
+            bottom: Image::new(0, 0),
+            left: Image::new(0, 0),
+            right: Image::new(0, 0)
+        }
+    }
+
+    /// The area covered by the shadow of a window, including the window, given the window and its title bar
+    pub fn rect(frame: &Rect, scale: i32) -> Rect {
+        let size = SHADOW_SIZE * scale;
+        Rect::new(frame.left() - size, frame.top() - size, frame.width() + 2 * size, frame.height() + 2 * size)
+    }
+
+    /// Compute the strips for a window size and scale, if they changed
+    fn update(&mut self, width: i32, height: i32, scale: i32) {
+        if width == self.width && height == self.height && scale == self.scale {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+        self.scale = scale;
+
+        let size = SHADOW_SIZE * scale;
+        let mask = |x: i32, y: i32| -> u32 {
+            //The distance from the window, to a point left of, above, right of, or below it
+            let dx = if x < 0 { -x } else if x >= width { x - width + 1 } else { 0 };
+            let dy = if y < 0 { -y } else if y >= height { y - height + 1 } else { 0 };
+            let distance = ((dx * dx + dy * dy) as f32).sqrt() / size as f32;
+            if distance >= 1.0 {
+                0
+            } else {
+                let fade = 1.0 - distance;
+                Color::rgba(0, 0, 0, (SHADOW_ALPHA * fade * fade) as u8).data
+            }
+        };
+
+        let strip = |left: i32, top: i32, w: i32, h: i32| -> Image {
+            let mut data = Vec::with_capacity((w * h) as usize);
+            for y in top..top + h {
+                for x in left..left + w {
+                    data.push(mask(x, y));
+                }
+            }
+            Image::from_data(w, h, data.into_boxed_slice())
+        };
+
+        self.top = strip(-size, -size, width + 2 * size, size);
+        self.bottom = strip(-size, height, width + 2 * size, size);
+        self.left = strip(-size, 0, size, height);
+        self.right = strip(width, 0, size, height);
+    }
+
+    /// Blend the shadow of a window, given the window and its title bar, onto the area of the screen being redrawn
+    pub fn draw(&mut self, image: &mut Image, clip: &Rect, frame: &Rect, scale: i32) {
+        self.update(frame.width(), frame.height(), scale);
+
+        let size = SHADOW_SIZE * scale;
+        let x = frame.left();
+        let y = frame.top();
+        blend_strip(image, clip, x - size, y - size, &mut self.top);
+        blend_strip(image, clip, x - size, y + frame.height(), &mut self.bottom);
+        blend_strip(image, clip, x - size, y, &mut self.left);
+        blend_strip(image, clip, x + frame.width(), y, &mut self.right);
+    }
+}
+
+/// Blend a strip of the shadow placed at a point onto the area being redrawn
+fn blend_strip(image: &mut Image, clip: &Rect, x: i32, y: i32, strip: &mut Image) {
+    let strip_rect = Rect::new(x, y, strip.width(), strip.height());
+    let strip_intersect = clip.intersection(&strip_rect);
+    if ! strip_intersect.is_empty() {
+        image.roi(&strip_intersect).blend(&strip.roi(&strip_intersect.offset(-x, -y)));
+    }
+}
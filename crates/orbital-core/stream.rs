@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use system::error::{Error, Result, EINVAL};
+
+/// The frames a stream keeps, queueing another drops the oldest
+pub const MAX_FRAMES: usize = 4;
+
+/// A frame waiting to be shown
+struct Frame {
+    time: Instant,
+    data: Box<[u32]>,
+}
+
+/// The queued frames of a streaming window, such as a video player, opened with the `v` flag
+///
+/// Clients queue frames ahead of time, each with the time to show it on their own clock in milliseconds. The clock
+/// of the client is lined up with the compositor when the first frame is queued, and again if it goes backwards,
+/// such as after seeking. At each flip the newest frame that is due is shown, and older ones are dropped, so a
+/// client rendering faster than the display neither tears nor falls behind.
+pub struct Stream {
+    frames: VecDeque<Frame>,
+    /// The first timestamp of the client and when it was queued
+    origin: Option<(u64, Instant)>,
+    /// The number of frames dropped without being shown
+    pub dropped: u64,
+}
+
+impl Stream {
+    pub fn new() -> Stream {
+        Stream {
+            frames: VecDeque::new(),
+            origin: None,
+            dropped: 0
+        }
+    }
+
+    /// Queue a frame to show at a time in milliseconds on the clock of the client
+    ///
+    /// Returns `EINVAL` if the time is too far ahead of the first frame to be represented.
+    pub fn queue(&mut self, timestamp: u64, data: Box<[u32]>) -> Result<()> {
+        let now = Instant::now();
+        let (start, start_time) = match self.origin {
+            Some((start, start_time)) if timestamp >= start => (start, start_time),
+            _ => {
+                self.dropped += self.frames.len() as u64;
+                self.frames.clear();
+                self.origin = Some((timestamp, now));
+                (timestamp, now)
+            }
+        };
+
+        let time = match start_time.checked_add(Duration::from_millis(timestamp - start)) {
+            Some(time) => time,
+            None => return Err(Error::new(EINVAL))
+        };
+        if self.frames.len() >= MAX_FRAMES {
+            self.frames.pop_front();
+            self.dropped += 1;
+        }
+
+        //Frames are usually queued in order, but are kept sorted by time if they are not
+        let i = self.frames.iter().position(|frame| frame.time > time).unwrap_or(self.frames.len());
+        self.frames.insert(i, Frame {
+            time: time,
+            data: data
+        });
+        Ok(())
+    }
+
+    /// Take the newest frame that is due, dropping the older frames that are due
+    pub fn present(&mut self, now: Instant) -> Option<Box<[u32]>> {
+        let mut newest = None;
+        while self.frames.front().map_or(false, |frame| frame.time <= now) {
+            if newest.is_some() {
+                self.dropped += 1;
+            }
+            newest = self.frames.pop_front().map(|frame| frame.data);
+        }
+        newest
+    }
+
+    /// When the next queued frame is due
+    pub fn next(&self) -> Option<Instant> {
+        self.frames.front().map(|frame| frame.time)
+    }
+
+    /// Drop the queued frames, which no longer fit the contents after a resize
+    pub fn clear(&mut self) {
+        self.dropped += self.frames.len() as u64;
+        self.frames.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{MAX_FRAMES, Stream};
+
+    fn frame(value: u32) -> Box<[u32]> {
+        vec![value; 4].into_boxed_slice()
+    }
+
+    #[test]
+    fn present_due() {
+        let mut stream = Stream::new();
+        stream.queue(1000, frame(1)).unwrap();
+        stream.queue(1000 + 3600 * 1000, frame(2)).unwrap();
+
+        let frame = stream.present(Instant::now()).unwrap();
+        assert_eq!(&frame[..], &[1; 4]);
+        assert!(stream.present(Instant::now()).is_none());
+        assert!(stream.next().unwrap() > Instant::now());
+        assert_eq!(stream.dropped, 0);
+    }
+
+    #[test]
+    fn present_newest() {
+        let mut stream = Stream::new();
+        for i in 0..3 {
+            stream.queue(i as u64 * 10, frame(i)).unwrap();
+        }
+
+        let frame = stream.present(Instant::now() + Duration::from_millis(100)).unwrap();
+        assert_eq!(&frame[..], &[2; 4]);
+        assert!(stream.next().is_none());
+        assert_eq!(stream.dropped, 2);
+    }
+
+    #[test]
+    fn drop_oldest() {
+        let mut stream = Stream::new();
+        for i in 0..MAX_FRAMES as u32 + 2 {
+            stream.queue(i as u64 * 10, frame(i)).unwrap();
+        }
+        assert_eq!(stream.dropped, 2);
+
+        let frame = stream.present(Instant::now() + Duration::from_millis(1000)).unwrap();
+        assert_eq!(&frame[..], &[MAX_FRAMES as u32 + 1; 4]);
+        assert_eq!(stream.dropped, 2 + MAX_FRAMES as u64 - 1);
+    }
+
+    #[test]
+    fn drop_on_seek() {
+        let mut stream = Stream::new();
+        stream.queue(100, frame(1)).unwrap();
+        stream.queue(200, frame(2)).unwrap();
+        stream.queue(50, frame(3)).unwrap();
+        assert_eq!(stream.dropped, 2);
+
+        let frame = stream.present(Instant::now()).unwrap();
+        assert_eq!(&frame[..], &[3; 4]);
+    }
+
+    #[test]
+    fn drop_on_clear() {
+        let mut stream = Stream::new();
+        stream.queue(0, frame(1)).unwrap();
+        stream.queue(10, frame(2)).unwrap();
+        stream.clear();
+        assert_eq!(stream.dropped, 2);
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn queue_far_ahead() {
+        // Whether the time can be represented depends on the clock, but it must not panic either way
+        let mut stream = Stream::new();
+        stream.queue(0, frame(1)).unwrap();
+        let _ = stream.queue(u64::max_value(), frame(2));
+        assert_eq!(stream.dropped, 0);
+        assert_eq!(&stream.present(Instant::now()).unwrap()[..], &[1; 4]);
+    }
+}
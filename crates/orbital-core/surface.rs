@@ -0,0 +1,89 @@
+use std::cmp::min;
+
+use system::error::Result;
+use system::graphics::fast_copy;
+use system::syscall::{sys_mlock, sys_munlock};
+
+use super::{Image, Rect};
+
+const PAGE_SIZE: usize = 4096;
+
+/// The memory of a window that its client maps with `sys_fmap` and draws into
+///
+/// The pixels are in pages of their own, so a client cannot reach any other memory of the compositor through its
+/// mapping. The compositor keeps drawing the contents of the window, and copies the rectangles the client reports
+/// as damaged from the surface.
+///
+/// A surface must not be dropped while a client may still map it. The kernel unmaps the mappings of a window when
+/// its client closes it, before the compositor is told, so surfaces are kept until the window is closed.
+pub struct Surface {
+    /// The pixels, with room to start them at a page
+    memory: Box<[u32]>,
+    /// The index of the first pixel, at the start of a page
+    start: usize,
+    width: i32,
+    height: i32,
+}
+
+impl Surface {
+    /// Create a surface holding the pixels of an image
+    pub fn new(image: &Image) -> Surface {
+        let size = image.data().len() * 4;
+        let pages = (size + PAGE_SIZE - 1) / PAGE_SIZE;
+        let memory = vec![0; (pages + 1) * PAGE_SIZE / 4].into_boxed_slice();
+        let address = memory.as_ptr() as usize;
+        let start = (PAGE_SIZE - address % PAGE_SIZE) % PAGE_SIZE / 4;
+
+        let mut surface = Surface {
+            memory: memory,
+            start: start,
+            width: image.width(),
+            height: image.height()
+        };
+        let len = image.data().len();
+        unsafe {
+            fast_copy(surface.memory[start ..].as_mut_ptr(), image.data().as_ptr(), len);
+        }
+        surface
+    }
+
+    /// The address of the first pixel
+    pub fn address(&self) -> usize {
+        self.memory[self.start ..].as_ptr() as usize
+    }
+
+    /// The size that can be mapped, in bytes, the pixels rounded up to whole pages
+    pub fn size(&self) -> usize {
+        (self.width as usize * self.height as usize * 4 + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE
+    }
+
+    /// Pin the surface, so it stays in place for the kernel to map it
+    pub fn lock(&self) -> Result<usize> {
+        sys_mlock(self.address(), self.size())
+    }
+
+    /// Copy a rectangle of the pixels to the same place in an image, returning the part inside both
+    pub fn copy(&self, image: &mut Image, rect: &Rect) -> Rect {
+        let rect = rect.intersection(&Rect::new(0, 0, min(self.width, image.width()), min(self.height, image.height())));
+        if rect.is_empty() {
+            return rect;
+        }
+
+        let width = image.width() as usize;
+        for y in rect.top() .. rect.bottom() {
+            let offset = y as usize * self.width as usize + rect.left() as usize;
+            let row = &self.memory[self.start + offset .. self.start + offset + rect.width() as usize];
+            let image_offset = y as usize * width + rect.left() as usize;
+            unsafe {
+                fast_copy(image.data_mut()[image_offset ..].as_mut_ptr(), row.as_ptr(), row.len());
+            }
+        }
+        rect
+    }
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        let _ = sys_munlock(self.address(), self.size());
+    }
+}
@@ -0,0 +1,236 @@
+//! The look of window decorations
+//!
+//! A theme is a file of `key=value` lines, like the config, such as `bar_focused_color=#505666`. Colors are
+//! `#RRGGBB` or `#AARRGGBB`, and keys that are missing keep the default.
+//!
+//! The buttons of title bars are listed left to right in `button_layout`, with a colon between the buttons on the left
+//! and those on the right, such as `button_layout=close:minimize,maximize` for the close button on the left. The
+//! buttons are `close`, `minimize`, `maximize`, and `zoom`.
+
+use std::fs::File;
+use std::io::Read;
+
+use super::Color;
+
+/// The most buttons on each side of a title bar
+pub const MAX_BUTTONS: usize = 4;
+
+/// A button of the title bar
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TitleButton {
+    Close,
+    Minimize,
+    /// Cover the display with the window, or restore it
+    Maximize,
+    /// Enlarge the contents of the window, showing the zoom factor
+    Zoom,
+}
+
+impl TitleButton {
+    pub fn parse(name: &str) -> Option<TitleButton> {
+        match name.trim() {
+            "close" => Some(TitleButton::Close),
+            "minimize" => Some(TitleButton::Minimize),
+            "maximize" => Some(TitleButton::Maximize),
+            "zoom" => Some(TitleButton::Zoom),
+            _ => None
+        }
+    }
+
+    /// The width of the button in logical pixels, the zoom button is wider to fit the zoom factor
+    pub fn width(&self) -> i32 {
+        match *self {
+            TitleButton::Zoom => 16,
+            _ => 10
+        }
+    }
+}
+
+/// The buttons on the left and right of title bars, in order from left to right
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ButtonLayout {
+    pub left: [Option<TitleButton>; MAX_BUTTONS],
+    pub right: [Option<TitleButton>; MAX_BUTTONS],
+}
+
+impl ButtonLayout {
+    /// The zoom and close buttons on the right
+    pub fn new() -> ButtonLayout {
+        ButtonLayout {
+            left: [None; MAX_BUTTONS],
+            right: [Some(TitleButton::Zoom), Some(TitleButton::Close), None, None]
+        }
+    }
+
+    /// Parse a layout, such as `close:minimize,maximize`, a layout without a colon has its buttons on the right
+    pub fn parse(value: &str) -> Option<ButtonLayout> {
+        let (left, right) = match value.find(':') {
+            Some(colon) => (&value[.. colon], &value[colon + 1 ..]),
+            None => ("", value)
+        };
+
+        let mut layout = ButtonLayout {
+            left: [None; MAX_BUTTONS],
+            right: [None; MAX_BUTTONS]
+        };
+        let mut seen = Vec::new();
+        if parse_side(left, &mut layout.left, &mut seen) && parse_side(right, &mut layout.right, &mut seen) {
+            Some(layout)
+        } else {
+            None
+        }
+    }
+
+    /// The buttons of a side, left to right
+    pub fn side(&self, right: bool) -> Vec<TitleButton> {
+        let buttons = if right { &self.right } else { &self.left };
+        buttons.iter().filter_map(|button| *button).collect()
+    }
+}
+
+/// Parse the buttons of one side of a layout, each button may be on the title bar once
+fn parse_side(value: &str, buttons: &mut [Option<TitleButton>; MAX_BUTTONS], seen: &mut Vec<TitleButton>) -> bool {
+    let mut i = 0;
+    for name in value.split(',').filter(|name| ! name.trim().is_empty()) {
+        match TitleButton::parse(name) {
+            Some(button) if i < MAX_BUTTONS && ! seen.contains(&button) => {
+                buttons[i] = Some(button);
+                seen.push(button);
+                i += 1;
+            },
+            _ => return false
+        }
+    }
+    true
+}
+
+/// The colors, sizes, and button glyphs of title bars
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub bar_color: Color,
+    pub bar_focused_color: Color,
+    pub text_color: Color,
+    pub text_focused_color: Color,
+    /// The height of title bars in logical pixels
+    pub bar_height: i32,
+    /// The radius of the rounded top corners of title bars in logical pixels, 0 for square corners
+    pub corner_radius: i32,
+    /// The size of the title in the vector font
+    pub font_size: u32,
+    /// The glyph of the button that closes a window
+    pub close_button: char,
+    /// The glyph after the zoom factor on the zoom button
+    pub zoom_button: char,
+    pub minimize_button: char,
+    pub maximize_button: char,
+    pub button_layout: ButtonLayout,
+}
+
+impl Theme {
+    pub fn new() -> Theme {
+        Theme {
+            bar_color: Color::rgb(40, 45, 57),
+            bar_focused_color: Color::rgb(80, 86, 102),
+            text_color: Color::rgb(204, 210, 224),
+            text_focused_color: Color::rgb(235, 241, 255),
+            bar_height: 18,
+            corner_radius: 4,
+            font_size: 13,
+            close_button: 'X',
+            zoom_button: 'x',
+            minimize_button: '_',
+            maximize_button: '+',
+            button_layout: ButtonLayout::new()
+        }
+    }
+
+    /// Load a theme, keeping the default if it cannot be read
+    pub fn from_path(path: &str) -> Theme {
+        let mut string = String::new();
+        match File::open(path).and_then(|mut file| file.read_to_string(&mut string)) {
+            Ok(_) => Theme::from_str(&string),
+            Err(err) => {
+                println!("orbital: failed to read theme '{}': {}", path, err);
+                Theme::new()
+            }
+        }
+    }
+
+    pub fn from_str(string: &str) -> Theme {
+        let mut theme = Theme::new();
+
+        for line in string.lines() {
+            let line = line.trim();
+            let (key, value) = match line.find('=') {
+                Some(equals) => (&line[.. equals], &line[equals + 1 ..]),
+                None => continue
+            };
+
+            let valid = match key {
+                "bar_color" => Color::parse(value).map(|color| theme.bar_color = color).is_some(),
+                "bar_focused_color" => Color::parse(value).map(|color| theme.bar_focused_color = color).is_some(),
+                "text_color" => Color::parse(value).map(|color| theme.text_color = color).is_some(),
+                "text_focused_color" => Color::parse(value).map(|color| theme.text_focused_color = color).is_some(),
+                "bar_height" => match value.parse::<i32>() {
+                    Ok(height) if height >= 8 && height <= 64 => {
+                        theme.bar_height = height;
+                        true
+                    },
+                    _ => false
+                },
+                "corner_radius" => match value.parse::<i32>() {
+                    Ok(radius) if radius >= 0 && radius <= 16 => {
+                        theme.corner_radius = radius;
+                        true
+                    },
+                    _ => false
+                },
+                "font_size" => match value.parse::<u32>() {
+                    Ok(size) if size >= 6 && size <= 64 => {
+                        theme.font_size = size;
+                        true
+                    },
+                    _ => false
+                },
+                "close_button" => parse_glyph(value).map(|c| theme.close_button = c).is_some(),
+                "zoom_button" => parse_glyph(value).map(|c| theme.zoom_button = c).is_some(),
+                "minimize_button" => parse_glyph(value).map(|c| theme.minimize_button = c).is_some(),
+                "maximize_button" => parse_glyph(value).map(|c| theme.maximize_button = c).is_some(),
+                "button_layout" => ButtonLayout::parse(value).map(|layout| theme.button_layout = layout).is_some(),
+                _ => true
+            };
+            if ! valid {
+                println!("orbital: invalid theme line '{}'", line);
+            }
+        }
+
+        theme
+    }
+
+    pub fn bar_color(&self, focused: bool) -> Color {
+        if focused { self.bar_focused_color } else { self.bar_color }
+    }
+
+    pub fn text_color(&self, focused: bool) -> Color {
+        if focused { self.text_focused_color } else { self.text_color }
+    }
+
+    /// The glyph of a button, the zoom button also shows the zoom factor before it
+    pub fn button_glyph(&self, button: TitleButton) -> char {
+        match button {
+            TitleButton::Close => self.close_button,
+            TitleButton::Minimize => self.minimize_button,
+            TitleButton::Maximize => self.maximize_button,
+            TitleButton::Zoom => self.zoom_button
+        }
+    }
+}
+
+/// Parse a glyph, a single character
+fn parse_glyph(value: &str) -> Option<char> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None
+    }
+}
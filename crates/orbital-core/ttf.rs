@@ -0,0 +1,558 @@
+use std::cmp::{min, max};
+use std::fs::File;
+use std::io::Read;
+
+/// A point of a glyph outline, in font units
+#[derive(Copy, Clone, Debug)]
+struct Point {
+    x: f32,
+    y: f32,
+    on_curve: bool,
+}
+
+/// A rasterized glyph, as coverage values from 0 to 255
+pub struct GlyphBitmap {
+    /// Width of the bitmap
+    pub width: i32,
+    /// Height of the bitmap
+    pub height: i32,
+    /// Offset from the pen position to the left of the bitmap
+    pub left: i32,
+    /// Offset from the baseline to the top of the bitmap, positive upwards
+    pub top: i32,
+    /// Horizontal advance to the next pen position
+    pub advance: i32,
+    /// Coverage, one byte per pixel
+    pub coverage: Vec<u8>,
+}
+
+/// A TrueType font file
+pub struct TtfFile {
+    data: Vec<u8>,
+    units_per_em: u16,
+    long_loca: bool,
+    num_glyphs: u16,
+    num_h_metrics: u16,
+    ascent: i16,
+    descent: i16,
+    cmap: usize,
+    loca: usize,
+    glyf: usize,
+    hmtx: usize,
+}
+
+impl TtfFile {
+    /// Load a TrueType font from the given path
+    pub fn from_path(path: &str) -> Option<TtfFile> {
+        let mut data: Vec<u8> = Vec::new();
+        if let Ok(mut file) = File::open(path) {
+            let _ = file.read_to_end(&mut data);
+        }
+        TtfFile::from_data(data)
+    }
+
+    /// Parse a TrueType font, returning None if a required table is missing
+    pub fn from_data(data: Vec<u8>) -> Option<TtfFile> {
+        let mut ttf = TtfFile {
+            data: data,
+            units_per_em: 0,
+            long_loca: false,
+            num_glyphs: 0,
+            num_h_metrics: 0,
+            ascent: 0,
+            descent: 0,
+            cmap: 0,
+            loca: 0,
+            glyf: 0,
+            hmtx: 0,
+        };
+
+        let (head, maxp, hhea, cmap) = match (ttf.table(b"head"), ttf.table(b"maxp"), ttf.table(b"hhea"), ttf.table(b"cmap")) {
+            (Some(head), Some(maxp), Some(hhea), Some(cmap)) => (head, maxp, hhea, cmap),
+            _ => return None
+        };
+
+        match (ttf.table(b"loca"), ttf.table(b"glyf"), ttf.table(b"hmtx")) {
+            (Some(loca), Some(glyf), Some(hmtx)) => {
+                ttf.loca = loca;
+                ttf.glyf = glyf;
+                ttf.hmtx = hmtx;
+            },
+            _ => return None
+        }
+
+        ttf.units_per_em = ttf.u16(head + 18);
+        ttf.long_loca = ttf.i16(head + 50) != 0;
+        ttf.num_glyphs = ttf.u16(maxp + 4);
+        ttf.ascent = ttf.i16(hhea + 4);
+        ttf.descent = ttf.i16(hhea + 6);
+        ttf.num_h_metrics = ttf.u16(hhea + 34);
+
+        //Find a Unicode BMP subtable in format 4
+        let count = ttf.u16(cmap + 2) as usize;
+        for i in 0..count {
+            let record = cmap + 4 + i * 8;
+            let platform = ttf.u16(record);
+            let encoding = ttf.u16(record + 2);
+            let subtable = cmap + ttf.u32(record + 4) as usize;
+            if (platform == 0 || (platform == 3 && encoding == 1)) && ttf.u16(subtable) == 4 {
+                ttf.cmap = subtable;
+                break;
+            }
+        }
+
+        if ttf.cmap == 0 || ttf.units_per_em == 0 {
+            None
+        } else {
+            Some(ttf)
+        }
+    }
+
+    fn u8(&self, i: usize) -> u8 {
+        match self.data.get(i) {
+            Some(byte) => *byte,
+            None => 0,
+        }
+    }
+
+    fn u16(&self, i: usize) -> u16 {
+        ((self.u8(i) as u16) << 8) | (self.u8(i + 1) as u16)
+    }
+
+    fn i16(&self, i: usize) -> i16 {
+        self.u16(i) as i16
+    }
+
+    fn u32(&self, i: usize) -> u32 {
+        ((self.u16(i) as u32) << 16) | (self.u16(i + 2) as u32)
+    }
+
+    fn table(&self, tag: &[u8; 4]) -> Option<usize> {
+        let count = self.u16(4) as usize;
+        for i in 0..count {
+            let record = 12 + i * 16;
+            if &self.data[min(record, self.data.len()) .. min(record + 4, self.data.len())] == tag {
+                return Some(self.u32(record + 8) as usize);
+            }
+        }
+        None
+    }
+
+    /// Size of an em square in font units
+    pub fn units_per_em(&self) -> u16 {
+        self.units_per_em
+    }
+
+    /// Distance from the baseline to the top of the tallest glyphs, in font units
+    pub fn ascent(&self) -> i16 {
+        self.ascent
+    }
+
+    /// Distance from the baseline to the bottom of the lowest glyphs, in font units, usually negative
+    pub fn descent(&self) -> i16 {
+        self.descent
+    }
+
+    /// Find the glyph for a character, 0 is the missing glyph
+    pub fn glyph_index(&self, character: char) -> u16 {
+        let c = character as u32;
+        if c > 0xFFFF {
+            return 0;
+        }
+        let c = c as u16;
+
+        let seg_count = (self.u16(self.cmap + 6) / 2) as usize;
+        let ends = self.cmap + 14;
+        let starts = ends + seg_count * 2 + 2;
+        let deltas = starts + seg_count * 2;
+        let range_offsets = deltas + seg_count * 2;
+
+        for i in 0..seg_count {
+            let end = self.u16(ends + i * 2);
+            if c <= end {
+                let start = self.u16(starts + i * 2);
+                if c < start {
+                    return 0;
+                }
+
+                let delta = self.u16(deltas + i * 2);
+                let range_offset = self.u16(range_offsets + i * 2) as usize;
+                if range_offset == 0 {
+                    return c.wrapping_add(delta);
+                } else {
+                    let address = range_offsets + i * 2 + range_offset + (c - start) as usize * 2;
+                    let glyph = self.u16(address);
+                    if glyph == 0 {
+                        return 0;
+                    } else {
+                        return glyph.wrapping_add(delta);
+                    }
+                }
+            }
+        }
+
+        0
+    }
+
+    /// Horizontal advance of a glyph in font units
+    pub fn advance(&self, glyph: u16) -> u16 {
+        if self.num_h_metrics == 0 {
+            0
+        } else {
+            let metric = min(glyph, self.num_h_metrics - 1) as usize;
+            self.u16(self.hmtx + metric * 4)
+        }
+    }
+
+    fn glyph_range(&self, glyph: u16) -> Option<(usize, usize)> {
+        if glyph >= self.num_glyphs {
+            return None;
+        }
+
+        let (start, end) = if self.long_loca {
+            (self.u32(self.loca + glyph as usize * 4) as usize,
+             self.u32(self.loca + glyph as usize * 4 + 4) as usize)
+        } else {
+            (self.u16(self.loca + glyph as usize * 2) as usize * 2,
+             self.u16(self.loca + glyph as usize * 2 + 2) as usize * 2)
+        };
+
+        if end > start {
+            Some((self.glyf + start, self.glyf + end))
+        } else {
+            None
+        }
+    }
+
+    /// Read the contours of a glyph, applying a transform to composite components
+    fn outline(&self, glyph: u16, transform: [f32; 6], depth: usize, contours: &mut Vec<Vec<Point>>) {
+        let (offset, _end) = match self.glyph_range(glyph) {
+            Some(range) => range,
+            None => return
+        };
+
+        let contour_count = self.i16(offset);
+        if contour_count >= 0 {
+            let contour_count = contour_count as usize;
+            let end_points = offset + 10;
+            let point_count = if contour_count > 0 {
+                self.u16(end_points + (contour_count - 1) * 2) as usize + 1
+            } else {
+                0
+            };
+
+            let instructions = self.u16(end_points + contour_count * 2) as usize;
+            let mut i = end_points + contour_count * 2 + 2 + instructions;
+
+            let mut flags = Vec::with_capacity(point_count);
+            while flags.len() < point_count {
+                let flag = self.u8(i);
+                i += 1;
+                flags.push(flag);
+                if flag & 8 == 8 {
+                    let repeat = self.u8(i);
+                    i += 1;
+                    for _ in 0..repeat {
+                        flags.push(flag);
+                    }
+                }
+            }
+            flags.truncate(point_count);
+
+            let mut xs = Vec::with_capacity(point_count);
+            let mut x = 0i32;
+            for &flag in flags.iter() {
+                if flag & 2 == 2 {
+                    let dx = self.u8(i) as i32;
+                    i += 1;
+                    x += if flag & 16 == 16 { dx } else { -dx };
+                } else if flag & 16 == 0 {
+                    x += self.i16(i) as i32;
+                    i += 2;
+                }
+                xs.push(x);
+            }
+
+            let mut ys = Vec::with_capacity(point_count);
+            let mut y = 0i32;
+            for &flag in flags.iter() {
+                if flag & 4 == 4 {
+                    let dy = self.u8(i) as i32;
+                    i += 1;
+                    y += if flag & 32 == 32 { dy } else { -dy };
+                } else if flag & 32 == 0 {
+                    y += self.i16(i) as i32;
+                    i += 2;
+                }
+                ys.push(y);
+            }
+
+            let mut start = 0;
+            for c in 0..contour_count {
+                let end = min(self.u16(end_points + c * 2) as usize + 1, point_count);
+                let mut contour = Vec::new();
+                for p in start..end {
+                    let (x, y) = (xs[p] as f32, ys[p] as f32);
+                    contour.push(Point {
+                        x: transform[0] * x + transform[2] * y + transform[4],
+                        y: transform[1] * x + transform[3] * y + transform[5],
+                        on_curve: flags[p] & 1 == 1,
+                    });
+                }
+                if ! contour.is_empty() {
+                    contours.push(contour);
+                }
+                start = end;
+            }
+        } else if depth < 8 {
+            //Composite glyph
+            let mut i = offset + 10;
+            loop {
+                let flags = self.u16(i);
+                let component = self.u16(i + 2);
+                i += 4;
+
+                let (dx, dy) = if flags & 1 == 1 {
+                    let args = (self.i16(i) as f32, self.i16(i + 2) as f32);
+                    i += 4;
+                    args
+                } else {
+                    let args = (self.u8(i) as i8 as f32, self.u8(i + 1) as i8 as f32);
+                    i += 2;
+                    args
+                };
+
+                let f2dot14 = |value: i16| -> f32 { value as f32 / 16384.0 };
+                let mut scale = [1.0, 0.0, 0.0, 1.0];
+                if flags & 8 == 8 {
+                    scale[0] = f2dot14(self.i16(i));
+                    scale[3] = scale[0];
+                    i += 2;
+                } else if flags & 0x40 == 0x40 {
+                    scale[0] = f2dot14(self.i16(i));
+                    scale[3] = f2dot14(self.i16(i + 2));
+                    i += 4;
+                } else if flags & 0x80 == 0x80 {
+                    scale[0] = f2dot14(self.i16(i));
+                    scale[1] = f2dot14(self.i16(i + 2));
+                    scale[2] = f2dot14(self.i16(i + 4));
+                    scale[3] = f2dot14(self.i16(i + 6));
+                    i += 8;
+                }
+
+                //Point matching is not supported, arguments are treated as offsets
+                let (dx, dy) = if flags & 2 == 2 { (dx, dy) } else { (0.0, 0.0) };
+
+                let combined = [
+                    transform[0] * scale[0] + transform[2] * scale[1],
+                    transform[1] * scale[0] + transform[3] * scale[1],
+                    transform[0] * scale[2] + transform[2] * scale[3],
+                    transform[1] * scale[2] + transform[3] * scale[3],
+                    transform[0] * dx + transform[2] * dy + transform[4],
+                    transform[1] * dx + transform[3] * dy + transform[5],
+                ];
+                self.outline(component, combined, depth + 1, contours);
+
+                if flags & 0x20 == 0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Rasterize a character at a size in pixels per em
+    pub fn rasterize(&self, character: char, size: f32) -> GlyphBitmap {
+        let glyph = self.glyph_index(character);
+        let scale = size / self.units_per_em as f32;
+        let advance = (self.advance(glyph) as f32 * scale + 0.5) as i32;
+
+        let mut contours = Vec::new();
+        self.outline(glyph, [1.0, 0.0, 0.0, 1.0, 0.0, 0.0], 0, &mut contours);
+
+        let mut lines = Vec::new();
+        for contour in contours.iter() {
+            flatten(contour, scale, &mut lines);
+        }
+
+        if lines.is_empty() {
+            return GlyphBitmap {
+                width: 0,
+                height: 0,
+                left: 0,
+                top: 0,
+                advance: advance,
+                coverage: Vec::new()
+            };
+        }
+
+        //Bounds in pixels, with y pointing down
+        let mut x_min = lines[0].0;
+        let mut x_max = lines[0].0;
+        let mut y_min = -lines[0].1;
+        let mut y_max = -lines[0].1;
+        for &(x0, y0, x1, y1) in lines.iter() {
+            x_min = x_min.min(x0.min(x1));
+            x_max = x_max.max(x0.max(x1));
+            y_min = y_min.min((-y0).min(-y1));
+            y_max = y_max.max((-y0).max(-y1));
+        }
+
+        let left = x_min.floor() as i32;
+        let top = y_min.floor() as i32;
+        let width = max(1, x_max.ceil() as i32 - left);
+        let height = max(1, y_max.ceil() as i32 - top);
+
+        let mut accumulator = vec![0.0f32; (width * height) as usize + 2];
+        for &(x0, y0, x1, y1) in lines.iter() {
+            draw_line(&mut accumulator, width as usize, height as usize,
+                      x0 - left as f32, -y0 - top as f32,
+                      x1 - left as f32, -y1 - top as f32);
+        }
+
+        let mut coverage = Vec::with_capacity((width * height) as usize);
+        let mut sum = 0.0;
+        for i in 0..(width * height) as usize {
+            sum += accumulator[i];
+            let alpha = sum.abs().min(1.0);
+            coverage.push((alpha * 255.0 + 0.5) as u8);
+        }
+
+        GlyphBitmap {
+            width: width,
+            height: height,
+            left: left,
+            top: -top,
+            advance: advance,
+            coverage: coverage
+        }
+    }
+}
+
+/// Convert a contour of on and off curve points into line segments, scaled to pixels
+fn flatten(contour: &[Point], scale: f32, lines: &mut Vec<(f32, f32, f32, f32)>) {
+    let len = contour.len();
+
+    //Start on an on curve point, or on the implied point between the first two off curve points
+    let first = match contour.iter().position(|point| point.on_curve) {
+        Some(i) => contour[i],
+        None => Point {
+            x: (contour[0].x + contour[len - 1].x) / 2.0,
+            y: (contour[0].y + contour[len - 1].y) / 2.0,
+            on_curve: true
+        }
+    };
+    let start = contour.iter().position(|point| point.on_curve).unwrap_or(0);
+
+    let mut last = first;
+    let mut control: Option<Point> = None;
+    for i in 1..len + 1 {
+        let point = contour[(start + i) % len];
+        if point.on_curve {
+            match control.take() {
+                Some(c) => quadratic(last, c, point, scale, lines),
+                None => lines.push((last.x * scale, last.y * scale, point.x * scale, point.y * scale)),
+            }
+            last = point;
+        } else {
+            if let Some(c) = control {
+                let mid = Point {
+                    x: (c.x + point.x) / 2.0,
+                    y: (c.y + point.y) / 2.0,
+                    on_curve: true
+                };
+                quadratic(last, c, mid, scale, lines);
+                last = mid;
+            }
+            control = Some(point);
+        }
+    }
+
+    match control {
+        Some(c) => quadratic(last, c, first, scale, lines),
+        None => if last.x != first.x || last.y != first.y {
+            lines.push((last.x * scale, last.y * scale, first.x * scale, first.y * scale));
+        }
+    }
+}
+
+/// Approximate a quadratic curve with line segments
+fn quadratic(p0: Point, p1: Point, p2: Point, scale: f32, lines: &mut Vec<(f32, f32, f32, f32)>) {
+    let dd = ((p0.x - 2.0 * p1.x + p2.x).abs() + (p0.y - 2.0 * p1.y + p2.y).abs()) * scale;
+    let steps = max(1, min(16, (dd.sqrt() * 2.0) as usize + 1));
+
+    let mut x = p0.x;
+    let mut y = p0.y;
+    for step in 1..steps + 1 {
+        let t = step as f32 / steps as f32;
+        let mt = 1.0 - t;
+        let nx = mt * mt * p0.x + 2.0 * mt * t * p1.x + t * t * p2.x;
+        let ny = mt * mt * p0.y + 2.0 * mt * t * p1.y + t * t * p2.y;
+        lines.push((x * scale, y * scale, nx * scale, ny * scale));
+        x = nx;
+        y = ny;
+    }
+}
+
+/// Accumulate the signed area covered by a line, the running sum of the accumulator is the coverage
+fn draw_line(acc: &mut [f32], width: usize, height: usize, x0: f32, y0: f32, x1: f32, y1: f32) {
+    if y0 == y1 {
+        return;
+    }
+
+    let (dir, x0, y0, x1, y1) = if y0 < y1 {
+        (1.0, x0, y0, x1, y1)
+    } else {
+        (-1.0, x1, y1, x0, y0)
+    };
+
+    let clamp = |x: f32| -> f32 { x.max(0.0).min(width as f32) };
+
+    let dxdy = (x1 - x0) / (y1 - y0);
+    let mut x = x0;
+    if y0 < 0.0 {
+        x -= y0 * dxdy;
+    }
+
+    let y_start = y0.max(0.0) as usize;
+    let y_end = min(height, y1.ceil() as usize);
+    for y in y_start..y_end {
+        let row = y * width;
+        let dy = ((y + 1) as f32).min(y1) - (y as f32).max(y0);
+        let x_next = x + dxdy * dy;
+        let d = dy * dir;
+
+        let (xa, xb) = if x < x_next { (clamp(x), clamp(x_next)) } else { (clamp(x_next), clamp(x)) };
+        let xa_floor = xa.floor();
+        let xa_i = xa_floor as usize;
+        let xb_ceil = xb.ceil();
+        let xb_i = xb_ceil as usize;
+
+        if xb_i <= xa_i + 1 {
+            let xm = 0.5 * (xa + xb) - xa_floor;
+            acc[row + xa_i] += d - d * xm;
+            acc[row + xa_i + 1] += d * xm;
+        } else {
+            let s = 1.0 / (xb - xa);
+            let xa_f = xa - xa_floor;
+            let a0 = 0.5 * s * (1.0 - xa_f) * (1.0 - xa_f);
+            let xb_f = xb - xb_ceil + 1.0;
+            let am = 0.5 * s * xb_f * xb_f;
+
+            acc[row + xa_i] += d * a0;
+            if xb_i == xa_i + 2 {
+                acc[row + xa_i + 1] += d * (1.0 - a0 - am);
+            } else {
+                let a1 = s * (1.5 - xa_f);
+                acc[row + xa_i + 1] += d * (a1 - a0);
+                for xi in xa_i + 2..xb_i - 1 {
+                    acc[row + xi] += d * s;
+                }
+                let a2 = a1 + (xb_i - xa_i - 3) as f32 * s;
+                acc[row + xb_i - 1] += d * (1.0 - a2 - am);
+            }
+            acc[row + xb_i] += d * am;
+        }
+
+        x = x_next;
+    }
+}
@@ -0,0 +1,524 @@
+//! A remote display, served to VNC viewers over the RFB protocol
+//!
+//! Viewers are sent the areas written since their last update as raw pixels, and their keyboard and pointer are
+//! used as input. There is no password, so the port should only be reachable from a trusted network.
+
+use std::cmp;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::File;
+use std::io::{self, Error, ErrorKind, Read, Result, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::{schedule, Display, Input, Monitor, Rect};
+use super::event::{self, Event, KeyEvent, MouseEvent, ScrollEvent};
+
+/// Milliseconds between checks for new areas to send to a viewer that asked for an update
+const UPDATE_INTERVAL: u32 = 16;
+
+/// Milliseconds between checks for input from the viewers
+const INPUT_INTERVAL: u32 = 10;
+
+/// The longest clipboard text accepted from a viewer, in bytes
+const MAX_CUT_TEXT: u32 = 1024 * 1024;
+
+/// The name of the desktop shown by viewers
+const DESKTOP_NAME: &'static str = "Redox";
+
+/// Characters of the US keyboard by scancode, without and with shift
+const UNSHIFTED: &'static str = "\0\x1B1234567890-=\0\tqwertyuiop[]\n\0asdfghjkl;'`\0\\zxcvbnm,./\0\0\0 ";
+const SHIFTED: &'static str = "\0\x1B!@#$%^&*()_+\0\tQWERTYUIOP{}\n\0ASDFGHJKL:\"~\0|ZXCVBNM<>?\0\0\0 ";
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    (bytes[0] as u16) << 8 | bytes[1] as u16
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    (bytes[0] as u32) << 24 | (bytes[1] as u32) << 16 | (bytes[2] as u32) << 8 | bytes[3] as u32
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u16) {
+    out.push((value >> 8) as u8);
+    out.push(value as u8);
+}
+
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    push_u16(out, (value >> 16) as u16);
+    push_u16(out, value as u16);
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Read and throw away the payload of a message that is not used, without buffering it
+fn discard(stream: &mut File, len: u64) -> Result<()> {
+    if try!(io::copy(&mut stream.take(len), &mut io::sink())) == len {
+        Ok(())
+    } else {
+        Err(Error::new(ErrorKind::UnexpectedEof, "message ended early"))
+    }
+}
+
+/// How a viewer wants pixels to be sent, which must be true color
+#[derive(Copy, Clone, Debug)]
+struct PixelFormat {
+    bits: u8,
+    big_endian: bool,
+    red_max: u32,
+    green_max: u32,
+    blue_max: u32,
+    red_shift: u8,
+    green_shift: u8,
+    blue_shift: u8,
+}
+
+impl PixelFormat {
+    /// The format of the desktop, 32-bit BGRA
+    fn desktop() -> PixelFormat {
+        PixelFormat {
+            bits: 32,
+            big_endian: false,
+            red_max: 255,
+            green_max: 255,
+            blue_max: 255,
+            red_shift: 16,
+            green_shift: 8,
+            blue_shift: 0
+        }
+    }
+
+    /// Parse the 16 bytes of a pixel format, if it is supported
+    fn parse(bytes: &[u8]) -> Option<PixelFormat> {
+        let bits = bytes[0];
+        if (bits != 8 && bits != 16 && bits != 32) || bytes[3] == 0 {
+            return None;
+        }
+
+        Some(PixelFormat {
+            bits: bits,
+            big_endian: bytes[2] != 0,
+            red_max: read_u16(&bytes[4..]) as u32,
+            green_max: read_u16(&bytes[6..]) as u32,
+            blue_max: read_u16(&bytes[8..]) as u32,
+            red_shift: bytes[10],
+            green_shift: bytes[11],
+            blue_shift: bytes[12]
+        })
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(self.bits);
+        out.push(if self.bits == 8 { 8 } else { 24 });
+        out.push(self.big_endian as u8);
+        out.push(1);
+        push_u16(out, self.red_max as u16);
+        push_u16(out, self.green_max as u16);
+        push_u16(out, self.blue_max as u16);
+        out.push(self.red_shift);
+        out.push(self.green_shift);
+        out.push(self.blue_shift);
+        out.extend_from_slice(&[0; 3]);
+    }
+
+    fn encode(&self, pixel: u32, out: &mut Vec<u8>) {
+        let r = (pixel >> 16) & 0xFF;
+        let g = (pixel >> 8) & 0xFF;
+        let b = pixel & 0xFF;
+        let value = (r * self.red_max / 255) << self.red_shift |
+                    (g * self.green_max / 255) << self.green_shift |
+                    (b * self.blue_max / 255) << self.blue_shift;
+
+        let bytes = self.bits as usize / 8;
+        for i in 0..bytes {
+            let byte = if self.big_endian { bytes - 1 - i } else { i };
+            out.push((value >> (byte * 8)) as u8);
+        }
+    }
+}
+
+/// A connected viewer
+struct Viewer {
+    format: PixelFormat,
+    /// Waiting for an update
+    requested: bool,
+    /// The areas written since the last update
+    damage: Vec<Rect>,
+}
+
+/// The pixels of the desktop and the viewers they are sent to
+struct Frame {
+    width: i32,
+    height: i32,
+    data: Vec<u32>,
+    viewers: BTreeMap<usize, Viewer>,
+    next_id: usize,
+}
+
+struct Shared {
+    frame: Mutex<Frame>,
+    events: Mutex<VecDeque<Event>>,
+}
+
+/// The display of a VNC server, which keeps the pixels for the viewers
+pub struct VncDisplay {
+    shared: Arc<Shared>,
+}
+
+impl Display for VncDisplay {
+    fn write(&mut self, offset: usize, pixels: &[u32]) {
+        let mut guard = self.shared.frame.lock().unwrap();
+        let frame = &mut *guard;
+
+        let end = cmp::min(offset + pixels.len(), frame.data.len());
+        if offset >= end {
+            return;
+        }
+        frame.data[offset .. end].copy_from_slice(&pixels[.. end - offset]);
+
+        let width = frame.width as usize;
+        let x = offset % width;
+        let y = offset / width;
+        let rect = if x + end - offset <= width {
+            Rect::new(x as i32, y as i32, (end - offset) as i32, 1)
+        } else {
+            Rect::new(0, y as i32, width as i32, ((x + end - offset + width - 1) / width) as i32)
+        };
+        for viewer in frame.viewers.values_mut() {
+            schedule(&mut viewer.damage, rect);
+        }
+    }
+}
+
+/// The keyboards and pointers of the viewers of a VNC server
+pub struct VncInput {
+    shared: Arc<Shared>,
+}
+
+impl Input for VncInput {
+    fn read(&mut self, events: &mut [Event]) -> Result<usize> {
+        loop {
+            {
+                let mut queue = self.shared.events.lock().unwrap();
+                let mut count = 0;
+                while count < events.len() {
+                    match queue.pop_front() {
+                        Some(event) => {
+                            events[count] = event;
+                            count += 1;
+                        },
+                        None => break
+                    }
+                }
+                if count > 0 {
+                    return Ok(count);
+                }
+            }
+
+            thread::sleep_ms(INPUT_INTERVAL);
+        }
+    }
+}
+
+/// Serve a desktop of a size to VNC viewers connecting to a TCP port
+///
+/// Returns the monitor that shows the desktop to the viewers, and their input
+pub fn listen(port: u16, width: i32, height: i32) -> (Monitor, VncInput) {
+    let shared = Arc::new(Shared {
+        frame: Mutex::new(Frame {
+            width: width,
+            height: height,
+            data: vec![0; width as usize * height as usize],
+            viewers: BTreeMap::new(),
+            next_id: 1
+        }),
+        events: Mutex::new(VecDeque::new())
+    });
+
+    let shared_listen = shared.clone();
+    thread::spawn(move || {
+        //Opening a port waits for the next connection
+        loop {
+            match File::open(&format!("tcp:/{}", port)) {
+                Ok(stream) => {
+                    let shared_viewer = shared_listen.clone();
+                    thread::spawn(move || {
+                        if let Err(err) = serve(stream, shared_viewer) {
+                            println!("orbital: vnc: viewer disconnected: {}", err);
+                        }
+                    });
+                },
+                Err(err) => {
+                    println!("orbital: vnc: could not listen on port {}: {}", port, err);
+                    break;
+                }
+            }
+        }
+    });
+
+    let monitor = Monitor::new(Rect::new(0, 0, width, height), Box::new(VncDisplay {
+        shared: shared.clone()
+    }));
+    (monitor, VncInput {
+        shared: shared
+    })
+}
+
+/// Agree on a version, without security, and describe the desktop
+fn handshake(stream: &mut File, width: i32, height: i32) -> Result<()> {
+    try!(stream.write_all(b"RFB 003.008\n"));
+
+    let mut version = [0; 12];
+    try!(stream.read_exact(&mut version));
+    if &version[.. 4] != b"RFB " {
+        return Err(invalid("not an RFB viewer"));
+    }
+    let minor = String::from_utf8_lossy(&version[8 .. 11]).parse::<u32>().unwrap_or(3);
+
+    if minor >= 7 {
+        try!(stream.write_all(&[1, 1]));
+        let mut security = [0; 1];
+        try!(stream.read_exact(&mut security));
+        if security[0] != 1 {
+            return Err(invalid("unsupported security type"));
+        }
+        if minor >= 8 {
+            try!(stream.write_all(&[0; 4]));
+        }
+    } else {
+        try!(stream.write_all(&[0, 0, 0, 1]));
+    }
+
+    //Whether other viewers should be disconnected, which they never are
+    let mut shared = [0; 1];
+    try!(stream.read_exact(&mut shared));
+
+    let mut init = Vec::new();
+    push_u16(&mut init, width as u16);
+    push_u16(&mut init, height as u16);
+    PixelFormat::desktop().write(&mut init);
+    push_u32(&mut init, DESKTOP_NAME.len() as u32);
+    init.extend_from_slice(DESKTOP_NAME.as_bytes());
+    stream.write_all(&init)
+}
+
+fn serve(mut stream: File, shared: Arc<Shared>) -> Result<()> {
+    let (width, height) = {
+        let frame = shared.frame.lock().unwrap();
+        (frame.width, frame.height)
+    };
+
+    try!(handshake(&mut stream, width, height));
+
+    let id = {
+        let mut frame = shared.frame.lock().unwrap();
+        let id = frame.next_id;
+        frame.next_id += 1;
+        frame.viewers.insert(id, Viewer {
+            format: PixelFormat::desktop(),
+            requested: false,
+            damage: Vec::new()
+        });
+        id
+    };
+
+    let updates = try!(stream.dup());
+    let shared_updates = shared.clone();
+    thread::spawn(move || {
+        send_updates(updates, shared_updates, id);
+    });
+
+    let result = receive_messages(&mut stream, &shared, id);
+    shared.frame.lock().unwrap().viewers.remove(&id);
+    result
+}
+
+/// Handle the messages of a viewer until it disconnects
+fn receive_messages(stream: &mut File, shared: &Shared, id: usize) -> Result<()> {
+    let mut buttons = 0;
+    loop {
+        let mut kind = [0; 1];
+        try!(stream.read_exact(&mut kind));
+        match kind[0] {
+            //SetPixelFormat
+            0 => {
+                let mut msg = [0; 19];
+                try!(stream.read_exact(&mut msg));
+                match PixelFormat::parse(&msg[3 ..]) {
+                    Some(format) => if let Some(viewer) = shared.frame.lock().unwrap().viewers.get_mut(&id) {
+                        viewer.format = format;
+                    },
+                    None => return Err(invalid("unsupported pixel format"))
+                }
+            },
+            //SetEncodings, only raw pixels are sent
+            2 => {
+                let mut msg = [0; 3];
+                try!(stream.read_exact(&mut msg));
+                try!(discard(stream, read_u16(&msg[1 ..]) as u64 * 4));
+            },
+            //FramebufferUpdateRequest
+            3 => {
+                let mut msg = [0; 9];
+                try!(stream.read_exact(&mut msg));
+                let rect = Rect::new(read_u16(&msg[1 ..]) as i32, read_u16(&msg[3 ..]) as i32,
+                                     read_u16(&msg[5 ..]) as i32, read_u16(&msg[7 ..]) as i32);
+
+                let mut guard = shared.frame.lock().unwrap();
+                let frame = &mut *guard;
+                let screen = Rect::new(0, 0, frame.width, frame.height);
+                if let Some(viewer) = frame.viewers.get_mut(&id) {
+                    viewer.requested = true;
+                    //Areas that are not incremental are sent even if they did not change
+                    if msg[0] == 0 {
+                        schedule(&mut viewer.damage, rect.intersection(&screen));
+                    }
+                }
+            },
+            //KeyEvent
+            4 => {
+                let mut msg = [0; 7];
+                try!(stream.read_exact(&mut msg));
+                if let Some(key_event) = key_event(read_u32(&msg[3 ..]), msg[0] != 0) {
+                    shared.events.lock().unwrap().push_back(key_event.to_event());
+                }
+            },
+            //PointerEvent
+            5 => {
+                let mut msg = [0; 5];
+                try!(stream.read_exact(&mut msg));
+                let mask = msg[0];
+                let mut events = shared.events.lock().unwrap();
+                events.push_back(MouseEvent {
+                    x: read_u16(&msg[1 ..]) as i32,
+                    y: read_u16(&msg[3 ..]) as i32,
+                    left_button: mask & 1 == 1,
+                    middle_button: mask & 2 == 2,
+                    right_button: mask & 4 == 4
+                }.to_event());
+
+                //Wheel steps are buttons 4 to 7, pressed and released
+                let pressed = mask & ! buttons;
+                for &(bit, x, y) in [(8, 0, 1), (16, 0, -1), (32, -1, 0), (64, 1, 0)].iter() {
+                    if pressed & bit == bit {
+                        events.push_back(ScrollEvent {
+                            x: x,
+                            y: y
+                        }.to_event());
+                    }
+                }
+                buttons = mask;
+            },
+            //ClientCutText, the clipboard is not shared
+            6 => {
+                let mut msg = [0; 7];
+                try!(stream.read_exact(&mut msg));
+                let len = read_u32(&msg[3 ..]);
+                if len > MAX_CUT_TEXT {
+                    return Err(invalid("cut text too long"));
+                }
+                try!(discard(stream, len as u64));
+            },
+            _ => return Err(invalid("unknown message"))
+        }
+    }
+}
+
+/// Send the areas written to a viewer, whenever it asked for an update, until it disconnects
+fn send_updates(mut stream: File, shared: Arc<Shared>, id: usize) {
+    loop {
+        let message = {
+            let mut guard = shared.frame.lock().unwrap();
+            let frame = &mut *guard;
+            match frame.viewers.get_mut(&id) {
+                Some(viewer) => if viewer.requested && ! viewer.damage.is_empty() {
+                    viewer.requested = false;
+                    let message = update(&frame.data, frame.width, &viewer.format, &viewer.damage);
+                    viewer.damage.clear();
+                    Some(message)
+                } else {
+                    None
+                },
+                None => return
+            }
+        };
+
+        match message {
+            Some(message) => if stream.write_all(&message).is_err() {
+                shared.frame.lock().unwrap().viewers.remove(&id);
+                return;
+            },
+            None => thread::sleep_ms(UPDATE_INTERVAL)
+        }
+    }
+}
+
+/// A FramebufferUpdate with the pixels of areas
+fn update(data: &[u32], width: i32, format: &PixelFormat, rects: &[Rect]) -> Vec<u8> {
+    let mut out = vec![0, 0];
+    push_u16(&mut out, rects.len() as u16);
+    for rect in rects.iter() {
+        push_u16(&mut out, rect.left() as u16);
+        push_u16(&mut out, rect.top() as u16);
+        push_u16(&mut out, rect.width() as u16);
+        push_u16(&mut out, rect.height() as u16);
+        //Raw encoding
+        push_u32(&mut out, 0);
+        for row in rect.top()..rect.bottom() {
+            let offset = (row * width + rect.left()) as usize;
+            for &pixel in data[offset .. offset + rect.width() as usize].iter() {
+                format.encode(pixel, &mut out);
+            }
+        }
+    }
+    out
+}
+
+/// Convert an X keysym from a viewer to a key event, using the scancodes of a US keyboard
+fn key_event(keysym: u32, pressed: bool) -> Option<KeyEvent> {
+    let (character, scancode) = match keysym {
+        0xFF08 => ('\0', event::K_BKSP),
+        0xFF09 => ('\t', event::K_TAB),
+        0xFF0D => ('\n', event::K_ENTER),
+        0xFF1B => ('\x1B', event::K_ESC),
+        0xFF50 => ('\0', event::K_HOME),
+        0xFF51 => ('\0', event::K_LEFT),
+        0xFF52 => ('\0', event::K_UP),
+        0xFF53 => ('\0', event::K_RIGHT),
+        0xFF54 => ('\0', event::K_DOWN),
+        0xFF55 => ('\0', event::K_PGUP),
+        0xFF56 => ('\0', event::K_PGDN),
+        0xFF57 => ('\0', event::K_END),
+        0xFF63 => ('\0', event::K_INS),
+        0xFF67 => ('\0', event::K_MENU),
+        0xFFBE ... 0xFFC7 => ('\0', event::K_F1 + (keysym - 0xFFBE) as u8),
+        0xFFC8 => ('\0', event::K_F11),
+        0xFFC9 => ('\0', event::K_F12),
+        0xFFE1 => ('\0', event::K_LEFT_SHIFT),
+        0xFFE2 => ('\0', event::K_RIGHT_SHIFT),
+        0xFFE3 | 0xFFE4 => ('\0', event::K_CTRL),
+        0xFFE5 => ('\0', event::K_CAPS),
+        0xFFE9 | 0xFFEA => ('\0', event::K_ALT),
+        0xFFEB => ('\0', event::K_SUPER),
+        0xFFEC => ('\0', event::K_RIGHT_SUPER),
+        0xFFFF => ('\0', event::K_DEL),
+        //Latin-1 matches Unicode, and other characters are Unicode with 0x1000000 added
+        0x20 ... 0xFF | 0x1000100 ... 0x110FFFF => {
+            let character = match ::std::char::from_u32(keysym & 0xFFFFFF) {
+                Some(character) => character,
+                None => return None
+            };
+            let scancode = UNSHIFTED.chars().position(|c| c == character)
+                .or_else(|| SHIFTED.chars().position(|c| c == character))
+                .unwrap_or(0);
+            (character, scancode as u8)
+        },
+        _ => return None
+    };
+
+    Some(KeyEvent {
+        character: character,
+        scancode: scancode,
+        pressed: pressed
+    })
+}
@@ -0,0 +1,125 @@
+use super::{Color, Image, Rect, ResizeFilter};
+
+/// The color shown where the wallpaper does not cover the desktop
+pub const BACKGROUND_COLOR: Color = Color::rgb(75, 163, 253);
+
+/// How the wallpaper is fit to each monitor
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WallpaperMode {
+    /// Shown at its size in the middle of the monitor
+    Center,
+    /// Enlarged or shrunk to cover the monitor, keeping its aspect ratio and cropping the edges
+    Scale,
+    /// Repeated from the top left of the monitor
+    Tile,
+}
+
+impl WallpaperMode {
+    /// Parse a mode name, `center`, `scale`, or `tile`
+    pub fn parse(name: &str) -> Option<WallpaperMode> {
+        match name.trim() {
+            "center" => Some(WallpaperMode::Center),
+            "scale" => Some(WallpaperMode::Scale),
+            "tile" => Some(WallpaperMode::Tile),
+            _ => None
+        }
+    }
+}
+
+/// The area an image centered on a monitor covers
+fn centered(image: &Image, monitor_rect: &Rect) -> Rect {
+    let w = image.width();
+    let h = image.height();
+    let x = monitor_rect.left() + monitor_rect.width()/2 - w/2;
+    let y = monitor_rect.top() + monitor_rect.height()/2 - h/2;
+    Rect::new(x, y, w, h)
+}
+
+/// The desktop background
+pub struct Wallpaper {
+    image: Image,
+    mode: WallpaperMode,
+    /// The image resized for each size of monitor, in `Scale` mode
+    scaled: Vec<Image>,
+}
+
+impl Wallpaper {
+    pub fn new(image: Image, mode: WallpaperMode) -> Wallpaper {
+        Wallpaper {
+            image: image,
+            mode: mode,
+            scaled: Vec::new()
+        }
+    }
+
+    /// The image resized to cover a monitor
+    fn scaled(&mut self, monitor_rect: &Rect) -> &mut Image {
+        let w = self.image.width();
+        let h = self.image.height();
+        //The larger ratio covers the monitor in both directions
+        let (width, height) = if monitor_rect.width() as i64 * h as i64 > monitor_rect.height() as i64 * w as i64 {
+            (monitor_rect.width(), (h as i64 * monitor_rect.width() as i64 / w as i64) as i32)
+        } else {
+            ((w as i64 * monitor_rect.height() as i64 / h as i64) as i32, monitor_rect.height())
+        };
+
+        let i = match self.scaled.iter().position(|image| image.width() == width && image.height() == height) {
+            Some(i) => i,
+            None => {
+                let image = self.image.resize(width, height, ResizeFilter::Bilinear);
+                self.scaled.push(image);
+                self.scaled.len() - 1
+            }
+        };
+        &mut self.scaled[i]
+    }
+
+    /// Draw the part of the wallpaper of a monitor that is in an area, over `BACKGROUND_COLOR`
+    pub fn draw(&mut self, image: &mut Image, rect: &Rect, monitor_rect: &Rect) {
+        let clip = rect.intersection(monitor_rect);
+        if clip.is_empty() {
+            return;
+        }
+
+        if self.image.width() <= 0 || self.image.height() <= 0 {
+            return;
+        }
+
+        match self.mode {
+            WallpaperMode::Center => {
+                let image_rect = centered(&self.image, monitor_rect);
+                let intersect = clip.intersection(&image_rect);
+                if ! intersect.is_empty() {
+                    image.roi(&intersect).blit(&self.image.roi(&intersect.offset(-image_rect.left(), -image_rect.top())));
+                }
+            },
+            WallpaperMode::Scale => {
+                let monitor_rect = *monitor_rect;
+                let scaled = self.scaled(&monitor_rect);
+                let image_rect = centered(scaled, &monitor_rect);
+                let intersect = clip.intersection(&image_rect);
+                if ! intersect.is_empty() {
+                    image.roi(&intersect).blit(&scaled.roi(&intersect.offset(-image_rect.left(), -image_rect.top())));
+                }
+            },
+            WallpaperMode::Tile => {
+                let w = self.image.width();
+                let h = self.image.height();
+                //The first tile touching the area
+                let mut y = monitor_rect.top() + (clip.top() - monitor_rect.top()) / h * h;
+                while y < clip.bottom() {
+                    let mut x = monitor_rect.left() + (clip.left() - monitor_rect.left()) / w * w;
+                    while x < clip.right() {
+                        let tile_rect = Rect::new(x, y, w, h);
+                        let intersect = clip.intersection(&tile_rect);
+                        if ! intersect.is_empty() {
+                            image.roi(&intersect).blit(&self.image.roi(&intersect.offset(-x, -y)));
+                        }
+                        x += w;
+                    }
+                    y += h;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,820 @@
+use std::cmp::{min, max};
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::{ptr, slice};
+use std::time::{Duration, Instant};
+
+use super::{escape_arg, Blitter, Color, CursorKind, Event, Image, Rect, ResizeFilter, TextAlign, TextFont, Theme, VectorFont};
+use super::theme::TitleButton;
+use super::animation;
+use super::clock;
+use super::stream::Stream;
+use super::surface::Surface;
+use super::pixel;
+use super::event::{KeyEvent, PingEvent, ResizeEvent};
+
+use system::error::{Error, Result, EINVAL};
+use system::graphics::fast_copy;
+
+/// The size of the icon in the title bar
+pub const ICON_SIZE: i32 = 16;
+/// A window with this many unread events is considered busy
+const BUSY_EVENTS: usize = 32;
+/// The largest factor the zoom button enlarges the contents by
+const MAX_ZOOM: i32 = 3;
+
+/// A query answered by the next read of a window, instead of events
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowQuery {
+    /// Where the window was placed, see `Window::geometry`
+    Geometry,
+    /// The scale factor of the display the window is on
+    Scale,
+    /// The id of the window, to make it the parent of others or to wait on it
+    Id(usize),
+}
+
+pub struct Window {
+    pub x: i32,
+    pub y: i32,
+    pub async: bool,
+    pub workspace: usize,
+    /// Hidden until it is activated
+    pub minimized: bool,
+    /// A menu or tooltip, drawn without a title bar above other windows, which does not take focus
+    /// and is closed when the user clicks elsewhere
+    pub popup: bool,
+    /// An input panel, such as an on-screen keyboard, docked at the bottom of a display on every workspace, which
+    /// does not take focus and types into the focused window
+    pub panel: bool,
+    /// Windows that are scale aware draw in physical pixels, others are enlarged by the compositor
+    pub scale_aware: bool,
+    /// Hide the cursor while it is over the window
+    pub hide_cursor: bool,
+    /// The cursor shown while it is over the contents of the window
+    pub cursor: CursorKind,
+    /// Send all mouse events to the window and keep the cursor inside it while it is focused
+    pub grab: bool,
+    /// Pin the cursor while the pointer is grabbed, sending relative motion instead
+    pub relative: bool,
+    /// Receive touch events, instead of pointer events emulated from the first contact
+    pub touch: bool,
+    /// Blend the contents by their alpha over what is behind the window, instead of copying them
+    pub translucent: bool,
+    /// The window this window belongs to, such as the window a dialog was opened for
+    pub parent: Option<usize>,
+    /// Stay above the parent and block its input while open
+    pub modal: bool,
+    /// Cover the display without decorations, remembering the geometry to restore
+    fullscreen: Option<Rect>,
+    /// The scale factor of the display the window is on
+    scale: i32,
+    /// A factor to enlarge the contents by, chosen with the zoom button, for small fixed-size windows
+    zoom: i32,
+    image: Image,
+    /// The memory the client maps with `sys_fmap` and draws into, instead of writing the contents
+    surface: Option<Surface>,
+    /// Surfaces replaced by resizing, kept until the window is closed since the client may still map them
+    retired: Vec<Surface>,
+    restore: Option<Rect>,
+    title: String,
+    /// The icon, enlarged for the display
+    icon: Option<Image>,
+    /// The next write sets the icon instead of the contents
+    pub icon_pending: bool,
+    /// The next read answers a query instead of returning events
+    pub query: Option<WindowQuery>,
+    /// Events waiting to be read, oldest first
+    events: VecDeque<Event>,
+    /// The serial of the ping waiting for an answer, and when it was sent
+    pending_ping: Option<(u32, Instant)>,
+    /// The client answered a ping, so it is watched for not responding
+    answers_pings: bool,
+    /// The client did not answer a ping in time, so the window is dimmed and closing it does not wait for the client
+    pub not_responding: bool,
+    /// The queued frames of a streaming window, see `Stream`
+    stream: Option<Stream>,
+    /// The look of the title bar
+    pub theme: Theme,
+}
+
+impl Window {
+    /// Create a window with a size in logical pixels, on a display with the given scale factor
+    pub fn new(x: i32, y: i32, w: i32, h: i32, title: String, async: bool, scale_aware: bool, popup: bool, scale: i32, workspace: usize) -> Window {
+        let buffer_scale = if scale_aware { scale } else { 1 };
+        Window {
+            x: x,
+            y: y,
+            scale_aware: scale_aware,
+            hide_cursor: false,
+            cursor: CursorKind::Arrow,
+            grab: false,
+            relative: false,
+            touch: false,
+            translucent: false,
+            parent: None,
+            modal: false,
+            fullscreen: None,
+            scale: scale,
+            zoom: 1,
+            image: Image::new(w * buffer_scale, h * buffer_scale),
+            surface: None,
+            retired: Vec::new(),
+            restore: None,
+            title: title,
+            icon: None,
+            icon_pending: false,
+            query: None,
+            async: async,
+            workspace: workspace,
+            minimized: false,
+            popup: popup,
+            panel: false,
+            events: VecDeque::new(),
+            pending_ping: None,
+            answers_pings: false,
+            not_responding: false,
+            stream: None,
+            theme: Theme::new()
+        }
+    }
+
+    /// Set the flags that can be changed after the window is opened, `h` to hide the cursor, `g` to grab the pointer,
+    /// `r` for relative motion while grabbed, `t` for touch events, `m` to be modal for the parent, `b` to blend
+    /// translucent contents, and `v` to stream queued frames
+    ///
+    /// The `f` flag for fullscreen is handled by the scheme, which knows the display
+    pub fn set_flags(&mut self, flags: &str) {
+        self.hide_cursor = flags.contains('h');
+        self.grab = flags.contains('g');
+        self.relative = flags.contains('r');
+        self.touch = flags.contains('t');
+        self.modal = flags.contains('m');
+        self.translucent = flags.contains('b');
+        if ! flags.contains('v') {
+            self.stream = None;
+        } else if self.stream.is_none() {
+            self.stream = Some(Stream::new());
+        }
+    }
+
+    /// The factor the window contents are enlarged by when drawn
+    fn content_scale(&self) -> i32 {
+        (if self.scale_aware { 1 } else { self.scale }) * self.zoom
+    }
+
+    /// Enlarge the contents by the next zoom factor, going back to the normal size after the largest
+    ///
+    /// The client is not resized, its pixels are repeated
+    pub fn cycle_zoom(&mut self) {
+        self.zoom = self.zoom % MAX_ZOOM + 1;
+    }
+
+    /// The width on screen, in physical pixels
+    pub fn width(&self) -> i32 {
+        self.image.width() * self.content_scale()
+    }
+
+    /// The height on screen, in physical pixels
+    pub fn height(&self) -> i32 {
+        self.image.height() * self.content_scale()
+    }
+
+    fn title_height(&self) -> i32 {
+        if self.title.is_empty() || self.popup || self.panel || self.fullscreen.is_some() { 0 } else { self.theme.bar_height * self.scale }
+    }
+
+    /// Move the window to a display with a different scale factor, keeping its logical size
+    pub fn set_scale(&mut self, scale: i32) {
+        if scale != self.scale {
+            let old_scale = self.scale;
+            self.scale = scale;
+            if let Some(icon) = self.icon.take() {
+                self.icon = Some(icon.resize(icon.width() * scale / old_scale, icon.height() * scale / old_scale, ResizeFilter::Bilinear));
+            }
+            if self.scale_aware {
+                let w = self.image.width() * scale / old_scale;
+                let h = self.image.height() * scale / old_scale;
+                self.resize(w, h);
+            }
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Draw the icon with its top left corner at a point, clipped to a rectangle, returning its width
+    pub fn draw_icon(&mut self, image: &mut Image, rect: &Rect, x: i32, y: i32) -> i32 {
+        if let Some(ref mut icon) = self.icon {
+            let icon_rect = Rect::new(x, y, icon.width(), icon.height());
+            let icon_intersect = rect.intersection(&icon_rect);
+            if ! icon_intersect.is_empty() {
+                image.roi(&icon_intersect).blend(&icon.roi(&icon_intersect.offset(-icon_rect.left(), -icon_rect.top())));
+            }
+            icon.width()
+        } else {
+            0
+        }
+    }
+
+    /// Set the icon from a BMP, PNG, or JPEG image, resizing it for the title bar
+    pub fn set_icon(&mut self, data: &[u8]) -> Result<usize> {
+        let image = Image::decode(data);
+        if image.width() > 0 && image.height() > 0 {
+            let size = ICON_SIZE * self.scale;
+            self.icon = Some(image.resize(size, size, ResizeFilter::Bilinear));
+            Ok(data.len())
+        } else {
+            Err(Error::new(EINVAL))
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width(), self.height())
+    }
+
+    pub fn title_rect(&self) -> Rect {
+        if self.title_height() == 0 {
+            Rect::default()
+        } else {
+            Rect::new(self.x, self.y - self.title_height(), self.width(), self.title_height())
+        }
+    }
+
+    /// The window and its title bar
+    pub fn frame_rect(&self) -> Rect {
+        let title_rect = self.title_rect();
+        if title_rect.is_empty() {
+            self.rect()
+        } else {
+            title_rect.container(&self.rect())
+        }
+    }
+
+    /// The scale factor of the display the window is on
+    pub fn scale(&self) -> i32 {
+        self.scale
+    }
+
+    /// The buttons of the title bar, placed by the button layout of the theme
+    ///
+    /// Buttons that do not fit on a narrow window are left out, the right side first.
+    pub fn buttons(&self) -> Vec<(TitleButton, Rect)> {
+        let mut buttons = Vec::new();
+        let title_rect = self.title_rect();
+        if title_rect.is_empty() {
+            return buttons;
+        }
+
+        let mut left = title_rect.left();
+        for button in self.theme.button_layout.side(false) {
+            let width = button.width() * self.scale;
+            if left + width > title_rect.right() {
+                break;
+            }
+            buttons.push((button, Rect::new(left, title_rect.top(), width, title_rect.height())));
+            left += width;
+        }
+
+        let mut right = title_rect.right();
+        for button in self.theme.button_layout.side(true).into_iter().rev() {
+            let width = button.width() * self.scale;
+            if right - width < left {
+                break;
+            }
+            right -= width;
+            buttons.push((button, Rect::new(right, title_rect.top(), width, title_rect.height())));
+        }
+
+        buttons
+    }
+
+    /// The button of the title bar at a point
+    pub fn button_at(&self, x: i32, y: i32) -> Option<TitleButton> {
+        self.buttons().into_iter().find(|&(_, rect)| rect.contains(x, y)).map(|(button, _)| button)
+    }
+
+    /// The part of the title bar between the buttons, where the icon and title are drawn
+    fn title_text_rect(&self) -> Rect {
+        let title_rect = self.title_rect();
+        let mut left = title_rect.left();
+        let mut right = title_rect.right();
+        for (_, rect) in self.buttons() {
+            if rect.left() == left {
+                left = rect.right();
+            } else {
+                right = min(right, rect.left());
+            }
+        }
+        Rect::new(left, title_rect.top(), max(0, right - left), title_rect.height())
+    }
+
+    /// Whether a read would return data, the answer to a query or queued events
+    pub fn ready(&self) -> bool {
+        self.query.is_some() || ! self.events.is_empty()
+    }
+
+    /// A window is busy when its client has stopped reading events
+    pub fn busy(&self) -> bool {
+        self.events.len() >= BUSY_EVENTS
+    }
+
+    /// Check that the client is responding, sending a ping if none is waiting for an answer
+    ///
+    /// Returns true if the client just stopped responding. Clients that never answered a ping are not watched.
+    pub fn ping(&mut self, serial: u32, timeout: Duration) -> bool {
+        match self.pending_ping {
+            Some((_, sent)) => if self.answers_pings && ! self.not_responding && sent.elapsed() >= timeout {
+                self.not_responding = true;
+                return true;
+            },
+            None => {
+                self.pending_ping = Some((serial, Instant::now()));
+                self.event(PingEvent { serial: serial }.to_event());
+            }
+        }
+        false
+    }
+
+    /// Resize the window to a size on screen, keeping the overlapping contents, and notify the client
+    pub fn resize(&mut self, w: i32, h: i32) {
+        let w = w / self.content_scale();
+        let h = h / self.content_scale();
+        if w != self.image.width() || h != self.image.height() {
+            let mut image = Image::new(w, h);
+            let rect = Rect::new(0, 0, min(w, self.image.width()), min(h, self.image.height()));
+            image.roi(&rect).blit(&self.image.roi(&rect));
+            self.image = image;
+            if let Some(surface) = self.surface.take() {
+                self.retired.push(surface);
+            }
+            if let Some(ref mut stream) = self.stream {
+                stream.clear();
+            }
+
+            self.event(ResizeEvent {
+                width: w as u32,
+                height: h as u32
+            }.to_event());
+        }
+    }
+
+    pub fn fullscreen(&self) -> bool {
+        self.fullscreen.is_some()
+    }
+
+    /// Cover a display, or leave fullscreen with `None`, restoring the previous geometry
+    pub fn set_fullscreen(&mut self, display: Option<Rect>) {
+        match display {
+            Some(rect) => {
+                if self.fullscreen.is_none() {
+                    self.fullscreen = Some(self.rect());
+                }
+                self.x = rect.left();
+                self.y = rect.top();
+                self.resize(rect.width(), rect.height());
+            },
+            None => if let Some(rect) = self.fullscreen.take() {
+                self.x = rect.left();
+                self.y = rect.top();
+                self.resize(rect.width(), rect.height());
+            }
+        }
+    }
+
+    /// Snap the window and its title bar to the given rectangle, remembering the previous geometry
+    pub fn snap(&mut self, rect: Rect) {
+        if self.restore.is_none() {
+            self.restore = Some(self.rect());
+        }
+        let title_height = self.title_height();
+        self.x = rect.left();
+        self.y = rect.top() + title_height;
+        self.resize(rect.width(), max(0, rect.height() - title_height));
+    }
+
+    /// Cover a rectangle with the window, or restore the geometry it had before if it is snapped
+    pub fn toggle_maximize(&mut self, rect: Rect) {
+        if let Some(restore) = self.restore.take() {
+            self.x = restore.left();
+            self.y = restore.top();
+            self.resize(restore.width(), restore.height());
+        } else {
+            self.snap(rect);
+        }
+    }
+
+    /// Restore the size the window had before snapping, returning true if it was snapped
+    pub fn unsnap(&mut self) -> bool {
+        if let Some(rect) = self.restore.take() {
+            self.resize(rect.width(), rect.height());
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn draw_title(&mut self, image: &mut Image, rect: &Rect, focused: bool, font: Option<&mut VectorFont>) {
+        let title_rect = self.title_rect();
+        let title_intersect = rect.intersection(&title_rect);
+        if ! title_intersect.is_empty() {
+            let scale = self.scale;
+            let bar_color = self.theme.bar_color(focused);
+
+            //The top corners are rounded, blending their antialiased edges with what is behind the title bar
+            let radius = min(self.theme.corner_radius * scale, min(title_rect.height(), title_rect.width()/2));
+            let top_rect = Rect::new(title_rect.left() + radius, title_rect.top(), title_rect.width() - 2 * radius, radius);
+            let bottom_rect = Rect::new(title_rect.left(), title_rect.top() + radius, title_rect.width(), title_rect.height() - radius);
+            image.roi(&title_intersect.intersection(&top_rect)).set(bar_color);
+            image.roi(&title_intersect.intersection(&bottom_rect)).set(bar_color);
+            if radius > 0 {
+                for &right in [false, true].iter() {
+                    let mut corner = corner_image(radius, bar_color, right);
+                    let x = if right { title_rect.right() - radius } else { title_rect.left() };
+                    let corner_rect = Rect::new(x, title_rect.top(), radius, radius);
+                    let corner_intersect = title_intersect.intersection(&corner_rect);
+                    if ! corner_intersect.is_empty() {
+                        image.roi(&corner_intersect).blend(&corner.roi(&corner_intersect.offset(-corner_rect.left(), -corner_rect.top())));
+                    }
+                }
+            }
+
+            let text_rect = self.title_text_rect();
+            let text_intersect = title_intersect.intersection(&text_rect);
+            let mut x = text_rect.left() + 2 * scale;
+            if x + ICON_SIZE * scale <= text_rect.right() {
+                let icon_width = self.draw_icon(image, &text_intersect, x, title_rect.top() + scale);
+                if icon_width > 0 {
+                    x += icon_width + 2 * scale;
+                }
+            }
+            let mut text_font = match font {
+                Some(font) => TextFont::Vector(font, self.theme.font_size * scale as u32, scale),
+                None => TextFont::Bitmap(scale)
+            };
+            let text_color = self.theme.text_color(focused);
+
+            let status = if self.not_responding { " (Not Responding)" } else { "" };
+            let title = format!("{}{}", self.title, status);
+            let layout = Rect::new(x, title_rect.top() + scale, text_rect.right() - x, title_rect.height() - scale);
+            image.roi(&text_intersect).draw_text(&mut text_font, &title, text_color,
+                                                 &layout.offset(-text_intersect.left(), -text_intersect.top()), TextAlign::Left);
+
+            for (button, button_rect) in self.buttons() {
+                let button_intersect = title_intersect.intersection(&button_rect);
+                if button_intersect.is_empty() {
+                    continue;
+                }
+
+                let label = if button == TitleButton::Zoom {
+                    format!("{}{}", self.zoom, self.theme.zoom_button)
+                } else {
+                    self.theme.button_glyph(button).to_string()
+                };
+                let layout = Rect::new(button_rect.left(), title_rect.top() + scale, button_rect.width(), title_rect.height() - scale);
+                image.roi(&button_intersect).draw_text(&mut text_font, &label, text_color,
+                                                       &layout.offset(-button_intersect.left(), -button_intersect.top()), TextAlign::Left);
+            }
+        }
+    }
+
+    /// Draw the contents the client last wrote, which are kept so the window is redrawn when it is uncovered, moved,
+    /// or drawn over without asking the client, blending them if the window is translucent
+    pub fn draw(&mut self, image: &mut Image, rect: &Rect, blitter: &mut Blitter) {
+        let self_rect = self.rect();
+        let intersect = self_rect.intersection(&rect);
+        if ! intersect.is_empty() {
+            let scale = self.content_scale();
+            if scale == 1 {
+                if self.translucent {
+                    blitter.blend(image, &intersect, &mut self.image, intersect.left() - self_rect.left(), intersect.top() - self_rect.top());
+                } else {
+                    blitter.blit(image, &intersect, &mut self.image, intersect.left() - self_rect.left(), intersect.top() - self_rect.top());
+                }
+            } else {
+                //Enlarge the contents, repeating each pixel
+                let width = image.width();
+                let data = image.data_mut();
+                let src_width = self.image.width();
+                let src = self.image.data();
+                for y in intersect.top()..intersect.bottom() {
+                    let src_row = (y - self_rect.top()) / scale * src_width;
+                    for x in intersect.left()..intersect.right() {
+                        let i = (y * width + x) as usize;
+                        let pixel = src[(src_row + (x - self_rect.left()) / scale) as usize];
+                        if self.translucent {
+                            pixel::blend_color_scalar(&mut data[i .. i + 1], pixel);
+                        } else {
+                            data[i] = pixel;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draw the contents scaled to an area with an opacity, clipped to a rectangle, as animations do
+    pub fn draw_animated(&self, image: &mut Image, rect: &Rect, dest: &Rect, alpha: u8) {
+        animation::draw_scaled(image, rect, dest, &self.image, alpha);
+    }
+
+    /// Send an event, events made by the compositor, such as resizes, have the time they are sent
+    pub fn event(&mut self, mut event: Event) {
+        if event.time == 0 {
+            event.time = clock::monotonic();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Send a mouse or touch event at a point on screen, converting it to the window's pixels
+    pub fn mouse_event(&mut self, mut event: Event) {
+        let scale = self.content_scale() as i64;
+        event.a = (event.a - self.x as i64) / scale;
+        event.b = (event.b - self.y as i64) / scale;
+        self.events.push_back(event);
+    }
+
+    /// Read the answer to a query, or as many queued events as fit in the buffer
+    ///
+    /// Events are packed one after another, oldest first, each `size_of::<Event>()` bytes, so a client can take a
+    /// burst of input in one read with a buffer of many events. Bytes after the last whole event are left as they are.
+    /// Returns 0 if there are no events, and `EINVAL` if the buffer cannot hold one.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if let Some(query) = self.query.take() {
+            let answer = match query {
+                WindowQuery::Geometry => self.geometry(),
+                WindowQuery::Scale => format!("{}", self.scale),
+                WindowQuery::Id(id) => format!("{}", id),
+            };
+            let mut i = 0;
+            for b in answer.bytes().take(buf.len()) {
+                buf[i] = b;
+                i += 1;
+            }
+            return Ok(i);
+        }
+
+        if buf.len() >= size_of::<Event>() {
+            let mut i = 0;
+            while i <= buf.len() - size_of::<Event>() {
+                if let Some(event) = self.events.pop_front() {
+                    unsafe { ptr::write(buf.as_mut_ptr().offset(i as isize) as *mut Event, event) };
+                    i += size_of::<Event>();
+                } else {
+                    break;
+                }
+            }
+            Ok(i)
+        } else {
+            Err(Error::new(EINVAL))
+        }
+    }
+
+    /// A write starting with `T,` that is not a whole frame sets the title, such as `T,notes.txt - Editor`
+    pub fn is_title_write(&self, buf: &[u8]) -> bool {
+        buf.starts_with(b"T,") && buf.len() != self.image.data().len() * 4
+    }
+
+    /// A write starting with `F,` that is not a whole frame changes the flags, optionally followed by a command, such
+    /// as `F,hg` or `F,m/parent/3`, see `OrbitalScheme::set_window_flags`
+    pub fn is_flags_write(&self, buf: &[u8]) -> bool {
+        buf.starts_with(b"F,") && buf.len() != self.image.data().len() * 4
+    }
+
+    /// A write starting with `D,` that is not a whole frame redraws a rectangle of mapped contents, such as `D,0,0,64,32`
+    pub fn is_damage_write(&self, buf: &[u8]) -> bool {
+        buf.starts_with(b"D,") && buf.len() != self.image.data().len() * 4
+    }
+
+    /// A write starting with `K,` that is not a whole frame types a key, see `key`
+    pub fn is_key_write(&self, buf: &[u8]) -> bool {
+        buf.starts_with(b"K,") && buf.len() != self.image.data().len() * 4
+    }
+
+    /// A write starting with `S,` that is not a whole frame sets the primary selection to the text after it, such as
+    /// `S,hello` when `hello` is selected, or `S,` when nothing is
+    pub fn is_selection_write(&self, buf: &[u8]) -> bool {
+        buf.starts_with(b"S,") && buf.len() != self.image.data().len() * 4
+    }
+
+    /// A write starting with `C,` that is not a whole frame sets the cursor shown over the contents, such as `C,text`
+    /// in an editor or `C,crosshair` in a drawing program, see `CursorKind::parse`
+    pub fn is_cursor_write(&self, buf: &[u8]) -> bool {
+        buf.starts_with(b"C,") && buf.len() != self.image.data().len() * 4
+    }
+
+    /// Set the cursor shown over the contents from a cursor write, see `is_cursor_write`
+    pub fn set_cursor(&mut self, buf: &[u8]) -> Result<usize> {
+        match CursorKind::parse(&String::from_utf8_lossy(&buf[2..])) {
+            Some(cursor) => {
+                self.cursor = cursor;
+                Ok(buf.len())
+            },
+            None => Err(Error::new(EINVAL))
+        }
+    }
+
+    /// A write starting with `P,` that is not a whole frame answers a ping, such as `P,12`
+    pub fn is_pong_write(&self, buf: &[u8]) -> bool {
+        buf.starts_with(b"P,") && buf.len() != self.image.data().len() * 4
+    }
+
+    /// Answer a ping from a pong write, see `is_pong_write`, returning true if the client was not responding
+    pub fn pong(&mut self, buf: &[u8]) -> Result<bool> {
+        let serial = try!(String::from_utf8_lossy(&buf[2..]).trim().parse::<u32>().or(Err(Error::new(EINVAL))));
+        if let Some((ping_serial, _)) = self.pending_ping {
+            if serial == ping_serial {
+                self.pending_ping = None;
+            }
+        }
+        self.answers_pings = true;
+
+        let not_responding = self.not_responding;
+        self.not_responding = false;
+        Ok(not_responding)
+    }
+
+    /// A write starting with `Q,` that is not a whole frame queues a frame of a streaming window, as the time to show
+    /// it in milliseconds on the clock of the client, a comma, and the pixels of a whole frame, such as `Q,40,` and
+    /// the pixels
+    pub fn is_queue_write(&self, buf: &[u8]) -> bool {
+        buf.starts_with(b"Q,") && buf.len() != self.image.data().len() * 4
+    }
+
+    /// Queue a frame from a queue write, see `is_queue_write`
+    pub fn queue(&mut self, buf: &[u8]) -> Result<usize> {
+        let len = self.image.data().len();
+        let stream = match self.stream {
+            Some(ref mut stream) => stream,
+            None => return Err(Error::new(EINVAL))
+        };
+
+        let comma = match buf[2..].iter().position(|&b| b == b',') {
+            Some(comma) => 2 + comma,
+            None => return Err(Error::new(EINVAL))
+        };
+        let timestamp = try!(String::from_utf8_lossy(&buf[2..comma]).trim().parse::<u64>().or(Err(Error::new(EINVAL))));
+
+        let pixels = &buf[comma + 1..];
+        if pixels.len() != len * 4 {
+            return Err(Error::new(EINVAL));
+        }
+        let mut data = vec![0; len].into_boxed_slice();
+        unsafe {
+            ptr::copy_nonoverlapping(pixels.as_ptr(), data.as_mut_ptr() as *mut u8, pixels.len());
+        }
+
+        try!(stream.queue(timestamp, data));
+        Ok(buf.len())
+    }
+
+    /// Show the newest frame of the stream that is due, returning true if the contents changed
+    pub fn present(&mut self, now: Instant) -> bool {
+        let frame = match self.stream {
+            Some(ref mut stream) => stream.present(now),
+            None => None
+        };
+        match frame {
+            Some(data) => {
+                let len = min(data.len(), self.image.data().len());
+                unsafe {
+                    fast_copy(self.image.data_mut().as_mut_ptr(), data.as_ptr(), len);
+                }
+                true
+            },
+            None => false
+        }
+    }
+
+    /// When the next queued frame of the stream is due, `None` if it has none or is not streaming
+    pub fn next_frame(&self) -> Option<Instant> {
+        self.stream.as_ref().and_then(|stream| stream.next())
+    }
+
+    /// Whether the window streams queued frames, see `Stream`
+    pub fn streaming(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// The key event of a key write, with the character as a code point, the scancode, and 1 if it is pressed or 0
+    /// if it is released, such as `K,97,30,1` to press `a`
+    pub fn key(&self, buf: &[u8]) -> Result<Event> {
+        let string = String::from_utf8_lossy(&buf[2..]);
+        let mut parts = string.split(',').map(|part| part.trim().parse::<u32>());
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(character)), Some(Ok(scancode)), Some(Ok(pressed)), None) if scancode < 256 && pressed < 2 => {
+                match ::std::char::from_u32(character) {
+                    Some(character) => Ok(KeyEvent {
+                        character: character,
+                        scancode: scancode as u8,
+                        pressed: pressed == 1
+                    }.to_event()),
+                    None => Err(Error::new(EINVAL))
+                }
+            },
+            _ => Err(Error::new(EINVAL))
+        }
+    }
+
+    /// Copy a rectangle of a damage write from the surface to the contents, returning the rectangle on screen to
+    /// redraw, see `is_damage_write`
+    pub fn damage(&mut self, buf: &[u8]) -> Result<Rect> {
+        let string = String::from_utf8_lossy(&buf[2..]);
+        let mut parts = string.split(',').map(|part| part.trim().parse::<i32>());
+        match (parts.next(), parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(x)), Some(Ok(y)), Some(Ok(w)), Some(Ok(h)), None) => {
+                let rect = match self.surface {
+                    Some(ref surface) => surface.copy(&mut self.image, &Rect::new(x, y, w, h)),
+                    None => return Ok(Rect::new(0, 0, 0, 0))
+                };
+                let scale = self.content_scale();
+                Ok(Rect::new(self.x + rect.left() * scale, self.y + rect.top() * scale, rect.width() * scale,
+                             rect.height() * scale).intersection(&self.rect()))
+            },
+            _ => Err(Error::new(EINVAL))
+        }
+    }
+
+    /// Share `size` bytes of the surface at `offset` with the client, returning their address, see `Scheme::fmap`
+    ///
+    /// The surface is created with the contents the first time, and again after each resize, when the client maps
+    /// it again. The old surface stays valid until the window is closed, but is no longer drawn.
+    pub fn map(&mut self, offset: usize, size: usize) -> Result<usize> {
+        if self.surface.is_none() {
+            self.surface = Some(Surface::new(&self.image));
+        }
+        let surface = self.surface.as_ref().unwrap();
+        if offset.checked_add(size).map_or(true, |end| end > surface.size()) {
+            return Err(Error::new(EINVAL));
+        }
+
+        try!(surface.lock());
+        Ok(surface.address() + offset)
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.is_title_write(buf) {
+            self.title = String::from_utf8_lossy(&buf[2..]).into_owned();
+            return Ok(buf.len());
+        }
+
+        let old = self.image.data_mut();
+        let new = unsafe { slice::from_raw_parts(buf.as_ptr() as *const u32, buf.len() / 4) };
+
+        let len = min(old.len(), new.len());
+        unsafe {
+            fast_copy(old.as_mut_ptr(), new.as_ptr(), len);
+        }
+
+        Ok(len)
+    }
+
+    /// The position of the contents on the desktop, the size of the contents in the pixels of writes, and the title,
+    /// as `x/y/width/height/title`
+    pub fn geometry(&self) -> String {
+        format!("{}/{}/{}/{}/{}", self.x, self.y, self.image.width(), self.image.height(), escape_arg(&self.title))
+    }
+
+    pub fn path(&self, buf: &mut [u8]) -> Result<usize> {
+        let mut i = 0;
+        let path_str = format!("orbital:{}{}{}{}{}{}{}{}{}{}{}{}/{}", if self.async { "a" } else { "" }, if self.scale_aware { "s" } else { "" },
+                               if self.popup { "p" } else { "" }, if self.panel { "k" } else { "" },
+                               if self.hide_cursor { "h" } else { "" }, if self.grab { "g" } else { "" }, if self.relative { "r" } else { "" },
+                               if self.touch { "t" } else { "" }, if self.modal { "m" } else { "" }, if self.translucent { "b" } else { "" },
+                               if self.stream.is_some() { "v" } else { "" }, if self.fullscreen.is_some() { "f" } else { "" }, self.geometry());
+        let path = path_str.as_bytes();
+        while i < buf.len() && i < path.len() {
+            buf[i] = path[i];
+            i += 1;
+        }
+        Ok(i)
+    }
+}
+
+/// A rounded top corner of the title bar, the color covering the part of each pixel inside the circle
+///
+/// Each pixel is sampled four by four times, so the edge is antialiased.
+fn corner_image(radius: i32, color: Color, right: bool) -> Image {
+    let alpha = (color.data >> 24) & 0xFF;
+    let rgb = color.data & 0xFFFFFF;
+    let r = radius as f32;
+    let mut data = Vec::with_capacity((radius * radius) as usize);
+    for y in 0..radius {
+        for x in 0..radius {
+            let mut inside = 0;
+            for sy in 0..4 {
+                for sx in 0..4 {
+                    //The center of the circle is at the inner corner
+                    let px = if right { x as f32 + (sx as f32 + 0.5)/4.0 } else { r - x as f32 - (sx as f32 + 0.5)/4.0 };
+                    let py = r - y as f32 - (sy as f32 + 0.5)/4.0;
+                    if px * px + py * py <= r * r {
+                        inside += 1;
+                    }
+                }
+            }
+            data.push((alpha * inside / 16) << 24 | rgb);
+        }
+    }
+    Image::from_data(radius, radius, data.into_boxed_slice())
+}
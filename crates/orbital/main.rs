@@ -1,472 +1,303 @@
-#![feature(asm)]
-#![feature(const_fn)]
-
-extern crate core;
+extern crate orbital_core;
 extern crate system;
 
-use std::collections::BTreeMap;
-use std::collections::VecDeque;
-use std::io::{Read, Write, SeekFrom};
-use std::mem;
-use std::process::Command;
-use std::sync::{Arc, Mutex};
+use std::cmp;
+use std::env;
+use std::fs::File;
+use std::io::{Read, Result, SeekFrom, Write};
+use std::process::{self, Command};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
-use std::time::Instant;
+use std::time::Duration;
+
+use system::getopt::{Arg, Getopt, Help, Opt};
+use system::scheme::Packet;
 
-use system::error::{Error, Result, EBADF};
-use system::scheme::{Packet, Scheme};
-use system::syscall::SYS_READ;
+use orbital_core::{bench, vnc, Config, Display, Event, Input, Monitor, OrbitalScheme, Rect};
 
-pub use self::color::Color;
-pub use self::event::{Event, EventOption};
-pub use self::font::Font;
-pub use self::image::{Image, ImageRoi};
-pub use self::rect::Rect;
 pub use self::socket::Socket;
-pub use self::window::Window;
-
-use self::bmp::BmpFile;
-use self::config::Config;
-use self::event::{EVENT_KEY, EVENT_MOUSE, QuitEvent};
-
-pub mod bmp;
-pub mod color;
-pub mod config;
-#[path="../../kernel/common/event.rs"]
-pub mod event;
-pub mod font;
-pub mod image;
-pub mod rect;
-pub mod socket;
-pub mod window;
-
-fn schedule(redraws: &mut Vec<Rect>, request: Rect) {
-    let mut push = true;
-    for mut rect in redraws.iter_mut() {
-        //If contained, ignore new redraw request
-        let container = rect.container(&request);
-        if container.area() <= rect.area() + request.area() {
-            *rect = container;
-            push = false;
-            break;
-        }
-    }
 
-    if push {
-        redraws.push(request);
-    }
-}
+pub mod socket;
 
-struct OrbitalScheme {
-    start: Instant,
-    image: Image,
-    background: Image,
-    cursor: Image,
-    cursor_x: i32,
-    cursor_y: i32,
-    dragging: bool,
-    drag_x: i32,
-    drag_y: i32,
-    next_id: isize,
-    next_x: i32,
-    next_y: i32,
-    order: VecDeque<usize>,
-    windows: BTreeMap<usize, Window>,
-    redraws: Vec<Rect>,
-    todo: Vec<Packet>
+/// A display of the kernel, which also sends the input events
+struct SocketDisplay {
+    socket: Arc<Socket>,
 }
 
-impl OrbitalScheme {
-    fn new(width: i32, height: i32, config: &Config) -> OrbitalScheme {
-        OrbitalScheme {
-            start: Instant::now(),
-            image: Image::new(width, height),
-            background: BmpFile::from_path(&config.background),
-            cursor: BmpFile::from_path(&config.cursor),
-            cursor_x: 0,
-            cursor_y: 0,
-            dragging: false,
-            drag_x: 0,
-            drag_y: 0,
-            next_id: 1,
-            next_x: 20,
-            next_y: 20,
-            order: VecDeque::new(),
-            windows: BTreeMap::new(),
-            redraws: vec![Rect::new(0, 0, width, height)],
-            todo: Vec::new()
-        }
-    }
-
-    fn background_rect(&self) -> Rect {
-        let w = self.background.width();
-        let h = self.background.height();
-        let x = self.image.width()/2 - w/2;
-        let y = self.image.height()/2 - h/2;
-        Rect::new(x, y, w, h)
-    }
+impl SocketDisplay {
+    /// Open a display, its path is `display:width/height/x/y`
+    fn open(path: &str) -> Result<(Monitor, SocketDisplay)> {
+        let socket = Arc::new(try!(Socket::open(path)));
 
-    fn cursor_rect(&self) -> Rect {
-        Rect::new(self.cursor_x, self.cursor_y, self.cursor.width(), self.cursor.height())
+        let path = socket.path().map(|path| path.into_os_string().into_string().unwrap_or(String::new())).unwrap_or(String::new());
+        let res = path.split(":").nth(1).unwrap_or("");
+        let mut parts = res.split("/");
+        let width = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
+        let height = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
+        let x = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
+        let y = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
+
+        let monitor = Monitor::new(Rect::new(x, y, width, height), Box::new(SocketDisplay {
+            socket: socket.clone()
+        }));
+        Ok((monitor, SocketDisplay {
+            socket: socket
+        }))
     }
+}
 
-    fn screen_rect(&self) -> Rect {
-        Rect::new(0, 0, self.image.width(), self.image.height())
+impl Display for SocketDisplay {
+    fn write(&mut self, offset: usize, pixels: &[u32]) {
+        unsafe { self.socket.seek(SeekFrom::Start(offset as u64)).unwrap(); }
+        self.socket.send_type(pixels).unwrap();
     }
 
-    fn redraw(&mut self, display: &Socket){
-        let mut redraws = Vec::new();
-        mem::swap(&mut self.redraws, &mut redraws);
-
-        let screen_rect = self.screen_rect();
-
-        for mut rect in redraws.iter_mut() {
-            *rect = rect.intersection(&screen_rect);
-
-            if ! rect.is_empty() {
-                //TODO: only clear area not covered by background
-                self.image.roi(&rect).set(Color::rgb(75, 163, 253));
-                
-                let background_rect = self.background_rect();
-                let background_intersect = rect.intersection(&background_rect);
-                if ! background_intersect.is_empty(){
-                    self.image.roi(&background_intersect).blit(&self.background.roi(&background_intersect.offset(-background_rect.left(), -background_rect.top())));
-                }
-
-                let mut i = self.order.len();
-                for id in self.order.iter().rev() {
-                    i -= 1;
-                    if let Some(mut window) = self.windows.get_mut(&id) {
-                        window.draw_title(&mut self.image, &rect, i == 0);
-                        window.draw(&mut self.image, &rect);
-                    }
-                }
-
-                let cursor_rect = self.cursor_rect();
-                let cursor_intersect = rect.intersection(&cursor_rect);
-                if ! cursor_intersect.is_empty() {
-                    self.image.roi(&cursor_intersect).blend(&self.cursor.roi(&cursor_intersect.offset(-cursor_rect.left(), -cursor_rect.top())));
-                }
-            }
-        }
-
-        for rect in redraws.iter_mut() {
-            if ! rect.is_empty() {
-                let data = self.image.data();
-                for row in rect.top()..rect.bottom() {
-                    let off1 = row * self.image.width() + rect.left();
-                    let off2 = row * self.image.width() + rect.right();
-
-                    unsafe { display.seek(SeekFrom::Start(off1 as u64)).unwrap(); }
-                    display.send_type(&data[off1 as usize .. off2 as usize]).unwrap();
-                }
-            }
+    fn brightness(&mut self) -> Option<u8> {
+        let mut string = String::new();
+        match File::open("display:backlight").and_then(|mut file| file.read_to_string(&mut string)) {
+            Ok(_) => string.trim().parse::<u8>().ok(),
+            Err(_) => None
         }
     }
 
-    fn event(&mut self, event: Event){
-        if event.code == EVENT_KEY {
-            if event.c > 0 {
-                if event.b as u8 == event::K_F1 {
-                    let cursor_rect = self.cursor_rect();
-                    schedule(&mut self.redraws, cursor_rect);
-
-                    self.cursor_x = 0;
-                    self.cursor_y = 0;
-
-                    let cursor_rect = self.cursor_rect();
-                    schedule(&mut self.redraws, cursor_rect);
-                } else if event.b as u8 == event::K_F2 {
-                    let cursor_rect = self.cursor_rect();
-                    schedule(&mut self.redraws, cursor_rect);
-
-                    self.cursor_x = self.screen_rect().width();
-                    self.cursor_y = self.screen_rect().height();
-
-                    let cursor_rect = self.cursor_rect();
-                    schedule(&mut self.redraws, cursor_rect);
-                }
-            }
-            if let Some(id) = self.order.front() {
-                if let Some(mut window) = self.windows.get_mut(&id) {
-                    window.event(event);
-                }
-            }
-        } else if event.code == EVENT_MOUSE {
-            if event.a as i32 != self.cursor_x || event.b as i32 != self.cursor_y {
-                let cursor_rect = self.cursor_rect();
-                schedule(&mut self.redraws, cursor_rect);
-
-                self.cursor_x = event.a as i32;
-                self.cursor_y = event.b as i32;
-
-                let cursor_rect = self.cursor_rect();
-                schedule(&mut self.redraws, cursor_rect);
-            }
-
-            if self.dragging {
-                if event.c > 0 {
-                    if let Some(id) = self.order.front() {
-                        if let Some(mut window) = self.windows.get_mut(&id) {
-                            if self.drag_x != self.cursor_x || self.drag_y != self.cursor_y {
-                                schedule(&mut self.redraws, window.title_rect());
-                                schedule(&mut self.redraws, window.rect());
-                                window.x += self.cursor_x - self.drag_x;
-                                window.y += self.cursor_y - self.drag_y;
-                                self.drag_x = self.cursor_x;
-                                self.drag_y = self.cursor_y;
-                                schedule(&mut self.redraws, window.title_rect());
-                                schedule(&mut self.redraws, window.rect());
-                            }
-                        } else {
-                            self.dragging = false;
-                        }
-                    } else {
-                        self.dragging = false;
-                    }
-                } else {
-                    self.dragging = false;
-                }
-            } else {
-                let mut focus = 0;
-                let mut i = 0;
-                for id in self.order.iter() {
-                    if let Some(mut window) = self.windows.get_mut(&id) {
-                        if window.rect().contains(event.a as i32, event.b as i32) {
-                            let mut window_event = event;
-                            window_event.a -= window.x as i64;
-                            window_event.b -= window.y as i64;
-                            window.event(window_event);
-                            if event.c > 0 {
-                                focus = i;
-                            }
-                            break;
-                        } else if window.title_rect().contains(event.a as i32, event.b as i32) {
-                            if event.c > 0 {
-                                focus = i;
-                                if window.exit_contains(event.a as i32, event.b as i32) {
-                                    window.event(QuitEvent.to_event());
-                                } else {
-                                    self.dragging = true;
-                                    self.drag_x = self.cursor_x;
-                                    self.drag_y = self.cursor_y;
-                                }
-                            }
-                            break;
-                        }
-                    }
-                    i += 1;
-                }
-                if focus > 0 {
-                    //Redraw old focused window
-                    if let Some(id) = self.order.front() {
-                        if let Some(window) = self.windows.get(&id){
-                            schedule(&mut self.redraws, window.title_rect());
-                            schedule(&mut self.redraws, window.rect());
-                        }
-                    }
-                    //Redraw new focused window
-                    if let Some(id) = self.order.remove(focus) {
-                        if let Some(window) = self.windows.get(&id){
-                            schedule(&mut self.redraws, window.title_rect());
-                            schedule(&mut self.redraws, window.rect());
-                        }
-                        self.order.push_front(id);
-                    }
-                }
-            }
+    fn set_brightness(&mut self, percent: u8) {
+        if let Err(err) = File::create("display:backlight").and_then(|mut file| file.write(format!("{}", percent).as_bytes())) {
+            println!("orbital: failed to set brightness: {}", err);
         }
     }
 }
 
-impl Scheme for OrbitalScheme {
-    fn open(&mut self, url: &str, _flags: usize, _mode: usize) -> Result<usize> {
-        let path = url.split(":").last().unwrap_or("");
-        let mut parts = path.split("/");
-
-        let flags = parts.next().unwrap_or("");
+impl Input for SocketDisplay {
+    fn read(&mut self, events: &mut [Event]) -> Result<usize> {
+        self.socket.receive_type(events)
+    }
+}
 
-        let mut async = false;
-        for flag in flags.chars() {
-            if flag == 'a' {
-                async = true;
-            }
+fn event_loop<I: Input>(scheme_mutex: Arc<Mutex<OrbitalScheme>>, mut input: I, socket: Arc<Socket>, timer: Arc<Timer>){
+    loop {
+        //Reads from recordings wait for a frame to be drawn
+        let responses = {
+            let mut scheme = scheme_mutex.lock().unwrap();
+            scheme.redraw();
+            scheme.retry()
+        };
+        if ! responses.is_empty() {
+            socket.send_type(&responses).unwrap();
         }
 
-        let mut x = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
-        let mut y = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
-        let width = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
-        let height = parts.next().unwrap_or("").parse::<i32>().unwrap_or(0);
-
-        let mut title = parts.next().unwrap_or("").to_string();
-        for part in parts {
-            title.push('/');
-            title.push_str(part);
-        }
+        let mut events = [Event::new(); 128];
+        let count = input.read(&mut events).unwrap();
+        let responses = {
+            let mut scheme = scheme_mutex.lock().unwrap();
+            for &event in events[.. count].iter() {
+                scheme.input(event);
+            }
+            scheme.update_focus();
+            timer.check(&mut scheme);
 
-        let id = self.next_id as usize;
-        self.next_id += 1;
-        if self.next_id < 0 {
-            self.next_id = 1;
+            scheme.retry()
+        };
+        if ! responses.is_empty() {
+            socket.send_type(&responses).unwrap();
         }
+    }
+}
 
-        if x < 0 && y < 0 {
-            x = self.next_x;
-            y = self.next_y;
-
-            self.next_x += 20;
-            if self.next_x + 20 >= self.image.width() {
-                self.next_x = 20;
-            }
-            self.next_y += 20;
-            if self.next_y + 20 >= self.image.height() {
-                self.next_y = 20;
-            }
+fn server_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>, socket: Arc<Socket>, timer: Arc<Timer>){
+    loop {
+        //Reads from recordings wait for a frame to be drawn
+        let responses = {
+            let mut scheme = scheme_mutex.lock().unwrap();
+            scheme.redraw();
+            scheme.retry()
+        };
+        if ! responses.is_empty() {
+            socket.send_type(&responses).unwrap();
         }
 
-        if let Some(id) = self.order.front() {
-            if let Some(window) = self.windows.get(&id){
-                schedule(&mut self.redraws, window.title_rect());
-                schedule(&mut self.redraws, window.rect());
-            }
+        let mut packets = [Packet::default(); 128];
+        let count = socket.receive_type(&mut packets).unwrap();
+        let responses = {
+            let mut scheme = scheme_mutex.lock().unwrap();
+            let responses = scheme.requests(&mut packets[.. count]);
+            scheme.update_focus();
+            timer.check(&mut scheme);
+            responses
+        };
+        if ! responses.is_empty() {
+            socket.send_type(&responses).unwrap();
         }
+    }
+}
 
-        let window = Window::new(x, y, width, height, title, async);
-        schedule(&mut self.redraws, window.title_rect());
-        schedule(&mut self.redraws, window.rect());
-        self.order.push_front(id);
-        self.windows.insert(id, window);
+/// Update the clock and the night light when the minute changes
+fn clock_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>) {
+    loop {
+        let wait = {
+            let mut scheme = scheme_mutex.lock().unwrap();
+            let wait = scheme.update_clock();
+            scheme.redraw();
+            wait
+        };
 
-        Ok(id)
+        thread::sleep_ms(wait);
     }
+}
 
-    fn read(&mut self, id: usize, buf: &mut [u8]) -> Result<usize> {
-        if let Some(mut window) = self.windows.get_mut(&id) {
-            window.read(buf)
-        } else {
-            Err(Error::new(EBADF))
-        }
-    }
+/// Wakes the timer thread when the scheme added a deadline, see `OrbitalScheme::take_deadline_added`
+struct Timer {
+    added: Mutex<bool>,
+    condvar: Condvar,
+}
 
-    fn write(&mut self, id: usize, buf: &[u8]) -> Result<usize> {
-        if let Some(mut window) = self.windows.get_mut(&id) {
-            schedule(&mut self.redraws, window.rect());
-            window.write(buf)
-        } else {
-            Err(Error::new(EBADF))
+impl Timer {
+    fn new() -> Timer {
+        Timer {
+            added: Mutex::new(false),
+            condvar: Condvar::new()
         }
     }
 
-    fn fpath(&self, id: usize, buf: &mut [u8]) -> Result<usize> {
-        if let Some(window) = self.windows.get(&id) {
-            window.path(buf)
-        } else {
-            Err(Error::new(EBADF))
+    /// Wake the timer thread if the scheme added a deadline, called with the scheme locked
+    fn check(&self, scheme: &mut OrbitalScheme) {
+        if scheme.take_deadline_added() {
+            *self.added.lock().unwrap() = true;
+            self.condvar.notify_one();
         }
     }
+}
 
-    fn close(&mut self, id: usize) -> Result<usize> {
-        self.order.retain(|&e| e != id);
-
-        if let Some(id) = self.order.front() {
-            if let Some(window) = self.windows.get(&id){
-                schedule(&mut self.redraws, window.title_rect());
-                schedule(&mut self.redraws, window.rect());
+/// Repeat held keys, draw the frames of animations and animated cursors, and show the frames of streaming windows as
+/// they are due, finishing the reads of the windows keys are sent to
+///
+/// Sleeps until the earliest deadline, or until woken by `Timer::check` when nothing is pending.
+fn timer_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>, socket: Arc<Socket>, timer: Arc<Timer>) {
+    loop {
+        let (wait, responses) = {
+            let mut scheme = scheme_mutex.lock().unwrap();
+            let mut wait = None;
+            for next in [scheme.update_repeat(), scheme.update_animations(), scheme.update_streams(),
+                         scheme.update_cursor_animation()].iter() {
+                if let Some(next) = *next {
+                    wait = Some(wait.map_or(next, |wait| cmp::min(wait, next)));
+                }
             }
+            scheme.redraw();
+            (wait, scheme.retry())
+        };
+        if ! responses.is_empty() {
+            socket.send_type(&responses).unwrap();
         }
 
-        if let Some(window) = self.windows.remove(&id) {
-            schedule(&mut self.redraws, window.title_rect());
-            schedule(&mut self.redraws, window.rect());
-            Ok(0)
-        } else {
-            Err(Error::new(EBADF))
+        let mut added = timer.added.lock().unwrap();
+        match wait {
+            Some(wait) => if ! *added {
+                added = timer.condvar.wait_timeout(added, Duration::from_millis(wait as u64)).unwrap().0;
+            },
+            None => while ! *added {
+                added = timer.condvar.wait(added).unwrap();
+            }
         }
+        *added = false;
     }
 }
 
-fn event_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>, display: Arc<Socket>, socket: Arc<Socket>){
+/// Blank the displays when there was no input for the timeout
+fn blank_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>) {
     loop {
-        {
+        let wait = {
             let mut scheme = scheme_mutex.lock().unwrap();
-            scheme.redraw(&display);
-        }
+            let wait = scheme.update_blank();
+            scheme.redraw();
+            wait
+        };
 
-        let mut events = [Event::new(); 128];
-        let count = display.receive_type(&mut events).unwrap();
-        let mut responses = Vec::new();
-        {
-            let mut scheme = scheme_mutex.lock().unwrap();
-            for &event in events[.. count].iter() {
-                scheme.event(event);
-            }
-
-            let mut packets = Vec::new();
-            mem::swap(&mut scheme.todo, &mut packets);
-            for mut packet in packets.iter_mut() {
-                let delay = if packet.a == SYS_READ {
-                    if let Some(window) = scheme.windows.get(&packet.b) {
-                        window.async == false
-                    } else {
-                        true
-                    }
-                } else {
-                    false
-                };
-
-                scheme.handle(packet);
+        thread::sleep_ms(wait);
+    }
+}
 
-                if delay && packet.a == 0 {
-                    scheme.todo.push(*packet);
-                }else{
-                    responses.push(*packet);
-                }
-            }
-        }
+/// Ping the clients of windows, finishing the reads of the windows the pings are sent to
+fn watchdog_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>, socket: Arc<Socket>) {
+    loop {
+        let (wait, responses) = {
+            let mut scheme = scheme_mutex.lock().unwrap();
+            let wait = scheme.update_watchdog();
+            scheme.redraw();
+            (wait, scheme.retry())
+        };
         if ! responses.is_empty() {
             socket.send_type(&responses).unwrap();
         }
+
+        thread::sleep_ms(wait);
     }
 }
 
-fn server_loop(scheme_mutex: Arc<Mutex<OrbitalScheme>>, display: Arc<Socket>, socket: Arc<Socket>){
-    loop {
-        {
-            let mut scheme = scheme_mutex.lock().unwrap();
-            scheme.redraw(&display);
-        }
+/// Run the display manager on monitors, until the scheme or the input fails
+fn run<I: Input>(monitors: Vec<Monitor>, input: I, socket: Arc<Socket>, config: &Config, status: &Mutex<Status>) {
+    let scheme = Arc::new(Mutex::new(OrbitalScheme::new(monitors, config)));
 
-        let mut packets = [Packet::default(); 128];
-        let count = socket.receive_type(&mut packets).unwrap();
-        let mut responses = Vec::new();
-        {
-            let mut scheme = scheme_mutex.lock().unwrap();
-            for mut packet in packets[.. count].iter_mut() {
-                let delay = if packet.a == SYS_READ {
-                    if let Some(window) = scheme.windows.get(&packet.b) {
-                        window.async == false
-                    } else {
-                        true
-                    }
-                } else {
-                    false
-                };
+    //The night light can be turned on later with a control command, so the clock is always followed
+    let scheme_clock = scheme.clone();
+    thread::spawn(move || {
+        clock_loop(scheme_clock);
+    });
 
-                scheme.handle(packet);
+    let timer = Arc::new(Timer::new());
+    let scheme_timer = scheme.clone();
+    let socket_timer = socket.clone();
+    let timer_timer = timer.clone();
+    thread::spawn(move || {
+        timer_loop(scheme_timer, socket_timer, timer_timer);
+    });
 
-                if delay && packet.a == 0 {
-                    scheme.todo.push(*packet);
-                } else {
-                    responses.push(*packet);
-                }
-            }
-        }
-        if ! responses.is_empty() {
-            socket.send_type(&responses).unwrap();
-        }
+    let scheme_watchdog = scheme.clone();
+    let socket_watchdog = socket.clone();
+    thread::spawn(move || {
+        watchdog_loop(scheme_watchdog, socket_watchdog);
+    });
+
+    if config.blank.is_some() {
+        let scheme_blank = scheme.clone();
+        thread::spawn(move || {
+            blank_loop(scheme_blank);
+        });
     }
+
+    *status.lock().unwrap() = Status::Running;
+
+    let scheme_event = scheme.clone();
+    let socket_event = socket.clone();
+    let timer_event = timer.clone();
+
+    let server_thread = thread::spawn(move || {
+        server_loop(scheme, socket, timer);
+    });
+
+    event_loop(scheme_event, input, socket_event, timer_event);
+
+    let _ = server_thread.join();
 }
 
+/// Parse a size such as `1024x768`
+fn parse_size(size: &str) -> Option<(i32, i32)> {
+    let mut parts = size.split('x');
+    match (parts.next().and_then(|w| w.parse::<i32>().ok()), parts.next().and_then(|h| h.parse::<i32>().ok()), parts.next()) {
+        (Some(width), Some(height), None) if width > 0 && height > 0 && width <= 0xFFFF && height <= 0xFFFF => Some((width, height)),
+        _ => None
+    }
+}
+
+const OPTS: &'static [Opt] = &[
+    Opt { short: Some('b'), long: "bench", value: Some("FRAMES"), help: "composite FRAMES frames in memory and print the timings" },
+    Opt { short: Some('c'), long: "config", value: Some("PATH"), help: "read the configuration from PATH" },
+    Opt { short: Some('d'), long: "displays", value: Some("COUNT"), help: "use at most COUNT displays" },
+    Opt { short: Some('n'), long: "no-launcher", value: None, help: "do not run the launcher" },
+    Opt { short: Some('v'), long: "vnc", value: Some("PORT"), help: "serve the desktop to VNC viewers on PORT instead of using the displays, without a password" },
+    Opt { short: None, long: "vnc-size", value: Some("SIZE"), help: "the size of the desktop served with --vnc, 1024x768 by default" },
+    Opt { short: Some('h'), long: "help", value: None, help: "print this help" },
+];
+
 enum Status {
     Starting,
     Running,
@@ -474,39 +305,104 @@ enum Status {
 }
 
 fn main() {
+    let mut config_path = "/etc/orbital.conf".to_string();
+    let mut max_displays = None;
+    let mut launcher = true;
+    let mut bench_frames = None;
+    let mut vnc_port = None;
+    let mut vnc_size = (1024, 768);
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    for arg in Getopt::new(OPTS, args.iter().map(|arg| arg.as_str())) {
+        match arg {
+            Ok(Arg::Opt("bench", Some(frames))) => match frames.parse::<usize>() {
+                Ok(frames) if frames > 0 => bench_frames = Some(frames),
+                _ => {
+                    println!("orbital: invalid frame count '{}'", frames);
+                    process::exit(1);
+                }
+            },
+            Ok(Arg::Opt("config", Some(path))) => config_path = path.to_string(),
+            Ok(Arg::Opt("displays", Some(count))) => match count.parse::<usize>() {
+                Ok(count) if count > 0 => max_displays = Some(count),
+                _ => {
+                    println!("orbital: invalid display count '{}'", count);
+                    process::exit(1);
+                }
+            },
+            Ok(Arg::Opt("no-launcher", _)) => launcher = false,
+            Ok(Arg::Opt("vnc", Some(port))) => match port.parse::<u16>() {
+                Ok(port) if port > 0 => vnc_port = Some(port),
+                _ => {
+                    println!("orbital: invalid port '{}'", port);
+                    process::exit(1);
+                }
+            },
+            Ok(Arg::Opt("vnc-size", Some(size))) => match parse_size(size) {
+                Some(size) => vnc_size = size,
+                None => {
+                    println!("orbital: invalid size '{}'", size);
+                    process::exit(1);
+                }
+            },
+            Ok(Arg::Opt("help", _)) => {
+                print!("usage: orbital [OPTIONS]\n{}", Help(OPTS));
+                process::exit(0);
+            },
+            Ok(arg) => {
+                println!("orbital: unexpected argument {:?}", arg);
+                process::exit(1);
+            },
+            Err(err) => {
+                println!("orbital: {}", err);
+                process::exit(1);
+            }
+        }
+    }
+
+    if let Some(frames) = bench_frames {
+        bench::bench(&Config::from_path(&config_path), frames);
+        return;
+    }
+
     let status_mutex = Arc::new(Mutex::new(Status::Starting));
 
     let status_daemon = status_mutex.clone();
     thread::spawn(move || {
         match Socket::create(":orbital").map(|socket| Arc::new(socket)) {
-            Ok(socket) => match Socket::open("display:manager").map(|display| Arc::new(display)) {
-                Ok(display) => {
-                    let path = display.path().map(|path| path.into_os_string().into_string().unwrap_or(String::new())).unwrap_or(String::new());
-                    let res = path.split(":").nth(1).unwrap_or("");
-                    let width = res.split("/").nth(0).unwrap_or("").parse::<i32>().unwrap_or(0);
-                    let height = res.split("/").nth(1).unwrap_or("").parse::<i32>().unwrap_or(0);
-
-                    println!("orbital: found display {}x{}", width, height);
-
-                    let config = Config::from_path("/etc/orbital.conf");
-
-                    let scheme = Arc::new(Mutex::new(OrbitalScheme::new(width, height, &config)));
-
-                    *status_daemon.lock().unwrap() = Status::Running;
-
-                    let scheme_event = scheme.clone();
-                    let display_event = display.clone();
-                    let socket_event = socket.clone();
+            Ok(socket) => {
+                let config = Config::from_path(&config_path);
+
+                if let Some(port) = vnc_port {
+                    let (width, height) = vnc_size;
+                    let (monitor, input) = vnc::listen(port, width, height);
+                    println!("orbital: serving {}x{} to VNC viewers on port {}", width, height, port);
+                    run(vec![monitor], input, socket, &config, &status_daemon);
+                } else {
+                    match SocketDisplay::open("display:manager") {
+                        Ok((monitor, display)) => {
+                            let mut monitors = vec![monitor];
+                            while max_displays.map_or(true, |max| monitors.len() < max) {
+                                match SocketDisplay::open(&format!("display:manager/{}", monitors.len())) {
+                                    Ok((monitor, _)) => monitors.push(monitor),
+                                    Err(_) => break
+                                }
+                            }
 
-                    let server_thread = thread::spawn(move || {
-                        server_loop(scheme, display, socket);
-                    });
+                            for monitor in monitors.iter() {
+                                println!("orbital: found display {}x{} at {},{}", monitor.rect.width(), monitor.rect.height(), monitor.rect.left(), monitor.rect.top());
+                            }
 
-                    event_loop(scheme_event, display_event, socket_event);
+                            //Keys are typed with the keymap of Orbital, so the kernel sends them without characters
+                            if let Err(err) = File::open("keyboard:raw") {
+                                println!("orbital: failed to switch the keyboard to raw mode: {}", err);
+                            }
 
-                    let _ = server_thread.join();
-                },
-                Err(err) => println!("orbital: no display found: {}", err)
+                            run(monitors, display, socket, &config, &status_daemon);
+                        },
+                        Err(err) => println!("orbital: no display found: {}", err)
+                    }
+                }
             },
             Err(err) => println!("orbital: could not register orbital: {}", err)
         }
@@ -518,7 +414,9 @@ fn main() {
         match *status_mutex.lock().unwrap() {
             Status::Starting => (),
             Status::Running => {
-                Command::new("launcher").spawn().unwrap();
+                if launcher {
+                    Command::new("launcher").spawn().unwrap();
+                }
                 break 'waiting;
             },
             Status::Stopping => break 'waiting,
@@ -0,0 +1,164 @@
+use core::fmt;
+
+/// A command line option
+pub struct Opt {
+    /// The short form, such as `-c`
+    pub short: Option<char>,
+    /// The long form, such as `--config`
+    pub long: &'static str,
+    /// The name of the value the option takes, such as `PATH`, or `None` for a flag
+    pub value: Option<&'static str>,
+    /// A description of the option
+    pub help: &'static str,
+}
+
+/// A parsed argument
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arg<'a> {
+    /// An option, by its long name, with its value if it takes one
+    Opt(&'static str, Option<&'a str>),
+    /// An argument that is not an option
+    Free(&'a str),
+}
+
+/// An argument that could not be parsed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GetoptError<'a> {
+    /// An option that is not described
+    Unknown(&'a str),
+    /// An option that takes a value was last
+    MissingValue(&'static str),
+    /// A value was given with `=` to an option that does not take one
+    UnexpectedValue(&'static str),
+}
+
+impl<'a> fmt::Display for GetoptError<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GetoptError::Unknown(arg) => write!(f, "unknown option '{}'", arg),
+            GetoptError::MissingValue(long) => write!(f, "option '--{}' requires a value", long),
+            GetoptError::UnexpectedValue(long) => write!(f, "option '--{}' does not take a value", long),
+        }
+    }
+}
+
+/// An iterator over parsed arguments, such as `Getopt::new(OPTS, args.iter().map(|arg| arg.as_str()))`
+///
+/// Supports flags grouped as `-ab`, values given as `-cVALUE`, `-c VALUE`, `--config=VALUE`,
+/// or `--config VALUE`, and `--` to end the options. Print `Help(OPTS)` to show the options.
+pub struct Getopt<'o, 'a, I: Iterator<Item=&'a str>> {
+    opts: &'o [Opt],
+    args: I,
+    /// The rest of a group of short options
+    short: &'a str,
+    /// After `--`, every argument is free
+    done: bool,
+}
+
+impl<'o, 'a, I: Iterator<Item=&'a str>> Getopt<'o, 'a, I> {
+    /// Parse arguments, which should not include the program name
+    pub fn new(opts: &'o [Opt], args: I) -> Self {
+        Getopt {
+            opts: opts,
+            args: args,
+            short: "",
+            done: false,
+        }
+    }
+
+    /// Take the value of an option, from the rest of the argument or the next one
+    fn value(&mut self, opt: &Opt, rest: &'a str) -> Result<Arg<'a>, GetoptError<'a>> {
+        if opt.value.is_none() {
+            return Ok(Arg::Opt(opt.long, None));
+        }
+
+        if ! rest.is_empty() {
+            Ok(Arg::Opt(opt.long, Some(rest)))
+        } else if let Some(value) = self.args.next() {
+            Ok(Arg::Opt(opt.long, Some(value)))
+        } else {
+            Err(GetoptError::MissingValue(opt.long))
+        }
+    }
+}
+
+impl<'o, 'a, I: Iterator<Item=&'a str>> Iterator for Getopt<'o, 'a, I> {
+    type Item = Result<Arg<'a>, GetoptError<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let opts = self.opts;
+
+        if ! self.short.is_empty() {
+            let short = self.short;
+            let c = short.chars().next().unwrap_or('\0');
+            let rest = &short[c.len_utf8() ..];
+            self.short = "";
+            return Some(match opts.iter().find(|opt| opt.short == Some(c)) {
+                Some(opt) => if opt.value.is_some() {
+                    self.value(opt, rest)
+                } else {
+                    self.short = rest;
+                    Ok(Arg::Opt(opt.long, None))
+                },
+                None => Err(GetoptError::Unknown(short))
+            });
+        }
+
+        let arg = match self.args.next() {
+            Some(arg) => arg,
+            None => return None
+        };
+
+        if self.done || arg == "-" || ! arg.starts_with('-') {
+            Some(Ok(Arg::Free(arg)))
+        } else if arg == "--" {
+            self.done = true;
+            self.next()
+        } else if arg.starts_with("--") {
+            let (name, value) = match arg.find('=') {
+                Some(equal) => (&arg[2 .. equal], Some(&arg[equal + 1 ..])),
+                None => (&arg[2 ..], None)
+            };
+            Some(match opts.iter().find(|opt| opt.long == name) {
+                Some(opt) => match value {
+                    Some(value) => if opt.value.is_some() {
+                        Ok(Arg::Opt(opt.long, Some(value)))
+                    } else {
+                        Err(GetoptError::UnexpectedValue(opt.long))
+                    },
+                    None => self.value(opt, "")
+                },
+                None => Err(GetoptError::Unknown(arg))
+            })
+        } else {
+            self.short = &arg[1 ..];
+            self.next()
+        }
+    }
+}
+
+/// The help for a set of options, with one line per option
+pub struct Help<'o>(pub &'o [Opt]);
+
+impl<'o> fmt::Display for Help<'o> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.0.iter().map(|opt| opt.long.len() + opt.value.map_or(0, |value| value.len() + 1)).max().unwrap_or(0);
+        for opt in self.0.iter() {
+            match opt.short {
+                Some(c) => try!(write!(f, "    -{}, ", c)),
+                None => try!(write!(f, "        "))
+            }
+            try!(write!(f, "--{}", opt.long));
+            let mut len = opt.long.len();
+            if let Some(value) = opt.value {
+                try!(write!(f, " {}", value));
+                len += value.len() + 1;
+            }
+            for _ in len .. width {
+                try!(write!(f, " "));
+            }
+            try!(write!(f, "  {}\n", opt.help));
+        }
+        Ok(())
+    }
+}
@@ -9,9 +9,14 @@ use core::{ptr, slice, str};
 pub mod error;
 #[cfg(target_os="redox")]
 pub mod externs;
+pub mod getopt;
 pub mod graphics;
+pub mod path;
+pub mod reactor;
 pub mod scheme;
+pub mod serialize;
 pub mod syscall;
+pub mod timepage;
 
 /// Helper function for handling C strings, please do not copy it or make it pub or change it
 pub fn c_string_to_slice<'a>(ptr: *const u8) -> &'a [u8] {
@@ -0,0 +1,168 @@
+//! Path rules shared by the kernel and scheme daemons
+//!
+//! A path is `scheme:reference`, where the reference is split into segments by `/`. Canonical paths have a scheme and
+//! no `.` or `..` segments. Schemes that take arguments in their paths, like `orbital:`, escape the arguments so
+//! they can contain `/`, `%`, or be `.` or `..`, with `%` followed by two hex digits.
+
+use error::{Error, Result, EINVAL, ENAMETOOLONG};
+
+/// A buffer that errors instead of overflowing
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn push(&mut self, b: u8) -> Result<()> {
+        if self.len < self.buf.len() {
+            self.buf[self.len] = b;
+            self.len += 1;
+            Ok(())
+        } else {
+            Err(Error::new(ENAMETOOLONG))
+        }
+    }
+
+    fn push_str(&mut self, s: &str) -> Result<()> {
+        for b in s.bytes() {
+            try!(self.push(b));
+        }
+        Ok(())
+    }
+}
+
+/// The scheme and reference of a path, without the `:`, or `None` if it has no scheme
+pub fn split(path: &str) -> Option<(&str, &str)> {
+    path.find(':').map(|i| (&path[.. i], &path[i + 1 ..]))
+}
+
+/// Join a path to a working directory, such as `file:/home/`, and resolve `.` and `..` segments
+///
+/// Relative paths are appended to the working directory, and paths starting with `/` replace its reference. The
+/// separators at the start of the reference are kept as they are, so `scheme://host/` stays as it is, and `..` never
+/// goes above them. Empty segments are kept, as they are arguments for some schemes. The result is written to `buf`,
+/// which never needs to be longer than the working directory and the path together. Returns `EINVAL` if neither the
+/// path nor the working directory has a scheme.
+pub fn canonicalize(cwd: &str, path: &str, buf: &mut [u8]) -> Result<usize> {
+    let (scheme, reference, base) = match split(path) {
+        Some((scheme, reference)) => (scheme, reference, ""),
+        None => {
+            let (scheme, cwd_reference) = try!(split(cwd).ok_or(Error::new(EINVAL)));
+            if path.starts_with('/') {
+                (scheme, path, "")
+            } else {
+                (scheme, path, cwd_reference)
+            }
+        }
+    };
+
+    let mut writer = Writer {
+        buf: buf,
+        len: 0
+    };
+    try!(writer.push_str(scheme));
+    try!(writer.push(b':'));
+
+    let rooted = if base.is_empty() { reference } else { base };
+    let separators = rooted.len() - rooted.trim_left_matches('/').len();
+    try!(writer.push_str(&rooted[.. separators]));
+    let root = writer.len;
+
+    //The base is a directory, so its trailing separator is not an empty segment
+    let base = base.trim_left_matches('/');
+    let base = if base.ends_with('/') { &base[.. base.len() - 1] } else { base };
+    let reference = if base.is_empty() { reference.trim_left_matches('/') } else { reference };
+
+    let mut segments = 0;
+    let mut last = "";
+    for (i, segment) in base.split('/').chain(reference.split('/')).enumerate() {
+        //An empty base adds one empty segment, which is skipped
+        if i == 0 && base.is_empty() {
+            continue;
+        }
+
+        last = segment;
+        match segment {
+            "." => (),
+            ".." => if segments > 0 {
+                segments -= 1;
+                let start = writer.len - writer.buf[root .. writer.len].iter().rev().position(|&b| b == b'/').unwrap_or(writer.len - root);
+                writer.len = if segments > 0 { start - 1 } else { root };
+            },
+            _ => {
+                if segments > 0 {
+                    try!(writer.push(b'/'));
+                }
+                try!(writer.push_str(segment));
+                segments += 1;
+            }
+        }
+    }
+
+    //A path ending in `.` or `..` is a directory
+    if (last == "." || last == "..") && segments > 0 {
+        try!(writer.push(b'/'));
+    }
+
+    Ok(writer.len)
+}
+
+/// Whether a byte has to be escaped in an argument
+fn needs_escape(b: u8) -> bool {
+    b == b'%' || b == b'/' || b < 0x20 || b == 0x7F
+}
+
+/// Escape an argument for a path, so it is one segment that is neither `.` nor `..`
+pub fn escape(arg: &str, buf: &mut [u8]) -> Result<usize> {
+    const HEX: &'static [u8] = b"0123456789ABCDEF";
+
+    let dots = arg == "." || arg == "..";
+    let mut writer = Writer {
+        buf: buf,
+        len: 0
+    };
+    for b in arg.bytes() {
+        if needs_escape(b) || (dots && b == b'.') {
+            try!(writer.push(b'%'));
+            try!(writer.push(HEX[(b >> 4) as usize]));
+            try!(writer.push(HEX[(b & 0xF) as usize]));
+        } else {
+            try!(writer.push(b));
+        }
+    }
+    Ok(writer.len)
+}
+
+fn hex_digit(b: u8) -> Option<u8> {
+    match b {
+        b'0' ... b'9' => Some(b - b'0'),
+        b'a' ... b'f' => Some(b - b'a' + 10),
+        b'A' ... b'F' => Some(b - b'A' + 10),
+        _ => None
+    }
+}
+
+/// Decode an argument escaped with `escape`, returning `EINVAL` if an escape is incomplete
+///
+/// The result is never longer than the argument
+pub fn unescape(arg: &str, buf: &mut [u8]) -> Result<usize> {
+    let bytes = arg.as_bytes();
+    let mut writer = Writer {
+        buf: buf,
+        len: 0
+    };
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            match (bytes.get(i + 1).and_then(|&b| hex_digit(b)), bytes.get(i + 2).and_then(|&b| hex_digit(b))) {
+                (Some(high), Some(low)) => try!(writer.push(high << 4 | low)),
+                _ => return Err(Error::new(EINVAL))
+            }
+            i += 3;
+        } else {
+            try!(writer.push(bytes[i]));
+            i += 1;
+        }
+    }
+    Ok(writer.len)
+}
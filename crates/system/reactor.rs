@@ -0,0 +1,152 @@
+use error::{Result, EAGAIN};
+use syscall::{sys_clock_gettime, sys_nanosleep, sys_read, sys_yield, CLOCK_MONOTONIC, TimeSpec};
+
+/// The longest sleep between rounds when no source has data, in nanoseconds
+const MAX_WAIT_NANOS: i32 = 16000000;
+
+/// The first sleep after a round without data, doubled every round until `MAX_WAIT_NANOS`
+const MIN_WAIT_NANOS: i32 = 500000;
+
+/// Data read from one of the sources of a `Reactor`
+#[derive(Copy, Clone, Debug)]
+pub struct Ready {
+    /// The file descriptor the data was read from
+    pub fd: usize,
+    /// The number of bytes read into the buffer
+    pub count: usize,
+}
+
+/// Wait for data on several file descriptors at once, in place of a loop written for each daemon
+///
+/// The sources must not block when they have nothing to read, such as handles opened with `O_NONBLOCK` or
+/// async Orbital windows. A read that returns 0 or `EAGAIN` means a source has nothing yet. Sources are read in
+/// turn, so a busy one cannot starve the others, and the reactor sleeps longer the longer they stay idle, so
+/// waiting costs little CPU while data is picked up quickly once it arrives.
+pub struct Reactor<'a> {
+    fds: &'a [usize],
+    next: usize,
+    wait: i32,
+}
+
+impl<'a> Reactor<'a> {
+    pub fn new(fds: &'a [usize]) -> Reactor<'a> {
+        Reactor {
+            fds: fds,
+            next: 0,
+            wait: 0
+        }
+    }
+
+    /// Read from the next source with data, returning `None` if there was none before the timeout
+    ///
+    /// The timeout is a duration, with `None` waiting as long as it takes
+    pub fn poll(&mut self, buf: &mut [u8], timeout: Option<&TimeSpec>) -> Result<Option<Ready>> {
+        let deadline = match timeout {
+            Some(timeout) => Some(add(&try!(now()), timeout)),
+            None => None
+        };
+
+        loop {
+            if let Some(ready) = try!(self.round(buf)) {
+                self.wait = 0;
+                return Ok(Some(ready));
+            }
+
+            let mut sleep = TimeSpec {
+                tv_sec: 0,
+                tv_nsec: self.wait
+            };
+            if let Some(ref deadline) = deadline {
+                let time = try!(now());
+                if ! before(&time, deadline) {
+                    return Ok(None);
+                }
+                let left = sub(deadline, &time);
+                if left.tv_sec == 0 && left.tv_nsec < sleep.tv_nsec {
+                    sleep = left;
+                }
+            }
+
+            if sleep.tv_nsec == 0 {
+                try!(sys_yield());
+            } else {
+                let mut rem = TimeSpec::default();
+                try!(sys_nanosleep(&sleep, &mut rem));
+            }
+
+            self.wait = if self.wait == 0 {
+                MIN_WAIT_NANOS
+            } else if self.wait < MAX_WAIT_NANOS / 2 {
+                self.wait * 2
+            } else {
+                MAX_WAIT_NANOS
+            };
+        }
+    }
+
+    /// Call `f` with the source and the data of every read, until it returns false
+    pub fn run<F: FnMut(usize, &[u8]) -> bool>(&mut self, buf: &mut [u8], mut f: F) -> Result<()> {
+        loop {
+            if let Some(ready) = try!(self.poll(buf, None)) {
+                if ! f(ready.fd, &buf[.. ready.count]) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Try every source once, starting after the last one with data
+    fn round(&mut self, buf: &mut [u8]) -> Result<Option<Ready>> {
+        for _ in 0..self.fds.len() {
+            let fd = self.fds[self.next];
+            self.next = (self.next + 1) % self.fds.len();
+
+            match sys_read(fd, buf) {
+                Ok(0) => (),
+                Ok(count) => return Ok(Some(Ready {
+                    fd: fd,
+                    count: count
+                })),
+                Err(err) => if err.errno != EAGAIN {
+                    return Err(err);
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn now() -> Result<TimeSpec> {
+    let mut time = TimeSpec::default();
+    try!(sys_clock_gettime(CLOCK_MONOTONIC, &mut time));
+    Ok(time)
+}
+
+fn add(a: &TimeSpec, b: &TimeSpec) -> TimeSpec {
+    let mut time = TimeSpec {
+        tv_sec: a.tv_sec + b.tv_sec,
+        tv_nsec: a.tv_nsec + b.tv_nsec
+    };
+    if time.tv_nsec >= 1000000000 {
+        time.tv_sec += 1;
+        time.tv_nsec -= 1000000000;
+    }
+    time
+}
+
+/// `a - b`, where `a` is not before `b`
+fn sub(a: &TimeSpec, b: &TimeSpec) -> TimeSpec {
+    let mut time = TimeSpec {
+        tv_sec: a.tv_sec - b.tv_sec,
+        tv_nsec: a.tv_nsec - b.tv_nsec
+    };
+    if time.tv_nsec < 0 {
+        time.tv_sec -= 1;
+        time.tv_nsec += 1000000000;
+    }
+    time
+}
+
+fn before(a: &TimeSpec, b: &TimeSpec) -> bool {
+    a.tv_sec < b.tv_sec || (a.tv_sec == b.tv_sec && a.tv_nsec < b.tv_nsec)
+}
@@ -37,6 +37,7 @@ pub trait Scheme {
         packet.a = Error::mux(match packet.a {
             SYS_OPEN => self.open(c_string_to_str(packet.b as *const u8), packet.c, packet.d),
             SYS_MKDIR => self.mkdir(c_string_to_str(packet.b as *const u8), packet.c),
+            SYS_RENAME => self.rename(c_string_to_str(packet.b as *const u8), c_string_to_str(packet.c as *const u8)),
             SYS_RMDIR => self.rmdir(c_string_to_str(packet.b as *const u8)),
             SYS_STAT => self.stat(c_string_to_str(packet.b as *const u8), unsafe { &mut *(packet.c as *mut Stat) }),
             SYS_UNLINK => self.unlink(c_string_to_str(packet.b as *const u8)),
@@ -44,7 +45,10 @@ pub trait Scheme {
             SYS_READ => self.read(packet.b, unsafe { slice::from_raw_parts_mut(packet.c as *mut u8, packet.d) }),
             SYS_WRITE => self.write(packet.b, unsafe { slice::from_raw_parts(packet.c as *const u8, packet.d) }),
             SYS_LSEEK => self.seek(packet.b, packet.c, packet.d),
+            SYS_FLINK => self.flink(packet.b, c_string_to_str(packet.c as *const u8)),
+            SYS_FMAP => self.fmap(packet.b, packet.c, packet.d),
             SYS_FPATH => self.fpath(packet.b, unsafe { slice::from_raw_parts_mut(packet.c as *mut u8, packet.d) }),
+            SYS_FPUNCH => self.fpunch(packet.b, packet.c, packet.d),
             SYS_FSTAT => self.fstat(packet.b, unsafe { &mut *(packet.c as *mut Stat) }),
             SYS_FSYNC => self.fsync(packet.b),
             SYS_FTRUNCATE => self.ftruncate(packet.b, packet.c),
@@ -66,6 +70,12 @@ pub trait Scheme {
         Err(Error::new(ENOENT))
     }
 
+    /// Rename path to new_path, replacing new_path if it exists, without a window where neither exists
+    #[allow(unused_variables)]
+    fn rename(&mut self, path: &str, new_path: &str) -> Result<usize> {
+        Err(Error::new(ENOSYS))
+    }
+
     #[allow(unused_variables)]
     fn rmdir(&mut self, path: &str) -> Result<usize> {
         Err(Error::new(ENOENT))
@@ -97,11 +107,33 @@ pub trait Scheme {
         Err(Error::new(EBADF))
     }
 
+    /// Link the resource to path, used to name files opened with `O_TMPFILE`
+    #[allow(unused_variables)]
+    fn flink(&mut self, id: usize, path: &str) -> Result<usize> {
+        Err(Error::new(ENOSYS))
+    }
+
+    /// Return the address of `size` bytes at `offset` to share with the caller, which must be pinned with
+    /// `sys_mlock` and stay allocated until the resource is closed
+    ///
+    /// The caller can reach the whole pages of the memory, so they should hold nothing else, and can only write to
+    /// them if it opened the resource for writing. The kernel unmaps them before the resource is closed.
+    #[allow(unused_variables)]
+    fn fmap(&mut self, id: usize, offset: usize, size: usize) -> Result<usize> {
+        Err(Error::new(ENOSYS))
+    }
+
     #[allow(unused_variables)]
     fn fpath(&self, id: usize, buf: &mut [u8]) -> Result<usize> {
         Err(Error::new(EBADF))
     }
 
+    /// Deallocate `len` bytes at `offset`, which must read as zeros afterwards
+    #[allow(unused_variables)]
+    fn fpunch(&mut self, id: usize, offset: usize, len: usize) -> Result<usize> {
+        Err(Error::new(ENOSYS))
+    }
+
     #[allow(unused_variables)]
     fn fstat(&self, id: usize, stat: &mut Stat) -> Result<usize> {
         Err(Error::new(EBADF))
@@ -0,0 +1,602 @@
+//! A JSON reader and writer that do not allocate
+//!
+//! `parse` checks a whole document and returns its top value. Strings, arrays, and objects borrow the text of the
+//! document, and are decoded as they are read, such as with `Value::get` or `Value::elements`:
+//!
+//! ```ignore
+//! let theme = try!(json::parse(r#"{"bar": {"height": 18, "font": "DroidSans"}}"#));
+//! let height = theme.get("bar").and_then(|bar| bar.get("height")).and_then(|height| height.as_i64());
+//! ```
+//!
+//! `Writer` writes a document to anything that implements `fmt::Write`, adding the commas and escapes.
+
+use core::{char, fmt, str};
+
+/// Arrays and objects nested deeper than this are an error, so reading cannot overflow the stack
+pub const MAX_DEPTH: usize = 32;
+
+/// A document that is not valid JSON
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JsonError {
+    /// The byte offset in the document where the error was found
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+/// A string in a document, with its escapes, such as `\n` or `\u00e9`, decoded as it is read
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JsonStr<'a> {
+    raw: &'a str,
+}
+
+impl<'a> JsonStr<'a> {
+    /// The text between the quotes, with its escapes
+    pub fn raw(&self) -> &'a str {
+        self.raw
+    }
+
+    /// The text, if it has no escapes and can be borrowed as it is
+    pub fn as_str(&self) -> Option<&'a str> {
+        if self.raw.contains('\\') { None } else { Some(self.raw) }
+    }
+
+    pub fn chars(&self) -> JsonChars<'a> {
+        JsonChars {
+            chars: self.raw.chars()
+        }
+    }
+
+    /// Compare the decoded text with a string
+    pub fn eq_str(&self, other: &str) -> bool {
+        self.chars().eq(other.chars())
+    }
+}
+
+impl<'a> fmt::Display for JsonStr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.chars() {
+            try!(fmt::Write::write_char(f, c));
+        }
+        Ok(())
+    }
+}
+
+/// The decoded characters of a string, invalid escaped surrogates are `U+FFFD`
+pub struct JsonChars<'a> {
+    chars: str::Chars<'a>,
+}
+
+impl<'a> JsonChars<'a> {
+    /// The code unit of a `\uXXXX` escape, after the `\u`
+    fn hex(&mut self) -> u32 {
+        let mut code = 0;
+        for _ in 0..4 {
+            code = code * 16 + self.chars.next().and_then(|c| c.to_digit(16)).unwrap_or(0);
+        }
+        code
+    }
+}
+
+impl<'a> Iterator for JsonChars<'a> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = match self.chars.next() {
+            Some(c) => c,
+            None => return None
+        };
+        if c != '\\' {
+            return Some(c);
+        }
+
+        let escape = match self.chars.next() {
+            Some(escape) => escape,
+            None => return None
+        };
+        Some(match escape {
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let high = self.hex();
+                if high >= 0xD800 && high < 0xDC00 {
+                    //A character outside the basic plane is a pair of escaped surrogates
+                    let rest = self.chars.as_str();
+                    if rest.starts_with("\\u") {
+                        self.chars.next();
+                        self.chars.next();
+                        let low = self.hex();
+                        if low >= 0xDC00 && low < 0xE000 {
+                            char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).unwrap_or('\u{FFFD}')
+                        } else {
+                            '\u{FFFD}'
+                        }
+                    } else {
+                        '\u{FFFD}'
+                    }
+                } else {
+                    char::from_u32(high).unwrap_or('\u{FFFD}')
+                }
+            },
+            other => other
+        })
+    }
+}
+
+/// A value in a document
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value<'a> {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(JsonStr<'a>),
+    /// The text of an array, including its brackets, see `Value::elements`
+    Array(&'a str),
+    /// The text of an object, including its braces, see `Value::members` and `Value::get`
+    Object(&'a str),
+}
+
+impl<'a> Value<'a> {
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(value) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Number(value) => Some(value),
+            _ => None
+        }
+    }
+
+    /// The number, if it is a whole number that fits
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Number(value) if value >= -9007199254740992.0 && value <= 9007199254740992.0 && value as i64 as f64 == value => {
+                Some(value as i64)
+            },
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<JsonStr<'a>> {
+        match *self {
+            Value::String(value) => Some(value),
+            _ => None
+        }
+    }
+
+    /// The elements of an array, none if it is not an array
+    pub fn elements(&self) -> Elements<'a> {
+        match *self {
+            Value::Array(text) => Elements {
+                parser: Parser::new(text, 1)
+            },
+            _ => Elements {
+                parser: Parser::new("", 0)
+            }
+        }
+    }
+
+    /// The keys and values of an object, in the order they are written, none if it is not an object
+    pub fn members(&self) -> Members<'a> {
+        match *self {
+            Value::Object(text) => Members {
+                parser: Parser::new(text, 1)
+            },
+            _ => Members {
+                parser: Parser::new("", 0)
+            }
+        }
+    }
+
+    /// The value of a key of an object, the last if the key is repeated
+    pub fn get(&self, key: &str) -> Option<Value<'a>> {
+        self.members().filter(|&(name, _)| name.eq_str(key)).last().map(|(_, value)| value)
+    }
+}
+
+/// Parse a document, returning its top value
+pub fn parse(text: &str) -> Result<Value, JsonError> {
+    let mut parser = Parser::new(text, 0);
+    parser.skip_whitespace();
+    let value = try!(parser.value(0));
+    parser.skip_whitespace();
+    if parser.pos < text.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+/// An iterator over the elements of an array, see `Value::elements`
+pub struct Elements<'a> {
+    parser: Parser<'a>,
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Value<'a>;
+
+    fn next(&mut self) -> Option<Value<'a>> {
+        //The array was checked when it was parsed
+        self.parser.skip_whitespace();
+        match self.parser.peek() {
+            None | Some(b']') => None,
+            _ => {
+                let value = self.parser.value(0).ok();
+                self.parser.skip_whitespace();
+                self.parser.eat(b',');
+                value
+            }
+        }
+    }
+}
+
+/// An iterator over the keys and values of an object, see `Value::members`
+pub struct Members<'a> {
+    parser: Parser<'a>,
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = (JsonStr<'a>, Value<'a>);
+
+    fn next(&mut self) -> Option<(JsonStr<'a>, Value<'a>)> {
+        //The object was checked when it was parsed
+        self.parser.skip_whitespace();
+        match self.parser.peek() {
+            None | Some(b'}') => None,
+            _ => {
+                let key = match self.parser.string() {
+                    Ok(key) => key,
+                    Err(_) => return None
+                };
+                self.parser.skip_whitespace();
+                self.parser.eat(b':');
+                self.parser.skip_whitespace();
+                let value = self.parser.value(0).ok();
+                self.parser.skip_whitespace();
+                self.parser.eat(b',');
+                value.map(|value| (key, value))
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str, pos: usize) -> Parser<'a> {
+        Parser {
+            text: text,
+            pos: pos
+        }
+    }
+
+    fn error(&self, message: &'static str) -> JsonError {
+        JsonError {
+            offset: self.pos,
+            message: message
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).map(|b| *b)
+    }
+
+    /// Skip a byte if it is next, returning true if it was
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') => self.pos += 1,
+                _ => break
+            }
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value<'a>, JsonError> {
+        match self.peek() {
+            Some(b'{') => self.object(depth),
+            Some(b'[') => self.array(depth),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'-') | Some(b'0' ... b'9') => self.number(),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end"))
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value<'a>) -> Result<Value<'a>, JsonError> {
+        if self.text[self.pos ..].starts_with(word) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.pos;
+        while let Some(b'0' ... b'9') = self.peek() {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn number(&mut self) -> Result<Value<'a>, JsonError> {
+        let start = self.pos;
+        self.eat(b'-');
+        if ! self.eat(b'0') && self.digits() == 0 {
+            return Err(self.error("expected a digit"));
+        }
+        if self.eat(b'.') && self.digits() == 0 {
+            return Err(self.error("expected a digit"));
+        }
+        if self.eat(b'e') || self.eat(b'E') {
+            if ! self.eat(b'+') {
+                self.eat(b'-');
+            }
+            if self.digits() == 0 {
+                return Err(self.error("expected a digit"));
+            }
+        }
+
+        match self.text[start .. self.pos].parse::<f64>() {
+            Ok(number) => Ok(Value::Number(number)),
+            Err(_) => Err(self.error("invalid number"))
+        }
+    }
+
+    fn string(&mut self) -> Result<JsonStr<'a>, JsonError> {
+        if ! self.eat(b'"') {
+            return Err(self.error("expected a string"));
+        }
+
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(b'"') | Some(b'\\') | Some(b'/') | Some(b'b') | Some(b'f') | Some(b'n') | Some(b'r')
+                        | Some(b't') => self.pos += 1,
+                        Some(b'u') => {
+                            self.pos += 1;
+                            for _ in 0..4 {
+                                match self.peek() {
+                                    Some(b'0' ... b'9') | Some(b'a' ... b'f') | Some(b'A' ... b'F') => self.pos += 1,
+                                    _ => return Err(self.error("invalid unicode escape"))
+                                }
+                            }
+                        },
+                        _ => return Err(self.error("invalid escape"))
+                    }
+                },
+                Some(0 ... 0x1F) => return Err(self.error("control character in string")),
+                Some(_) => self.pos += 1,
+                None => return Err(self.error("unterminated string"))
+            }
+        }
+        let raw = &self.text[start .. self.pos];
+        self.pos += 1;
+
+        Ok(JsonStr {
+            raw: raw
+        })
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value<'a>, JsonError> {
+        if depth >= MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+
+        let start = self.pos;
+        self.pos += 1;
+        self.skip_whitespace();
+        if ! self.eat(b']') {
+            loop {
+                self.skip_whitespace();
+                try!(self.value(depth + 1));
+                self.skip_whitespace();
+                if self.eat(b']') {
+                    break;
+                } else if ! self.eat(b',') {
+                    return Err(self.error("expected ',' or ']'"));
+                }
+            }
+        }
+
+        Ok(Value::Array(&self.text[start .. self.pos]))
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value<'a>, JsonError> {
+        if depth >= MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+
+        let start = self.pos;
+        self.pos += 1;
+        self.skip_whitespace();
+        if ! self.eat(b'}') {
+            loop {
+                self.skip_whitespace();
+                try!(self.string());
+                self.skip_whitespace();
+                if ! self.eat(b':') {
+                    return Err(self.error("expected ':'"));
+                }
+                self.skip_whitespace();
+                try!(self.value(depth + 1));
+                self.skip_whitespace();
+                if self.eat(b'}') {
+                    break;
+                } else if ! self.eat(b',') {
+                    return Err(self.error("expected ',' or '}'"));
+                }
+            }
+        }
+
+        Ok(Value::Object(&self.text[start .. self.pos]))
+    }
+}
+
+/// Writes a document, adding the commas between values and escaping strings
+///
+/// ```ignore
+/// let mut writer = Writer::new(&mut string);
+/// try!(writer.begin_object());
+/// try!(writer.key("height"));
+/// try!(writer.integer(18));
+/// try!(writer.end_object());
+/// ```
+pub struct Writer<W: fmt::Write> {
+    out: W,
+    /// A bit for each level of nesting, set once a value was written at that level
+    written: u64,
+    depth: usize,
+    /// A key was written, so the next value follows its colon
+    after_key: bool,
+}
+
+impl<W: fmt::Write> Writer<W> {
+    pub fn new(out: W) -> Writer<W> {
+        Writer {
+            out: out,
+            written: 0,
+            depth: 0,
+            after_key: false
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Write a comma before a value that is not the first at its level
+    fn separator(&mut self) -> fmt::Result {
+        if self.after_key {
+            self.after_key = false;
+            return Ok(());
+        }
+
+        let bit = 1 << (self.depth % 64);
+        if self.written & bit == bit {
+            try!(self.out.write_char(','));
+        }
+        self.written |= bit;
+        Ok(())
+    }
+
+    fn begin(&mut self, open: char) -> fmt::Result {
+        try!(self.separator());
+        try!(self.out.write_char(open));
+        self.depth += 1;
+        self.written &= ! (1 << (self.depth % 64));
+        Ok(())
+    }
+
+    fn end(&mut self, close: char) -> fmt::Result {
+        self.depth = self.depth.saturating_sub(1);
+        self.out.write_char(close)
+    }
+
+    pub fn begin_object(&mut self) -> fmt::Result {
+        self.begin('{')
+    }
+
+    pub fn end_object(&mut self) -> fmt::Result {
+        self.end('}')
+    }
+
+    pub fn begin_array(&mut self) -> fmt::Result {
+        self.begin('[')
+    }
+
+    pub fn end_array(&mut self) -> fmt::Result {
+        self.end(']')
+    }
+
+    /// Write the key of the next value in an object
+    pub fn key(&mut self, key: &str) -> fmt::Result {
+        try!(self.separator());
+        try!(write_escaped(&mut self.out, key));
+        try!(self.out.write_char(':'));
+        self.after_key = true;
+        Ok(())
+    }
+
+    pub fn string(&mut self, value: &str) -> fmt::Result {
+        try!(self.separator());
+        write_escaped(&mut self.out, value)
+    }
+
+    /// Write a number, infinities and NaN are written as `null`, which JSON has instead
+    pub fn number(&mut self, value: f64) -> fmt::Result {
+        try!(self.separator());
+        //Only finite numbers are equal to themselves minus themselves
+        if value - value == 0.0 {
+            write!(self.out, "{}", value)
+        } else {
+            self.out.write_str("null")
+        }
+    }
+
+    pub fn integer(&mut self, value: i64) -> fmt::Result {
+        try!(self.separator());
+        write!(self.out, "{}", value)
+    }
+
+    pub fn bool(&mut self, value: bool) -> fmt::Result {
+        try!(self.separator());
+        self.out.write_str(if value { "true" } else { "false" })
+    }
+
+    pub fn null(&mut self) -> fmt::Result {
+        try!(self.separator());
+        self.out.write_str("null")
+    }
+}
+
+/// Write a string with quotes, escaping quotes, backslashes, and control characters
+pub fn write_escaped<W: fmt::Write>(out: &mut W, value: &str) -> fmt::Result {
+    try!(out.write_char('"'));
+    for c in value.chars() {
+        match c {
+            '"' => try!(out.write_str("\\\"")),
+            '\\' => try!(out.write_str("\\\\")),
+            '\n' => try!(out.write_str("\\n")),
+            '\r' => try!(out.write_str("\\r")),
+            '\t' => try!(out.write_str("\\t")),
+            '\u{0}' ... '\u{1f}' => try!(write!(out, "\\u{:04x}", c as u32)),
+            _ => try!(out.write_char(c))
+        }
+    }
+    out.write_char('"')
+}
@@ -0,0 +1,10 @@
+//! Serialization of scheme control messages and config files
+//!
+//! `pod` copies plain structs to and from bytes, for messages written to schemes, and `json` reads and writes JSON
+//! without allocating, for config files such as themes, service manifests, and package metadata.
+
+pub use self::json::{JsonError, Value, Writer};
+pub use self::pod::Pod;
+
+pub mod json;
+pub mod pod;
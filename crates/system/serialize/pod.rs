@@ -0,0 +1,93 @@
+use core::{mem, ptr, slice};
+use core::marker::PhantomData;
+
+use super::super::scheme::Packet;
+use super::super::syscall::{Stat, TimeSpec};
+
+/// Plain old data, a type that can be copied to and from any bytes of its size
+///
+/// Implementing it is unsafe: the type must be `Copy`, have no padding, no pointers or references, and every bit
+/// pattern must be a valid value, so it cannot be a `bool`, a `char`, or an enum. `#[repr(packed)]` structs of
+/// integers are plain old data, like `Packet`.
+pub unsafe trait Pod: Copy {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for usize {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for i64 {}
+unsafe impl Pod for isize {}
+unsafe impl Pod for Packet {}
+unsafe impl Pod for Stat {}
+unsafe impl Pod for TimeSpec {}
+
+/// The bytes of a value
+pub fn as_bytes<T: Pod>(value: &T) -> &[u8] {
+    unsafe { slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+/// The bytes of a value, to write it in place, such as when reading it from a scheme
+pub fn as_bytes_mut<T: Pod>(value: &mut T) -> &mut [u8] {
+    unsafe { slice::from_raw_parts_mut(value as *mut T as *mut u8, mem::size_of::<T>()) }
+}
+
+/// The bytes of a slice of values
+pub fn slice_as_bytes<T: Pod>(values: &[T]) -> &[u8] {
+    unsafe { slice::from_raw_parts(values.as_ptr() as *const u8, values.len() * mem::size_of::<T>()) }
+}
+
+/// Copy a value from the start of some bytes, which do not need to be aligned, returning `None` if they are too short
+pub fn read<T: Pod>(bytes: &[u8]) -> Option<T> {
+    if bytes.len() < mem::size_of::<T>() {
+        return None;
+    }
+
+    let mut value: T = unsafe { mem::zeroed() };
+    unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), &mut value as *mut T as *mut u8, mem::size_of::<T>()); }
+    Some(value)
+}
+
+/// Copy a value to the start of a buffer, returning the bytes written, or `None` if the buffer is too short
+pub fn write<T: Pod>(value: &T, buf: &mut [u8]) -> Option<usize> {
+    let bytes = as_bytes(value);
+    if buf.len() < bytes.len() {
+        return None;
+    }
+
+    buf[.. bytes.len()].copy_from_slice(bytes);
+    Some(bytes.len())
+}
+
+/// An iterator over the values in some bytes, one after another, ignoring bytes after the last whole value
+pub struct Values<'a, T: Pod> {
+    bytes: &'a [u8],
+    phantom: PhantomData<T>,
+}
+
+/// Read the values in some bytes, such as the events of a window read in one call
+pub fn values<T: Pod>(bytes: &[u8]) -> Values<T> {
+    Values {
+        bytes: bytes,
+        phantom: PhantomData
+    }
+}
+
+impl<'a, T: Pod> Iterator for Values<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if mem::size_of::<T>() == 0 {
+            return None;
+        }
+
+        let value = read::<T>(self.bytes);
+        if value.is_some() {
+            self.bytes = &self.bytes[mem::size_of::<T>() ..];
+        }
+        value
+    }
+}
@@ -24,10 +24,14 @@ pub const SYS_CLOCK_GETTIME: usize = 265;
 pub const SYS_DUP: usize = 41;
 pub const SYS_EXECVE: usize = 11;
 pub const SYS_EXIT: usize = 1;
+pub const SYS_FLINK: usize = 929;
+pub const SYS_FMAP: usize = 90;
 pub const SYS_FPATH: usize = 928;
+pub const SYS_FPUNCH: usize = 930;
 pub const SYS_FSTAT: usize = 28;
 pub const SYS_FSYNC: usize = 118;
 pub const SYS_FTRUNCATE: usize = 93;
+pub const SYS_FUNMAP: usize = 91;
 pub const SYS_GETPID: usize = 20;
 pub const SYS_IOPL: usize = 110;
 pub const SYS_LINK: usize = 9;
@@ -36,6 +40,8 @@ pub const SYS_LSEEK: usize = 19;
     pub const SEEK_CUR: usize = 1;
     pub const SEEK_END: usize = 2;
 pub const SYS_MKDIR: usize = 39;
+pub const SYS_MLOCK: usize = 150;
+pub const SYS_MUNLOCK: usize = 151;
 pub const SYS_NANOSLEEP: usize = 162;
 pub const SYS_OPEN: usize = 5;
     pub const O_RDONLY: usize = 0;
@@ -50,17 +56,31 @@ pub const SYS_OPEN: usize = 5;
     pub const O_CREAT: usize = 0x200;
     pub const O_TRUNC: usize = 0x400;
     pub const O_EXCL: usize = 0x800;
+    /// Create an unnamed file in the directory given by path, which can later be named with `sys_flink`
+    pub const O_TMPFILE: usize = 0x1000;
 pub const SYS_PIPE2: usize = 331;
 pub const SYS_READ: usize = 3;
+pub const SYS_RENAME: usize = 38;
 pub const SYS_RMDIR: usize = 84;
 pub const SYS_STAT: usize = 18;
     pub const MODE_DIR: u16 = 0x4000;
     pub const MODE_FILE: u16 = 0x8000;
+pub const SYS_UMASK: usize = 60;
 pub const SYS_UNLINK: usize = 10;
 pub const SYS_WAITPID: usize = 7;
 pub const SYS_WRITE: usize = 4;
 pub const SYS_YIELD: usize = 158;
 
+/// A program killed by a fault exits with this plus the signal of the fault, as shells report it
+pub const EXIT_SIGNAL: usize = 128;
+
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGILL: usize = 4;
+pub const SIGKILL: usize = 9;
+pub const SIGSEGV: usize = 11;
+pub const SIGTRAP: usize = 5;
+
 #[derive(Copy, Clone, Debug, Default)]
 #[repr(packed)]
 pub struct Stat {
@@ -107,10 +127,25 @@ pub fn sys_exit(status: usize) -> Result<usize> {
     unsafe { syscall1(SYS_EXIT, status) }
 }
 
+/// Give a file a new name, such as an unnamed file created with `O_TMPFILE`
+pub unsafe fn sys_flink(fd: usize, path: *const u8) -> Result<usize> {
+    syscall2(SYS_FLINK, fd, path as usize)
+}
+
+/// Map `size` bytes of a file at `offset` into memory, shared with the scheme that provides it
+pub fn sys_fmap(fd: usize, offset: usize, size: usize) -> Result<usize> {
+    unsafe { syscall3(SYS_FMAP, fd, offset, size) }
+}
+
 pub fn sys_fpath(fd: usize, buf: &mut [u8]) -> Result<usize> {
     unsafe { syscall3(SYS_FPATH, fd, buf.as_mut_ptr() as usize, buf.len()) }
 }
 
+/// Deallocate a range of a file, which will read as zeros afterwards without changing the file size
+pub fn sys_fpunch(fd: usize, offset: usize, len: usize) -> Result<usize> {
+    unsafe { syscall3(SYS_FPUNCH, fd, offset, len) }
+}
+
 pub fn sys_fstat(fd: usize, stat: &mut Stat) -> Result<usize> {
     unsafe { syscall2(SYS_FSTAT, fd, stat as *mut Stat as usize) }
 }
@@ -123,6 +158,11 @@ pub fn sys_ftruncate(fd: usize, len: usize) -> Result<usize> {
     unsafe { syscall2(SYS_FTRUNCATE, fd, len) }
 }
 
+/// Unmap memory mapped with `sys_fmap`
+pub unsafe fn sys_funmap(addr: usize) -> Result<usize> {
+    syscall1(SYS_FUNMAP, addr)
+}
+
 pub fn sys_getpid() -> Result<usize> {
     unsafe { syscall0(SYS_GETPID) }
 }
@@ -143,6 +183,15 @@ pub unsafe fn sys_mkdir(path: *const u8, mode: usize) -> Result<usize> {
     syscall2(SYS_MKDIR, path as usize, mode)
 }
 
+/// Pin the memory containing a range, so it is not swapped out or moved, for audio and DMA buffers
+pub fn sys_mlock(addr: usize, len: usize) -> Result<usize> {
+    unsafe { syscall2(SYS_MLOCK, addr, len) }
+}
+
+pub fn sys_munlock(addr: usize, len: usize) -> Result<usize> {
+    unsafe { syscall2(SYS_MUNLOCK, addr, len) }
+}
+
 pub fn sys_nanosleep(req: &TimeSpec, rem: &mut TimeSpec) -> Result<usize> {
     unsafe { syscall2(SYS_NANOSLEEP, req as *const TimeSpec as usize, rem as *mut TimeSpec as usize) }
 }
@@ -159,6 +208,11 @@ pub fn sys_read(fd: usize, buf: &mut [u8]) -> Result<usize> {
     unsafe { syscall3(SYS_READ, fd, buf.as_mut_ptr() as usize, buf.len()) }
 }
 
+/// Atomically rename a path, replacing the destination if it exists
+pub unsafe fn sys_rename(old: *const u8, new: *const u8) -> Result<usize> {
+    syscall2(SYS_RENAME, old as usize, new as usize)
+}
+
 pub unsafe fn sys_rmdir(path: *const u8) -> Result<usize> {
     syscall1(SYS_RMDIR, path as usize)
 }
@@ -167,6 +221,11 @@ pub unsafe fn sys_stat(path: *const u8, stat: &mut Stat) -> Result<usize> {
     syscall2(SYS_STAT, path as usize, stat as *mut Stat as usize)
 }
 
+/// Set the permissions removed from created files and directories, returning the old umask
+pub fn sys_umask(mask: usize) -> Result<usize> {
+    unsafe { syscall1(SYS_UMASK, mask) }
+}
+
 pub unsafe fn sys_unlink(path: *const u8) -> Result<usize> {
     syscall1(SYS_UNLINK, path as usize)
 }
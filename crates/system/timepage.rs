@@ -0,0 +1,80 @@
+use core::ptr;
+
+use error::{Error, Result, EINVAL};
+use syscall::{CLOCK_MONOTONIC, CLOCK_REALTIME, TimeSpec};
+
+/// The address the time page is mapped at, read only, in every context
+pub const TIME_PAGE_ADDR: usize = 0x7FFFF000;
+
+/// The clocks, updated by the kernel on every timer tick, so they can be read without a syscall
+///
+/// Reads use the sequence like a lock: it is odd while the kernel is writing, and changes with every
+/// update, so a read that saw it change is retried.
+#[repr(C)]
+pub struct TimePage {
+    pub sequence: u32,
+    /// The length of a timer tick in nanoseconds
+    pub tick_nanos: u32,
+    pub monotonic_secs: i64,
+    pub monotonic_nanos: i32,
+    pub realtime_secs: i64,
+    pub realtime_nanos: i32,
+    /// The time stamp counter when the clocks were updated
+    pub tsc: u64,
+    /// The time stamp counter cycles in the last tick, 0 before it has been measured
+    pub tsc_per_tick: u64,
+}
+
+/// Read the time stamp counter
+pub fn rdtsc() -> u64 {
+    let low: u32;
+    let high: u32;
+    unsafe { asm!("rdtsc" : "={eax}"(low), "={edx}"(high) : : : "volatile") };
+    (high as u64) << 32 | low as u64
+}
+
+/// The nanoseconds since the clocks were updated at `last` cycles of the time stamp counter, kept within the tick
+/// so the time never passes the next update
+fn elapsed_nanos(tsc: u64, last: u64, tsc_per_tick: u64, tick_nanos: u32) -> i32 {
+    let elapsed = tsc.wrapping_sub(last);
+    if tsc_per_tick == 0 || tick_nanos == 0 {
+        0
+    } else if elapsed >= tsc_per_tick {
+        tick_nanos as i32 - 1
+    } else {
+        (elapsed * tick_nanos as u64 / tsc_per_tick) as i32
+    }
+}
+
+/// Read `CLOCK_REALTIME` or `CLOCK_MONOTONIC` from the time page, like `sys_clock_gettime` but without a syscall
+pub fn clock_gettime(clock: usize, tp: &mut TimeSpec) -> Result<usize> {
+    let page = TIME_PAGE_ADDR as *const TimePage;
+    loop {
+        unsafe {
+            let sequence = ptr::read_volatile(&(*page).sequence);
+            if sequence & 1 == 1 {
+                continue;
+            }
+
+            let (secs, nanos) = match clock {
+                CLOCK_REALTIME => (ptr::read_volatile(&(*page).realtime_secs), ptr::read_volatile(&(*page).realtime_nanos)),
+                CLOCK_MONOTONIC => (ptr::read_volatile(&(*page).monotonic_secs), ptr::read_volatile(&(*page).monotonic_nanos)),
+                _ => return Err(Error::new(EINVAL))
+            };
+            let elapsed = elapsed_nanos(rdtsc(), ptr::read_volatile(&(*page).tsc),
+                                        ptr::read_volatile(&(*page).tsc_per_tick), ptr::read_volatile(&(*page).tick_nanos));
+
+            if ptr::read_volatile(&(*page).sequence) == sequence {
+                let nanos = nanos + elapsed;
+                if nanos >= 1000000000 {
+                    tp.tv_sec = secs + 1;
+                    tp.tv_nsec = nanos - 1000000000;
+                } else {
+                    tp.tv_sec = secs;
+                    tp.tv_nsec = nanos;
+                }
+                return Ok(0);
+            }
+        }
+    }
+}
@@ -0,0 +1,367 @@
+#![deny(warnings)]
+
+extern crate orbclient;
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use orbclient::{event, Color, EventOption, Window};
+
+/// The logs read when no paths are given
+const DEFAULT_SOURCES: [&'static str; 1] = ["syslog:"];
+/// How often the logs are read again in follow mode
+const FOLLOW_INTERVAL_MS: u64 = 1000;
+
+const BACKGROUND_COLOR: Color = Color::rgb(255, 255, 255);
+const TEXT_COLOR: Color = Color::rgb(0, 0, 0);
+const TIME_COLOR: Color = Color::rgb(128, 128, 128);
+const STATUS_COLOR: Color = Color::rgb(40, 45, 57);
+const STATUS_TEXT_COLOR: Color = Color::rgb(235, 241, 255);
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+enum Level {
+    Critical,
+    Error,
+    Warning,
+    Info,
+    Debug,
+}
+
+impl Level {
+    /// Parse the level column of the kernel log, such as `WARN`
+    fn parse(name: &str) -> Option<Level> {
+        match name {
+            "CRIT" => Some(Level::Critical),
+            "ERROR" => Some(Level::Error),
+            "WARN" => Some(Level::Warning),
+            "INFO" => Some(Level::Info),
+            "DEBUG" => Some(Level::Debug),
+            _ => None
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Level::Critical => "CRIT",
+            Level::Error => "ERROR",
+            Level::Warning => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match *self {
+            Level::Critical => Color::rgb(192, 0, 192),
+            Level::Error => Color::rgb(204, 0, 0),
+            Level::Warning => Color::rgb(196, 128, 0),
+            Level::Info => Color::rgb(0, 128, 0),
+            Level::Debug => Color::rgb(96, 96, 96),
+        }
+    }
+
+    /// The levels chosen with the keys `1` to `5`
+    fn from_key(c: char) -> Option<Level> {
+        match c {
+            '1' => Some(Level::Critical),
+            '2' => Some(Level::Error),
+            '3' => Some(Level::Warning),
+            '4' => Some(Level::Info),
+            '5' => Some(Level::Debug),
+            _ => None
+        }
+    }
+}
+
+/// A line of a log, such as `[12.345] WARN  SWAP: out of space`
+struct Entry {
+    time: String,
+    level: Level,
+    /// The word before a colon that starts many messages, such as `SWAP`
+    subsystem: String,
+    message: String,
+}
+
+impl Entry {
+    fn parse(line: &str) -> Option<Entry> {
+        let line = line.trim_right();
+        if line.is_empty() {
+            return None;
+        }
+
+        let (time, rest) = if line.starts_with('[') {
+            match line.find("] ") {
+                Some(end) => (line[1 .. end].to_string(), &line[end + 2 ..]),
+                None => (String::new(), line)
+            }
+        } else {
+            (String::new(), line)
+        };
+
+        let (level, message) = match rest.find(' ') {
+            Some(end) => match Level::parse(&rest[.. end]) {
+                Some(level) => (level, rest[end ..].trim_left()),
+                None => (Level::Info, rest)
+            },
+            None => (Level::Info, rest)
+        };
+
+        let subsystem = match message.find(": ") {
+            Some(end) if end > 0 && ! message[.. end].contains(' ') => message[.. end].to_string(),
+            _ => String::new()
+        };
+
+        Some(Entry {
+            time: time,
+            level: level,
+            subsystem: subsystem,
+            message: message.to_string(),
+        })
+    }
+
+    fn matches(&self, search: &str) -> bool {
+        search.is_empty() || self.message.to_lowercase().contains(&search.to_lowercase())
+    }
+}
+
+struct LogViewer {
+    window: Box<Window>,
+    sources: Vec<String>,
+    entries: Vec<Entry>,
+    /// The least important level shown
+    level: Level,
+    /// Only show messages of this subsystem
+    subsystem: Option<String>,
+    search: String,
+    /// Typing edits the search instead of running commands
+    searching: bool,
+    /// Read the logs again periodically and keep the newest entries in view
+    follow: bool,
+    /// The first shown entry of the filtered entries
+    scroll: usize,
+}
+
+impl LogViewer {
+    fn new(sources: Vec<String>) -> LogViewer {
+        let title = format!("Log Viewer ({})", sources.join(" "));
+        LogViewer {
+            window: Window::new_flags(-1, -1, 800, 600, &title, true).unwrap(),
+            sources: sources,
+            entries: Vec::new(),
+            level: Level::Debug,
+            subsystem: None,
+            search: String::new(),
+            searching: false,
+            follow: true,
+            scroll: 0,
+        }
+    }
+
+    fn reload(&mut self) {
+        self.entries.clear();
+        for source in self.sources.iter() {
+            let mut string = String::new();
+            match File::open(source) {
+                Ok(mut file) => if let Err(err) = file.read_to_string(&mut string) {
+                    println!("log_viewer: failed to read {}: {}", source, err);
+                },
+                Err(err) => println!("log_viewer: failed to open {}: {}", source, err)
+            }
+            for line in string.lines() {
+                if let Some(entry) = Entry::parse(line) {
+                    self.entries.push(entry);
+                }
+            }
+        }
+    }
+
+    fn visible(&self) -> Vec<&Entry> {
+        self.entries.iter().filter(|entry| {
+            entry.level <= self.level
+                && self.subsystem.as_ref().map_or(true, |subsystem| &entry.subsystem == subsystem)
+                && entry.matches(&self.search)
+        }).collect()
+    }
+
+    /// The number of log lines that fit above the status line
+    fn rows(&self) -> usize {
+        (self.window.height() as usize / 16).saturating_sub(1)
+    }
+
+    fn scroll_to_end(&mut self) {
+        let count = self.visible().len();
+        self.scroll = count.saturating_sub(self.rows());
+    }
+
+    /// Show the next subsystem, going back to all of them after the last
+    fn cycle_subsystem(&mut self) {
+        let mut subsystems: Vec<String> = self.entries.iter().map(|entry| entry.subsystem.clone()).filter(|subsystem| ! subsystem.is_empty()).collect();
+        subsystems.sort();
+        subsystems.dedup();
+
+        self.subsystem = match self.subsystem.take() {
+            Some(current) => subsystems.into_iter().find(|subsystem| *subsystem > current),
+            None => subsystems.into_iter().next()
+        };
+        self.scroll = 0;
+    }
+
+    fn text(&mut self, x: i32, y: i32, text: &str, color: Color) -> i32 {
+        let mut x = x;
+        for c in text.chars() {
+            if x + 8 > self.window.width() as i32 {
+                break;
+            }
+            self.window.char(x, y, c, color);
+            x += 8;
+        }
+        x
+    }
+
+    fn draw(&mut self) {
+        self.window.set(BACKGROUND_COLOR);
+
+        let rows = self.rows();
+        let width = self.window.width();
+        let lines: Vec<(String, Level, String)> = self.visible().iter().skip(self.scroll).take(rows).map(|entry| {
+            (entry.time.clone(), entry.level, entry.message.clone())
+        }).collect();
+
+        let mut y = 0;
+        for (time, level, message) in lines {
+            let x = self.text(0, y, &format!("{:>12} ", time), TIME_COLOR);
+            let x = self.text(x, y, &format!("{:<6}", level.name()), level.color());
+            self.text(x, y, &message, TEXT_COLOR);
+            y += 16;
+        }
+
+        let status_y = self.window.height() as i32 - 16;
+        self.window.rect(0, status_y, width, 16, STATUS_COLOR);
+        let status = if self.searching {
+            format!("Search: {}_", self.search)
+        } else {
+            format!("Level <= {} (1-5)  Subsystem: {} (Tab)  Search: {} (/)  Follow: {} (F)  {} entries",
+                    self.level.name(), self.subsystem.as_ref().map_or("all", |subsystem| &subsystem[..]),
+                    if self.search.is_empty() { "none" } else { &self.search[..] }, if self.follow { "on" } else { "off" },
+                    self.visible().len())
+        };
+        self.text(0, status_y, &status, STATUS_TEXT_COLOR);
+
+        self.window.sync();
+    }
+
+    /// Handle the events, returning whether to keep running and whether to redraw
+    fn events(&mut self) -> (bool, bool) {
+        let mut redraw = false;
+        for event in self.window.events() {
+            match event.to_option() {
+                EventOption::Key(key_event) => if key_event.pressed {
+                    redraw = true;
+                    if self.searching {
+                        match key_event.scancode {
+                            event::K_ESC => {
+                                self.search.clear();
+                                self.searching = false;
+                            },
+                            event::K_BKSP => {
+                                self.search.pop();
+                            },
+                            _ => match key_event.character {
+                                '\n' => self.searching = false,
+                                '\0' => (),
+                                c => self.search.push(c)
+                            }
+                        }
+                        self.scroll = 0;
+                        continue;
+                    }
+
+                    match key_event.scancode {
+                        event::K_ESC => return (false, redraw),
+                        event::K_UP => {
+                            self.follow = false;
+                            self.scroll = self.scroll.saturating_sub(1);
+                        },
+                        event::K_DOWN => self.scroll += 1,
+                        event::K_PGUP => {
+                            self.follow = false;
+                            self.scroll = self.scroll.saturating_sub(self.rows());
+                        },
+                        event::K_PGDN => self.scroll += self.rows(),
+                        event::K_HOME => {
+                            self.follow = false;
+                            self.scroll = 0;
+                        },
+                        event::K_END => self.scroll_to_end(),
+                        _ => match key_event.character {
+                            '/' => self.searching = true,
+                            '\t' => self.cycle_subsystem(),
+                            'f' => {
+                                self.follow = ! self.follow;
+                                if self.follow {
+                                    self.reload();
+                                    self.scroll_to_end();
+                                }
+                            },
+                            'r' => self.reload(),
+                            c => if let Some(level) = Level::from_key(c) {
+                                self.level = level;
+                                self.scroll = 0;
+                            }
+                        }
+                    }
+
+                    let count = self.visible().len();
+                    if self.scroll > count.saturating_sub(1) {
+                        self.scroll = count.saturating_sub(1);
+                    }
+                },
+                EventOption::Quit(_) => return (false, redraw),
+                _ => ()
+            }
+        }
+        (true, redraw)
+    }
+
+    fn main(&mut self) {
+        self.reload();
+        self.scroll_to_end();
+        self.draw();
+
+        let mut last_reload = Instant::now();
+        loop {
+            let (running, mut redraw) = self.events();
+            if ! running {
+                break;
+            }
+
+            if self.follow && last_reload.elapsed() >= Duration::from_millis(FOLLOW_INTERVAL_MS) {
+                let count = self.entries.len();
+                self.reload();
+                if self.entries.len() != count {
+                    self.scroll_to_end();
+                    redraw = true;
+                }
+                last_reload = Instant::now();
+            }
+
+            if redraw {
+                self.draw();
+            }
+
+            thread::sleep_ms(30);
+        }
+    }
+}
+
+fn main() {
+    let mut sources: Vec<String> = env::args().skip(1).collect();
+    if sources.is_empty() {
+        sources = DEFAULT_SOURCES.iter().map(|source| source.to_string()).collect();
+    }
+
+    LogViewer::new(sources).main();
+}
@@ -1,10 +1,12 @@
-use common::slice::GetSlice;
-
 use alloc::arc::Arc;
 use alloc::boxed::{Box, FnBox};
 
+#[cfg(target_arch = "x86")]
+use arch::fpu;
 use arch::memory;
 use arch::paging::Page;
+use arch::swap;
+use arch::timepage;
 use arch::regs::Regs;
 
 use collections::string::{String, ToString};
@@ -21,7 +23,7 @@ use fs::Resource;
 
 use syscall::{do_sys_exit, CLONE_FILES, CLONE_FS, CLONE_VM, CLONE_VFORK, CLONE_SUPERVISE};
 
-use system::error::{Error, Result, EBADF, EFAULT, ENOMEM, ESRCH, ENOENT, EINVAL};
+use system::error::{Error, Result, EAGAIN, EBADF, EFAULT, ENOMEM, ESRCH, ENOENT, EINVAL};
 
 use sync::WaitMap;
 
@@ -37,6 +39,20 @@ pub const CONTEXT_MMAP_SIZE: usize = 0x20000000;
 pub const CONTEXT_STACK_ADDR: usize = CONTEXT_MMAP_ADDR + CONTEXT_MMAP_SIZE + memory::CLUSTER_SIZE;
 pub const CONTEXT_STACK_SIZE: usize = 0x100000;
 
+/// The most memory a context may pin with `mlock`
+pub const CONTEXT_MLOCK_LIMIT: usize = 0x4000000;
+
+/// The permissions removed from created files and directories until a process changes its umask
+pub const CONTEXT_DEFAULT_UMASK: usize = 0o022;
+
+/// The system call interface of a context, chosen by exec from the executable
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Personality {
+    Redox,
+    /// Linux system calls, see `syscall::linux`
+    Linux,
+}
+
 pub struct ContextManager {
     pub inner: Vec<Box<Context>>,
     pub enabled: bool,
@@ -175,6 +191,7 @@ pub unsafe fn context_switch() {
 
                 if let Ok(mut next) = contexts.current_mut() {
                     next.switch += 1;
+                    next.last_run = Duration::monotonic();
 
                     if let Some(ref mut tss) = ::TSS_PTR {
                         if next.kernel_stack > 0 {
@@ -243,15 +260,18 @@ pub unsafe fn context_clone(regs: &Regs) -> Result<usize> {
                     None
                 },
                 wake: None,
+                last_run: Duration::new(0, 0),
 
                 supervised: flags & CLONE_SUPERVISE == CLONE_SUPERVISE,
                 blocked_syscall: false,
+                personality: parent.personality,
+                compat: parent.compat,
 
                 kernel_stack: kernel_stack,
                 regs: kernel_regs,
                 fx: fx,
                 stack: if let Some(ref entry) = parent.stack {
-                    let physical_address = memory::alloc(entry.virtual_size);
+                    let physical_address = swap::alloc_user(entry.virtual_size);
                     if physical_address > 0 {
                         ::memcpy(physical_address as *mut u8,
                                  entry.physical_address as *const u8,
@@ -262,6 +282,8 @@ pub unsafe fn context_clone(regs: &Regs) -> Result<usize> {
                             virtual_size: entry.virtual_size,
                             writeable: entry.writeable,
                             allocated: true,
+                            swap: None,
+                            locked: false,
                         })
                     } else {
                         None
@@ -302,6 +324,11 @@ pub unsafe fn context_clone(regs: &Regs) -> Result<usize> {
                 } else {
                     Arc::new(UnsafeCell::new((*parent.cwd.get()).clone()))
                 },
+                umask: if flags & CLONE_FS == CLONE_FS {
+                    parent.umask.clone()
+                } else {
+                    Arc::new(UnsafeCell::new(*parent.umask.get()))
+                },
                 files: if flags & CLONE_FILES == CLONE_FILES {
                     //debugln!("{}: {}: clone resources for {}", parent.pid, parent.name, clone_pid);
 
@@ -316,6 +343,7 @@ pub unsafe fn context_clone(regs: &Regs) -> Result<usize> {
                                 files.push(ContextFile {
                                     fd: file.fd,
                                     resource: resource,
+                                    maps: Vec::new(),
                                 });
                             },
                             Err(_err) => () //debugln!("{}: {}: failed to dup resource {} for {}: {}", parent.pid, parent.name, file.fd, clone_pid, err)
@@ -402,10 +430,36 @@ pub struct ContextMemory {
     pub virtual_size: usize,
     pub writeable: bool,
     pub allocated: bool,
+    /// The first page of swap holding this memory, if it has been swapped out
+    pub swap: Option<usize>,
+    /// Pinned with `mlock`, so it is not swapped out or moved
+    pub locked: bool,
 }
 
 impl ContextMemory {
+    /// Check if any part of a virtual range is in this memory
+    pub fn overlaps(&self, ptr: usize, len: usize) -> bool {
+        ptr < self.virtual_address + self.virtual_size && self.virtual_address < ptr.saturating_add(len)
+    }
+
     pub unsafe fn map(&mut self) {
+        //Swap the memory back in, swapping out other contexts if memory is exhausted
+        while self.swap.is_some() {
+            let result = match *::env().swap.lock() {
+                Some(ref mut swap) => swap.page_in(self),
+                None => Err(Error::new(ENOMEM))
+            };
+
+            match result {
+                Ok(()) => (),
+                Err(ref err) if err.errno == ENOMEM && swap::reclaim() > 0 => (),
+                Err(err) => {
+                    debugln!("SWAP: failed to page in {:X}: {}", self.virtual_address, err);
+                    return;
+                }
+            }
+        }
+
         for i in 0..(self.virtual_size + 4095) / 4096 {
             if self.writeable {
                 Page::new(self.virtual_address + i * 4096)
@@ -427,6 +481,11 @@ impl ContextMemory {
 
 impl Drop for ContextMemory {
     fn drop(&mut self) {
+        if self.swap.is_some() {
+            if let Some(ref mut swap) = *::env().swap.lock() {
+                swap.release(self);
+            }
+        }
         if self.allocated {
             unsafe { memory::unalloc(self.physical_address) };
         }
@@ -436,6 +495,31 @@ impl Drop for ContextMemory {
 pub struct ContextFile {
     pub fd: usize,
     pub resource: Box<Resource>,
+    /// The zone and address of each mapping of the file, made with `sys_fmap`
+    pub maps: Vec<(Arc<UnsafeCell<ContextZone>>, usize)>,
+}
+
+impl ContextFile {
+    /// Remove the mappings of the file, so none remain when the scheme is told it is closed
+    ///
+    /// Mappings in the zone of the current context are unmapped, the others are not mapped until their context runs.
+    pub unsafe fn unmap(&mut self, current: Option<&Context>) {
+        for (zone, address) in self.maps.drain(..) {
+            let memory = &mut (*zone.get()).memory;
+            if let Some(i) = memory.iter().position(|mem| mem.virtual_address == address && ! mem.allocated) {
+                let mut mem = memory.remove(i);
+                if current.map_or(false, |current| current.mmap.get() == zone.get()) {
+                    mem.unmap();
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ContextFile {
+    fn drop(&mut self) {
+        unsafe { self.unmap(None) };
+    }
 }
 
 pub struct ContextZone {
@@ -456,7 +540,7 @@ impl ContextZone {
     pub fn dup(&self) -> ContextZone {
         let mut mem: Vec<ContextMemory> = Vec::new();
         for entry in self.memory.iter() {
-            let physical_address = unsafe { memory::alloc(entry.virtual_size) };
+            let physical_address = unsafe { swap::alloc_user(entry.virtual_size) };
             if physical_address > 0 {
                 //TODO: Remap pages during memcpy
                 unsafe {
@@ -473,6 +557,8 @@ impl ContextZone {
                     virtual_size: entry.virtual_size,
                     writeable: entry.writeable,
                     allocated: true,
+                    swap: None,
+                    locked: false,
                 });
             } else {
                 //debugln!("{}: {}: failed to dup memory {:X}:{:X} for {}", parent.pid, parent.name, entry.virtual_address, entry.virtual_address + entry.virtual_size, clone_pid);
@@ -513,8 +599,12 @@ impl ContextZone {
 
     /// Translate to physical if a ptr is inside of the mapped memory
     pub fn translate(&self, ptr: usize, len: usize) -> Option<usize> {
+        let end = match ptr.checked_add(len) {
+            Some(end) => end,
+            None => return None
+        };
         for mem in self.memory.iter() {
-            if ptr >= mem.virtual_address && ptr + len <= mem.virtual_address + mem.virtual_size {
+            if ptr >= mem.virtual_address && end <= mem.virtual_address + mem.virtual_size {
                 return Some(ptr - mem.virtual_address + mem.physical_address);
             }
         }
@@ -590,6 +680,8 @@ pub struct Context {
     pub vfork: Option<*mut Context>,
     /// When to wake up
     pub wake: Option<Duration>,
+    /// When the context was last switched to, used to pick memory to swap out
+    pub last_run: Duration,
     // }
 
     /// Is this process supervised?
@@ -601,6 +693,10 @@ pub struct Context {
     ///
     /// This means that the process is waiting for the superviser to handle the syscall.
     pub blocked_syscall: bool,
+    /// The system call interface, kept by clone and set by exec
+    pub personality: Personality,
+    /// Running a 32-bit program in compatibility mode on a 64-bit kernel, kept by clone and set by exec
+    pub compat: bool,
 
     // These members control the stack and registers and are unique to each context {
     // The kernel stack
@@ -627,6 +723,8 @@ pub struct Context {
 
     /// Program working directory, cloned for threads, copied or created for processes. Modified by chdir
     pub cwd: Arc<UnsafeCell<String>>,
+    /// Permissions removed from created files, cloned for threads, copied for processes. Modified by umask
+    pub umask: Arc<UnsafeCell<usize>>,
     /// Program files, cloned for threads, copied or created for processes. Modified by file operations
     pub files: Arc<UnsafeCell<Vec<ContextFile>>>,
     // }
@@ -679,9 +777,12 @@ impl Context {
             time: 0,
             vfork: None,
             wake: None,
+            last_run: Duration::new(0, 0),
 
             supervised: false,
             blocked_syscall: false,
+            personality: Personality::Redox,
+            compat: false,
 
             kernel_stack: 0,
             regs: Regs::default(),
@@ -695,6 +796,7 @@ impl Context {
             env_vars: Arc::new(UnsafeCell::new(Vec::new())),
 
             cwd: Arc::new(UnsafeCell::new(String::new())),
+            umask: Arc::new(UnsafeCell::new(CONTEXT_DEFAULT_UMASK)),
             files: Arc::new(UnsafeCell::new(Vec::new())),
 
             statuses: WaitMap::new(),
@@ -720,9 +822,12 @@ impl Context {
             time: 0,
             vfork: None,
             wake: None,
+            last_run: Duration::new(0, 0),
 
             supervised: false,
             blocked_syscall: false,
+            personality: Personality::Redox,
+            compat: false,
 
             kernel_stack: kernel_stack,
             regs: regs,
@@ -736,6 +841,7 @@ impl Context {
             env_vars: Arc::new(UnsafeCell::new(Vec::new())),
 
             cwd: Arc::new(UnsafeCell::new(String::new())),
+            umask: Arc::new(UnsafeCell::new(CONTEXT_DEFAULT_UMASK)),
             files: Arc::new(UnsafeCell::new(Vec::new())),
 
             statuses: WaitMap::new(),
@@ -771,31 +877,18 @@ impl Context {
         ret
     }
 
-    pub fn canonicalize(&self, path: &str) -> String {
-        if path.find(':').is_none() {
-            let cwd = unsafe { &*self.cwd.get() };
-            if path == "." {
-                cwd.to_string()
-            } else if path == ".." {
-                cwd.get_slice(..cwd.get_slice(..cwd.len() - 1)
-                                   .rfind('/')
-                                   .map_or(cwd.len(), |i| i + 1))
-                   .to_string()
-            } else if path.starts_with("./") {
-                cwd.to_string() + &path.get_slice(2..)
-            } else if path.starts_with("../") {
-                cwd.get_slice(..cwd.get_slice(..cwd.len() - 1)
-                                   .rfind('/')
-                                   .map_or(cwd.len(), |i| i + 1))
-                   .to_string() + &path.get_slice(3..)
-            } else if path.starts_with('/') {
-                cwd.get_slice(..cwd.find(':').map_or(1, |i| i + 1)).to_string() + &path
-            } else {
-                cwd.to_string() + &path
-            }
-        } else {
-            path.to_string()
-        }
+    /// The permissions of a created file or directory, with those in the umask removed
+    pub fn create_mode(&self, mode: usize) -> usize {
+        mode & ! unsafe { *self.umask.get() }
+    }
+
+    /// Join a path to the working directory and resolve `.` and `..`, see `system::path::canonicalize`
+    pub fn canonicalize(&self, path: &str) -> Result<String> {
+        let cwd = unsafe { &*self.cwd.get() };
+        let mut buf = vec![0; cwd.len() + path.len() + 2];
+        let len = try!(::system::path::canonicalize(cwd, path, &mut buf));
+        buf.truncate(len);
+        String::from_utf8(buf).or(Err(Error::new(EINVAL)))
     }
 
     /// Get the next available file descriptor
@@ -848,10 +941,75 @@ impl Context {
         ptr::write(self.regs.sp as *mut usize, data);
     }
 
+    /// The memory of the stack and zones overlapping a virtual range
+    fn memory_in<'a>(&'a self, ptr: usize, len: usize) -> Vec<&'a ContextMemory> {
+        let mut memory = Vec::new();
+        if let Some(ref stack) = self.stack {
+            if stack.overlaps(ptr, len) {
+                memory.push(stack);
+            }
+        }
+        for zone in [&self.image, &self.heap, &self.mmap].iter() {
+            for mem in unsafe { (*zone.get()).memory.iter() } {
+                if mem.overlaps(ptr, len) {
+                    memory.push(mem);
+                }
+            }
+        }
+        memory
+    }
+
+    /// The number of bytes pinned with `mlock`, shared with threads of the same process
+    pub fn locked_size(&self) -> usize {
+        self.memory_in(0, usize::max_value()).iter().filter(|mem| mem.locked).fold(0, |size, mem| size + mem.virtual_size)
+    }
+
+    /// Check if all of the memory overlapping a virtual range is pinned
+    pub fn locked(&self, ptr: usize, len: usize) -> bool {
+        let memory = self.memory_in(ptr, len);
+        ! memory.is_empty() && memory.iter().all(|mem| mem.locked)
+    }
+
+    /// Pin or unpin the memory overlapping a virtual range, returning the number of bytes that changed,
+    /// or an error if there is no memory in the range or it would exceed `CONTEXT_MLOCK_LIMIT`
+    ///
+    /// Memory is pinned a whole allocation at a time, since it is swapped out and moved that way
+    pub unsafe fn set_locked(&mut self, ptr: usize, len: usize, locked: bool) -> Result<usize> {
+        let (found, changed) = {
+            let memory = self.memory_in(ptr, len);
+            (! memory.is_empty(), memory.iter().filter(|mem| mem.locked != locked).fold(0, |size, mem| size + mem.virtual_size))
+        };
+        if ! found {
+            return Err(Error::new(ENOMEM));
+        }
+        if locked && self.locked_size() + changed > CONTEXT_MLOCK_LIMIT {
+            return Err(Error::new(EAGAIN));
+        }
+
+        if let Some(ref mut stack) = self.stack {
+            if stack.overlaps(ptr, len) {
+                stack.locked = locked;
+            }
+        }
+        for zone in [&self.image, &self.heap, &self.mmap].iter() {
+            for mem in (*zone.get()).memory.iter_mut() {
+                if mem.overlaps(ptr, len) {
+                    mem.locked = locked;
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+
     /// Translate to physical if a ptr is inside of the mapped memory
     pub fn translate(&self, ptr: usize, len: usize) -> Result<usize> {
+        let end = match ptr.checked_add(len) {
+            Some(end) => end,
+            None => return Err(Error::new(EFAULT))
+        };
         if let Some(ref stack) = self.stack {
-            if ptr >= stack.virtual_address && ptr + len <= stack.virtual_address + stack.virtual_size {
+            if ptr >= stack.virtual_address && end <= stack.virtual_address + stack.virtual_size {
                 return Ok(ptr - stack.virtual_address + stack.physical_address);
             }
         }
@@ -925,9 +1083,11 @@ impl Context {
         (*self.image.get()).map();
         (*self.heap.get()).map();
         (*self.mmap.get()).map();
+        timepage::map();
     }
 
     pub unsafe fn unmap(&mut self) {
+        timepage::unmap();
         (*self.mmap.get()).unmap();
         (*self.heap.get()).unmap();
         (*self.image.get()).unmap();
@@ -944,11 +1104,21 @@ impl Context {
     pub unsafe fn switch_to(&mut self, next: &mut Context) {
         //asm!("xchg bx, bx" : : : "memory" : "intel", "volatile");
 
-        asm!("fxsave [$0]" : : "r"(self.fx) : "memory" : "intel", "volatile");
+        //Processors without fxsave save the floating point registers with fnsave, and without a floating point
+        //unit there are none
+        if fpu::FXSR {
+            asm!("fxsave [$0]" : : "r"(self.fx) : "memory" : "intel", "volatile");
+        } else if fpu::FPU {
+            asm!("fnsave [$0]" : : "r"(self.fx) : "memory" : "intel", "volatile");
+        }
         self.loadable = true;
         if next.loadable {
-            asm!("fxrstor [$0]" : : "r"(next.fx) : "memory" : "intel", "volatile");
-        }else{
+            if fpu::FXSR {
+                asm!("fxrstor [$0]" : : "r"(next.fx) : "memory" : "intel", "volatile");
+            } else if fpu::FPU {
+                asm!("frstor [$0]" : : "r"(next.fx) : "memory" : "intel", "volatile");
+            }
+        }else if fpu::FPU {
             asm!("fninit" : : : "memory" : "intel", "volatile");
         }
 
@@ -0,0 +1,243 @@
+//! The processor, as described by `cpuid`
+//!
+//! The description is gathered once at boot. Features are only listed if they can be used, so SSE is left out where
+//! the boot code could not enable it, and AVX where the kernel does not save its registers.
+
+use collections::string::{String, ToString};
+use collections::vec::Vec;
+
+use arch::fpu;
+
+/// Features of leaf 1 in edx, by bit
+const FEATURES_EDX: [(u32, &'static str); 17] = [
+    (0, "fpu"), (4, "tsc"), (5, "msr"), (6, "pae"), (8, "cx8"), (9, "apic"), (11, "sep"), (13, "pge"),
+    (15, "cmov"), (16, "pat"), (17, "pse36"), (19, "clflush"), (23, "mmx"), (24, "fxsr"), (25, "sse"),
+    (26, "sse2"), (28, "htt"),
+];
+/// Features of leaf 1 in ecx, by bit
+const FEATURES_ECX: [(u32, &'static str); 16] = [
+    (0, "sse3"), (1, "pclmulqdq"), (9, "ssse3"), (12, "fma"), (13, "cx16"), (19, "sse4_1"), (20, "sse4_2"),
+    (21, "x2apic"), (22, "movbe"), (23, "popcnt"), (25, "aes"), (26, "xsave"), (28, "avx"), (29, "f16c"),
+    (30, "rdrand"), (31, "hypervisor"),
+];
+/// Features of leaf 7 in ebx, by bit
+const FEATURES_7_EBX: [(u32, &'static str); 8] = [
+    (3, "bmi1"), (5, "avx2"), (8, "bmi2"), (9, "erms"), (16, "avx512f"), (18, "rdseed"), (19, "adx"), (29, "sha"),
+];
+/// Extended features of leaf 0x80000001 in edx, by bit
+const FEATURES_EXT_EDX: [(u32, &'static str); 2] = [(20, "nx"), (29, "lm")];
+
+/// Features that need the SSE registers, which are saved with `fxsave`
+const SSE_FEATURES: [&'static str; 9] = ["sse", "sse2", "sse3", "ssse3", "sse4_1", "sse4_2", "pclmulqdq", "aes", "sha"];
+/// Features that need the AVX registers, which are saved with `xsave`
+const AVX_FEATURES: [&'static str; 5] = ["avx", "avx2", "avx512f", "fma", "f16c"];
+
+/// Run `cpuid` for a leaf and subleaf, returning eax, ebx, ecx, and edx
+pub fn cpuid(leaf: u32, subleaf: u32) -> (u32, u32, u32, u32) {
+    let eax: u32;
+    let ebx: u32;
+    let ecx: u32;
+    let edx: u32;
+    unsafe {
+        asm!("cpuid"
+            : "={eax}"(eax), "={ebx}"(ebx), "={ecx}"(ecx), "={edx}"(edx)
+            : "{eax}"(leaf), "{ecx}"(subleaf)
+            :
+            : "intel", "volatile");
+    }
+    (eax, ebx, ecx, edx)
+}
+
+/// `cpuid` exists if the ID flag can be changed
+#[cfg(target_arch = "x86")]
+fn has_cpuid() -> bool {
+    let flags: usize;
+    let toggled: usize;
+    unsafe {
+        asm!("pushfd ; pop $0" : "=r"(flags) : : "memory" : "intel", "volatile");
+        asm!("push $1 ; popfd ; pushfd ; pop $0" : "=r"(toggled) : "r"(flags ^ 1 << 21) : "memory" : "intel", "volatile");
+        asm!("push $0 ; popfd" : : "r"(flags) : "memory" : "intel", "volatile");
+    }
+    (flags ^ toggled) & 1 << 21 == 1 << 21
+}
+
+#[cfg(target_arch = "x86_64")]
+fn has_cpuid() -> bool {
+    true
+}
+
+/// A description of the processor
+pub struct CpuInfo {
+    pub vendor: String,
+    pub brand: String,
+    pub family: u32,
+    pub model: u32,
+    pub stepping: u32,
+    /// Cores in the package
+    pub cores: u32,
+    /// Logical processors in the package, more than the cores with hyperthreading
+    pub threads: u32,
+    /// The sizes of the caches in bytes, as level 1 data, level 1 instruction, level 2, and level 3, 0 if unknown
+    pub caches: [usize; 4],
+    /// The size of a cache line in bytes, 0 if unknown
+    pub cache_line: usize,
+    /// The features that can be used
+    pub features: Vec<&'static str>,
+}
+
+impl CpuInfo {
+    /// Describe the processor, after the floating point unit was set up
+    pub fn new() -> CpuInfo {
+        let mut info = CpuInfo {
+            vendor: String::new(),
+            brand: String::new(),
+            family: 0,
+            model: 0,
+            stepping: 0,
+            cores: 1,
+            threads: 1,
+            caches: [0; 4],
+            cache_line: 0,
+            features: Vec::new(),
+        };
+
+        if ! has_cpuid() {
+            return info;
+        }
+
+        let (max_leaf, vendor_b, vendor_c, vendor_d) = cpuid(0, 0);
+        let mut vendor = [0; 12];
+        for i in 0..4 {
+            vendor[i] = (vendor_b >> (i * 8)) as u8;
+            vendor[i + 4] = (vendor_d >> (i * 8)) as u8;
+            vendor[i + 8] = (vendor_c >> (i * 8)) as u8;
+        }
+        info.vendor = String::from_utf8_lossy(&vendor).into_owned();
+
+        let (max_ext, _, _, _) = cpuid(0x80000000, 0);
+
+        let mut osxsave = false;
+        if max_leaf >= 1 {
+            let (eax, ebx, ecx, edx) = cpuid(1, 0);
+            let base_family = (eax >> 8) & 0xF;
+            let base_model = (eax >> 4) & 0xF;
+            info.family = if base_family == 0xF { base_family + ((eax >> 20) & 0xFF) } else { base_family };
+            info.model = if base_family == 0x6 || base_family == 0xF {
+                ((eax >> 12) & 0xF0) | base_model
+            } else {
+                base_model
+            };
+            info.stepping = eax & 0xF;
+
+            if edx & 1 << 28 == 1 << 28 {
+                info.threads = (ebx >> 16) & 0xFF;
+            }
+
+            push_features(&mut info.features, &FEATURES_EDX, edx);
+            push_features(&mut info.features, &FEATURES_ECX, ecx);
+            osxsave = ecx & 1 << 27 == 1 << 27;
+        }
+        if max_leaf >= 7 {
+            let (_, ebx, _, _) = cpuid(7, 0);
+            push_features(&mut info.features, &FEATURES_7_EBX, ebx);
+        }
+        if max_ext >= 0x80000001 {
+            let (_, _, _, edx) = cpuid(0x80000001, 0);
+            push_features(&mut info.features, &FEATURES_EXT_EDX, edx);
+        }
+
+        //The SSE registers are only saved if the boot code enabled fxsave, and the AVX registers if xsave was
+        //enabled, which is shown as OSXSAVE
+        if ! unsafe { fpu::FXSR } {
+            info.features.retain(|feature| ! SSE_FEATURES.contains(feature));
+        }
+        if ! osxsave {
+            info.features.retain(|feature| ! AVX_FEATURES.contains(feature));
+        }
+
+        if max_ext >= 0x80000004 {
+            let mut brand = [0; 48];
+            for leaf in 0..3 {
+                let (eax, ebx, ecx, edx) = cpuid(0x80000002 + leaf as u32, 0);
+                for (j, register) in [eax, ebx, ecx, edx].iter().enumerate() {
+                    for i in 0..4 {
+                        brand[leaf * 16 + j * 4 + i] = (register >> (i * 8)) as u8;
+                    }
+                }
+            }
+            let len = brand.iter().position(|&b| b == 0).unwrap_or(brand.len());
+            info.brand = String::from_utf8_lossy(&brand[.. len]).trim().to_string();
+        }
+
+        if info.vendor == "GenuineIntel" && max_leaf >= 4 {
+            //Each subleaf of leaf 4 describes a cache, until one of type 0
+            for subleaf in 0..16 {
+                let (eax, ebx, ecx, _) = cpuid(4, subleaf);
+                let kind = eax & 0x1F;
+                if kind == 0 {
+                    break;
+                }
+                if subleaf == 0 {
+                    info.cores = (eax >> 26) + 1;
+                }
+
+                let level = (eax >> 5) & 0x7;
+                let line = (ebx & 0xFFF) as usize + 1;
+                let size = (((ebx >> 22) + 1) * (((ebx >> 12) & 0x3FF) + 1) * (ecx + 1)) as usize * line;
+                let index = match (level, kind) {
+                    (1, 1) => 0,
+                    (1, 2) => 1,
+                    (2, _) => 2,
+                    (3, _) => 3,
+                    _ => continue
+                };
+                info.caches[index] = size;
+                info.cache_line = line;
+            }
+        } else if max_ext >= 0x80000006 {
+            let (_, _, l1d, l1i) = cpuid(0x80000005, 0);
+            let (_, _, l2, l3) = cpuid(0x80000006, 0);
+            info.caches = [(l1d >> 24) as usize * 1024, (l1i >> 24) as usize * 1024,
+                           (l2 >> 16) as usize * 1024, (l3 >> 18) as usize * 512 * 1024];
+            info.cache_line = (l1d & 0xFF) as usize;
+
+            if max_ext >= 0x80000008 {
+                let (_, _, ecx, _) = cpuid(0x80000008, 0);
+                info.cores = (ecx & 0xFF) + 1;
+            }
+        }
+
+        if info.threads < info.cores {
+            info.threads = info.cores;
+        }
+
+        info
+    }
+
+    /// The description as `key=value` lines, with the features separated by spaces
+    ///
+    /// `online` is the number of processors the kernel runs on, which can be less than the processors there are.
+    pub fn to_string(&self) -> String {
+        let mut features = String::new();
+        for feature in self.features.iter() {
+            if ! features.is_empty() {
+                features.push(' ');
+            }
+            features.push_str(feature);
+        }
+
+        format!("vendor={}\nbrand={}\nfamily={}\nmodel={}\nstepping={}\nonline={}\ncores={}\nthreads={}\n\
+                 l1d_cache={}\nl1i_cache={}\nl2_cache={}\nl3_cache={}\ncache_line={}\nfeatures={}\n",
+                self.vendor, self.brand, self.family, self.model, self.stepping, 1, self.cores, self.threads,
+                self.caches[0], self.caches[1], self.caches[2], self.caches[3], self.cache_line, features)
+    }
+}
+
+/// Add the features of the bits that are set in a register
+fn push_features(features: &mut Vec<&'static str>, names: &[(u32, &'static str)], register: u32) {
+    for &(bit, name) in names.iter() {
+        if register & 1 << bit == 1 << bit {
+            features.push(name);
+        }
+    }
+}
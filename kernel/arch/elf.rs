@@ -17,6 +17,10 @@ mod arch;
 #[path="x86_64/elf.rs"]
 mod arch;
 
+/// 32-bit executables, which a 64-bit kernel runs in compatibility mode
+#[path="x86/elf.rs"]
+mod elf32;
+
 /// An ELF executable
 pub struct Elf<'a> {
     pub data: &'a [u8],
@@ -29,14 +33,19 @@ impl<'a> Elf<'a> {
             Err(format!("Elf: Not enough data: {} < {}", data.len(), mem::size_of::<ElfHeader>()))
         } else if data.get_slice(..4) != b"\x7FELF" {
             Err(format!("Elf: Invalid magic: {:?} != {:?}", data.get_slice(..4), b"\x7FELF"))
-        } else if data.get(4) != Some(&ELF_CLASS) {
+        } else if data.get(4) != Some(&ELF_CLASS) && ! (cfg!(target_arch = "x86_64") && data.get(4) == Some(&elf32::ELF_CLASS)) {
             Err(format!("Elf: Invalid architecture: {:?} != {:?}", data.get(4), Some(&ELF_CLASS)))
         } else {
             Ok(Elf { data: data })
         }
     }
 
-    /// Debug
+    /// Is this a 32-bit executable on a 64-bit kernel, which runs in compatibility mode
+    pub fn compat(&self) -> bool {
+        cfg!(target_arch = "x86_64") && self.data.get(4) == Some(&elf32::ELF_CLASS)
+    }
+
+    /// Debug, for executables of the native class
     pub unsafe fn d(&self) {
         debug::d("Debug ELF\n");
         let header = &*(self.data.as_ptr() as *const ElfHeader);
@@ -287,26 +296,94 @@ impl<'a> Elf<'a> {
         debug::dl();
     }
 
-    pub unsafe fn load_segment(&self) -> Vec<ElfSegment> {
+    /// All program headers, converted to the native class
+    unsafe fn segments(&self) -> Vec<ElfSegment> {
         let mut segments = Vec::new();
 
-        let header = &*(self.data.as_ptr() as usize as *const ElfHeader);
-
-        for i in 0..header.ph_len {
-            let segment = ptr::read((self.data.as_ptr() as usize + header.ph_off as usize + i as usize * header.ph_ent_len as usize) as *const ElfSegment);
+        if self.compat() {
+            let header = &*(self.data.as_ptr() as usize as *const elf32::ElfHeader);
+
+            for i in 0..header.ph_len {
+                let segment = ptr::read((self.data.as_ptr() as usize + header.ph_off as usize + i as usize * header.ph_ent_len as usize) as *const elf32::ElfSegment);
+
+                segments.push(ElfSegment {
+                    _type: segment._type,
+                    flags: segment.flags,
+                    off: segment.off as _,
+                    vaddr: segment.vaddr as _,
+                    paddr: segment.paddr as _,
+                    file_len: segment.file_len as _,
+                    mem_len: segment.mem_len as _,
+                    align: segment.align as _,
+                });
+            }
+        } else {
+            let header = &*(self.data.as_ptr() as usize as *const ElfHeader);
 
-            if segment._type == 1 {
-                segments.push(segment);
+            for i in 0..header.ph_len {
+                segments.push(ptr::read((self.data.as_ptr() as usize + header.ph_off as usize + i as usize * header.ph_ent_len as usize) as *const ElfSegment));
             }
         }
 
         segments
     }
 
+    pub unsafe fn load_segment(&self) -> Vec<ElfSegment> {
+        let mut segments = self.segments();
+        segments.retain(|segment| segment._type == 1);
+        segments
+    }
+
     /// Get the entry field of the header
     pub unsafe fn entry(&self) -> usize {
-        let header = &*(self.data.as_ptr() as usize as *const ElfHeader);
-        header.entry as usize
+        if self.compat() {
+            let header = &*(self.data.as_ptr() as usize as *const elf32::ElfHeader);
+            header.entry as usize
+        } else {
+            let header = &*(self.data.as_ptr() as usize as *const ElfHeader);
+            header.entry as usize
+        }
+    }
+
+    /// Is this a Linux executable, by its OS ABI or a GNU ABI note for Linux
+    pub unsafe fn linux(&self) -> bool {
+        // The OS ABI is at the same place in every class
+        if self.data.get(7) == Some(&3) {
+            return true;
+        }
+
+        for segment in self.segments().iter() {
+            // PT_NOTE
+            if segment._type == 4 {
+                let notes = self.data.get_slice(segment.off as usize .. (segment.off as usize + segment.file_len as usize));
+                let mut off = 0;
+                while off + 12 <= notes.len() {
+                    let word = |i: usize| notes.get_slice(i .. i + 4).iter().rev().fold(0, |n, &b| n << 8 | b as usize);
+                    let name_len = word(off);
+                    let desc_len = word(off + 4);
+                    let kind = word(off + 8);
+                    let name_off = off + 12;
+                    let desc_off = match name_len.checked_add(3).and_then(|len| name_off.checked_add(len/4 * 4)) {
+                        Some(desc_off) => desc_off,
+                        None => break
+                    };
+                    // Stop at lengths that wrap around, rather than walking the same notes forever
+                    let next = match desc_len.checked_add(3).and_then(|len| desc_off.checked_add(len/4 * 4)) {
+                        Some(next) if next > off => next,
+                        _ => break
+                    };
+
+                    // NT_GNU_ABI_TAG, with the OS first
+                    if kind == 1 && desc_len >= 4 && notes.get_slice(name_off .. name_off + name_len) == b"GNU\0" && desc_off + 4 <= notes.len() {
+                        return word(desc_off) == 0;
+                    }
+
+                    off = next;
+                }
+            }
+        }
+
+        false
     }
 
     /// ELF symbol
@@ -0,0 +1,45 @@
+//! The floating point registers of contexts
+//!
+//! The registers are saved with `fxsave` where the processor has it, which includes the SSE registers. Older
+//! processors save the floating point registers with `fnsave` instead, and without a floating point unit there are
+//! none to save, and floating point instructions trap. Processes are built for the floating point unit without SSE
+//! on 32-bit x86, so they run on either.
+
+/// There is a floating point unit, found at boot
+pub static mut FPU: bool = true;
+/// The floating point and SSE registers are saved with `fxsave`, found at boot
+pub static mut FXSR: bool = true;
+
+/// Find what the boot code enabled, as the floating point unit and `fxsave` are only enabled where they exist
+pub unsafe fn init() {
+    let cr0: usize;
+    asm!("mov $0, cr0"
+        : "=r"(cr0)
+        :
+        : "memory"
+        : "intel", "volatile");
+    let cr4: usize;
+    asm!("mov $0, cr4"
+        : "=r"(cr4)
+        :
+        : "memory"
+        : "intel", "volatile");
+
+    //Emulation is set when there is no floating point unit
+    FPU = cr0 & 1 << 2 == 0;
+    //OSFXSR is set when the processor has fxsave
+    FXSR = FPU && cr4 & 1 << 9 == 1 << 9;
+}
+
+/// The way the floating point registers are saved, for the boot log
+pub fn name() -> &'static str {
+    unsafe {
+        if FXSR {
+            "fxsave"
+        } else if FPU {
+            "fnsave"
+        } else {
+            "none"
+        }
+    }
+}
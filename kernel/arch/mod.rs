@@ -1,7 +1,11 @@
 pub mod context;
+pub mod cpu;
 pub mod elf;
+pub mod fpu;
 pub mod intex;
 pub mod memory;
 pub mod paging;
 pub mod regs;
+pub mod swap;
+pub mod timepage;
 pub mod tss;
@@ -0,0 +1,291 @@
+//! Swap space for the memory of contexts that are not running
+//!
+//! A swap device begins with a header page. The header starts with the magic `REDOXSWP`, followed
+//! by the number of pages in the swap area as a little endian `u64`. Pages follow the header.
+//!
+//! Memory is swapped out a whole `ContextMemory` at a time, into a contiguous run of pages, from the
+//! context that has gone the longest without running. It is swapped back in when its context is
+//! next switched to. Memory pinned with `mlock` is never swapped out.
+
+use alloc::arc::Arc;
+use alloc::boxed::Box;
+
+use arch::context::{Context, ContextMemory, ContextZone};
+use arch::memory::{self, CLUSTER_SIZE};
+use arch::paging::Page;
+
+use collections::Vec;
+
+use core::{cmp, slice};
+use core::cmp::Ordering;
+
+use disk::Disk;
+
+use sync::Intex;
+
+use system::error::{Error, Result, EIO, ENOMEM};
+
+/// The magic at the start of a swap device
+pub const SWAP_MAGIC: &'static [u8; 8] = b"REDOXSWP";
+
+/// The number of disk blocks in a page
+const PAGE_BLOCKS: u64 = (CLUSTER_SIZE / 512) as u64;
+
+/// A swap device
+pub struct Swap {
+    disk: Arc<Intex<Box<Disk>>>,
+    /// Which pages of the swap area are in use
+    pages: Vec<bool>,
+}
+
+impl Swap {
+    /// Open a swap device, returning None if it does not have a swap header
+    pub fn open(disk: Arc<Intex<Box<Disk>>>) -> Option<Swap> {
+        let mut header = [0; 512];
+        if disk.lock().read(0, &mut header).is_err() || &header[.. 8] != SWAP_MAGIC {
+            return None;
+        }
+
+        let mut count = 0;
+        for i in 0..8 {
+            count |= (header[8 + i] as u64) << (i * 8);
+        }
+
+        let disk_pages = disk.lock().size() / CLUSTER_SIZE as u64;
+        if disk_pages > 0 {
+            count = cmp::min(count, disk_pages - 1);
+        } else {
+            count = 0;
+        }
+
+        Some(Swap {
+            disk: disk,
+            pages: vec![false; count as usize]
+        })
+    }
+
+    /// The size of the swap area in bytes
+    pub fn size(&self) -> usize {
+        self.pages.len() * CLUSTER_SIZE
+    }
+
+    /// The number of bytes of swap in use
+    pub fn used(&self) -> usize {
+        self.pages.iter().filter(|page| **page).count() * CLUSTER_SIZE
+    }
+
+    fn alloc(&mut self, count: usize) -> Option<usize> {
+        let mut start = 0;
+        let mut run = 0;
+        for i in 0..self.pages.len() {
+            if self.pages[i] {
+                run = 0;
+            } else {
+                if run == 0 {
+                    start = i;
+                }
+                run += 1;
+                if run >= count {
+                    for page in start..start + count {
+                        self.pages[page] = true;
+                    }
+                    return Some(start);
+                }
+            }
+        }
+        None
+    }
+
+    fn free(&mut self, start: usize, count: usize) {
+        for page in start..cmp::min(start + count, self.pages.len()) {
+            self.pages[page] = false;
+        }
+    }
+
+    /// Read or write physical memory, one page at a time, mapping each page for the kernel
+    unsafe fn transfer(&mut self, start: usize, physical_address: usize, count: usize, write: bool) -> Result<()> {
+        for i in 0..count {
+            let address = physical_address + i * CLUSTER_SIZE;
+            let block = (1 + start + i) as u64 * PAGE_BLOCKS;
+
+            let mut page = Page::new(address);
+            let old = page.entry_data();
+            page.map_kernel_write(address);
+
+            let result = if write {
+                self.disk.lock().write(block, slice::from_raw_parts(address as *const u8, CLUSTER_SIZE))
+            } else {
+                self.disk.lock().read(block, slice::from_raw_parts_mut(address as *mut u8, CLUSTER_SIZE))
+            };
+
+            page.set_entry_data(old);
+            page.flush();
+
+            match result {
+                Ok(size) if size == CLUSTER_SIZE => (),
+                Ok(_) => return Err(Error::new(EIO)),
+                Err(err) => return Err(err)
+            }
+        }
+        Ok(())
+    }
+
+    /// Write memory out to swap and free its physical memory
+    pub unsafe fn page_out(&mut self, mem: &mut ContextMemory) -> Result<()> {
+        if ! mem.allocated || mem.locked || mem.swap.is_some() || mem.physical_address == 0 {
+            return Ok(());
+        }
+
+        let count = (mem.virtual_size + CLUSTER_SIZE - 1) / CLUSTER_SIZE;
+        let start = try!(self.alloc(count).ok_or(Error::new(ENOMEM)));
+
+        if let Err(err) = self.transfer(start, mem.physical_address, count, true) {
+            self.free(start, count);
+            return Err(err);
+        }
+
+        memory::unalloc(mem.physical_address);
+        mem.physical_address = 0;
+        mem.swap = Some(start);
+
+        Ok(())
+    }
+
+    /// Read memory back in from swap, the caller must map it afterwards
+    pub unsafe fn page_in(&mut self, mem: &mut ContextMemory) -> Result<()> {
+        if let Some(start) = mem.swap {
+            let count = (mem.virtual_size + CLUSTER_SIZE - 1) / CLUSTER_SIZE;
+
+            let physical_address = memory::alloc_aligned(count * CLUSTER_SIZE, 4096);
+            if physical_address == 0 {
+                return Err(Error::new(ENOMEM));
+            }
+
+            if let Err(err) = self.transfer(start, physical_address, count, false) {
+                memory::unalloc(physical_address);
+                return Err(err);
+            }
+
+            self.free(start, count);
+            mem.physical_address = physical_address;
+            mem.swap = None;
+        }
+
+        Ok(())
+    }
+
+    /// Release the swap used by memory that is being dropped
+    pub fn release(&mut self, mem: &mut ContextMemory) {
+        if let Some(start) = mem.swap.take() {
+            let count = (mem.virtual_size + CLUSTER_SIZE - 1) / CLUSTER_SIZE;
+            self.free(start, count);
+        }
+    }
+}
+
+/// Check if a scheme has captured any part of a physical range into its address space
+unsafe fn captured(contexts: &[*mut Context], physical_address: usize, size: usize) -> bool {
+    for &context in contexts.iter() {
+        for mem in (*(*context).mmap.get()).memory.iter() {
+            if ! mem.allocated && mem.physical_address < physical_address + size && physical_address < mem.physical_address + mem.virtual_size {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Swap out the memory of a zone, returning the number of bytes freed
+unsafe fn page_out_zone(swap: &mut Swap, zone: &mut ContextZone, contexts: &[*mut Context]) -> usize {
+    let mut freed = 0;
+    for mem in zone.memory.iter_mut() {
+        if mem.allocated && ! mem.locked && mem.swap.is_none() && ! captured(contexts, mem.physical_address, mem.virtual_size) {
+            let size = mem.virtual_size;
+            if swap.page_out(mem).is_ok() {
+                freed += size;
+            }
+        }
+    }
+    freed
+}
+
+/// Swap out the memory of the context that has gone the longest without running
+///
+/// Contexts sharing memory with the current context are skipped. Returns the number of bytes freed,
+/// which is 0 if there is no swap or nothing left to swap out.
+pub unsafe fn reclaim() -> usize {
+    let mut swap_option = ::env().swap.lock();
+    let swap = match *swap_option {
+        Some(ref mut swap) => swap,
+        None => return 0
+    };
+
+    let mut contexts = ::env().contexts.lock();
+    let current_i = contexts.i;
+
+    let mut pointers: Vec<*mut Context> = Vec::new();
+    for context in contexts.iter_mut() {
+        pointers.push(&mut **context);
+    }
+
+    let current = match pointers.get(current_i) {
+        Some(current) => *current,
+        None => return 0
+    };
+
+    let mut candidates: Vec<*mut Context> = Vec::new();
+    for &context in pointers.iter() {
+        if context != current && ! (*context).exited && (*context).kernel_stack > 0
+           && (*context).image.get() != (*current).image.get()
+           && (*context).heap.get() != (*current).heap.get()
+           && (*context).mmap.get() != (*current).mmap.get() {
+            candidates.push(context);
+        }
+    }
+    candidates.sort_by(|a, b| (**a).last_run.partial_cmp(&(**b).last_run).unwrap_or(Ordering::Equal));
+
+    for &context in candidates.iter() {
+        let mut freed = 0;
+        if let Some(ref mut stack) = (*context).stack {
+            if stack.allocated && ! stack.locked && stack.swap.is_none() && ! captured(&pointers, stack.physical_address, stack.virtual_size) {
+                let size = stack.virtual_size;
+                if swap.page_out(stack).is_ok() {
+                    freed += size;
+                }
+            }
+        }
+        freed += page_out_zone(swap, &mut *(*context).image.get(), &pointers);
+        freed += page_out_zone(swap, &mut *(*context).heap.get(), &pointers);
+        freed += page_out_zone(swap, &mut *(*context).mmap.get(), &pointers);
+
+        if freed > 0 {
+            debugln!("SWAP: paged out {} KB from {}: {}", freed / 1024, (*context).pid, (*context).name);
+            return freed;
+        }
+    }
+
+    0
+}
+
+/// Allocate memory for a context, swapping out other contexts while memory is exhausted
+pub unsafe fn alloc_user(size: usize) -> usize {
+    loop {
+        let physical_address = memory::alloc_aligned(size, 4096);
+        if physical_address > 0 || reclaim() == 0 {
+            return physical_address;
+        }
+    }
+}
+
+/// Reallocate memory for a context, swapping out other contexts while memory is exhausted
+pub unsafe fn realloc_user(ptr: usize, size: usize) -> usize {
+    if size > memory::alloc_size(ptr) {
+        //Make sure the space is available first, since a failed reallocation frees the old memory
+        let reserve = alloc_user(size);
+        if reserve == 0 {
+            return 0;
+        }
+        memory::unalloc(reserve);
+    }
+    memory::realloc_aligned(ptr, size, 4096)
+}
@@ -0,0 +1,75 @@
+use arch::memory;
+use arch::paging::Page;
+
+use common::time::Duration;
+
+use core::ptr;
+
+use system::timepage::{rdtsc, TimePage, TIME_PAGE_ADDR};
+
+/// The physical address of the time page, 0 before it is allocated
+static mut TIME_PAGE: usize = 0;
+
+/// Allocate the time page, which is shared read only with every context
+pub unsafe fn init() {
+    let physical_address = memory::alloc_aligned(4096, 4096);
+    if physical_address > 0 {
+        ::memset(physical_address as *mut u8, 0, 4096);
+        TIME_PAGE = physical_address;
+    }
+}
+
+/// Update the clocks on the time page, called on every timer tick
+pub unsafe fn update(monotonic: Duration, realtime: Duration, tick: Duration) {
+    if TIME_PAGE == 0 {
+        return;
+    }
+
+    let page = TIME_PAGE as *mut TimePage;
+    let tsc = rdtsc();
+    let sequence = ptr::read_volatile(&(*page).sequence);
+
+    ptr::write_volatile(&mut (*page).sequence, sequence.wrapping_add(1));
+    let last = ptr::read_volatile(&(*page).tsc);
+    if last > 0 && tsc > last {
+        ptr::write_volatile(&mut (*page).tsc_per_tick, tsc - last);
+    }
+    ptr::write_volatile(&mut (*page).tsc, tsc);
+    ptr::write_volatile(&mut (*page).tick_nanos, tick.nanos as u32);
+    ptr::write_volatile(&mut (*page).monotonic_secs, monotonic.secs);
+    ptr::write_volatile(&mut (*page).monotonic_nanos, monotonic.nanos);
+    ptr::write_volatile(&mut (*page).realtime_secs, realtime.secs);
+    ptr::write_volatile(&mut (*page).realtime_nanos, realtime.nanos);
+    ptr::write_volatile(&mut (*page).sequence, sequence.wrapping_add(2));
+}
+
+/// The nanoseconds since the time stamp counter read `start`, 0 before the cycles of a tick have been measured
+pub fn nanos_since(start: u64) -> u64 {
+    unsafe {
+        if TIME_PAGE == 0 {
+            return 0;
+        }
+
+        let page = TIME_PAGE as *const TimePage;
+        let tsc_per_tick = ptr::read_volatile(&(*page).tsc_per_tick);
+        let tick_nanos = ptr::read_volatile(&(*page).tick_nanos) as u64;
+        if tsc_per_tick == 0 {
+            return 0;
+        }
+
+        let elapsed = rdtsc().wrapping_sub(start);
+        elapsed / tsc_per_tick * tick_nanos + elapsed % tsc_per_tick * tick_nanos / tsc_per_tick
+    }
+}
+
+/// Map the time page into the current context
+pub unsafe fn map() {
+    if TIME_PAGE > 0 {
+        Page::new(TIME_PAGE_ADDR).map_user_read(TIME_PAGE);
+    }
+}
+
+/// Unmap the time page from the current context
+pub unsafe fn unmap() {
+    Page::new(TIME_PAGE_ADDR).map_kernel_write(TIME_PAGE_ADDR);
+}
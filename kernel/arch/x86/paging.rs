@@ -201,6 +201,9 @@ pub const PAGE_TABLE_SIZE: usize = 1024;
 pub const PAGE_ENTRY_SIZE: usize = 4;
 pub const PAGE_SIZE: usize = 4096;
 
+/// The size of a large page, mapped directly by a page directory entry
+pub const LARGE_PAGE_SIZE: usize = PAGE_TABLE_SIZE * PAGE_SIZE;
+
 pub const PAGE_DIRECTORY: usize = 0x1000000;
 pub const PAGE_TABLES: usize = PAGE_DIRECTORY + PAGE_TABLE_SIZE * PAGE_ENTRY_SIZE;
 pub const PAGE_END: usize = PAGE_TABLES + PAGE_TABLE_SIZE * PAGE_TABLE_SIZE * PAGE_ENTRY_SIZE;
@@ -241,6 +244,76 @@ impl Page {
         Page { virtual_address: virtual_address }
     }
 
+    /// Use large pages for the 4 MiB regions overlapping `start` to `end`, where every page is
+    /// mapped to itself for the kernel. Returns the number of large pages used
+    ///
+    /// The page tables are left in place, so a large page can be split again when one of its pages
+    /// is remapped
+    pub unsafe fn map_large(start: usize, end: usize) -> usize {
+        let edx: usize;
+        asm!("cpuid"
+            : "={edx}"(edx)
+            : "{eax}"(1)
+            : "ebx", "ecx"
+            : "intel", "volatile");
+
+        //Page size extensions are required
+        if edx & 1 << 3 == 0 {
+            return 0;
+        }
+
+        let mut cr4: usize;
+        asm!("mov $0, cr4"
+            : "=r"(cr4)
+            :
+            : "memory"
+            : "intel", "volatile");
+        cr4 |= 1 << 4;
+        asm!("mov cr4, $0"
+            :
+            : "r"(cr4)
+            : "memory"
+            : "intel", "volatile");
+
+        let mut count = 0;
+        for table_i in start / LARGE_PAGE_SIZE .. (end + LARGE_PAGE_SIZE - 1) / LARGE_PAGE_SIZE {
+            let mut identity = true;
+            for entry_i in 0..PAGE_TABLE_SIZE {
+                let addr = (table_i * PAGE_TABLE_SIZE + entry_i) * PAGE_SIZE;
+                if Page::new(addr).entry_data() & !(PF_ACCESSED | PF_DIRTY) != addr | PF_WRITE | PF_PRESENT {
+                    identity = false;
+                    break;
+                }
+            }
+
+            if identity {
+                ptr::write((PAGE_DIRECTORY + table_i * PAGE_ENTRY_SIZE) as *mut usize,
+                           (table_i * LARGE_PAGE_SIZE) | PF_SIZE | PF_WRITE | PF_PRESENT);
+                count += 1;
+            }
+        }
+
+        //Flush all pages
+        asm!("mov cr3, $0"
+            :
+            : "r"(PAGE_DIRECTORY)
+            : "memory"
+            : "intel", "volatile");
+
+        count
+    }
+
+    /// Go back to the page table if this page is in a large page
+    unsafe fn split_large(&self) {
+        let directory_entry = (PAGE_DIRECTORY + (self.virtual_address / LARGE_PAGE_SIZE) * PAGE_ENTRY_SIZE) as *mut usize;
+        if ptr::read(directory_entry) & PF_SIZE == PF_SIZE {
+            let table_i = self.virtual_address / LARGE_PAGE_SIZE;
+            ptr::write(directory_entry,
+                       (PAGE_TABLES + table_i * PAGE_TABLE_SIZE * PAGE_ENTRY_SIZE) |
+                       PF_USER | PF_WRITE | PF_PRESENT);
+        }
+    }
+
     /// Get the entry address
     fn entry_address(&self) -> usize {
         let page = self.virtual_address / PAGE_SIZE;
@@ -265,7 +338,13 @@ impl Page {
     }
 
     /// Set the current entry data
+    ///
+    /// Kernel mappings of a page to itself stay in a large page, which is always writeable, so that
+    /// temporary mappings made by the allocator do not split it
     pub unsafe fn set_entry_data(&mut self, data: usize) {
+        if data & PF_NONE != self.virtual_address & PF_NONE || data & (PF_USER | PF_PRESENT) != PF_PRESENT {
+            self.split_large();
+        }
         ptr::write(self.entry_address() as *mut usize, data)
     }
 
@@ -33,6 +33,9 @@ pub const PAGE_TABLE_SIZE: usize = 512;
 pub const PAGE_ENTRY_SIZE: usize = 8;
 pub const PAGE_SIZE: usize = 4096;
 
+/// The size of a large page, mapped directly by a page directory entry
+pub const LARGE_PAGE_SIZE: usize = PAGE_TABLE_SIZE * PAGE_SIZE;
+
 extern {
     static mut __bss_end: u8;
 }
@@ -98,6 +101,73 @@ impl Page {
         Page { virtual_address: virtual_address }
     }
 
+    /// Use large pages for the 2 MiB regions overlapping `start` to `end`, where every page is
+    /// mapped to itself for the kernel. Returns the number of large pages used
+    ///
+    /// The page tables are left in place, so a large page can be split again when one of its pages
+    /// is remapped
+    pub unsafe fn map_large(start: usize, end: usize) -> usize {
+        let mut count = 0;
+        for table_i in start / LARGE_PAGE_SIZE .. (end + LARGE_PAGE_SIZE - 1) / LARGE_PAGE_SIZE {
+            if table_i >= 4 * PAGE_TABLE_SIZE {
+                break;
+            }
+
+            let mut identity = true;
+            for entry_i in 0..PAGE_TABLE_SIZE {
+                let addr = (table_i * PAGE_TABLE_SIZE + entry_i) * PAGE_SIZE;
+                if Page::new(addr).entry_data() & !(PF_ACCESSED | PF_DIRTY) != addr | PF_WRITE | PF_PRESENT {
+                    identity = false;
+                    break;
+                }
+            }
+
+            if identity {
+                ptr::write((PAGE_DIRECTORIES + table_i * PAGE_ENTRY_SIZE) as *mut usize,
+                           (table_i * LARGE_PAGE_SIZE) | PF_SIZE | PF_WRITE | PF_PRESENT);
+                count += 1;
+            }
+        }
+
+        //Flush all pages
+        asm!("mov cr3, $0"
+            :
+            : "r"(PAGE_LEVEL_4)
+            : "memory"
+            : "intel", "volatile");
+
+        count
+    }
+
+    /// Go back to the page table if this page is in a large page
+    unsafe fn split_large(&self) {
+        let table_i = self.virtual_address / LARGE_PAGE_SIZE;
+        if table_i < 4 * PAGE_TABLE_SIZE {
+            let directory_entry = (PAGE_DIRECTORIES + table_i * PAGE_ENTRY_SIZE) as *mut usize;
+            if ptr::read(directory_entry) & PF_SIZE == PF_SIZE {
+                ptr::write(directory_entry,
+                           (PAGE_TABLES + table_i * PAGE_TABLE_SIZE * PAGE_ENTRY_SIZE) |
+                           PF_USER | PF_WRITE | PF_PRESENT);
+            }
+        }
+    }
+
+    /// Get the current entry data
+    unsafe fn entry_data(&self) -> usize {
+        ptr::read(self.entry_address() as *mut usize)
+    }
+
+    /// Set the current entry data
+    ///
+    /// Kernel mappings of a page to itself stay in a large page, which is always writeable, so that
+    /// temporary mappings made by the allocator do not split it
+    unsafe fn set_entry_data(&mut self, data: usize) {
+        if data & PF_NONE != self.virtual_address & PF_NONE || data & (PF_USER | PF_PRESENT) != PF_PRESENT {
+            self.split_large();
+        }
+        ptr::write(self.entry_address() as *mut usize, data)
+    }
+
     /// Get the entry address
     fn entry_address(&self) -> usize {
         let page = self.virtual_address / PAGE_SIZE;
@@ -128,35 +198,31 @@ impl Page {
 
     /// Map the memory page to a given physical memory address
     pub unsafe fn map_kernel_read(&mut self, physical_address: usize) {
-        ptr::write(self.entry_address() as *mut usize,
-                   (physical_address & PF_NONE) | PF_PRESENT); //present
+        self.set_entry_data((physical_address & PF_NONE) | PF_PRESENT); //present
         self.flush();
     }
 
     /// Map the memory page to a given physical memory address and allow userspace read access
     pub unsafe fn map_kernel_write(&mut self, physical_address: usize) {
-        ptr::write(self.entry_address() as *mut usize,
-                   (physical_address & PF_NONE) | PF_WRITE | PF_PRESENT); //Allow write, present
+        self.set_entry_data((physical_address & PF_NONE) | PF_WRITE | PF_PRESENT); //Allow write, present
         self.flush();
     }
 
     /// Map the memory page to a given physical memory address and allow userspace read access
     pub unsafe fn map_user_read(&mut self, physical_address: usize) {
-        ptr::write(self.entry_address() as *mut usize,
-                   (physical_address & PF_NONE) | PF_USER | PF_PRESENT); //Allow userspace, present
+        self.set_entry_data((physical_address & PF_NONE) | PF_USER | PF_PRESENT); //Allow userspace, present
         self.flush();
     }
 
     /// Map the memory page to a given physical memory address and allow userspace read/write access
     pub unsafe fn map_user_write(&mut self, physical_address: usize) {
-        ptr::write(self.entry_address() as *mut usize,
-                   (physical_address & PF_NONE) | PF_USER | PF_WRITE | PF_PRESENT); //Allow userspace, read/write, present
+        self.set_entry_data((physical_address & PF_NONE) | PF_USER | PF_WRITE | PF_PRESENT); //Allow userspace, read/write, present
         self.flush();
     }
 
     /// Unmap the memory page
     pub unsafe fn unmap(&mut self) {
-        ptr::write(self.entry_address() as *mut usize, 0);
+        self.set_entry_data(0);
         self.flush();
     }
 }
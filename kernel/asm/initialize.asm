@@ -7,16 +7,51 @@ initialize:
     and al, 11110011b
     or al, 00100010b
     mov cr0, eax
-    mov eax, cr4
-    or eax, 0x200
-    mov cr4, eax
     fninit
+    ;without an fpu the status word is not written, and floating point instructions are made to trap
+    mov word [.fpu_status], 0xFFFF
+    fnstsw [.fpu_status]
+    cmp word [.fpu_status], 0
+    je .fpu_done
+    mov eax, cr0
+    and al, 11111101b
+    or al, 00000100b
+    mov cr0, eax
+.fpu_done:
     ret
 
-.sse: ;enable sse
+.fpu_status: dw 0
+
+.sse: ;enable fxsave and sse, if the cpu has them
+    push ebx
+    ;cpuid is there if the id flag can be changed
+    pushfd
+    pop eax
+    mov ecx, eax
+    xor eax, 1 << 21
+    push eax
+    popfd
+    pushfd
+    pop eax
+    push ecx
+    popfd
+    xor eax, ecx
+    test eax, 1 << 21
+    jz .sse_done
+
+    mov eax, 1
+    cpuid
+    test edx, 1 << 24 ;fxsave and fxrstor
+    jz .sse_done
     mov eax, cr4
-    or ax, 0000011000000000b
+    or eax, 1 << 9
+    test edx, 1 << 25 ;sse
+    jz .sse_set
+    or eax, 1 << 10
+.sse_set:
     mov cr4, eax
+.sse_done:
+    pop ebx
     ret
 
 ;PIT Frequency
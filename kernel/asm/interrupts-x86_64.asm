@@ -92,7 +92,12 @@ idt:
 	istruc IDTEntry
 		at IDTEntry.offsetl, dw interrupts+(interrupts.second-interrupts.first)*i
 		at IDTEntry.selector, dw gdt.kernel_code
+	;The double fault runs on its own stack, set up in the TSS, as it often comes from overflowing the stack
+	%if i == 8
+		at IDTEntry.ist, db 1
+	%else
 		at IDTEntry.ist, db 0
+	%endif
 		at IDTEntry.attribute, db attrib.present | attrib.interrupt64
 		at IDTEntry.offsetm, dw 0
 		at IDTEntry.offseth, dd 0
@@ -126,12 +126,13 @@ long_mode:
 
     .user_data equ $ - gdt
     istruc GDTEntry
-        at GDTEntry.limitl, dw 0
+    ; The limit and size are ignored in long mode, but used by 32-bit programs in compatibility mode
+        at GDTEntry.limitl, dw 0xFFFF
         at GDTEntry.basel, dw 0
         at GDTEntry.basem, db 0
     ; AMD System Programming Manual states that the writeable bit is ignored in long mode, but ss can not be set to this descriptor without it
         at GDTEntry.attribute, db attrib.present | attrib.ring3 | attrib.user | attrib.writable
-        at GDTEntry.flags__limith, db 0
+        at GDTEntry.flags__limith, db 0xF | flags.granularity | flags.default_operand_size
         at GDTEntry.baseh, db 0
     iend
 
@@ -146,6 +147,17 @@ long_mode:
     iend
     dq 0 ;tss descriptors are extended to 16 Bytes
 
+    ; 32-bit code, for programs run in compatibility mode
+    .user_code32 equ $ - gdt
+    istruc GDTEntry
+        at GDTEntry.limitl, dw 0xFFFF
+        at GDTEntry.basel, dw 0
+        at GDTEntry.basem, db 0
+        at GDTEntry.attribute, db attrib.present | attrib.ring3 | attrib.user | attrib.code | attrib.readable
+        at GDTEntry.flags__limith, db 0xF | flags.granularity | flags.default_operand_size
+        at GDTEntry.baseh, db 0
+    iend
+
     .end equ $ - gdt
 
     struc TSS
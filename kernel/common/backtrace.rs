@@ -0,0 +1,150 @@
+//! Backtraces of the kernel stack, by following the chain of frame pointers
+//!
+//! The kernel is built with frame pointers, so the frame pointer of each function points to the frame pointer of its
+//! caller, followed by the return address into the caller. A backtrace only reads inside the stack it was given,
+//! stops at the first return address outside of kernel code, and does not allocate, so it can be taken from a
+//! panic, an exception, or for a context that is not running.
+
+use core::{mem, ptr};
+
+use arch::context::{Context, CONTEXT_STACK_SIZE};
+use arch::regs::Regs;
+
+use common::{debug, symbols};
+
+/// The most frames followed, in case a corrupt stack loops
+pub const MAX_FRAMES: usize = 64;
+
+const WORD: usize = mem::size_of::<usize>();
+
+#[cfg(target_arch = "x86")]
+fn frame_pointer() -> usize {
+    let bp: usize;
+    unsafe { asm!("mov $0, ebp" : "=r"(bp) : : : "intel", "volatile") };
+    bp
+}
+
+#[cfg(target_arch = "x86_64")]
+fn frame_pointer() -> usize {
+    let bp: usize;
+    unsafe { asm!("mov $0, rbp" : "=r"(bp) : : : "intel", "volatile") };
+    bp
+}
+
+/// A function on the stack
+#[derive(Copy, Clone, Debug)]
+pub struct Frame {
+    /// The return address into the function
+    pub address: usize,
+    /// The frame pointer of the function that was called by it
+    pub bp: usize,
+}
+
+impl Frame {
+    /// The name of the function and the offset of the return address into it
+    pub fn symbol(&self) -> Option<(&'static str, usize)> {
+        symbols::lookup(self.address)
+    }
+}
+
+/// The frames of a stack, from the innermost to the outermost
+pub struct Backtrace {
+    bp: usize,
+    stack_start: usize,
+    stack_end: usize,
+    depth: usize,
+}
+
+impl Backtrace {
+    /// Follow the frame pointers from `bp`, reading only from `stack_start` to `stack_end`
+    ///
+    /// The stack has to be mapped in the current address space.
+    pub unsafe fn new(bp: usize, stack_start: usize, stack_end: usize) -> Backtrace {
+        Backtrace {
+            bp: bp,
+            stack_start: stack_start,
+            stack_end: stack_end,
+            depth: 0,
+        }
+    }
+
+    /// The callers of this function
+    ///
+    /// This does not lock the contexts to find the bounds of the stack, so the walk is limited to the size of a
+    /// context stack above the frame pointer.
+    #[inline(never)]
+    pub fn current() -> Backtrace {
+        let bp = frame_pointer();
+        unsafe { Backtrace::new(bp, bp, bp.saturating_add(CONTEXT_STACK_SIZE)) }
+    }
+
+    /// The callers of the function that was interrupted, `None` if it was in userspace
+    pub fn from_regs(regs: &Regs) -> Option<Backtrace> {
+        if regs.cs & 3 == 3 {
+            None
+        } else {
+            Some(unsafe { Backtrace::new(regs.bp, regs.bp, regs.bp.saturating_add(CONTEXT_STACK_SIZE)) })
+        }
+    }
+
+    /// Where a context is in the kernel, from the frame pointer saved when it was switched out
+    ///
+    /// The context must not be the current one, as its saved registers are stale while it runs.
+    pub fn from_context(context: &Context) -> Backtrace {
+        let bp = context.regs.bp;
+        if context.kernel_stack > 0 {
+            unsafe { Backtrace::new(bp, context.kernel_stack, context.kernel_stack + CONTEXT_STACK_SIZE) }
+        } else {
+            unsafe { Backtrace::new(bp, bp, bp.saturating_add(CONTEXT_STACK_SIZE)) }
+        }
+    }
+
+    /// Print the frames and their functions to the debug console
+    pub fn print(self) {
+        debug::d("Backtrace:");
+        debug::dl();
+
+        for frame in self {
+            debug::d("    ");
+            debug::dh(frame.address);
+            if let Some((name, offset)) = frame.symbol() {
+                debug::d(" ");
+                debug::d(name);
+                debug::d("+");
+                debug::dh(offset);
+            }
+            debug::dl();
+        }
+    }
+}
+
+impl Iterator for Backtrace {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        if self.depth >= MAX_FRAMES || self.bp == 0 || self.bp % WORD != 0
+           || self.bp < self.stack_start || self.bp.saturating_add(2 * WORD) > self.stack_end {
+            return None;
+        }
+
+        let (next_bp, address) = unsafe { (ptr::read(self.bp as *const usize), ptr::read((self.bp + WORD) as *const usize)) };
+        if ! symbols::is_code(address) {
+            return None;
+        }
+
+        let frame = Frame {
+            address: address,
+            bp: self.bp,
+        };
+
+        //The stack grows down, so each caller has its frame above, anything else ends the chain
+        self.bp = if next_bp > self.bp {
+            next_bp
+        } else {
+            0
+        };
+        self.depth += 1;
+
+        Some(frame)
+    }
+}
@@ -0,0 +1,175 @@
+use collections::string::{String, ToString};
+
+use core::fmt;
+
+use common::time::Duration;
+
+const SECS_PER_MINUTE: i64 = 60;
+const SECS_PER_HOUR: i64 = 60 * SECS_PER_MINUTE;
+const SECS_PER_DAY: i64 = 24 * SECS_PER_HOUR;
+
+/// The largest UTC offset accepted, in seconds
+pub const MAX_UTC_OFFSET: i32 = 14 * 60 * 60;
+
+const WEEKDAYS: [&'static str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&'static str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun",
+                                    "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Check if a year of the Gregorian calendar has a 29th of February
+pub fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// The number of days in a month, from 1 to 12
+pub fn days_in_month(year: i64, month: u8) -> u8 {
+    match month {
+        2 => if is_leap_year(year) { 29 } else { 28 },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+/// A time of day broken down into calendar fields, in a fixed offset from UTC
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DateTime {
+    pub year: i64,
+    /// The month, from 1 to 12
+    pub month: u8,
+    /// The day of the month, from 1 to 31
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanos: i32,
+    /// The day of the week, where 0 is Sunday
+    pub weekday: u8,
+    /// The offset from UTC, in seconds
+    pub offset: i32,
+}
+
+impl DateTime {
+    /// Break down a time since the Unix epoch, shifted by an offset from UTC in seconds
+    pub fn from_duration(duration: Duration, offset: i32) -> DateTime {
+        let mut secs = duration.secs + offset as i64;
+        let mut nanos = duration.nanos;
+        if nanos < 0 {
+            secs -= 1;
+            nanos += 1000000000;
+        }
+
+        let mut days = secs / SECS_PER_DAY;
+        let mut time = secs % SECS_PER_DAY;
+        if time < 0 {
+            days -= 1;
+            time += SECS_PER_DAY;
+        }
+
+        // Civil from days, counting in 400 year eras that start on the 1st of March
+        let shifted = days + 719468;
+        let era = (if shifted >= 0 { shifted } else { shifted - 146096 }) / 146097;
+        let day_of_era = shifted - era * 146097;
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        // The epoch was a Thursday
+        let mut weekday = (days + 4) % 7;
+        if weekday < 0 {
+            weekday += 7;
+        }
+
+        DateTime {
+            year: year,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / SECS_PER_HOUR) as u8,
+            minute: (time % SECS_PER_HOUR / SECS_PER_MINUTE) as u8,
+            second: (time % SECS_PER_MINUTE) as u8,
+            nanos: nanos,
+            weekday: weekday as u8,
+            offset: offset,
+        }
+    }
+
+    /// The current time of day in UTC
+    pub fn utc() -> DateTime {
+        DateTime::from_duration(Duration::realtime(), 0)
+    }
+
+    /// The current time of day in the configured offset from UTC
+    pub fn local() -> DateTime {
+        let offset = *::env().utc_offset.lock();
+        DateTime::from_duration(Duration::realtime(), offset)
+    }
+
+    /// Format as ISO-8601, such as `2016-07-12T13:45:01+02:00`, or with `Z` for UTC
+    pub fn iso8601(&self) -> String {
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+                self.year, self.month, self.day,
+                self.hour, self.minute, self.second,
+                format_offset(self.offset, true))
+    }
+}
+
+impl fmt::Display for DateTime {
+    /// Show the time in the style of `date`, such as `Tue Jul 12 13:45:01 2016`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {:2} {:02}:{:02}:{:02} {}",
+               WEEKDAYS[self.weekday as usize % 7],
+               MONTHS[(self.month as usize + 11) % 12],
+               self.day, self.hour, self.minute, self.second, self.year)
+    }
+}
+
+/// Format an offset from UTC in seconds as `+HH:MM`, using `Z` for zero if `zulu` is set
+pub fn format_offset(offset: i32, zulu: bool) -> String {
+    if offset == 0 && zulu {
+        return "Z".to_string();
+    }
+    let sign = if offset < 0 { '-' } else { '+' };
+    let minutes = (offset as i64).abs() / SECS_PER_MINUTE;
+    format!("{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// Parse an offset from UTC, such as `+02:00`, `-0530`, `+2`, or `Z`, into seconds
+pub fn parse_offset(string: &str) -> Option<i32> {
+    let string = string.trim();
+    if string == "Z" || string == "z" {
+        return Some(0);
+    }
+
+    let (sign, rest) = if string.starts_with('+') {
+        (1, &string[1..])
+    } else if string.starts_with('-') {
+        (-1, &string[1..])
+    } else {
+        (1, string)
+    };
+
+    let (hours, minutes) = if let Some(colon) = rest.find(':') {
+        (&rest[.. colon], &rest[colon + 1 ..])
+    } else if rest.len() == 4 {
+        (&rest[.. 2], &rest[2 ..])
+    } else {
+        (rest, "0")
+    };
+
+    let hours = match hours.parse::<i32>() {
+        Ok(hours) if hours >= 0 => hours,
+        _ => return None
+    };
+    let minutes = match minutes.parse::<i32>() {
+        Ok(minutes) if minutes >= 0 && minutes < 60 => minutes,
+        _ => return None
+    };
+
+    let offset = sign * (hours * 3600 + minutes * 60);
+    if offset.abs() <= MAX_UTC_OFFSET {
+        Some(offset)
+    } else {
+        None
+    }
+}
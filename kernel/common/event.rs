@@ -5,6 +5,12 @@ pub const EVENT_NONE: i64 = 0;
 pub const EVENT_MOUSE: i64 = 1;
 pub const EVENT_KEY: i64 = 2;
 pub const EVENT_QUIT: i64 = 3;
+pub const EVENT_RESIZE: i64 = 4;
+pub const EVENT_MOUSE_RELATIVE: i64 = 5;
+pub const EVENT_SCROLL: i64 = 6;
+pub const EVENT_TOUCH: i64 = 7;
+pub const EVENT_DISPLAY: i64 = 8;
+pub const EVENT_PING: i64 = 9;
 
 /// An optional event
 #[derive(Copy, Clone, Debug)]
@@ -15,6 +21,18 @@ pub enum EventOption {
     Key(KeyEvent),
     /// A quit request event
     Quit(QuitEvent),
+    /// A window resize event
+    Resize(ResizeEvent),
+    /// A relative mouse motion event
+    MouseRelative(MouseRelativeEvent),
+    /// A scroll event
+    Scroll(ScrollEvent),
+    /// A touch event
+    Touch(TouchEvent),
+    /// A display changed resolution
+    Display(DisplayEvent),
+    /// A check that the client is responding
+    Ping(PingEvent),
     /// An unknown event
     Unknown(Event),
     /// No event
@@ -29,6 +47,8 @@ pub struct Event {
     pub a: i64,
     pub b: i64,
     pub c: i64,
+    /// The monotonic time the event was generated, in nanoseconds, 0 if it is not known
+    pub time: i64,
 }
 
 impl Event {
@@ -39,6 +59,7 @@ impl Event {
             a: 0,
             b: 0,
             c: 0,
+            time: 0,
         }
     }
 
@@ -50,6 +71,12 @@ impl Event {
             EVENT_MOUSE => EventOption::Mouse(MouseEvent::from_event(self)),
             EVENT_KEY => EventOption::Key(KeyEvent::from_event(self)),
             EVENT_QUIT => EventOption::Quit(QuitEvent::from_event(self)),
+            EVENT_RESIZE => EventOption::Resize(ResizeEvent::from_event(self)),
+            EVENT_MOUSE_RELATIVE => EventOption::MouseRelative(MouseRelativeEvent::from_event(self)),
+            EVENT_SCROLL => EventOption::Scroll(ScrollEvent::from_event(self)),
+            EVENT_TOUCH => EventOption::Touch(TouchEvent::from_event(self)),
+            EVENT_DISPLAY => EventOption::Display(DisplayEvent::from_event(self)),
+            EVENT_PING => EventOption::Ping(PingEvent::from_event(self)),
             _ => EventOption::Unknown(self),
         }
     }
@@ -96,6 +123,7 @@ impl MouseEvent {
             b: self.y as i64,
             c: self.left_button as i64 | (self.middle_button as i64) << 1 |
                (self.right_button as i64) << 2,
+            time: 0,
         }
     }
 
@@ -113,14 +141,38 @@ impl MouseEvent {
 
 /// Escape key
 pub const K_ESC: u8 = 0x01;
+/// 1 key
+pub const K_1: u8 = 0x02;
+/// 2 key
+pub const K_2: u8 = 0x03;
+/// 3 key
+pub const K_3: u8 = 0x04;
+/// 4 key
+pub const K_4: u8 = 0x05;
+/// 5 key
+pub const K_5: u8 = 0x06;
+/// 6 key
+pub const K_6: u8 = 0x07;
+/// 7 key
+pub const K_7: u8 = 0x08;
+/// 8 key
+pub const K_8: u8 = 0x09;
+/// 9 key
+pub const K_9: u8 = 0x0A;
+/// 0 key
+pub const K_0: u8 = 0x0B;
 /// Backspace key
 pub const K_BKSP: u8 = 0x0E;
 /// Tab key
 pub const K_TAB: u8 = 0x0F;
+/// Enter key
+pub const K_ENTER: u8 = 0x1C;
 /// Control key
 pub const K_CTRL: u8 = 0x1D;
 /// Alt key
 pub const K_ALT: u8 = 0x38;
+/// Caps lock key
+pub const K_CAPS: u8 = 0x3A;
 /// F1 key
 pub const K_F1: u8 = 0x3B;
 /// F2 key
@@ -157,6 +209,8 @@ pub const K_END: u8 = 0x4F;
 pub const K_DOWN: u8 = 0x50;
 /// Page down key
 pub const K_PGDN: u8 = 0x51;
+/// Insert key
+pub const K_INS: u8 = 0x52;
 /// Delete key
 pub const K_DEL: u8 = 0x53;
 /// F11 key
@@ -167,6 +221,14 @@ pub const K_F12: u8 = 0x58;
 pub const K_LEFT_SHIFT: u8 = 0x2A;
 /// Right shift
 pub const K_RIGHT_SHIFT: u8 = 0x36;
+/// Left super (Windows) key
+pub const K_SUPER: u8 = 0x5B;
+/// Right super (Windows) key
+pub const K_RIGHT_SUPER: u8 = 0x5C;
+/// Menu key
+pub const K_MENU: u8 = 0x5D;
+/// Set on the scancode of keys with the `E0` prefix, such as the arrows and the right control, in raw keyboard mode
+pub const K_EXTENDED: u8 = 0x80;
 
 /// A key event (such as a pressed key)
 #[derive(Copy, Clone, Debug)]
@@ -187,6 +249,7 @@ impl KeyEvent {
             a: self.character as i64,
             b: self.scancode as i64,
             c: self.pressed as i64,
+            time: 0,
         }
     }
 
@@ -210,6 +273,7 @@ impl QuitEvent {
             a: 0,
             b: 0,
             c: 0,
+            time: 0,
         }
     }
 
@@ -217,3 +281,211 @@ impl QuitEvent {
         QuitEvent
     }
 }
+
+/// A window resize event
+#[derive(Copy, Clone, Debug)]
+pub struct ResizeEvent {
+    /// The new width
+    pub width: u32,
+    /// The new height
+    pub height: u32,
+}
+
+impl ResizeEvent {
+    /// Convert to an `Event`
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: EVENT_RESIZE,
+            a: self.width as i64,
+            b: self.height as i64,
+            c: 0,
+            time: 0,
+        }
+    }
+
+    /// Convert from an `Event`
+    pub fn from_event(event: Event) -> ResizeEvent {
+        ResizeEvent {
+            width: event.a as u32,
+            height: event.b as u32,
+        }
+    }
+}
+
+/// A relative mouse motion event, in mouse units, sent even when the cursor cannot move
+#[derive(Copy, Clone, Debug)]
+pub struct MouseRelativeEvent {
+    /// The motion to the right
+    pub dx: i32,
+    /// The motion down
+    pub dy: i32,
+}
+
+impl MouseRelativeEvent {
+    /// Convert to an `Event`
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: EVENT_MOUSE_RELATIVE,
+            a: self.dx as i64,
+            b: self.dy as i64,
+            c: 0,
+            time: 0,
+        }
+    }
+
+    /// Convert from an `Event`
+    pub fn from_event(event: Event) -> MouseRelativeEvent {
+        MouseRelativeEvent {
+            dx: event.a as i32,
+            dy: event.b as i32,
+        }
+    }
+}
+
+/// A scroll event, in steps of the scroll wheel
+#[derive(Copy, Clone, Debug)]
+pub struct ScrollEvent {
+    /// The horizontal scroll, positive to the right
+    pub x: i32,
+    /// The vertical scroll, positive up
+    pub y: i32,
+}
+
+impl ScrollEvent {
+    /// Convert to an `Event`
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: EVENT_SCROLL,
+            a: self.x as i64,
+            b: self.y as i64,
+            c: 0,
+            time: 0,
+        }
+    }
+
+    /// Convert from an `Event`
+    pub fn from_event(event: Event) -> ScrollEvent {
+        ScrollEvent {
+            x: event.a as i32,
+            y: event.b as i32,
+        }
+    }
+}
+
+/// The phase of a touch contact
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TouchPhase {
+    /// The contact touched the screen
+    Down,
+    /// The contact moved
+    Move,
+    /// The contact left the screen
+    Up,
+}
+
+/// A touch event, for one contact of a touch screen
+#[derive(Copy, Clone, Debug)]
+pub struct TouchEvent {
+    /// The ID of the contact, which stays the same from down until up
+    pub id: u32,
+    /// The x coordinate of the contact
+    pub x: i32,
+    /// The y coordinate of the contact
+    pub y: i32,
+    /// The phase of the contact
+    pub phase: TouchPhase,
+}
+
+impl TouchEvent {
+    /// Convert to an `Event`, the phase is stored above the ID
+    pub fn to_event(&self) -> Event {
+        let phase = match self.phase {
+            TouchPhase::Down => 0,
+            TouchPhase::Move => 1,
+            TouchPhase::Up => 2,
+        };
+        Event {
+            code: EVENT_TOUCH,
+            a: self.x as i64,
+            b: self.y as i64,
+            c: (phase << 32) | self.id as i64,
+            time: 0,
+        }
+    }
+
+    /// Convert from an `Event`
+    pub fn from_event(event: Event) -> TouchEvent {
+        TouchEvent {
+            id: event.c as u32,
+            x: event.a as i32,
+            y: event.b as i32,
+            phase: match event.c >> 32 {
+                0 => TouchPhase::Down,
+                1 => TouchPhase::Move,
+                _ => TouchPhase::Up,
+            },
+        }
+    }
+}
+
+/// A display changed resolution, such as when the window of a virtual machine is resized
+#[derive(Copy, Clone, Debug)]
+pub struct DisplayEvent {
+    /// The index of the display, as in `display:manager/N`
+    pub index: u32,
+    /// The new width
+    pub width: u32,
+    /// The new height
+    pub height: u32,
+}
+
+impl DisplayEvent {
+    /// Convert to an `Event`
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: EVENT_DISPLAY,
+            a: self.width as i64,
+            b: self.height as i64,
+            c: self.index as i64,
+            time: 0,
+        }
+    }
+
+    /// Convert from an `Event`
+    pub fn from_event(event: Event) -> DisplayEvent {
+        DisplayEvent {
+            index: event.c as u32,
+            width: event.a as u32,
+            height: event.b as u32,
+        }
+    }
+}
+
+/// A check that the client of a window is still reading its events, answered by writing `P,serial` to the window
+///
+/// A window that answers once is dimmed as not responding when a later ping goes unanswered for a few seconds.
+#[derive(Copy, Clone, Debug)]
+pub struct PingEvent {
+    /// The number to answer with
+    pub serial: u32,
+}
+
+impl PingEvent {
+    /// Convert to an `Event`
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: EVENT_PING,
+            a: self.serial as i64,
+            b: 0,
+            c: 0,
+            time: 0,
+        }
+    }
+
+    /// Convert from an `Event`
+    pub fn from_event(event: Event) -> PingEvent {
+        PingEvent {
+            serial: event.a as u32,
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Latency of the operations of userspace schemes
+//!
+//! Every call the kernel sends to a scheme is timed with the time stamp counter, and counted in a histogram of its
+//! scheme and operation with a bucket for each power of two nanoseconds. The percentiles are the upper bounds of
+//! buckets, so they are within a factor of two of the exact latency. See the `stats:` scheme.
+
+use collections::{BTreeMap, String, Vec};
+use collections::borrow::ToOwned;
+
+use system::syscall::{SYS_CLOSE, SYS_FLINK, SYS_FMAP, SYS_FPATH, SYS_FPUNCH, SYS_FSTAT, SYS_FSYNC, SYS_FTRUNCATE,
+                      SYS_LSEEK, SYS_MKDIR, SYS_OPEN, SYS_READ, SYS_RENAME, SYS_RMDIR, SYS_STAT, SYS_UNLINK,
+                      SYS_WRITE};
+
+/// The buckets of a histogram, the last also counts anything slower than about two seconds
+const BUCKETS: usize = 32;
+
+/// The latencies of an operation
+#[derive(Copy, Clone)]
+pub struct Histogram {
+    pub count: u64,
+    /// The total latency, in nanoseconds
+    pub total: u64,
+    buckets: [u64; BUCKETS],
+}
+
+impl Histogram {
+    pub fn new() -> Histogram {
+        Histogram {
+            count: 0,
+            total: 0,
+            buckets: [0; BUCKETS],
+        }
+    }
+
+    /// Count an operation that took a number of nanoseconds
+    pub fn record(&mut self, nanos: u64) {
+        let mut bucket = 0;
+        while bucket + 1 < BUCKETS && nanos >= 1 << (bucket + 1) {
+            bucket += 1;
+        }
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.total = self.total.saturating_add(nanos);
+    }
+
+    /// The latency that a percentage of the operations were faster than, in nanoseconds
+    pub fn percentile(&self, percent: u64) -> u64 {
+        let target = (self.count * percent + 99) / 100;
+        let mut seen = 0;
+        for bucket in 0..BUCKETS {
+            seen += self.buckets[bucket];
+            if seen >= target && seen > 0 {
+                return 1 << (bucket + 1);
+            }
+        }
+        0
+    }
+}
+
+/// The name of an operation sent to a scheme, by its system call
+pub fn operation_name(call: usize) -> &'static str {
+    match call {
+        SYS_CLOSE => "close",
+        SYS_FLINK => "flink",
+        SYS_FMAP => "fmap",
+        SYS_FPATH => "fpath",
+        SYS_FPUNCH => "fpunch",
+        SYS_FSTAT => "fstat",
+        SYS_FSYNC => "fsync",
+        SYS_FTRUNCATE => "ftruncate",
+        SYS_LSEEK => "lseek",
+        SYS_MKDIR => "mkdir",
+        SYS_OPEN => "open",
+        SYS_READ => "read",
+        SYS_RENAME => "rename",
+        SYS_RMDIR => "rmdir",
+        SYS_STAT => "stat",
+        SYS_UNLINK => "unlink",
+        SYS_WRITE => "write",
+        _ => "other"
+    }
+}
+
+/// Histograms by scheme and operation
+pub struct SchemeLatency {
+    schemes: BTreeMap<String, BTreeMap<&'static str, Histogram>>,
+}
+
+impl SchemeLatency {
+    pub fn new() -> SchemeLatency {
+        SchemeLatency {
+            schemes: BTreeMap::new()
+        }
+    }
+
+    /// Count a call to a scheme that took a number of nanoseconds
+    pub fn record(&mut self, scheme: &str, call: usize, nanos: u64) {
+        if ! self.schemes.contains_key(scheme) {
+            self.schemes.insert(scheme.to_owned(), BTreeMap::new());
+        }
+        if let Some(operations) = self.schemes.get_mut(scheme) {
+            operations.entry(operation_name(call)).or_insert(Histogram::new()).record(nanos);
+        }
+    }
+
+    /// Forget the latencies, such as before measuring a workload
+    pub fn clear(&mut self) {
+        self.schemes.clear();
+    }
+
+    /// The histograms of each scheme and operation, sorted by scheme and then by operation
+    pub fn list(&self) -> Vec<(&str, &'static str, &Histogram)> {
+        let mut list = Vec::new();
+        for (scheme, operations) in self.schemes.iter() {
+            for (operation, histogram) in operations.iter() {
+                list.push((&scheme[..], *operation, histogram));
+            }
+        }
+        list
+    }
+}
@@ -0,0 +1,200 @@
+use core::{cmp, fmt, i32};
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+/// Number of fractional bits in a `Fixed`
+pub const FIXED_FRAC_BITS: u32 = 16;
+
+/// A signed Q16.16 fixed point number, for fractional math where floats cannot be used,
+/// such as in interrupt handlers
+///
+/// The arithmetic operators saturate at the minimum and maximum instead of overflowing.
+/// Use the `checked_` functions to detect overflow.
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fixed(i32);
+
+pub const FIXED_ZERO: Fixed = Fixed(0);
+pub const FIXED_ONE: Fixed = Fixed(1 << FIXED_FRAC_BITS);
+pub const FIXED_MIN: Fixed = Fixed(i32::MIN);
+pub const FIXED_MAX: Fixed = Fixed(i32::MAX);
+
+impl Fixed {
+    /// Create from the raw Q16.16 representation
+    pub const fn from_raw(raw: i32) -> Fixed {
+        Fixed(raw)
+    }
+
+    /// The raw Q16.16 representation
+    pub fn raw(&self) -> i32 {
+        self.0
+    }
+
+    /// Create from an integer, saturating if it is out of range
+    pub fn from_int(value: i32) -> Fixed {
+        Fixed(saturate_i64((value as i64) << FIXED_FRAC_BITS))
+    }
+
+    /// Create from a fraction, such as `Fixed::from_ratio(3, 4)` for 0.75
+    pub fn from_ratio(numerator: i32, denominator: i32) -> Option<Fixed> {
+        Fixed::from_int(numerator).checked_div(Fixed::from_int(denominator))
+    }
+
+    /// The integer part, rounded toward negative infinity
+    pub fn floor(&self) -> i32 {
+        self.0 >> FIXED_FRAC_BITS
+    }
+
+    /// The nearest integer, with halves rounded up
+    pub fn round(&self) -> i32 {
+        ((self.0 as i64 + (1 << (FIXED_FRAC_BITS - 1))) >> FIXED_FRAC_BITS) as i32
+    }
+
+    /// The smallest integer not less than the number
+    pub fn ceil(&self) -> i32 {
+        ((self.0 as i64 + (1 << FIXED_FRAC_BITS) - 1) >> FIXED_FRAC_BITS) as i32
+    }
+
+    /// The fractional part, always positive
+    pub fn fract(&self) -> Fixed {
+        Fixed(self.0 & ((1 << FIXED_FRAC_BITS) - 1))
+    }
+
+    pub fn abs(&self) -> Fixed {
+        if self.0 < 0 { -*self } else { *self }
+    }
+
+    pub fn checked_add(self, other: Fixed) -> Option<Fixed> {
+        self.0.checked_add(other.0).map(Fixed)
+    }
+
+    pub fn checked_sub(self, other: Fixed) -> Option<Fixed> {
+        self.0.checked_sub(other.0).map(Fixed)
+    }
+
+    pub fn checked_mul(self, other: Fixed) -> Option<Fixed> {
+        checked_i64((self.0 as i64 * other.0 as i64) >> FIXED_FRAC_BITS).map(Fixed)
+    }
+
+    /// Divide, returning `None` on division by zero or overflow
+    pub fn checked_div(self, other: Fixed) -> Option<Fixed> {
+        if other.0 == 0 {
+            None
+        } else {
+            checked_i64(((self.0 as i64) << FIXED_FRAC_BITS) / other.0 as i64).map(Fixed)
+        }
+    }
+
+    /// Multiply an integer, rounding down and saturating
+    pub fn mul_int(self, value: i32) -> i32 {
+        saturate_i64((self.0 as i64 * value as i64) >> FIXED_FRAC_BITS)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: Fixed) -> Fixed {
+        Fixed(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Fixed;
+
+    fn mul(self, other: Fixed) -> Fixed {
+        Fixed(saturate_i64((self.0 as i64 * other.0 as i64) >> FIXED_FRAC_BITS))
+    }
+}
+
+impl Div for Fixed {
+    type Output = Fixed;
+
+    /// Divide, saturating on overflow and division by zero
+    fn div(self, other: Fixed) -> Fixed {
+        match self.checked_div(other) {
+            Some(value) => value,
+            None => if (self.0 < 0) != (other.0 < 0) { FIXED_MIN } else { FIXED_MAX }
+        }
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+
+    fn neg(self) -> Fixed {
+        Fixed(if self.0 == i32::MIN { i32::MAX } else { -self.0 })
+    }
+}
+
+impl fmt::Debug for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for Fixed {
+    /// Show the number with four decimal places
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = (self.0 as i64).abs();
+        let int = abs >> FIXED_FRAC_BITS;
+        let frac = ((abs & ((1 << FIXED_FRAC_BITS) - 1)) * 10000) >> FIXED_FRAC_BITS;
+        write!(f, "{}{}.{:04}", sign, int, frac)
+    }
+}
+
+fn checked_i64(value: i64) -> Option<i32> {
+    if value < i32::MIN as i64 || value > i32::MAX as i64 {
+        None
+    } else {
+        Some(value as i32)
+    }
+}
+
+fn saturate_i64(value: i64) -> i32 {
+    cmp::max(i32::MIN as i64, cmp::min(i32::MAX as i64, value)) as i32
+}
+
+/// Restrict a value to a range
+pub fn clamp<T: Ord>(value: T, min: T, max: T) -> T {
+    cmp::max(min, cmp::min(max, value))
+}
+
+/// Interpolate between `a` and `b`, where `t` is between zero and one
+pub fn lerp(a: i32, b: i32, t: Fixed) -> i32 {
+    let t = clamp(t, FIXED_ZERO, FIXED_ONE);
+    saturate_i64(a as i64 + ((((b as i64) - (a as i64)) * t.raw() as i64) >> FIXED_FRAC_BITS))
+}
+
+/// Compute `value * numerator / denominator` without overflowing the intermediate product,
+/// returning `None` on division by zero or if the result does not fit
+pub fn mul_div(value: u32, numerator: u32, denominator: u32) -> Option<u32> {
+    if denominator == 0 {
+        return None;
+    }
+    let result = value as u64 * numerator as u64 / denominator as u64;
+    if result > u32::max_value() as u64 {
+        None
+    } else {
+        Some(result as u32)
+    }
+}
+
+/// Scale an 8-bit channel by an 8-bit factor, such as alpha or volume, where 255 is one
+pub fn mul_u8(value: u8, factor: u8) -> u8 {
+    // Exact division by 255, rounded
+    let product = value as u32 * factor as u32 + 128;
+    ((product + (product >> 8)) >> 8) as u8
+}
+
+/// Scale a signed 16-bit sample by a volume, where `FIXED_ONE` keeps it unchanged, saturating
+pub fn scale_sample(sample: i16, volume: Fixed) -> i16 {
+    clamp(volume.mul_int(sample as i32), i16::min_value() as i32, i16::max_value() as i32) as i16
+}
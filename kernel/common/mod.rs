@@ -1,16 +1,24 @@
+/// Backtraces of the kernel stack
+pub mod backtrace;
 /// Debug
 #[macro_use]
 pub mod debug;
+/// Calendar dates and times of day
+pub mod datetime;
 /// Event input
 pub mod event;
+/// Latency of the operations of userspace schemes
+pub mod latency;
+/// Fixed point and overflow-safe arithmetic
+pub mod math;
 /// Slice-related traits
 pub mod slice;
-/// A module for parsing paths
-pub mod parse_path;
 /// A module for parsing IP related string
 pub mod parse_ip;
 /// A module for pseudorandom generator
 pub mod random;
+/// Symbol names of kernel functions
+pub mod symbols;
 /// A module for time
 pub mod time;
 /// String to number
@@ -1,19 +1,76 @@
-static mut seed: u64 = 19940046431; //259261034506304368955239; //1706322144714608529217229883707268827757977089;
+/// The seed used when none is given
+const DEFAULT_SEED: u64 = 19940046431;
+
+/// A seedable pseudorandom generator, using SplitMix64
+///
+/// The same seed always gives the same numbers, so runs using it can be reproduced. It is not suitable
+/// for anything that must be unpredictable.
+#[derive(Clone, Debug)]
+pub struct Prng {
+    state: u64,
+}
+
+impl Prng {
+    pub const fn new(seed: u64) -> Prng {
+        Prng {
+            state: seed
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// A number from `start` up to, but not including, `end`, or `start` if the range is empty
+    pub fn range(&mut self, start: usize, end: usize) -> usize {
+        if end <= start {
+            start
+        } else {
+            start + (self.next_u64() % (end - start) as u64) as usize
+        }
+    }
+
+    /// Fill a buffer with random bytes
+    pub fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let value = self.next_u64();
+            for (i, b) in chunk.iter_mut().enumerate() {
+                *b = (value >> (i * 8)) as u8;
+            }
+        }
+    }
+}
+
+static mut SEED: u64 = DEFAULT_SEED;
+static mut RNG: Prng = Prng::new(DEFAULT_SEED);
 
 /// Generate pseudo random number
 pub fn rand() -> usize {
-    unsafe {
-        seed ^= seed << 12;
-        seed ^= seed << 25;
-        seed ^= seed << 27;
-        seed = seed * 82724793451 + 12345;
-        seed as usize % ::core::usize::MAX
-    }
+    unsafe { RNG.next_u64() as usize }
 }
 
 /// Set the seed
-pub fn srand(s: usize) {
+pub fn srand(s: u64) {
     unsafe {
-        seed = s as u64;
+        SEED = s;
+        RNG = Prng::new(s);
     }
 }
+
+/// The last seed set, which reproduces the numbers generated since
+pub fn seed() -> u64 {
+    unsafe { SEED }
+}
+
+/// The seed given when the kernel was built, with `REDOX_SEED=N`, to reproduce a randomized run
+pub fn boot_seed() -> Option<u64> {
+    option_env!("REDOX_SEED").and_then(|seed| seed.parse::<u64>().ok())
+}
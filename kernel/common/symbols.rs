@@ -0,0 +1,53 @@
+//! Symbol names of kernel functions
+//!
+//! The kernel is linked twice. The code symbols of the first link are listed with `nm -n`, sorted by address, and the
+//! second link embeds that list after the data segment, where it does not move any code. Each line is
+//! `address type name`, such as `00101000 T kernel`.
+
+use core::{slice, str};
+
+use common::to_num::ToNum;
+
+extern {
+    static __text_start: u8;
+    static __text_end: u8;
+    static __symbols_start: u8;
+    static __symbols_end: u8;
+}
+
+/// The embedded symbol table, empty when the kernel was linked without one
+fn table() -> &'static [u8] {
+    unsafe {
+        let start = &__symbols_start as *const u8;
+        let end = &__symbols_end as *const u8;
+        slice::from_raw_parts(start, end as usize - start as usize)
+    }
+}
+
+/// Check if an address is in kernel code
+pub fn is_code(address: usize) -> bool {
+    unsafe { address >= &__text_start as *const u8 as usize && address < &__text_end as *const u8 as usize }
+}
+
+/// Find the function containing an address in kernel code, returning its name and the offset into it
+pub fn lookup(address: usize) -> Option<(&'static str, usize)> {
+    if ! is_code(address) {
+        return None;
+    }
+
+    let mut found = None;
+    for line in table().split(|&b| b == b'\n') {
+        let mut parts = line.splitn(3, |&b| b == b' ');
+        let symbol_address = match parts.next().and_then(|part| str::from_utf8(part).ok()) {
+            Some(part) if ! part.is_empty() => part.to_num_radix(16),
+            _ => continue
+        };
+        if symbol_address > address {
+            break;
+        }
+        if let Some(name) = parts.nth(1).and_then(|part| str::from_utf8(part).ok()) {
+            found = Some((name, address - symbol_address));
+        }
+    }
+    found
+}
@@ -1,9 +1,11 @@
+use arch::context::CONTEXT_IMAGE_ADDR;
 use arch::memory;
 
 use core::mem::size_of;
 use core::u32;
 
 use disk::Disk;
+use disk::smart::{ATA_CMD_SMART, SMART_BLOCK};
 
 use drivers::io::{Io, Mmio};
 
@@ -13,6 +15,8 @@ use super::fis::{FIS_TYPE_REG_H2D, FisRegH2D};
 
 const ATA_CMD_READ_DMA_EXT: u8 = 0x25;
 const ATA_CMD_WRITE_DMA_EXT: u8 = 0x35;
+const ATA_CMD_FLUSH_CACHE_EXT: u8 = 0xEA;
+const ATA_CMD_IDENTIFY: u8 = 0xEC;
 const ATA_DEV_BUSY: u8 = 0x80;
 const ATA_DEV_DRQ: u8 = 0x08;
 
@@ -125,15 +129,39 @@ impl HbaPort {
         None
     }
 
-    pub fn ata_dma_small(&mut self, block: u64, sectors: usize, mut buf: usize, write: bool) -> Result<usize> {
+    pub fn ata_dma_small(&mut self, block: u64, sectors: usize, buf: usize, write: bool) -> Result<usize> {
+        self.ata_command(if write {
+            ATA_CMD_WRITE_DMA_EXT
+        } else {
+            ATA_CMD_READ_DMA_EXT
+        }, 0, block, sectors, buf, write)
+    }
+
+    /// Write the volatile cache of the disk to its media
+    pub fn ata_flush(&mut self) -> Result<()> {
+        self.ata_command(ATA_CMD_FLUSH_CACHE_EXT, 0, 0, 0, 0, false).map(|_| ())
+    }
+
+    /// Read the 256 words that identify the device into a kernel buffer
+    pub fn ata_identify(&mut self, buf: usize) -> Result<()> {
+        self.ata_command(ATA_CMD_IDENTIFY, 0, 0, 1, buf, false).map(|_| ())
+    }
+
+    /// Run a SMART command that reads a sector into a kernel buffer
+    pub fn ata_smart(&mut self, feature: u8, buf: usize) -> Result<()> {
+        self.ata_command(ATA_CMD_SMART, feature, SMART_BLOCK, 1, buf, false).map(|_| ())
+    }
+
+    fn ata_command(&mut self, command: u8, feature: u8, block: u64, sectors: usize, mut buf: usize, write: bool) -> Result<usize> {
         if buf >= 0x80000000 {
             buf -= 0x80000000;
         }
 
         // TODO: PRDTL for files larger than 4MB
-        let entries = 1;
+        // Commands without data, such as a flush, have no entries
+        let entries = if sectors > 0 { 1 } else { 0 };
 
-        if buf > 0 && sectors > 0 {
+        if buf > 0 || sectors == 0 {
             self.is.write(u32::MAX);
 
             if let Some(slot) = self.slot() {
@@ -151,19 +179,18 @@ impl HbaPort {
                 unsafe { ::memset(ctba as *mut u8, 0, size_of::<HbaCmdTable>()) };
                 let cmdtbl = unsafe { &mut *(ctba as *mut HbaCmdTable) };
 
-                let prdt_entry = &mut cmdtbl.prdt_entry[0];
-                prdt_entry.dba.write(buf as u64);
-                prdt_entry.dbc.write(((sectors * 512) as u32) | 1);
+                if entries > 0 {
+                    let prdt_entry = &mut cmdtbl.prdt_entry[0];
+                    prdt_entry.dba.write(buf as u64);
+                    prdt_entry.dbc.write(((sectors * 512) as u32) | 1);
+                }
 
                 let cmdfis = unsafe { &mut *(cmdtbl.cfis.as_ptr() as *mut FisRegH2D) };
 
                 cmdfis.fis_type.write(FIS_TYPE_REG_H2D);
                 cmdfis.pm.write(1 << 7);
-                if write {
-                    cmdfis.command.write(ATA_CMD_WRITE_DMA_EXT);
-                } else {
-                    cmdfis.command.write(ATA_CMD_READ_DMA_EXT);
-                }
+                cmdfis.command.write(command);
+                cmdfis.featurel.write(feature);
 
                 cmdfis.lba0.write(block as u8);
                 cmdfis.lba1.write((block >> 8) as u8);
@@ -211,7 +238,12 @@ impl HbaPort {
         if sectors > 0 {
             let contexts = ::env().contexts.lock();
             let current = try!(contexts.current());
-            let physical_address = try!(current.translate(buf, sectors * 512));
+            // Kernel buffers, such as those of the I/O scheduler, are identity mapped below the memory of contexts
+            let physical_address = if buf + sectors * 512 <= CONTEXT_IMAGE_ADDR {
+                buf
+            } else {
+                try!(current.translate(buf, sectors * 512))
+            };
 
             let mut sector: usize = 0;
             while sectors - sector >= 255 {
@@ -3,12 +3,12 @@ use alloc::boxed::Box;
 use collections::string::String;
 use collections::vec::Vec;
 
-use disk::Disk;
+use disk::{smart, Disk};
 
 use drivers::io::Io;
 use drivers::pci::config::PciConfig;
 
-use system::error::Result;
+use system::error::{Error, Result, ENOSYS};
 
 use self::hba::{HbaMem, HbaPort, HbaPortType};
 
@@ -34,6 +34,7 @@ impl Ahci {
                                           match port_type {
                                               HbaPortType::SATA => {
                                                   disk.port.init();
+                                                  disk.identify();
                                                   Some(disk as Box<Disk>)
                                               }
                                               _ => None,
@@ -49,6 +50,8 @@ pub struct AhciDisk {
     port: &'static mut HbaPort,
     port_index: usize,
     size: u64,
+    /// SMART is supported and enabled
+    smart: bool,
 }
 
 impl AhciDisk {
@@ -56,9 +59,16 @@ impl AhciDisk {
         AhciDisk {
             port: &mut unsafe { &mut *(base as *mut HbaMem) }.ports[port_index],
             port_index: port_index,
-            size: 1024*1024*1024 //TODO: Get actual value
+            size: 1024*1024*1024, //TODO: Get actual value
+            smart: false
         }
     }
+
+    /// Find out from IDENTIFY whether SMART can be used
+    fn identify(&mut self) {
+        let mut words = [0u16; 256];
+        self.smart = self.port.ata_identify(words.as_mut_ptr() as usize).is_ok() && smart::identified(&words);
+    }
 }
 
 impl Disk for AhciDisk {
@@ -77,4 +87,24 @@ impl Disk for AhciDisk {
     fn write(&mut self, block: u64, buffer: &[u8]) -> Result<usize> {
         self.port.ata_dma(block, buffer.len() / 512, buffer.as_ptr() as usize, true)
     }
+
+    fn flush(&mut self) -> Result<()> {
+        self.port.ata_flush()
+    }
+
+    fn has_smart(&self) -> bool {
+        self.smart
+    }
+
+    fn smart(&mut self, feature: u8, buffer: &mut [u8; 512]) -> Result<()> {
+        if ! self.smart {
+            return Err(Error::new(ENOSYS));
+        }
+        self.port.ata_smart(feature, buffer.as_mut_ptr() as usize)
+    }
+
+    fn queue_depth(&self) -> usize {
+        // One for each command slot
+        32
+    }
 }
@@ -8,11 +8,12 @@ use core::ptr;
 use arch::memory::Memory;
 
 use disk::Disk;
+use disk::smart::{self, ATA_CMD_SMART, SMART_BLOCK};
 
 use drivers::pci::config::PciConfig;
 use drivers::io::{Io, Pio, ReadOnly, WriteOnly};
 
-use system::error::{Error, Result, EIO};
+use system::error::{Error, Result, EIO, ENOSYS};
 
 /// An disk extent
 #[derive(Copy, Clone)]
@@ -208,6 +209,7 @@ pub struct IdeDisk {
     prdt: Prdt,
     data: Pio<u16>,
     error: ReadOnly<u8, Pio<u8>>,
+    features: WriteOnly<u8, Pio<u8>>,
     seccount: Pio<u8>,
     sector0: Pio<u8>,
     sector1: Pio<u8>,
@@ -219,6 +221,8 @@ pub struct IdeDisk {
     irq: u8,
     master: bool,
     size: u64,
+    /// SMART is supported and enabled
+    smart: bool,
 }
 
 impl IdeDisk {
@@ -229,6 +233,7 @@ impl IdeDisk {
             prdt: Prdt::new(busmaster + 4),
             data: Pio::new(base),
             error: ReadOnly::new(Pio::new(base + 1)),
+            features: WriteOnly::new(Pio::new(base + 1)),
             seccount: Pio::new(base + 2),
             sector0: Pio::new(base + 3),
             sector1: Pio::new(base + 4),
@@ -240,6 +245,7 @@ impl IdeDisk {
             irq: irq,
             master: master,
             size: 0,
+            smart: false,
         };
 
         if let Some(size) = unsafe { ret.identify() } {
@@ -270,6 +276,10 @@ impl IdeDisk {
     }
 
     pub fn ata(&mut self, cmd: u8, block: u64, len: u16) {
+        self.ata_features(cmd, 0, block, len);
+    }
+
+    pub fn ata_features(&mut self, cmd: u8, features: u8, block: u64, len: u16) {
         while self.alt_sts.readf(ATA_SR_BSY) {}
 
         self.devsel.write(if self.master {
@@ -290,6 +300,7 @@ impl IdeDisk {
         self.sector1.write((block >> 32) as u8);
         self.sector2.write((block >> 40) as u8);*/
 
+        self.features.write(features);
         self.seccount.write(len as u8);
         self.sector0.write(block as u8);
         self.sector1.write((block >> 8) as u8);
@@ -323,9 +334,12 @@ impl IdeDisk {
         }
 
         let mut destination = Memory::<u16>::new(256).unwrap();
+        let mut words = [0; 256];
         for word in 0..256 {
-            destination.write(word, self.data.read());
+            words[word] = self.data.read();
+            destination.write(word, words[word]);
         }
+        self.smart = smart::identified(&words);
 
         debug!(" Serial: ");
         for word in 10..20 {
@@ -599,4 +613,48 @@ impl Disk for IdeDisk {
     fn write(&mut self, block: u64, buffer: &[u8]) -> Result<usize> {
         self.ata_pio(block, buffer.len() / 512, buffer.as_ptr() as usize, true)
     }
+
+    fn flush(&mut self) -> Result<()> {
+        self.ata(ATA_CMD_CACHE_FLUSH, 0, 0);
+
+        // Flushing transfers no data, so DRQ stays clear
+        unsafe { self.ide_poll(false) };
+        let state = self.alt_sts.read();
+        if state & (ATA_SR_ERR | ATA_SR_DF) != 0 {
+            debugln!("IDE Flush Error: {:X}={:X}", state, self.error.read());
+            return Err(Error::new(EIO));
+        }
+
+        Ok(())
+    }
+
+    fn has_smart(&self) -> bool {
+        self.smart
+    }
+
+    fn smart(&mut self, feature: u8, buffer: &mut [u8; 512]) -> Result<()> {
+        if ! self.smart {
+            return Err(Error::new(ENOSYS));
+        }
+
+        self.ata_features(ATA_CMD_SMART, feature, SMART_BLOCK, 1);
+
+        let err = unsafe { self.ide_poll(true) };
+        if err > 0 {
+            debugln!("IDE SMART Error: {:X}={:X}", err, self.error.read());
+            return Err(Error::new(EIO));
+        }
+
+        for word in 0..256 {
+            let data = self.data.read();
+            buffer[word * 2] = data as u8;
+            buffer[word * 2 + 1] = (data >> 8) as u8;
+        }
+
+        Ok(())
+    }
+
+    fn queue_depth(&self) -> usize {
+        16
+    }
 }
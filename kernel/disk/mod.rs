@@ -4,10 +4,21 @@ use system::error::Result;
 
 pub mod ahci;
 pub mod ide;
+pub mod queue;
+pub mod smart;
 
 pub trait Disk {
     fn name(&self) -> String;
     fn size(&self) -> u64;
     fn read(&mut self, block: u64, buffer: &mut [u8]) -> Result<usize>;
     fn write(&mut self, block: u64, buffer: &[u8]) -> Result<usize>;
+    /// Write the volatile cache of the disk to its media
+    fn flush(&mut self) -> Result<()>;
+    /// Whether the disk supports SMART and has it enabled
+    fn has_smart(&self) -> bool;
+    /// Run a SMART command that reads a sector, such as `SMART_READ_DATA`
+    /// Returns `ENOSYS` if the disk does not support SMART or has it disabled.
+    fn smart(&mut self, feature: u8, buffer: &mut [u8; 512]) -> Result<()>;
+    /// The number of requests queued for the disk before submitters wait
+    fn queue_depth(&self) -> usize;
 }
@@ -0,0 +1,243 @@
+//! An elevator I/O scheduler, between the users of a disk and its driver
+//!
+//! Requests are queued, and the context that submits a request while no dispatch is running dispatches the queue
+//! on behalf of all of them. Each dispatch sorts the queued requests by block, continuing upward from where the
+//! last dispatch ended before wrapping around to the lowest block, and merges requests in the same direction that
+//! are adjacent on the disk into one transfer. Contexts that submit while the queue is full wait for a slot.
+//!
+//! The data of a request is held in a kernel buffer, because the dispatch may run in another context than the one
+//! that submitted the request, where the memory of the submitter is not mapped.
+
+use alloc::arc::Arc;
+use alloc::boxed::Box;
+
+use arch::context::context_switch;
+
+use collections::{String, Vec};
+
+use core::{cmp, mem};
+
+use sync::{Intex, Semaphore};
+
+use system::error::{Error, Result, EIO};
+
+use super::Disk;
+use super::smart;
+
+/// The largest transfer that requests are merged into, in blocks
+const MAX_MERGE: u64 = 256;
+
+/// A queued read or write
+struct Request {
+    block: u64,
+    data: Vec<u8>,
+    write: bool,
+    result: Option<Result<usize>>,
+    done: Semaphore,
+}
+
+impl Request {
+    fn new(block: u64, data: Vec<u8>, write: bool) -> Request {
+        Request {
+            block: block,
+            data: data,
+            write: write,
+            result: None,
+            done: Semaphore::new(0)
+        }
+    }
+
+    /// The number of blocks the request covers, if it covers whole blocks
+    fn blocks(&self) -> Option<u64> {
+        if self.data.len() % 512 == 0 {
+            Some(self.data.len() as u64 / 512)
+        } else {
+            None
+        }
+    }
+}
+
+struct Queue {
+    requests: Vec<*mut Request>,
+    dispatching: bool,
+    /// The block after the last transfer
+    head: u64,
+}
+
+/// The request queue of a disk
+pub struct DiskQueue {
+    disk: Arc<Intex<Box<Disk>>>,
+    queue: Intex<Queue>,
+    slots: Semaphore,
+}
+
+impl DiskQueue {
+    pub fn new(disk: Arc<Intex<Box<Disk>>>) -> DiskQueue {
+        let depth = cmp::max(1, disk.lock().queue_depth());
+        DiskQueue {
+            disk: disk,
+            queue: Intex::new(Queue {
+                requests: Vec::new(),
+                dispatching: false,
+                head: 0
+            }),
+            slots: Semaphore::new(depth)
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.disk.lock().name()
+    }
+
+    pub fn size(&self) -> u64 {
+        self.disk.lock().size()
+    }
+
+    /// Write the volatile cache of the disk to its media, after the requests that have completed
+    pub fn flush(&self) -> Result<()> {
+        self.disk.lock().flush()
+    }
+
+    /// Whether the disk supports SMART and has it enabled
+    pub fn has_smart(&self) -> bool {
+        self.disk.lock().has_smart()
+    }
+
+    /// Describe the health of the disk, going to the driver directly
+    pub fn smart(&self) -> Result<String> {
+        smart::report(&mut **self.disk.lock())
+    }
+
+    pub fn read(&self, block: u64, buffer: &mut [u8]) -> Result<usize> {
+        let mut request = Request::new(block, vec![0; buffer.len()], false);
+        let count = cmp::min(buffer.len(), try!(unsafe { self.submit(&mut request) }));
+        for (b, d) in buffer[.. count].iter_mut().zip(request.data.iter()) {
+            *b = *d;
+        }
+        Ok(count)
+    }
+
+    pub fn write(&self, block: u64, buffer: &[u8]) -> Result<usize> {
+        let mut request = Request::new(block, buffer.to_vec(), true);
+        unsafe { self.submit(&mut request) }
+    }
+
+    /// Queue a request and wait for it to complete, dispatching the queue if no other context is
+    unsafe fn submit(&self, request: *mut Request) -> Result<usize> {
+        self.slots.wait();
+
+        let dispatch = {
+            let mut queue = self.queue.lock();
+            queue.requests.push(request);
+            ! mem::replace(&mut queue.dispatching, true)
+        };
+
+        if dispatch {
+            self.dispatch();
+        }
+        (*request).done.wait();
+
+        self.slots.post();
+        (*request).result.take().unwrap_or(Err(Error::new(EIO)))
+    }
+
+    /// Transfer queued requests until the queue is empty
+    unsafe fn dispatch(&self) {
+        loop {
+            let (mut batch, mut head) = {
+                let mut queue = self.queue.lock();
+                if queue.requests.is_empty() {
+                    queue.dispatching = false;
+                    return;
+                }
+                (mem::replace(&mut queue.requests, Vec::new()), queue.head)
+            };
+
+            // Continue upward from the head, then wrap around to the lowest block
+            batch.sort_by_key(|&request| ((*request).block < head, (*request).block));
+
+            let mut i = 0;
+            while i < batch.len() {
+                let block = (*batch[i]).block;
+                let write = (*batch[i]).write;
+                let mut end = i + 1;
+                let mut blocks = (*batch[i]).blocks().unwrap_or(0);
+                if (*batch[i]).blocks().is_some() {
+                    while end < batch.len() {
+                        let next = &*batch[end];
+                        match next.blocks() {
+                            Some(count) if next.write == write && next.block == block + blocks &&
+                                           blocks + count <= MAX_MERGE => blocks += count,
+                            _ => break
+                        }
+                        end += 1;
+                    }
+                }
+
+                self.transfer(&batch[i .. end]);
+
+                head = block + blocks;
+                i = end;
+            }
+
+            self.queue.lock().head = head;
+
+            for &request in batch.iter() {
+                (*request).done.post();
+            }
+
+            // Let the contexts that were waiting submit their next requests, so they are merged with the next batch
+            context_switch();
+        }
+    }
+
+    /// Transfer requests that are adjacent on the disk, as one transfer
+    unsafe fn transfer(&self, requests: &[*mut Request]) {
+        if requests.len() == 1 {
+            let request = &mut *requests[0];
+            request.result = Some(if request.write {
+                self.disk.lock().write(request.block, &request.data)
+            } else {
+                self.disk.lock().read(request.block, &mut request.data)
+            });
+            return;
+        }
+
+        let block = (*requests[0]).block;
+        let write = (*requests[0]).write;
+
+        let mut data = Vec::new();
+        for &request in requests.iter() {
+            if write {
+                data.extend_from_slice(&(*request).data);
+            } else {
+                data.resize(data.len() + (*request).data.len(), 0);
+            }
+        }
+
+        let result = if write {
+            self.disk.lock().write(block, &data)
+        } else {
+            self.disk.lock().read(block, &mut data)
+        };
+
+        let mut offset = 0;
+        for &request in requests.iter() {
+            let request = &mut *request;
+            let len = request.data.len();
+            request.result = Some(match result {
+                Ok(count) => {
+                    let count = cmp::min(len, count.saturating_sub(offset));
+                    if ! write {
+                        for (r, d) in request.data.iter_mut().zip(data[offset .. offset + count].iter()) {
+                            *r = *d;
+                        }
+                    }
+                    Ok(count)
+                },
+                Err(ref err) => Err(Error::new(err.errno))
+            });
+            offset += len;
+        }
+    }
+}
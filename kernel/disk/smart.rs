@@ -0,0 +1,122 @@
+//! SMART, the health a disk reports about itself
+//!
+//! A disk keeps up to 30 attributes, each with a normalized value from 1 to 253 that falls as the disk wears, and
+//! a threshold below which the attribute predicts a failure.
+
+use collections::{String, Vec};
+
+use super::Disk;
+
+use system::error::Result;
+
+pub const ATA_CMD_SMART: u8 = 0xB0;
+
+/// Read the values of the attributes
+pub const SMART_READ_DATA: u8 = 0xD0;
+/// Read the thresholds of the attributes
+pub const SMART_READ_THRESHOLDS: u8 = 0xD1;
+
+/// The LBA mid and high registers of SMART commands must be 0x4F and 0xC2
+pub const SMART_BLOCK: u64 = 0xC24F00;
+
+/// Whether the words returned by IDENTIFY report SMART as supported, in bit 0 of word 82, and enabled, in bit 0 of
+/// word 85
+pub fn identified(identify: &[u16]) -> bool {
+    identify.len() > 85 && identify[82] & 1 == 1 && identify[85] & 1 == 1
+}
+
+/// A SMART attribute
+pub struct SmartAttribute {
+    pub id: u8,
+    pub value: u8,
+    pub worst: u8,
+    pub threshold: u8,
+    pub raw: u64,
+}
+
+impl SmartAttribute {
+    /// The name of common attributes, the meaning of the others depends on the vendor
+    pub fn name(&self) -> &'static str {
+        match self.id {
+            1 => "read_error_rate",
+            3 => "spin_up_time",
+            4 => "start_stop_count",
+            5 => "reallocated_sectors",
+            7 => "seek_error_rate",
+            9 => "power_on_hours",
+            10 => "spin_retry_count",
+            12 => "power_cycle_count",
+            177 => "wear_leveling_count",
+            184 => "end_to_end_error",
+            187 => "reported_uncorrectable",
+            188 => "command_timeout",
+            190 | 194 => "temperature",
+            196 => "reallocation_events",
+            197 => "pending_sectors",
+            198 => "offline_uncorrectable",
+            199 => "crc_error_count",
+            231 => "life_left",
+            _ => "unknown"
+        }
+    }
+
+    /// Whether the value has fallen to the threshold
+    pub fn failing(&self) -> bool {
+        self.threshold > 0 && self.value <= self.threshold
+    }
+}
+
+/// Read the attributes of a disk
+pub fn attributes(disk: &mut Disk) -> Result<Vec<SmartAttribute>> {
+    let mut data = [0; 512];
+    try!(disk.smart(SMART_READ_DATA, &mut data));
+
+    let mut thresholds = [0; 512];
+    try!(disk.smart(SMART_READ_THRESHOLDS, &mut thresholds));
+
+    let mut attributes = Vec::new();
+    for i in 0..30 {
+        let entry = &data[2 + i * 12 .. 2 + (i + 1) * 12];
+        let id = entry[0];
+        if id == 0 {
+            continue;
+        }
+
+        let mut raw = 0;
+        for byte in 0..6 {
+            raw |= (entry[5 + byte] as u64) << (byte * 8);
+        }
+
+        let threshold = thresholds[2 .. 2 + 30 * 12].chunks(12)
+                                                    .find(|threshold| threshold[0] == id)
+                                                    .map_or(0, |threshold| threshold[1]);
+
+        attributes.push(SmartAttribute {
+            id: id,
+            value: entry[3],
+            worst: entry[4],
+            threshold: threshold,
+            raw: raw
+        });
+    }
+
+    Ok(attributes)
+}
+
+/// Describe the health of a disk, as `health=ok` or `health=failing`, followed by a line for each attribute as
+/// `attribute=id,name,value,worst,threshold,raw`
+pub fn report(disk: &mut Disk) -> Result<String> {
+    let attributes = try!(attributes(disk));
+
+    let mut string = format!("health={}\n", if attributes.iter().any(|attribute| attribute.failing()) {
+        "failing"
+    } else {
+        "ok"
+    });
+    for attribute in attributes.iter() {
+        string.push_str(&format!("attribute={},{},{},{},{},{}\n", attribute.id, attribute.name(), attribute.value,
+                                 attribute.worst, attribute.threshold, attribute.raw));
+    }
+
+    Ok(string)
+}
@@ -0,0 +1,99 @@
+use alloc::boxed::Box;
+
+use core::{cmp, ptr};
+
+use drivers::pci::config::PciConfig;
+
+/// The backlight of a laptop panel
+pub trait Backlight: Send {
+    /// The brightness at full duty cycle
+    fn max(&self) -> u32;
+
+    /// The current brightness, from 0 to `max`
+    fn get(&self) -> u32;
+
+    /// Set the brightness, from 0 to `max`
+    fn set(&mut self, level: u32);
+
+    /// The brightness in percent
+    fn percent(&self) -> u32 {
+        let max = self.max();
+        if max > 0 {
+            (self.get() * 100 + max / 2) / max
+        } else {
+            0
+        }
+    }
+
+    /// Set the brightness in percent, keeping the panel lit at 1% and above
+    fn set_percent(&mut self, percent: u32) {
+        let max = self.max();
+        let level = if percent >= 100 {
+            max
+        } else if percent > 0 {
+            cmp::max(1, max * percent / 100)
+        } else {
+            0
+        };
+        self.set(level);
+    }
+}
+
+/// Backlight PWM of the south display engine, on graphics with a PCH
+const BLC_PWM_PCH_CTL2: usize = 0xC8254;
+/// Backlight PWM of the display engine, on older graphics
+const BLC_PWM_CTL: usize = 0x61254;
+
+/// The backlight PWM of Intel integrated graphics
+///
+/// Both registers have the cycle length in the upper 16 bits and the duty cycle in the lower 16 bits. The firmware
+/// sets up the PWM, so the register with a cycle length is the one driving the panel.
+pub struct IntelBacklight {
+    register: usize,
+}
+
+impl IntelBacklight {
+    /// Find the backlight of the graphics device, returns `None` if the firmware did not set one up
+    pub unsafe fn new(mut pci: PciConfig) -> Option<Box<Backlight>> {
+        let base = pci.read(0x10) as usize & 0xFFFFFFF0;
+        if base == 0 {
+            return None;
+        }
+
+        for &offset in [BLC_PWM_PCH_CTL2, BLC_PWM_CTL].iter() {
+            let backlight = IntelBacklight {
+                register: base + offset
+            };
+            if backlight.max() > 0 {
+                debugln!(" + Intel backlight on: {:X}, {} of {}", backlight.register, backlight.get(), backlight.max());
+                return Some(box backlight);
+            }
+        }
+
+        None
+    }
+
+    fn read(&self) -> u32 {
+        unsafe { ptr::read_volatile(self.register as *const u32) }
+    }
+
+    fn write(&self, value: u32) {
+        unsafe { ptr::write_volatile(self.register as *mut u32, value) };
+    }
+}
+
+impl Backlight for IntelBacklight {
+    fn max(&self) -> u32 {
+        self.read() >> 16
+    }
+
+    fn get(&self) -> u32 {
+        self.read() & 0xFFFF
+    }
+
+    fn set(&mut self, level: u32) {
+        let max = self.max();
+        let level = if level > max { max } else { level };
+        self.write((max << 16) | level);
+    }
+}
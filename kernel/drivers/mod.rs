@@ -1,12 +1,18 @@
 pub extern crate io;
 
+/// Backlight
+pub mod backlight;
 /// PCI
 pub mod pci;
+/// Pointer settings
+pub mod pointer;
 /// PS2
 pub mod ps2;
 /// RTC
 pub mod rtc;
 /// Serial
 pub mod serial;
+/// VMware absolute pointer
+pub mod vmmouse;
 /// Layouts
 pub mod kb_layouts;
@@ -1,8 +1,13 @@
 use disk::ahci::Ahci;
 use disk::ide::Ide;
 
+use drivers::backlight::IntelBacklight;
+
 use env::Environment;
 
+use graphics::bochs;
+use graphics::virtio_gpu::VirtioGpu;
+
 use super::config::PciConfig;
 use super::common::class::*;
 use super::common::subclass::*;
@@ -37,12 +42,38 @@ pub unsafe fn pci_device(env: &mut Environment,
         (SERIAL_BUS, USB, OHCI) => env.schemes.lock().push(Ohci::new(pci)),
         (SERIAL_BUS, USB, EHCI) => env.schemes.lock().push(Ehci::new(pci)),
         (SERIAL_BUS, USB, XHCI) => env.schemes.lock().push(Xhci::new(pci)),
+        (DISPLAY, VGA, _) if vendor_code == INTEL => {
+            let mut backlight = env.backlight.lock();
+            if backlight.is_none() {
+                *backlight = IntelBacklight::new(pci);
+            }
+        },
         _ => match (vendor_code, device_code) {
             (REALTEK, RTL8139) => env.schemes.lock().push(Rtl8139::new(pci)),
             (INTEL, GBE_82540EM) => env.schemes.lock().push(Intel8254x::new(pci)),
             (INTEL, AC97_82801AA) => env.schemes.lock().push(Ac97::new(pci)),
             (INTEL, AC97_ICH4) => env.schemes.lock().push(Ac97::new(pci)),
             (INTEL, INTELHDA_ICH6) => env.schemes.lock().push(IntelHda::new(pci)),
+            (REDHAT, VIRTIO_GPU) => {
+                // With VGA, it is the display the console started on, otherwise it is another display
+                let primary = (class_id == DISPLAY && subclass_id == VGA) || env.console.lock().display.is_none();
+                let index = if primary { 0 } else { env.displays.lock().len() + 1 };
+                if let Some(gpu) = VirtioGpu::new(pci, index) {
+                    env.schemes.lock().push(gpu);
+                }
+            },
+            // The VGA one is the display the console started on, through VBE
+            (BOCHS, BOCHS_VGA) if class_id == DISPLAY && subclass_id != VGA => {
+                if let Some(display) = bochs::display(pci) {
+                    let mut console = env.console.lock();
+                    if console.display.is_none() {
+                        console.display = Some(display);
+                        console.redraw = true;
+                    } else {
+                        env.displays.lock().push(display);
+                    }
+                }
+            },
             _ => debugln!(" ? CLASS {:02X}.{:02X}.{:02X} ID {:04X}:{:04X}", class_id, subclass_id, interface_id, vendor_code, device_code),
         }
     }
@@ -0,0 +1,112 @@
+use collections::string::String;
+
+use core::cmp;
+
+/// The largest gain, in percent, so a jolt of the mouse does not throw the pointer across the desktop
+const MAX_GAIN: i32 = 2000;
+
+/// A pointer acceleration curve
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Acceleration {
+    /// The same gain at every speed
+    Flat,
+    /// The gain grows with the speed over the threshold
+    Linear,
+    /// The gain grows with the square of the speed over the threshold
+    Quadratic,
+}
+
+impl Acceleration {
+    pub fn parse(name: &str) -> Option<Acceleration> {
+        match name {
+            "flat" => Some(Acceleration::Flat),
+            "linear" => Some(Acceleration::Linear),
+            "quadratic" => Some(Acceleration::Quadratic),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Acceleration::Flat => "flat",
+            Acceleration::Linear => "linear",
+            Acceleration::Quadratic => "quadratic",
+        }
+    }
+}
+
+/// Pointer speed settings, applied to the motion of mice before it is sent to the display manager
+#[derive(Clone, Copy, Debug)]
+pub struct PointerSettings {
+    /// The gain at low speeds, in percent
+    pub sensitivity: i32,
+    /// The curve used above the threshold
+    pub acceleration: Acceleration,
+    /// How quickly the gain grows, in percent per count over the threshold, or per count squared
+    pub factor: i32,
+    /// The speed, in counts per packet, where acceleration starts
+    pub threshold: i32,
+}
+
+impl PointerSettings {
+    pub const fn new() -> PointerSettings {
+        PointerSettings {
+            sensitivity: 100,
+            acceleration: Acceleration::Flat,
+            factor: 10,
+            threshold: 4,
+        }
+    }
+
+    /// The gain in percent for motion of `dx` and `dy` counts in one packet
+    pub fn gain(&self, dx: i32, dy: i32) -> i32 {
+        let over = cmp::max(0, cmp::max(dx.abs(), dy.abs()) - self.threshold);
+        let accel = match self.acceleration {
+            Acceleration::Flat => 0,
+            Acceleration::Linear => self.factor * over,
+            Acceleration::Quadratic => self.factor * over * over,
+        };
+        cmp::min(MAX_GAIN, self.sensitivity * (100 + cmp::min(MAX_GAIN, accel)) / 100)
+    }
+
+    /// Scale motion by a gain, carrying fractions of a pixel, in hundredths, in `remainder`
+    pub fn apply(&self, delta: i32, gain: i32, remainder: &mut i32) -> i32 {
+        let total = delta * gain + *remainder;
+        let pixels = total / 100;
+        *remainder = total - pixels * 100;
+        pixels
+    }
+
+    /// The settings as `key=value` lines
+    pub fn to_string(&self) -> String {
+        format!("sensitivity={}\nacceleration={}\nfactor={}\nthreshold={}\n",
+                self.sensitivity, self.acceleration.name(), self.factor, self.threshold)
+    }
+
+    /// Change a setting from a `key=value` line, returning false if it is invalid
+    pub fn set(&mut self, line: &str) -> bool {
+        let mut parts = line.trim().splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        match key {
+            "sensitivity" => match value.parse::<i32>() {
+                Ok(sensitivity) if sensitivity > 0 && sensitivity <= MAX_GAIN => self.sensitivity = sensitivity,
+                _ => return false
+            },
+            "acceleration" => match Acceleration::parse(value) {
+                Some(acceleration) => self.acceleration = acceleration,
+                None => return false
+            },
+            "factor" => match value.parse::<i32>() {
+                Ok(factor) if factor >= 0 && factor <= MAX_GAIN => self.factor = factor,
+                _ => return false
+            },
+            "threshold" => match value.parse::<i32>() {
+                Ok(threshold) if threshold >= 0 => self.threshold = threshold,
+                _ => return false
+            },
+            _ => return false
+        }
+        true
+    }
+}
@@ -2,15 +2,70 @@ use alloc::boxed::Box;
 
 use core::cmp;
 
-use common::event::{KeyEvent, MouseEvent};
+use common::event::{self, KeyEvent, MouseEvent, MouseRelativeEvent, ScrollEvent};
 
 use drivers::io::{Io, Pio, ReadOnly, WriteOnly};
 
-use graphics::display::VBEMODEINFO;
+use schemes::display::desktop_size;
 
-use fs::KScheme;
+use fs::{KScheme, Resource, Url, VecResource};
+
+use system::error::{Error, Result, ENOENT};
 
 use drivers::kb_layouts::layouts;
+use drivers::vmmouse::{VmMouse, MAX_COORDINATE};
+
+/// Scancode set 1 codes for scancode set 2 codes, without the `E0` prefix, 0 if there is no key
+static SET2_TO_SET1: [u8; 0x84] = [
+    0x00, 0x43, 0x00, 0x3F, 0x3D, 0x3B, 0x3C, 0x58,
+    0x00, 0x44, 0x42, 0x40, 0x3E, 0x0F, 0x29, 0x00,
+    0x00, 0x38, 0x2A, 0x70, 0x1D, 0x10, 0x02, 0x00,
+    0x00, 0x00, 0x2C, 0x1F, 0x1E, 0x11, 0x03, 0x00,
+    0x00, 0x2E, 0x2D, 0x20, 0x12, 0x05, 0x04, 0x00,
+    0x00, 0x39, 0x2F, 0x21, 0x14, 0x13, 0x06, 0x00,
+    0x00, 0x31, 0x30, 0x23, 0x22, 0x15, 0x07, 0x00,
+    0x00, 0x00, 0x32, 0x24, 0x16, 0x08, 0x09, 0x00,
+    0x00, 0x33, 0x25, 0x17, 0x18, 0x0B, 0x0A, 0x00,
+    0x00, 0x34, 0x35, 0x26, 0x27, 0x19, 0x0C, 0x00,
+    0x00, 0x73, 0x28, 0x00, 0x1A, 0x0D, 0x00, 0x00,
+    0x3A, 0x36, 0x1C, 0x1B, 0x00, 0x2B, 0x00, 0x00,
+    0x00, 0x56, 0x00, 0x00, 0x79, 0x00, 0x0E, 0x7B,
+    0x00, 0x4F, 0x7D, 0x4B, 0x47, 0x00, 0x00, 0x00,
+    0x52, 0x53, 0x50, 0x4C, 0x4D, 0x48, 0x01, 0x45,
+    0x57, 0x4E, 0x51, 0x4A, 0x37, 0x49, 0x46, 0x00,
+    0x00, 0x00, 0x00, 0x41,
+];
+
+/// Scancode set 1 codes for `E0` prefixed scancode set 2 codes
+static SET2_TO_SET1_EXTENDED: &'static [(u8, u8)] = &[
+    (0x11, 0x38), // Right alt
+    (0x14, 0x1D), // Right control
+    (0x1F, 0x5B), // Left super
+    (0x27, 0x5C), // Right super
+    (0x2F, 0x5D), // Menu
+    (0x4A, 0x35), // Keypad slash
+    (0x5A, 0x1C), // Keypad enter
+    (0x69, 0x4F), // End
+    (0x6B, 0x4B), // Left
+    (0x6C, 0x47), // Home
+    (0x70, 0x52), // Insert
+    (0x71, 0x53), // Delete
+    (0x72, 0x50), // Down
+    (0x74, 0x4D), // Right
+    (0x75, 0x48), // Up
+    (0x7A, 0x51), // Page down
+    (0x7C, 0x37), // Print screen
+    (0x7D, 0x49), // Page up
+];
+
+/// How key events are reported to the display manager
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyboardMode {
+    /// Characters come from the kernel layout, and extended keys share scancodes with the keypad
+    Translated,
+    /// No characters, and extended keys have `K_EXTENDED` set in their scancode, for a keymap in userspace
+    Raw,
+}
 
 pub struct Ps2Keyboard<'a> {
     bus: &'a mut Ps2
@@ -37,6 +92,19 @@ impl<'a> Ps2Mouse<'a> {
         self.bus.wait_read();
         self.bus.data.read()
     }
+
+    /// Set the sample rate, which is also used to unlock the scroll wheel
+    fn sample_rate(&mut self, rate: u8) {
+        self.cmd(0xF3);
+        self.cmd(rate);
+    }
+
+    /// Read the device ID, 0 for a standard mouse, 3 with a scroll wheel, 4 with a scroll wheel and five buttons
+    fn id(&mut self) -> u8 {
+        self.cmd(0xF2);
+        self.bus.wait_read();
+        self.bus.data.read()
+    }
 }
 
 /// PS2
@@ -57,14 +125,30 @@ pub struct Ps2 {
     caps_lock_toggle: bool,
     /// AltGr?
     altgr: bool,
+    /// The keyboard sends scancode set 2, because the controller does not translate it to set 1
+    set2: bool,
+    /// The last byte was `E0`, the prefix of extended keys
+    extended: bool,
+    /// The last byte was `F0`, the prefix of released keys in scancode set 2
+    release: bool,
+    /// Bytes left in the pause key sequence, which has no release
+    skip: usize,
+    /// How key events are reported to the display manager
+    mode: KeyboardMode,
     /// The mouse packet
     mouse_packet: [u8; 4],
     /// Mouse packet index
     mouse_i: usize,
+    /// Mouse device ID, mice with an ID of 3 or 4 send a fourth byte with the scroll wheel
+    mouse_id: u8,
     /// Mouse point x
     mouse_x: i32,
     /// Mouse point y
     mouse_y: i32,
+    /// Fractions of a pixel of accelerated motion, in hundredths
+    mouse_remainder: (i32, i32),
+    /// The absolute pointer of a virtual machine, which moves the pointer in place of the PS/2 mouse
+    vmmouse: Option<VmMouse>,
     /// Layout for keyboard
     /// Default: English
     layout: layouts::Layout,
@@ -82,10 +166,18 @@ impl Ps2 {
             caps_lock: false,
             caps_lock_toggle: false,
             altgr: false,
+            set2: false,
+            extended: false,
+            release: false,
+            skip: 0,
+            mode: KeyboardMode::Translated,
             mouse_packet: [0; 4],
             mouse_i: 0,
+            mouse_id: 0,
             mouse_x: 0,
             mouse_y: 0,
+            mouse_remainder: (0, 0),
+            vmmouse: None,
             layout: layouts::Layout::English,
         };
 
@@ -170,6 +262,14 @@ impl Ps2 {
                 debugln!("Extra {}: {:X}", line!(), self.data.read());
             }
 
+            // Use scancode set 2, which the controller translates to set 1 if it can
+            debug!("     - Scancode set 2 {:X}", self.keyboard().cmd(0xF0));
+            debugln!(", {:X}", self.keyboard().cmd(2));
+
+            while self.sts.readf(1) {
+                debugln!("Extra {}: {:X}", line!(), self.data.read());
+            }
+
             // Enable Streaming
             debugln!("     - Enable streaming {:X}", self.keyboard().cmd(0xF4));
 
@@ -203,6 +303,28 @@ impl Ps2 {
                 debugln!("Extra {}: {:X}", line!(), self.data.read());
             }
 
+            // Enable the scroll wheel, with a magic sequence of sample rates
+            self.mouse_id = {
+                let mut mouse = self.mouse();
+                mouse.sample_rate(200);
+                mouse.sample_rate(100);
+                mouse.sample_rate(80);
+                if mouse.id() == 3 {
+                    // Enable the horizontal scroll wheel and extra buttons
+                    mouse.sample_rate(200);
+                    mouse.sample_rate(200);
+                    mouse.sample_rate(80);
+                }
+                let id = mouse.id();
+                mouse.sample_rate(100);
+                id
+            };
+            debugln!("     - Mouse ID {:X}", self.mouse_id);
+
+            while self.sts.readf(1) {
+                debugln!("Extra {}: {:X}", line!(), self.data.read());
+            }
+
             // Enable Streaming
             debugln!("     - Enable streaming {:X}", self.mouse().cmd(0xF4));
 
@@ -211,70 +333,125 @@ impl Ps2 {
             }
         }
 
+        // The pointer follows the host cursor in virtual machines, which still interrupt through the PS/2 mouse
+        self.vmmouse = unsafe { VmMouse::new() };
+
         // Key and mouse interrupts, system flag set, clocks enabled, translation enabled
         self.write(0x60, 0b01000111);
 
         while self.sts.readf(1) {
             debugln!("Extra {}: {:X}", line!(), self.data.read());
         }
+
+        // Some controllers cannot translate, and leave the bit clear
+        self.set2 = self.read(0x20) & 0x40 == 0;
+        if self.set2 {
+            debugln!("   + Untranslated scancode set 2");
+        }
+
+        while self.sts.readf(1) {
+            debugln!("Extra {}: {:X}", line!(), self.data.read());
+        }
     }
 
-    /// Keyboard interrupt
-    pub fn keyboard_interrupt(&mut self, mut scancode: u8) -> Option<KeyEvent> {
-        if scancode == 0 {
+    /// Keyboard interrupt, returning the key in scancode set 1 and if it had the `E0` prefix
+    ///
+    /// Bytes are handled one at a time, as prefixes and the rest of a key may arrive in separate interrupts
+    pub fn keyboard_interrupt(&mut self, byte: u8) -> Option<(KeyEvent, bool)> {
+        if self.skip > 0 {
+            self.skip -= 1;
             return None;
-        } else if scancode == 0x2A {
-            self.lshift = true;
-        } else if scancode == 0xAA {
-            self.lshift = false;
-        } else if scancode == 0x36 {
-            self.rshift = true;
-        } else if scancode == 0xB6 {
-            self.rshift = false;
-        } else if scancode == 0x3A {
-            if !self.caps_lock {
-                self.caps_lock = true;
-                self.caps_lock_toggle = true;
+        }
+
+        match byte {
+            0 => return None,
+            0xE0 => {
+                self.extended = true;
+                return None;
+            },
+            0xE1 => {
+                // Pause is E1 1D 45 E1 9D C5 in set 1, and E1 14 77 E1 F0 14 F0 77 in set 2
+                self.skip = if self.set2 { 7 } else { 5 };
+                return None;
+            },
+            0xF0 if self.set2 => {
+                self.release = true;
+                return None;
+            },
+            _ => ()
+        }
+
+        let extended = self.extended;
+        self.extended = false;
+
+        let (scancode, pressed) = if self.set2 {
+            let release = self.release;
+            self.release = false;
+
+            let scancode = if extended {
+                SET2_TO_SET1_EXTENDED.iter().find(|&&(set2, _)| set2 == byte).map_or(0, |&(_, set1)| set1)
             } else {
-                self.caps_lock_toggle = false;
+                SET2_TO_SET1.get(byte as usize).map_or(0, |&set1| set1)
+            };
+            (scancode, ! release)
+        } else {
+            (byte & 0x7F, byte < 0x80)
+        };
+
+        // Extended keys may send fake shifts around them, depending on the state of num lock and shift
+        if scancode == 0 || (extended && (scancode == event::K_LEFT_SHIFT || scancode == event::K_RIGHT_SHIFT)) {
+            return None;
+        }
+
+        if extended {
+            if scancode == event::K_ALT {
+                self.altgr = pressed;
             }
-        } else if scancode == 0xBA {
-            if self.caps_lock && !self.caps_lock_toggle {
+        } else if scancode == event::K_LEFT_SHIFT {
+            self.lshift = pressed;
+        } else if scancode == event::K_RIGHT_SHIFT {
+            self.rshift = pressed;
+        } else if scancode == event::K_CAPS {
+            if pressed {
+                if !self.caps_lock {
+                    self.caps_lock = true;
+                    self.caps_lock_toggle = true;
+                } else {
+                    self.caps_lock_toggle = false;
+                }
+            } else if self.caps_lock && !self.caps_lock_toggle {
                 self.caps_lock = false;
             }
-        } else if scancode == 0xE0 {
-            let scancode_byte_2 = self.data.read();
-            if scancode_byte_2 == 0x38 {
-                self.altgr = true;
-            } else if scancode_byte_2 == 0xB8 {
-                self.altgr = false;
-            } else {
-                scancode = scancode_byte_2;
-            }
         }
 
         let shift = self.caps_lock != (self.lshift || self.rshift);
 
-        return Some(KeyEvent {
-            character: layouts::char_for_scancode(scancode & 0x7F, shift, self.altgr, &self.layout),
-            scancode: scancode & 0x7F,
-            pressed: scancode < 0x80,
-        });
+        Some((KeyEvent {
+            character: layouts::char_for_scancode(scancode, shift, self.altgr, &self.layout),
+            scancode: scancode,
+            pressed: pressed,
+        }, extended))
     }
 
-    /// Mouse interrupt
-    pub fn mouse_interrupt(&mut self, byte: u8) -> Option<MouseEvent> {
+    /// Mouse interrupt, returning the position of the mouse, the motion of the packet, and the scroll
+    pub fn mouse_interrupt(&mut self, byte: u8) -> Option<(MouseEvent, MouseRelativeEvent, ScrollEvent)> {
+        let packet_len = if self.mouse_id == 3 || self.mouse_id == 4 { 4 } else { 3 };
         if self.mouse_i == 0 {
             if byte & 0x8 == 0x8 {
                 self.mouse_packet[0] = byte;
                 self.mouse_i += 1;
             }
-        } else if self.mouse_i == 1 {
-            self.mouse_packet[1] = byte;
+        } else if self.mouse_i + 1 < packet_len {
+            self.mouse_packet[self.mouse_i] = byte;
 
             self.mouse_i += 1;
         } else {
-            self.mouse_packet[2] = byte;
+            self.mouse_packet[self.mouse_i] = byte;
+
+            if self.vmmouse.is_some() {
+                self.mouse_i = 0;
+                return self.vmmouse_interrupt();
+            }
 
             let left_button = (self.mouse_packet[0] & 1) == 1;
             let right_button = (self.mouse_packet[0] & 2) == 2;
@@ -296,25 +473,98 @@ impl Ps2 {
                 y = 0;
             }
 
-            if let Some(mode_info) = unsafe { VBEMODEINFO } {
-                self.mouse_x = cmp::max(0, cmp::min(mode_info.xresolution as i32, self.mouse_x + x));
-                self.mouse_y = cmp::max(0, cmp::min(mode_info.yresolution as i32, self.mouse_y + y));
+            // The pointer is accelerated, relative motion is left raw for clients that grab the pointer
+            let (dx, dy) = {
+                let pointer = ::env().pointer.lock();
+                let gain = pointer.gain(x, y);
+                (pointer.apply(x, gain, &mut self.mouse_remainder.0), pointer.apply(y, gain, &mut self.mouse_remainder.1))
+            };
+
+            let (width, height) = desktop_size();
+            self.mouse_x = cmp::max(0, cmp::min(width as i32, self.mouse_x + dx));
+            self.mouse_y = cmp::max(0, cmp::min(height as i32, self.mouse_y + dy));
+
+            // The fourth byte has the steps of the wheel, as a signed byte or a signed field of the extended format
+            let mut scroll_x = 0;
+            let mut scroll_y = 0;
+            let extra = self.mouse_packet[3] as i32;
+            if self.mouse_id == 3 {
+                scroll_y = -(self.mouse_packet[3] as i8 as i32);
+            } else if self.mouse_id == 4 {
+                match extra & 0xC0 {
+                    0x80 => scroll_y = (extra & 0x1F) - (extra & 0x20),
+                    0x40 => scroll_x = (extra & 0x20) - (extra & 0x1F),
+                    _ => scroll_y = (extra & 0x7) - (extra & 0x8),
+                }
             }
 
             self.mouse_i = 0;
 
-            return Some(MouseEvent {
+            return Some((MouseEvent {
                 x: self.mouse_x,
                 y: self.mouse_y,
                 left_button: left_button,
                 right_button: right_button,
                 middle_button: middle_button,
-            });
+            }, MouseRelativeEvent {
+                dx: x,
+                dy: y,
+            }, ScrollEvent {
+                x: scroll_x,
+                y: scroll_y,
+            }));
         }
 
         return None;
     }
 
+    /// Read the packets of the absolute pointer, returning the last position, its motion, and the scroll of them all
+    ///
+    /// Absolute positions span the whole desktop, as the host shows it in one window
+    fn vmmouse_interrupt(&mut self) -> Option<(MouseEvent, MouseRelativeEvent, ScrollEvent)> {
+        let mut last = None;
+        let mut scroll_y = 0;
+        if let Some(ref mut vmmouse) = self.vmmouse {
+            while let Some(packet) = vmmouse.read() {
+                scroll_y -= packet.z as i8 as i32;
+                last = Some(packet);
+            }
+        }
+
+        let packet = match last {
+            Some(packet) => packet,
+            None => return None
+        };
+
+        let (width, height) = desktop_size();
+        let (x, y) = if packet.relative {
+            (self.mouse_x + packet.x, self.mouse_y + packet.y)
+        } else {
+            ((packet.x as i64 * width as i64 / (MAX_COORDINATE as i64 + 1)) as i32,
+             (packet.y as i64 * height as i64 / (MAX_COORDINATE as i64 + 1)) as i32)
+        };
+        let x = cmp::max(0, cmp::min(width as i32 - 1, x));
+        let y = cmp::max(0, cmp::min(height as i32 - 1, y));
+
+        let relative_event = MouseRelativeEvent {
+            dx: x - self.mouse_x,
+            dy: y - self.mouse_y,
+        };
+        self.mouse_x = x;
+        self.mouse_y = y;
+
+        Some((MouseEvent {
+            x: x,
+            y: y,
+            left_button: packet.left_button,
+            right_button: packet.right_button,
+            middle_button: packet.middle_button,
+        }, relative_event, ScrollEvent {
+            x: 0,
+            y: scroll_y,
+        }))
+    }
+
     /// Function to change the layout of the keyboard
     pub fn change_layout(&mut self, layout: usize) {
         self.layout = match layout {
@@ -327,26 +577,59 @@ impl Ps2 {
 }
 
 impl KScheme for Ps2 {
+    fn scheme(&self) -> &str {
+        "keyboard"
+    }
+
+    /// `keyboard:` reads the mode key events are reported in, `translated` or `raw`,
+    /// and opening `keyboard:translated` or `keyboard:raw` changes it
+    fn open(&mut self, url: Url, _: usize) -> Result<Box<Resource>> {
+        match url.reference().trim_matches('/') {
+            "" => (),
+            "translated" => self.mode = KeyboardMode::Translated,
+            "raw" => self.mode = KeyboardMode::Raw,
+            _ => return Err(Error::new(ENOENT))
+        }
+
+        let mode = match self.mode {
+            KeyboardMode::Translated => "translated",
+            KeyboardMode::Raw => "raw",
+        };
+        Ok(box VecResource::new(format!("keyboard:{}", mode), format!("{}\n", mode).into_bytes()))
+    }
+
     fn on_irq(&mut self, irq: u8) {
         if irq == 0xC || irq == 0x1 {
             loop {
                 let status = self.sts.read();
                 if status & 0x21 == 0x21 {
                     let data = self.data.read();
-                    if let Some(mouse_event) = self.mouse_interrupt(data) {
+                    if let Some((mouse_event, relative_event, scroll_event)) = self.mouse_interrupt(data) {
                         if ::env().console.lock().draw {
                             //Ignore mouse event
                         } else {
-                            ::env().events.send(mouse_event.to_event());
+                            ::env().send_event(mouse_event.to_event());
+                            if relative_event.dx != 0 || relative_event.dy != 0 {
+                                ::env().send_event(relative_event.to_event());
+                            }
+                            if scroll_event.x != 0 || scroll_event.y != 0 {
+                                ::env().send_event(scroll_event.to_event());
+                            }
                         }
                     }
                 } else if status & 0x21 == 0x01 {
                     let data = self.data.read();
-                    if let Some(key_event) = self.keyboard_interrupt(data) {
+                    if let Some((mut key_event, extended)) = self.keyboard_interrupt(data) {
                         if ::env().console.lock().draw {
                             ::env().console.lock().event(key_event.to_event());
                         } else {
-                            ::env().events.send(key_event.to_event());
+                            if self.mode == KeyboardMode::Raw {
+                                key_event.character = '\0';
+                                if extended {
+                                    key_event.scancode |= event::K_EXTENDED;
+                                }
+                            }
+                            ::env().send_event(key_event.to_event());
                         }
                     }
                 } else {
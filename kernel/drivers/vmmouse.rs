@@ -0,0 +1,129 @@
+//! The absolute pointer of virtual machines, through the VMware backdoor
+//!
+//! QEMU, VirtualBox, and VMware report the position of the host cursor over the guest display, so the pointer
+//! follows it without grabbing the mouse. The PS/2 mouse still interrupts for every packet, but its motion is
+//! replaced by the position read from the backdoor.
+
+/// The value in `eax` that marks a call to the backdoor, returned in `ebx` by a hypervisor that has one
+const MAGIC: u32 = 0x564D5868;
+/// The I/O port of the backdoor
+const PORT: u16 = 0x5658;
+
+const CMD_GETVERSION: u32 = 10;
+const CMD_ABSPOINTER_DATA: u32 = 39;
+const CMD_ABSPOINTER_STATUS: u32 = 40;
+const CMD_ABSPOINTER_COMMAND: u32 = 41;
+
+/// Subcommands of `CMD_ABSPOINTER_COMMAND`
+const ABSPOINTER_ENABLE: u32 = 0x45414552;
+const ABSPOINTER_RELATIVE: u32 = 0xF5;
+const ABSPOINTER_ABSOLUTE: u32 = 0x53424152;
+
+/// The first word of data after the pointer is enabled
+const VERSION_ID: u32 = 0x3442554A;
+/// The status when the pointer has to be enabled again
+const STATUS_ERROR: u32 = 0xFFFF0000;
+/// The words of data in a packet
+const PACKET_WORDS: u32 = 4;
+
+/// Flags and buttons in the first word of a packet
+const PACKET_RELATIVE: u32 = 0x10000;
+const BUTTON_LEFT: u32 = 0x20;
+const BUTTON_RIGHT: u32 = 0x10;
+const BUTTON_MIDDLE: u32 = 0x08;
+
+/// The largest coordinate of an absolute position, which is at the right or bottom edge of the display
+pub const MAX_COORDINATE: i32 = 0xFFFF;
+
+/// Call the backdoor, returning `eax`, `ebx`, `ecx`, and `edx`
+unsafe fn backdoor(command: u32, arg: u32) -> (u32, u32, u32, u32) {
+    let eax: usize;
+    let ebx: usize;
+    let ecx: usize;
+    let edx: usize;
+    asm!("in eax, dx"
+        : "={eax}"(eax), "={ebx}"(ebx), "={ecx}"(ecx), "={edx}"(edx)
+        : "{eax}"(MAGIC as usize), "{ebx}"(arg as usize), "{ecx}"(command as usize), "{edx}"(PORT as usize)
+        : "memory"
+        : "intel", "volatile");
+    (eax as u32, ebx as u32, ecx as u32, edx as u32)
+}
+
+/// A report of the pointer
+#[derive(Copy, Clone, Debug)]
+pub struct VmMousePacket {
+    /// The position from 0 to `MAX_COORDINATE`, or the motion if `relative`
+    pub x: i32,
+    pub y: i32,
+    /// The steps of the wheel in the low byte, signed like the fourth byte of a PS/2 packet
+    pub z: i32,
+    /// The host sent motion instead of a position, while it has grabbed the mouse
+    pub relative: bool,
+    pub left_button: bool,
+    pub middle_button: bool,
+    pub right_button: bool,
+}
+
+/// The absolute pointer of the hypervisor
+pub struct VmMouse;
+
+impl VmMouse {
+    /// Switch the hypervisor to absolute positions, returns `None` if there is no hypervisor with the backdoor
+    ///
+    /// On hardware, the port reads as all ones and `ebx` is left as it was, so this is safe to try anywhere.
+    pub unsafe fn new() -> Option<VmMouse> {
+        let (eax, ebx, _, _) = backdoor(CMD_GETVERSION, !MAGIC);
+        if ebx != MAGIC || eax == 0xFFFFFFFF {
+            return None;
+        }
+
+        backdoor(CMD_ABSPOINTER_COMMAND, ABSPOINTER_ENABLE);
+        let (status, _, _, _) = backdoor(CMD_ABSPOINTER_STATUS, 0);
+        if status & 0xFFFF == 0 {
+            return None;
+        }
+        let (version, _, _, _) = backdoor(CMD_ABSPOINTER_DATA, 1);
+        if version != VERSION_ID {
+            return None;
+        }
+
+        backdoor(CMD_ABSPOINTER_COMMAND, ABSPOINTER_ABSOLUTE);
+        debugln!(" + VMware absolute pointer");
+
+        Some(VmMouse)
+    }
+
+    /// Read the next queued packet, if there is one
+    pub fn read(&mut self) -> Option<VmMousePacket> {
+        let (status, _, _, _) = unsafe { backdoor(CMD_ABSPOINTER_STATUS, 0) };
+        if status & STATUS_ERROR == STATUS_ERROR {
+            //The hypervisor lost its state, such as after the machine was restored
+            unsafe {
+                backdoor(CMD_ABSPOINTER_COMMAND, ABSPOINTER_ENABLE);
+                backdoor(CMD_ABSPOINTER_COMMAND, ABSPOINTER_ABSOLUTE);
+            }
+            return None;
+        }
+        if status & 0xFFFF < PACKET_WORDS {
+            return None;
+        }
+
+        let (flags, x, y, z) = unsafe { backdoor(CMD_ABSPOINTER_DATA, PACKET_WORDS) };
+        Some(VmMousePacket {
+            x: x as i32,
+            y: y as i32,
+            z: z as i32,
+            relative: flags & PACKET_RELATIVE == PACKET_RELATIVE,
+            left_button: flags & BUTTON_LEFT == BUTTON_LEFT,
+            middle_button: flags & BUTTON_MIDDLE == BUTTON_MIDDLE,
+            right_button: flags & BUTTON_RIGHT == BUTTON_RIGHT,
+        })
+    }
+}
+
+impl Drop for VmMouse {
+    /// Give the pointer back to the PS/2 mouse
+    fn drop(&mut self) {
+        unsafe { backdoor(CMD_ABSPOINTER_COMMAND, ABSPOINTER_RELATIVE) };
+    }
+}
@@ -1,3 +1,4 @@
+use alloc::arc::Weak;
 use alloc::boxed::Box;
 
 use collections::string::{String, ToString};
@@ -5,65 +6,109 @@ use collections::vec::Vec;
 use collections::vec_deque::VecDeque;
 
 use arch::context::ContextManager;
+use arch::cpu::CpuInfo;
 use arch::intex::Intex;
+use arch::swap::Swap;
 use common::event::Event;
-use common::time::Duration;
+use common::latency::SchemeLatency;
+use common::time::{Duration, NANOS_PER_SEC};
 use disk::Disk;
+use drivers::backlight::Backlight;
+use drivers::pointer::PointerSettings;
+use graphics::display::Display;
 use fs::{KScheme, Resource, Scheme, VecResource, Url};
 use logging::LogLevel;
+use schemes::watch::{watch_notify, Watch, WatchKind};
 use sync::WaitQueue;
 
-use system::error::{Error, Result, ENOENT, EEXIST};
+use system::error::{Error, Result, ENOENT, EEXIST, EXDEV};
 use system::syscall::{O_CREAT, Stat};
 
 use self::console::Console;
+use self::tunables::Tunables;
 
 /// The Kernel Console
 pub mod console;
+/// Settings that can be changed while running
+pub mod tunables;
 
 /// The kernel environment
 pub struct Environment {
     /// Contexts
     pub contexts: Intex<ContextManager>,
+    /// The processor, described at boot
+    pub cpu: CpuInfo,
 
     /// Clock realtime (default)
     pub clock_realtime: Intex<Duration>,
     /// Monotonic clock
     pub clock_monotonic: Intex<Duration>,
+    /// Offset of local time from UTC, in seconds
+    pub utc_offset: Intex<i32>,
 
     /// Default console
     pub console: Intex<Console>,
     /// Disks
     pub disks: Intex<Vec<Box<Disk>>>,
+    /// Secondary displays, the primary display belongs to the console
+    pub displays: Intex<Vec<Box<Display>>>,
+    /// Backlight of the panel of a laptop
+    pub backlight: Intex<Option<Box<Backlight>>>,
     /// Pending events
     pub events: WaitQueue<Event>,
+    /// Pointer speed settings
+    pub pointer: Intex<PointerSettings>,
+    /// Settings that can be changed while running
+    pub tunables: Intex<Tunables>,
     /// Kernel logs
     pub logs: Intex<VecDeque<(Duration, LogLevel, String)>>,
     /// Schemes
     pub schemes: Intex<Vec<Box<KScheme>>>,
+    /// Swap device
+    pub swap: Intex<Option<Swap>>,
+    /// File change watches
+    pub watches: Intex<Vec<Weak<Watch>>>,
 
     /// Interrupt stats
     pub interrupts: Intex<[u64; 256]>,
+    /// Latency of the operations of userspace schemes
+    pub scheme_latency: Intex<SchemeLatency>,
 }
 
 impl Environment {
     pub fn new() -> Box<Environment> {
         box Environment {
             contexts: Intex::new(ContextManager::new()),
+            cpu: CpuInfo::new(),
 
             clock_realtime: Intex::new(Duration::new(0, 0)),
             clock_monotonic: Intex::new(Duration::new(0, 0)),
+            utc_offset: Intex::new(0),
 
             console: Intex::new(Console::new()),
             disks: Intex::new(Vec::new()),
+            displays: Intex::new(Vec::new()),
+            backlight: Intex::new(None),
             events: WaitQueue::new(),
+            pointer: Intex::new(PointerSettings::new()),
+            tunables: Intex::new(Tunables::new()),
             logs: Intex::new(VecDeque::new()),
             schemes: Intex::new(Vec::new()),
+            swap: Intex::new(None),
+            watches: Intex::new(Vec::new()),
 
             interrupts: Intex::new([0; 256]),
+            scheme_latency: Intex::new(SchemeLatency::new()),
         }
     }
 
+    /// Send an input event to the display manager, with the time it was generated
+    pub fn send_event(&self, mut event: Event) {
+        let time = *self.clock_monotonic.lock();
+        event.time = time.secs * NANOS_PER_SEC as i64 + time.nanos as i64;
+        self.events.send(event);
+    }
+
     pub fn on_irq(&self, irq: u8) {
         for mut scheme in self.schemes.lock().iter_mut() {
             scheme.on_irq(irq);
@@ -72,6 +117,11 @@ impl Environment {
 
     /// Open a new resource
     pub fn open(&self, url: Url, flags: usize) -> Result<Box<Resource>> {
+        self.open_mode(url, flags, 0)
+    }
+
+    /// Open a new resource, with the permissions to create it with
+    pub fn open_mode(&self, url: Url, flags: usize, mode: usize) -> Result<Box<Resource>> {
         let url_scheme = url.scheme();
         if url_scheme.is_empty() {
             let url_path = url.reference();
@@ -108,9 +158,16 @@ impl Environment {
                 Err(Error::new(ENOENT))
             }
         } else {
+            //Only pay for the stat when someone is watching
+            let create = flags & O_CREAT == O_CREAT && ! self.watches.lock().is_empty() && self.stat(url, &mut Stat::default()).is_err();
+
             for mut scheme in self.schemes.lock().iter_mut() {
                 if scheme.scheme() == url_scheme {
-                    return scheme.open(url, flags);
+                    let result = scheme.open_mode(url, flags, mode);
+                    if create && result.is_ok() {
+                        watch_notify(WatchKind::Create, &url.to_string());
+                    }
+                    return result;
                 }
             }
             Err(Error::new(ENOENT))
@@ -118,12 +175,37 @@ impl Environment {
     }
 
     /// Makes a directory
-    pub fn mkdir(&self, url: Url, flags: usize) -> Result<()> {
+    pub fn mkdir(&self, url: Url, mode: usize) -> Result<()> {
         let url_scheme = url.scheme();
         if !url_scheme.is_empty() {
             for mut scheme in self.schemes.lock().iter_mut() {
                 if scheme.scheme() == url_scheme {
-                    return scheme.mkdir(url, flags);
+                    let result = scheme.mkdir(url, mode);
+                    if result.is_ok() {
+                        watch_notify(WatchKind::Create, &url.to_string());
+                    }
+                    return result;
+                }
+            }
+        }
+        Err(Error::new(ENOENT))
+    }
+
+    /// Rename a resource, both URLs must have the same scheme
+    pub fn rename(&self, url: Url, new_url: Url) -> Result<()> {
+        let url_scheme = url.scheme();
+        if url_scheme != new_url.scheme() {
+            return Err(Error::new(EXDEV));
+        }
+        if !url_scheme.is_empty() {
+            for mut scheme in self.schemes.lock().iter_mut() {
+                if scheme.scheme() == url_scheme {
+                    let result = scheme.rename(url, new_url);
+                    if result.is_ok() {
+                        watch_notify(WatchKind::Delete, &url.to_string());
+                        watch_notify(WatchKind::Create, &new_url.to_string());
+                    }
+                    return result;
                 }
             }
         }
@@ -136,7 +218,11 @@ impl Environment {
         if !url_scheme.is_empty() {
             for mut scheme in self.schemes.lock().iter_mut() {
                 if scheme.scheme() == url_scheme {
-                    return scheme.rmdir(url);
+                    let result = scheme.rmdir(url);
+                    if result.is_ok() {
+                        watch_notify(WatchKind::Delete, &url.to_string());
+                    }
+                    return result;
                 }
             }
         }
@@ -162,7 +248,11 @@ impl Environment {
         if !url_scheme.is_empty() {
             for mut scheme in self.schemes.lock().iter_mut() {
                 if scheme.scheme() == url_scheme {
-                    return scheme.unlink(url);
+                    let result = scheme.unlink(url);
+                    if result.is_ok() {
+                        watch_notify(WatchKind::Delete, &url.to_string());
+                    }
+                    return result;
                 }
             }
         }
@@ -0,0 +1,25 @@
+use logging::LogLevel;
+
+/// The longest timeslice, in timer ticks
+pub const MAX_TIMESLICE: usize = 100;
+
+/// Kernel settings that can be changed while running, through `sys:/config`
+#[derive(Clone, Copy)]
+pub struct Tunables {
+    /// The timer ticks a context runs before switching to the next one
+    pub timeslice: usize,
+    /// The least important messages kept in the kernel logs
+    pub log_level: LogLevel,
+    /// The receive window advertised by TCP connections, in bytes
+    pub tcp_window: u16,
+}
+
+impl Tunables {
+    pub const fn new() -> Tunables {
+        Tunables {
+            timeslice: 1,
+            log_level: LogLevel::Debug,
+            tcp_window: 65535,
+        }
+    }
+}
@@ -19,7 +19,16 @@ pub trait KScheme {
         Err(Error::new(EPERM))
     }
 
-    fn mkdir(&mut self, path: Url, flags: usize) -> Result<()> {
+    /// Open with the permissions for a created file, which only schemes with permissions need
+    fn open_mode(&mut self, path: Url, flags: usize, mode: usize) -> Result<Box<Resource>> {
+        self.open(path, flags)
+    }
+
+    fn mkdir(&mut self, path: Url, mode: usize) -> Result<()> {
+        Err(Error::new(EPERM))
+    }
+
+    fn rename(&mut self, path: Url, new_path: Url) -> Result<()> {
         Err(Error::new(EPERM))
     }
 
@@ -3,6 +3,8 @@ use alloc::boxed::Box;
 use system::error::{Error, Result, EPERM, ESPIPE};
 use system::syscall::Stat;
 
+use super::Url;
+
 /// Resource seek
 #[derive(Copy, Clone, Debug)]
 pub enum ResourceSeek {
@@ -23,12 +25,24 @@ pub trait Resource {
         Err(Error::new(EPERM))
     }
 
+    /// Link the resource to a path, used to name files created with `O_TMPFILE`
+    /// Returns `EPERM` if the operation is not supported.
+    fn link(&mut self, path: Url) -> Result<()> {
+        Err(Error::new(EPERM))
+    }
+
     /// Return the path of this resource
     /// Returns `EPERM` if the operation is not supported.
     fn path(&self, buf: &mut [u8]) -> Result<usize> {
         Err(Error::new(EPERM))
     }
 
+    /// Return the path of this resource into a buffer in kernel memory, to match it against watches
+    /// Resources whose `path` only accepts the memory of the caller override this.
+    fn kernel_path(&self, buf: &mut [u8]) -> Result<usize> {
+        self.path(buf)
+    }
+
     /// Read data to buffer
     /// Returns `EPERM` if the operation is not supported.
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
@@ -59,6 +73,19 @@ pub trait Resource {
         Err(Error::new(EPERM))
     }
 
+    /// Deallocate a range, which reads as zeros afterwards, without changing the length
+    /// Returns `EPERM` if the operation is not supported.
+    fn punch(&mut self, offset: usize, len: usize) -> Result<()> {
+        Err(Error::new(EPERM))
+    }
+
+    /// Return the physical address of `size` bytes at `offset`, to map them into the memory of the caller, and
+    /// whether the caller may write to them
+    /// Returns `EPERM` if the operation is not supported.
+    fn map(&mut self, offset: usize, size: usize) -> Result<(usize, bool)> {
+        Err(Error::new(EPERM))
+    }
+
     /// Truncate to the given length
     /// Returns `EPERM` if the operation is not supported.
     fn truncate(&mut self, len: usize) -> Result<()> {
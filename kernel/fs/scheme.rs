@@ -10,14 +10,16 @@ use core::ops::DerefMut;
 use core::{ptr, slice};
 
 use arch::context::{Context, ContextMemory};
+use arch::timepage;
 
 use sync::{WaitMap, WaitQueue};
 
 use system::error::{Error, Result, EBADF, EFAULT, EINVAL, ENODEV, ESPIPE};
 use system::scheme::Packet;
-use system::syscall::{SYS_CLOSE, SYS_FPATH, SYS_FSTAT, SYS_FSYNC, SYS_FTRUNCATE,
+use system::syscall::{SYS_CLOSE, SYS_FLINK, SYS_FMAP, SYS_FPATH, SYS_FPUNCH, SYS_FSTAT, SYS_FSYNC, SYS_FTRUNCATE,
                     SYS_OPEN, SYS_LSEEK, SEEK_SET, SEEK_CUR, SEEK_END, SYS_MKDIR,
-                    SYS_READ, SYS_WRITE, SYS_RMDIR, SYS_STAT, SYS_UNLINK, Stat};
+                    SYS_READ, SYS_WRITE, SYS_RENAME, SYS_RMDIR, SYS_STAT, SYS_UNLINK, O_RDWR, O_WRONLY, Stat};
+use system::timepage::rdtsc;
 
 use super::{Resource, ResourceSeek, KScheme, Url};
 
@@ -51,6 +53,7 @@ impl SchemeInner {
             }
             scheme.next_id.set(next_id);
 
+            let start = rdtsc();
             scheme.todo.send(Packet {
                 id: id,
                 a: a,
@@ -58,7 +61,10 @@ impl SchemeInner {
                 c: c,
                 d: d
             });
-            Error::demux(scheme.done.receive(&id).0)
+            let result = scheme.done.receive(&id).0;
+            ::env().scheme_latency.lock().record(&scheme.name, a, timepage::nanos_since(start));
+
+            Error::demux(result)
         } else {
             Err(Error::new(ENODEV))
         }
@@ -78,6 +84,8 @@ impl SchemeInner {
                     virtual_size: size,
                     writeable: writeable,
                     allocated: false,
+                    swap: None,
+                    locked: false,
                 });
                 return Ok(virtual_address);
             }
@@ -86,6 +94,20 @@ impl SchemeInner {
         }
     }
 
+    /// Translate memory of the scheme to physical, if it is pinned so it can be shared
+    fn translate(inner: &Weak<SchemeInner>, virtual_address: usize, size: usize) -> Result<usize> {
+        if let Some(scheme) = inner.upgrade() {
+            let context = unsafe { &*scheme.context };
+            if context.locked(virtual_address, size) {
+                context.translate(virtual_address, size)
+            } else {
+                Err(Error::new(EFAULT))
+            }
+        } else {
+            Err(Error::new(ENODEV))
+        }
+    }
+
     fn release(inner: &Weak<SchemeInner>, virtual_address: usize) {
         if let Some(scheme) = inner.upgrade() {
             unsafe {
@@ -108,6 +130,8 @@ impl Drop for SchemeInner {
 pub struct SchemeResource {
     inner: Weak<SchemeInner>,
     file_id: usize,
+    /// Opened for writing, so the memory of the file is mapped writeable
+    writeable: bool,
 }
 
 impl SchemeResource {
@@ -130,6 +154,19 @@ impl Resource for SchemeResource {
         Err(Error::new(EBADF))
     }
 
+    /// Link this resource to a new path
+    fn link(&mut self, url: Url) -> Result<()> {
+        let c_str = url.to_string() + "\0";
+
+        let virtual_address = try!(self.capture(c_str.as_ptr() as usize, c_str.len(), false));
+
+        let result = self.call(SYS_FLINK, self.file_id, virtual_address, 0);
+
+        self.release(virtual_address);
+
+        result.and(Ok(()))
+    }
+
     /// Return the url of this resource
     fn path(&self, buf: &mut [u8]) -> Result <usize> {
         let contexts = ::env().contexts.lock();
@@ -152,6 +189,20 @@ impl Resource for SchemeResource {
         }
     }
 
+    /// Return the url of this resource into kernel memory, which is mapped to itself
+    fn kernel_path(&self, buf: &mut [u8]) -> Result<usize> {
+        let physical_address = buf.as_mut_ptr() as usize;
+        let offset = physical_address % 4096;
+
+        let virtual_address = try!(self.capture(physical_address - offset, buf.len() + offset, true));
+
+        let result = self.call(SYS_FPATH, self.file_id, virtual_address + offset, buf.len());
+
+        self.release(virtual_address);
+
+        result
+    }
+
     /// Read data to buffer
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         let contexts = ::env().contexts.lock();
@@ -234,6 +285,17 @@ impl Resource for SchemeResource {
         self.call(SYS_FSYNC, self.file_id, 0, 0).and(Ok(()))
     }
 
+    fn punch(&mut self, offset: usize, len: usize) -> Result<()> {
+        self.call(SYS_FPUNCH, self.file_id, offset, len).and(Ok(()))
+    }
+
+    /// Map memory of the scheme, which returns its address
+    fn map(&mut self, offset: usize, size: usize) -> Result<(usize, bool)> {
+        let virtual_address = try!(self.call(SYS_FMAP, self.file_id, offset, size));
+        let physical_address = try!(SchemeInner::translate(&self.inner, virtual_address, size));
+        Ok((physical_address, self.writeable))
+    }
+
     fn truncate(&mut self, len: usize) -> Result<()> {
         self.call(SYS_FTRUNCATE, self.file_id, len, 0).and(Ok(()))
     }
@@ -376,11 +438,15 @@ impl KScheme for Scheme {
     }
 
     fn open(&mut self, url: Url, flags: usize) -> Result<Box<Resource>> {
+        self.open_mode(url, flags, 0)
+    }
+
+    fn open_mode(&mut self, url: Url, flags: usize, mode: usize) -> Result<Box<Resource>> {
         let c_str = url.to_string() + "\0";
 
         let virtual_address = try!(self.capture(c_str.as_ptr() as usize, c_str.len(), false));
 
-        let result = self.call(SYS_OPEN, virtual_address, flags, 0);
+        let result = self.call(SYS_OPEN, virtual_address, flags, mode);
 
         self.release(virtual_address);
 
@@ -388,18 +454,41 @@ impl KScheme for Scheme {
             Ok(file_id) => Ok(box SchemeResource {
                 inner: self.inner.clone(),
                 file_id: file_id,
+                writeable: flags & (O_WRONLY | O_RDWR) != 0,
             }),
             Err(err) => Err(err)
         }
     }
 
-    fn mkdir(&mut self, url: Url, flags: usize) -> Result<()> {
+    fn mkdir(&mut self, url: Url, mode: usize) -> Result<()> {
+        let c_str = url.to_string() + "\0";
+
+        let virtual_address = try!(self.capture(c_str.as_ptr() as usize, c_str.len(), false));
+
+        let result = self.call(SYS_MKDIR, virtual_address, mode, 0);
+
+        self.release(virtual_address);
+
+        result.and(Ok(()))
+    }
+
+    fn rename(&mut self, url: Url, new_url: Url) -> Result<()> {
         let c_str = url.to_string() + "\0";
+        let new_c_str = new_url.to_string() + "\0";
 
         let virtual_address = try!(self.capture(c_str.as_ptr() as usize, c_str.len(), false));
 
-        let result = self.call(SYS_MKDIR, virtual_address, flags, 0);
+        let new_virtual_address = match self.capture(new_c_str.as_ptr() as usize, new_c_str.len(), false) {
+            Ok(new_virtual_address) => new_virtual_address,
+            Err(err) => {
+                self.release(virtual_address);
+                return Err(err);
+            }
+        };
+
+        let result = self.call(SYS_RENAME, virtual_address, new_virtual_address, 0);
 
+        self.release(new_virtual_address);
         self.release(virtual_address);
 
         result.and(Ok(()))
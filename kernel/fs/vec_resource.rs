@@ -96,6 +96,14 @@ impl Resource for VecResource {
         Ok(())
     }
 
+    fn punch(&mut self, offset: usize, len: usize) -> Result<()> {
+        let end = min(self.data.len(), offset.saturating_add(len));
+        for i in min(offset, end)..end {
+            self.data[i] = 0;
+        }
+        Ok(())
+    }
+
     fn truncate(&mut self, len: usize) -> Result<()> {
         while len > self.data.len() {
             self.data.push(0);
@@ -0,0 +1,92 @@
+//! A driver for the secondary VGA of QEMU and Bochs, a second framebuffer beside the display the console started on
+//!
+//! The device is the standard VGA without the legacy I/O ports. Its linear framebuffer is BAR 0, and the Bochs
+//! display interface that sets the mode is in the memory mapped registers of BAR 2.
+
+use alloc::boxed::Box;
+
+use arch::memory;
+
+use core::intrinsics::{volatile_load, volatile_store};
+
+use drivers::pci::config::PciConfig;
+
+use super::color::Color;
+use super::display::Display;
+
+/// The resolution the display is set to
+const WIDTH: u16 = 1024;
+const HEIGHT: u16 = 768;
+
+/// Where the VGA registers are in BAR 2, from port 0x3C0
+const VGA_OFFSET: usize = 0x400;
+/// Where the display interface registers are in BAR 2, each 16 bits
+const DISPI_OFFSET: usize = 0x500;
+
+const DISPI_INDEX_ID: usize = 0;
+const DISPI_INDEX_XRES: usize = 1;
+const DISPI_INDEX_YRES: usize = 2;
+const DISPI_INDEX_BPP: usize = 3;
+const DISPI_INDEX_ENABLE: usize = 4;
+const DISPI_INDEX_VIRT_WIDTH: usize = 6;
+const DISPI_INDEX_VIRT_HEIGHT: usize = 7;
+
+/// The lowest version of the display interface with 32 bit pixels
+const DISPI_ID4: u16 = 0xB0C4;
+const DISPI_ENABLED: u16 = 0x01;
+const DISPI_LFB_ENABLED: u16 = 0x40;
+
+/// Set the mode of the secondary VGA, returning its display
+pub unsafe fn display(mut pci: PciConfig) -> Option<Box<Display>> {
+    let framebuffer = pci.read(0x10);
+    let registers = pci.read(0x18);
+    // Both must be memory BARs
+    if framebuffer & 1 == 1 || registers & 1 == 1 {
+        return None;
+    }
+    let framebuffer = (framebuffer & 0xFFFFFFF0) as usize;
+    let registers = (registers & 0xFFFFFFF0) as usize;
+    if framebuffer == 0 || registers == 0 {
+        return None;
+    }
+
+    pci.flag(4, 2, true); // Memory space
+
+    let dispi = |index: usize| (registers + DISPI_OFFSET + index * 2) as *mut u16;
+    let id = volatile_load(dispi(DISPI_INDEX_ID));
+    if id < DISPI_ID4 || id & 0xFFF0 != 0xB0C0 {
+        debugln!(" ! Bochs VGA: display interface {:X} without 32 bit pixels", id);
+        return None;
+    }
+
+    let size = WIDTH as usize * HEIGHT as usize;
+    let offscreen = memory::alloc(size * 4);
+    if offscreen == 0 {
+        debugln!(" ! Bochs VGA: out of memory");
+        return None;
+    }
+
+    volatile_store(dispi(DISPI_INDEX_ENABLE), 0);
+    volatile_store(dispi(DISPI_INDEX_XRES), WIDTH);
+    volatile_store(dispi(DISPI_INDEX_YRES), HEIGHT);
+    volatile_store(dispi(DISPI_INDEX_BPP), 32);
+    volatile_store(dispi(DISPI_INDEX_VIRT_WIDTH), WIDTH);
+    volatile_store(dispi(DISPI_INDEX_VIRT_HEIGHT), HEIGHT);
+    volatile_store(dispi(DISPI_INDEX_ENABLE), DISPI_ENABLED | DISPI_LFB_ENABLED);
+
+    // Unblank the screen, through the palette address source bit of the attribute controller
+    volatile_store((registers + VGA_OFFSET) as *mut u8, 0x20);
+
+    let display = box Display {
+        offscreen: offscreen as *mut u32,
+        onscreen: framebuffer as *mut u32,
+        size: size,
+        width: WIDTH as usize,
+        height: HEIGHT as usize,
+        scanout: None,
+    };
+    display.set(Color::new(0, 0, 0));
+    display.flip();
+
+    Some(display)
+}
@@ -42,7 +42,7 @@ pub struct VBEModeInfo {
     rsvdmasksize: u8,
     rsvdfieldposition: u8,
     directcolormodeinfo: u8,
-    physbaseptr: u32,
+    pub physbaseptr: u32,
     offscreenmemoryoffset: u32,
     offscreenmemsize: u16,
 }
@@ -58,6 +58,12 @@ pub unsafe fn vbe_init(){
     }
 }
 
+/// A display that has to be told which pixels changed, such as a paravirtual GPU that copies them to the host
+pub trait Scanout: Send {
+    /// Show a range of pixels written to the onscreen memory, starting at an offset from the top left
+    fn flush(&self, offset: usize, len: usize);
+}
+
 /// A display
 pub struct Display {
     pub offscreen: *mut u32,
@@ -65,6 +71,8 @@ pub struct Display {
     pub size: usize,
     pub width: usize,
     pub height: usize,
+    /// Told about writes to `onscreen`, `None` for framebuffers that are shown as they are written
+    pub scanout: Option<Box<Scanout>>,
 }
 
 impl Display {
@@ -77,6 +85,7 @@ impl Display {
                 size: mode_info.xresolution as usize * mode_info.yresolution as usize,
                 width: mode_info.xresolution as usize,
                 height: mode_info.yresolution as usize,
+                scanout: None,
             };
 
             ret.set(Color::new(0, 0, 0));
@@ -110,6 +119,14 @@ impl Display {
         unsafe {
             fast_copy(self.onscreen, self.offscreen, self.size);
         }
+        self.flush(0, self.size);
+    }
+
+    /// Show pixels written directly to `onscreen`
+    pub fn flush(&self, offset: usize, len: usize) {
+        if let Some(ref scanout) = self.scanout {
+            scanout.flush(offset, len);
+        }
     }
 
     /// Draw a rectangle
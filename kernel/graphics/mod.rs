@@ -1,7 +1,11 @@
 
 pub static FONT: &'static [u8] = include_bytes!("../../filesystem/ui/unifont.font");
 
+/// Bochs secondary VGA driver
+pub mod bochs;
 /// Color struct
 pub mod color;
 /// Display struct
 pub mod display;
+/// Virtio GPU driver
+pub mod virtio_gpu;
@@ -0,0 +1,693 @@
+//! A driver for the paravirtual GPU of QEMU and other hosts, with the resolution following the host window
+//!
+//! The device is a virtio 1.0 PCI device. The display is drawn to memory like the VBE framebuffer, and the rows
+//! that changed are copied to the host with a transfer and a flush on the control queue. When the host window is
+//! resized, the device raises a configuration change interrupt, and the display is replaced by one of the new size.
+
+use alloc::arc::Arc;
+use alloc::boxed::Box;
+
+use arch::intex::Intex;
+use arch::memory;
+
+use common::event::DisplayEvent;
+
+use core::{cmp, mem, ptr};
+use core::intrinsics::{volatile_load, volatile_store};
+
+use drivers::pci::config::PciConfig;
+
+use fs::KScheme;
+
+use super::color::Color;
+use super::display::{Display, Scanout};
+
+/// The size of the command buffer, the request is at the start and the response in the second half
+const BUFFER_SIZE: usize = 4096;
+/// The number of descriptors of the control queue, a command uses two
+const QUEUE_SIZE: u16 = 16;
+/// The resolution used when the host does not have one for the display
+const DEFAULT_WIDTH: u32 = 1024;
+const DEFAULT_HEIGHT: u32 = 768;
+/// The times to check for a response before giving up on the device, the clock does not advance while the GPU is
+/// locked
+const COMMAND_SPINS: usize = 100000000;
+
+// PCI capabilities
+const PCI_CAP_VENDOR: u32 = 0x09;
+const VIRTIO_PCI_CAP_COMMON_CFG: u32 = 1;
+const VIRTIO_PCI_CAP_NOTIFY_CFG: u32 = 2;
+const VIRTIO_PCI_CAP_ISR_CFG: u32 = 3;
+const VIRTIO_PCI_CAP_DEVICE_CFG: u32 = 4;
+
+// Common configuration registers
+const COMMON_DRIVER_FEATURE_SELECT: usize = 0x08;
+const COMMON_DRIVER_FEATURE: usize = 0x0C;
+const COMMON_DEVICE_FEATURE_SELECT: usize = 0x00;
+const COMMON_DEVICE_FEATURE: usize = 0x04;
+const COMMON_DEVICE_STATUS: usize = 0x14;
+const COMMON_QUEUE_SELECT: usize = 0x16;
+const COMMON_QUEUE_SIZE: usize = 0x18;
+const COMMON_QUEUE_ENABLE: usize = 0x1C;
+const COMMON_QUEUE_NOTIFY_OFF: usize = 0x1E;
+const COMMON_QUEUE_DESC: usize = 0x20;
+const COMMON_QUEUE_DRIVER: usize = 0x28;
+const COMMON_QUEUE_DEVICE: usize = 0x30;
+
+// Device status
+const STATUS_ACKNOWLEDGE: u8 = 1;
+const STATUS_DRIVER: u8 = 2;
+const STATUS_DRIVER_OK: u8 = 4;
+const STATUS_FEATURES_OK: u8 = 8;
+const STATUS_FAILED: u8 = 128;
+
+/// `VIRTIO_F_VERSION_1`, bit 32 of the features
+const FEATURE_VERSION_1: u32 = 1;
+
+// ISR status
+const ISR_CONFIG: u8 = 2;
+
+// Device configuration registers
+const CONFIG_EVENTS_READ: usize = 0x00;
+const CONFIG_EVENTS_CLEAR: usize = 0x04;
+const EVENT_DISPLAY: u32 = 1;
+
+// Descriptor and ring flags
+const DESC_F_NEXT: u16 = 1;
+const DESC_F_WRITE: u16 = 2;
+const AVAIL_F_NO_INTERRUPT: u16 = 1;
+
+// Commands and responses
+const CMD_GET_DISPLAY_INFO: u32 = 0x0100;
+const CMD_RESOURCE_CREATE_2D: u32 = 0x0101;
+const CMD_RESOURCE_UNREF: u32 = 0x0102;
+const CMD_SET_SCANOUT: u32 = 0x0103;
+const CMD_RESOURCE_FLUSH: u32 = 0x0104;
+const CMD_TRANSFER_TO_HOST_2D: u32 = 0x0105;
+const CMD_RESOURCE_ATTACH_BACKING: u32 = 0x0106;
+const RESP_OK_NODATA: u32 = 0x1100;
+const RESP_OK_DISPLAY_INFO: u32 = 0x1101;
+
+/// Blue, green, red, and an unused byte, the layout of the pixels of a `Display`
+const FORMAT_B8G8R8X8_UNORM: u32 = 2;
+
+const MAX_SCANOUTS: usize = 16;
+
+#[derive(Copy, Clone, Default)]
+#[repr(packed)]
+struct CtrlHeader {
+    kind: u32,
+    flags: u32,
+    fence_id: u64,
+    ctx_id: u32,
+    padding: u32,
+}
+
+impl CtrlHeader {
+    fn new(kind: u32) -> CtrlHeader {
+        CtrlHeader {
+            kind: kind,
+            .. CtrlHeader::default()
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+#[repr(packed)]
+struct GpuRect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Copy, Clone, Default)]
+#[repr(packed)]
+struct DisplayOne {
+    rect: GpuRect,
+    enabled: u32,
+    flags: u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(packed)]
+struct RespDisplayInfo {
+    header: CtrlHeader,
+    displays: [DisplayOne; MAX_SCANOUTS],
+}
+
+#[derive(Copy, Clone)]
+#[repr(packed)]
+struct ResourceCreate2d {
+    header: CtrlHeader,
+    resource_id: u32,
+    format: u32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(packed)]
+struct ResourceUnref {
+    header: CtrlHeader,
+    resource_id: u32,
+    padding: u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(packed)]
+struct SetScanout {
+    header: CtrlHeader,
+    rect: GpuRect,
+    scanout_id: u32,
+    resource_id: u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(packed)]
+struct ResourceFlush {
+    header: CtrlHeader,
+    rect: GpuRect,
+    resource_id: u32,
+    padding: u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(packed)]
+struct TransferToHost2d {
+    header: CtrlHeader,
+    rect: GpuRect,
+    offset: u64,
+    resource_id: u32,
+    padding: u32,
+}
+
+/// Attaching one range of memory as the backing of a resource
+#[derive(Copy, Clone)]
+#[repr(packed)]
+struct ResourceAttachBacking {
+    header: CtrlHeader,
+    resource_id: u32,
+    nr_entries: u32,
+    addr: u64,
+    length: u32,
+    padding: u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(packed)]
+struct QueueDesc {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+/// The memory of a display, shared with the host
+struct Framebuffer {
+    resource_id: u32,
+    memory: usize,
+    width: u32,
+    height: u32,
+}
+
+/// A virtio GPU, showing one display on its first scanout
+pub struct VirtioGpu {
+    common: usize,
+    notify: usize,
+    isr: usize,
+    device: usize,
+    /// The descriptors, driver ring, and device ring of the control queue
+    desc: usize,
+    avail: usize,
+    used: usize,
+    last_used: u16,
+    /// Whether a command timed out, the device may still own the queue so no more commands are sent
+    failed: bool,
+    /// Where commands and responses are copied for the device to read and write
+    buffer: usize,
+    framebuffer: Option<Framebuffer>,
+    next_resource_id: u32,
+    /// The index of the display in the display scheme
+    index: usize,
+}
+
+impl VirtioGpu {
+    /// Set up the GPU, replacing the display at `index` with its scanout
+    ///
+    /// The interrupt handler is returned, to be added to the schemes
+    pub unsafe fn new(mut pci: PciConfig, index: usize) -> Option<Box<KScheme>> {
+        let mut common = 0;
+        let mut notify = 0;
+        let mut notify_multiplier = 0;
+        let mut isr = 0;
+        let mut device = 0;
+
+        if pci.read(0x04) & (1 << 20) == 1 << 20 {
+            let mut cap = pci.read(0x34) & 0xFC;
+            while cap > 0 {
+                let header = pci.read(cap as u8);
+                if header & 0xFF == PCI_CAP_VENDOR {
+                    let bar = pci.read(cap as u8 + 4) & 0xFF;
+                    let offset = pci.read(cap as u8 + 8) as usize;
+                    let address = match bar_address(&mut pci, bar as u8) {
+                        Some(address) => address + offset,
+                        None => 0
+                    };
+                    // The first capability of each type is the preferred one
+                    match (header >> 24) & 0xFF {
+                        VIRTIO_PCI_CAP_COMMON_CFG if common == 0 => common = address,
+                        VIRTIO_PCI_CAP_NOTIFY_CFG if notify == 0 => {
+                            notify = address;
+                            notify_multiplier = pci.read(cap as u8 + 16) as usize;
+                        },
+                        VIRTIO_PCI_CAP_ISR_CFG if isr == 0 => isr = address,
+                        VIRTIO_PCI_CAP_DEVICE_CFG if device == 0 => device = address,
+                        _ => ()
+                    }
+                }
+                cap = (header >> 8) & 0xFC;
+            }
+        }
+
+        if common == 0 || notify == 0 || isr == 0 || device == 0 {
+            debugln!(" ! Virtio GPU without the virtio 1.0 capabilities");
+            return None;
+        }
+
+        let irq = pci.read(0x3C) as u8 & 0xF;
+        pci.flag(4, 4, true); // Bus mastering
+
+        let mut gpu = VirtioGpu {
+            common: common,
+            notify: 0,
+            isr: isr,
+            device: device,
+            desc: memory::alloc_aligned(mem::size_of::<QueueDesc>() * QUEUE_SIZE as usize, 4096),
+            avail: memory::alloc_aligned(6 + 2 * QUEUE_SIZE as usize, 4096),
+            used: memory::alloc_aligned(6 + 8 * QUEUE_SIZE as usize, 4096),
+            last_used: 0,
+            failed: false,
+            buffer: memory::alloc_aligned(BUFFER_SIZE, 4096),
+            framebuffer: None,
+            next_resource_id: 1,
+            index: index,
+        };
+
+        if gpu.desc == 0 || gpu.avail == 0 || gpu.used == 0 || gpu.buffer == 0 {
+            debugln!(" ! Virtio GPU: out of memory");
+            return None;
+        }
+        ptr::write_bytes(gpu.desc as *mut u8, 0, mem::size_of::<QueueDesc>() * QUEUE_SIZE as usize);
+        ptr::write_bytes(gpu.avail as *mut u8, 0, 6 + 2 * QUEUE_SIZE as usize);
+        ptr::write_bytes(gpu.used as *mut u8, 0, 6 + 8 * QUEUE_SIZE as usize);
+
+        // Reset, then tell the device it has a driver
+        gpu.write8(COMMON_DEVICE_STATUS, 0);
+        while gpu.read8(COMMON_DEVICE_STATUS) != 0 {}
+        gpu.write8(COMMON_DEVICE_STATUS, STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+
+        // No features are used, except the modern interface
+        gpu.write32(COMMON_DEVICE_FEATURE_SELECT, 1);
+        if gpu.read32(COMMON_DEVICE_FEATURE) & FEATURE_VERSION_1 == 0 {
+            debugln!(" ! Virtio GPU without virtio 1.0");
+            gpu.write8(COMMON_DEVICE_STATUS, STATUS_FAILED);
+            return None;
+        }
+        gpu.write32(COMMON_DRIVER_FEATURE_SELECT, 0);
+        gpu.write32(COMMON_DRIVER_FEATURE, 0);
+        gpu.write32(COMMON_DRIVER_FEATURE_SELECT, 1);
+        gpu.write32(COMMON_DRIVER_FEATURE, FEATURE_VERSION_1);
+        gpu.write8(COMMON_DEVICE_STATUS, STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_FEATURES_OK);
+        if gpu.read8(COMMON_DEVICE_STATUS) & STATUS_FEATURES_OK == 0 {
+            debugln!(" ! Virtio GPU did not accept the features");
+            gpu.write8(COMMON_DEVICE_STATUS, STATUS_FAILED);
+            return None;
+        }
+
+        // The control queue is queue 0, commands are polled so it does not interrupt
+        gpu.write16(COMMON_QUEUE_SELECT, 0);
+        let size = gpu.read16(COMMON_QUEUE_SIZE);
+        if size < QUEUE_SIZE {
+            debugln!(" ! Virtio GPU control queue too small: {}", size);
+            gpu.write8(COMMON_DEVICE_STATUS, STATUS_FAILED);
+            return None;
+        }
+        gpu.write16(COMMON_QUEUE_SIZE, QUEUE_SIZE);
+        gpu.write64(COMMON_QUEUE_DESC, gpu.desc as u64);
+        gpu.write64(COMMON_QUEUE_DRIVER, gpu.avail as u64);
+        gpu.write64(COMMON_QUEUE_DEVICE, gpu.used as u64);
+        gpu.notify = notify + gpu.read16(COMMON_QUEUE_NOTIFY_OFF) as usize * notify_multiplier;
+        volatile_store(gpu.avail as *mut u16, AVAIL_F_NO_INTERRUPT);
+        gpu.write16(COMMON_QUEUE_ENABLE, 1);
+
+        gpu.write8(COMMON_DEVICE_STATUS, STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_FEATURES_OK | STATUS_DRIVER_OK);
+
+        let (width, height) = gpu.display_size().unwrap_or((DEFAULT_WIDTH, DEFAULT_HEIGHT));
+        debugln!(" + Virtio GPU on: {:X}, IRQ: {:X}, {}x{}", common, irq, width, height);
+
+        let gpu = Arc::new(Intex::new(gpu));
+        if ! set_mode(&gpu, width, height) {
+            debugln!(" ! Virtio GPU: failed to set {}x{}", width, height);
+            return None;
+        }
+
+        Some(box VirtioGpuIrq {
+            gpu: gpu,
+            irq: irq
+        })
+    }
+
+    fn read8(&self, register: usize) -> u8 {
+        unsafe { volatile_load((self.common + register) as *const u8) }
+    }
+
+    fn write8(&self, register: usize, value: u8) {
+        unsafe { volatile_store((self.common + register) as *mut u8, value) };
+    }
+
+    fn read16(&self, register: usize) -> u16 {
+        unsafe { volatile_load((self.common + register) as *const u16) }
+    }
+
+    fn write16(&self, register: usize, value: u16) {
+        unsafe { volatile_store((self.common + register) as *mut u16, value) };
+    }
+
+    fn read32(&self, register: usize) -> u32 {
+        unsafe { volatile_load((self.common + register) as *const u32) }
+    }
+
+    fn write32(&self, register: usize, value: u32) {
+        unsafe { volatile_store((self.common + register) as *mut u32, value) };
+    }
+
+    /// 64-bit registers are written as two halves, low first
+    fn write64(&self, register: usize, value: u64) {
+        self.write32(register, value as u32);
+        self.write32(register + 4, (value >> 32) as u32);
+    }
+
+    /// Send a command and wait for the response, returning false if there is no room, the device does not respond,
+    /// or the type of the response is not `expected`
+    fn command<T: Copy, R: Copy>(&mut self, request: &T, response: &mut R, expected: u32) -> bool {
+        let request_size = mem::size_of::<T>();
+        let response_size = mem::size_of::<R>();
+        if self.failed || request_size > BUFFER_SIZE / 2 || response_size > BUFFER_SIZE / 2 {
+            return false;
+        }
+
+        unsafe {
+            let request_addr = self.buffer;
+            let response_addr = self.buffer + BUFFER_SIZE / 2;
+            ptr::write(request_addr as *mut T, *request);
+            ptr::write_bytes(response_addr as *mut u8, 0, response_size);
+
+            let desc = self.desc as *mut QueueDesc;
+            ptr::write(desc, QueueDesc {
+                addr: request_addr as u64,
+                len: request_size as u32,
+                flags: DESC_F_NEXT,
+                next: 1
+            });
+            ptr::write(desc.offset(1), QueueDesc {
+                addr: response_addr as u64,
+                len: response_size as u32,
+                flags: DESC_F_WRITE,
+                next: 0
+            });
+
+            let avail_idx = (self.avail + 2) as *mut u16;
+            let idx = volatile_load(avail_idx);
+            volatile_store((self.avail + 4 + 2 * (idx % QUEUE_SIZE) as usize) as *mut u16, 0);
+            volatile_store(avail_idx, idx.wrapping_add(1));
+            volatile_store(self.notify as *mut u16, 0);
+
+            let used_idx = (self.used + 2) as *const u16;
+            let mut spins = 0;
+            while volatile_load(used_idx) == self.last_used {
+                spins += 1;
+                if spins >= COMMAND_SPINS {
+                    debugln!(" ! Virtio GPU: command {:X} timed out", volatile_load(request_addr as *const u32));
+                    self.failed = true;
+                    return false;
+                }
+            }
+            self.last_used = self.last_used.wrapping_add(1);
+
+            *response = ptr::read(response_addr as *const R);
+            volatile_load(response_addr as *const u32) == expected
+        }
+    }
+
+    /// The size of the first scanout, as the host window is, if it is enabled
+    fn display_size(&mut self) -> Option<(u32, u32)> {
+        let request = CtrlHeader::new(CMD_GET_DISPLAY_INFO);
+        let mut response = RespDisplayInfo {
+            header: CtrlHeader::default(),
+            displays: [DisplayOne::default(); MAX_SCANOUTS]
+        };
+        if self.command(&request, &mut response, RESP_OK_DISPLAY_INFO) {
+            let display = response.displays[0];
+            if display.enabled != 0 && display.rect.width > 0 && display.rect.height > 0 {
+                return Some((display.rect.width, display.rect.height));
+            }
+        }
+        None
+    }
+
+    fn ok<T: Copy>(&mut self, request: &T) -> bool {
+        let mut response = CtrlHeader::default();
+        self.command(request, &mut response, RESP_OK_NODATA)
+    }
+
+    /// Create a resource with memory for a display of a size, and show it on the first scanout
+    fn create_framebuffer(&mut self, width: u32, height: u32) -> Option<Framebuffer> {
+        let size = width as usize * height as usize * 4;
+        let memory = unsafe { memory::alloc_aligned(size, 4096) };
+        if memory == 0 {
+            return None;
+        }
+        unsafe { ptr::write_bytes(memory as *mut u8, 0, size) };
+
+        let resource_id = self.next_resource_id;
+        self.next_resource_id += 1;
+
+        let rect = GpuRect {
+            x: 0,
+            y: 0,
+            width: width,
+            height: height
+        };
+        if self.ok(&ResourceCreate2d {
+            header: CtrlHeader::new(CMD_RESOURCE_CREATE_2D),
+            resource_id: resource_id,
+            format: FORMAT_B8G8R8X8_UNORM,
+            width: width,
+            height: height
+        }) && self.ok(&ResourceAttachBacking {
+            header: CtrlHeader::new(CMD_RESOURCE_ATTACH_BACKING),
+            resource_id: resource_id,
+            nr_entries: 1,
+            addr: memory as u64,
+            length: size as u32,
+            padding: 0
+        }) && self.ok(&SetScanout {
+            header: CtrlHeader::new(CMD_SET_SCANOUT),
+            rect: rect,
+            scanout_id: 0,
+            resource_id: resource_id
+        }) {
+            Some(Framebuffer {
+                resource_id: resource_id,
+                memory: memory,
+                width: width,
+                height: height
+            })
+        } else {
+            self.ok(&ResourceUnref {
+                header: CtrlHeader::new(CMD_RESOURCE_UNREF),
+                resource_id: resource_id,
+                padding: 0
+            });
+            unsafe { memory::unalloc(memory) };
+            None
+        }
+    }
+
+    /// Release a framebuffer that is no longer shown, its display must have been replaced
+    fn destroy_framebuffer(&mut self, framebuffer: Framebuffer) {
+        self.ok(&ResourceUnref {
+            header: CtrlHeader::new(CMD_RESOURCE_UNREF),
+            resource_id: framebuffer.resource_id,
+            padding: 0
+        });
+        unsafe { memory::unalloc(framebuffer.memory) };
+    }
+
+    /// Copy the rows containing a range of pixels to the host and show them
+    fn flush(&mut self, offset: usize, len: usize) {
+        let (resource_id, width, height) = match self.framebuffer {
+            Some(ref framebuffer) => (framebuffer.resource_id, framebuffer.width, framebuffer.height),
+            None => return
+        };
+        if width == 0 || len == 0 {
+            return;
+        }
+
+        let top = cmp::min(offset / width as usize, height as usize) as u32;
+        let bottom = cmp::min((offset + len + width as usize - 1) / width as usize, height as usize) as u32;
+        if bottom <= top {
+            return;
+        }
+
+        let rect = GpuRect {
+            x: 0,
+            y: top,
+            width: width,
+            height: bottom - top
+        };
+        self.ok(&TransferToHost2d {
+            header: CtrlHeader::new(CMD_TRANSFER_TO_HOST_2D),
+            rect: rect,
+            offset: top as u64 * width as u64 * 4,
+            resource_id: resource_id,
+            padding: 0
+        });
+        self.ok(&ResourceFlush {
+            header: CtrlHeader::new(CMD_RESOURCE_FLUSH),
+            rect: rect,
+            resource_id: resource_id,
+            padding: 0
+        });
+    }
+}
+
+/// The address of a memory BAR, `None` if it is for I/O ports or above 4 GiB
+unsafe fn bar_address(pci: &mut PciConfig, bar: u8) -> Option<usize> {
+    if bar > 5 {
+        return None;
+    }
+    let value = pci.read(0x10 + bar * 4);
+    if value & 1 == 1 {
+        return None;
+    }
+    if (value >> 1) & 3 == 2 && (bar == 5 || pci.read(0x10 + (bar + 1) * 4) != 0) {
+        return None;
+    }
+    Some((value & 0xFFFFFFF0) as usize)
+}
+
+/// Show a display of a size, replacing the display of the GPU in the display scheme
+fn set_mode(gpu: &Arc<Intex<VirtioGpu>>, width: u32, height: u32) -> bool {
+    let size = width as usize * height as usize;
+    let offscreen = unsafe { memory::alloc(size * 4) };
+    if offscreen == 0 {
+        return false;
+    }
+
+    let (framebuffer, index) = {
+        let mut gpu = gpu.lock();
+        match gpu.create_framebuffer(width, height) {
+            Some(framebuffer) => (framebuffer, gpu.index),
+            None => {
+                unsafe { memory::unalloc(offscreen) };
+                return false;
+            }
+        }
+    };
+
+    let display = box Display {
+        offscreen: offscreen as *mut u32,
+        onscreen: framebuffer.memory as *mut u32,
+        size: size,
+        width: width as usize,
+        height: height as usize,
+        scanout: Some(box VirtioScanout {
+            gpu: gpu.clone()
+        }),
+    };
+    display.set(Color::new(0, 0, 0));
+
+    // The old display is dropped before its memory is released
+    if index == 0 {
+        let mut console = ::env().console.lock();
+        console.display = Some(display);
+        console.point_x = 0;
+        console.point_y = 0;
+        console.redraw = true;
+    } else {
+        let mut displays = ::env().displays.lock();
+        if index - 1 < displays.len() {
+            displays[index - 1] = display;
+        } else {
+            displays.push(display);
+        }
+    }
+
+    let mut gpu = gpu.lock();
+    if let Some(old) = gpu.framebuffer.take() {
+        gpu.destroy_framebuffer(old);
+    }
+    gpu.framebuffer = Some(framebuffer);
+
+    true
+}
+
+/// Copies the pixels drawn to a display to the host
+struct VirtioScanout {
+    gpu: Arc<Intex<VirtioGpu>>,
+}
+
+impl Scanout for VirtioScanout {
+    fn flush(&self, offset: usize, len: usize) {
+        self.gpu.lock().flush(offset, len);
+    }
+}
+
+/// Follows the resolution of the host window, on the configuration change interrupt of the GPU
+struct VirtioGpuIrq {
+    gpu: Arc<Intex<VirtioGpu>>,
+    irq: u8,
+}
+
+impl KScheme for VirtioGpuIrq {
+    fn on_irq(&mut self, irq: u8) {
+        if irq != self.irq {
+            return;
+        }
+
+        let resize = {
+            let mut gpu = self.gpu.lock();
+            let isr = unsafe { volatile_load(gpu.isr as *const u8) };
+            if isr & ISR_CONFIG == ISR_CONFIG {
+                let events = unsafe { volatile_load((gpu.device + CONFIG_EVENTS_READ) as *const u32) };
+                if events & EVENT_DISPLAY == EVENT_DISPLAY {
+                    unsafe { volatile_store((gpu.device + CONFIG_EVENTS_CLEAR) as *mut u32, EVENT_DISPLAY) };
+                    let current = gpu.framebuffer.as_ref().map(|framebuffer| (framebuffer.width, framebuffer.height));
+                    match gpu.display_size() {
+                        Some(size) if Some(size) != current => Some((gpu.index, size)),
+                        _ => None
+                    }
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        };
+
+        if let Some((index, (width, height))) = resize {
+            if set_mode(&self.gpu, width, height) {
+                debugln!(" + Virtio GPU resized to {}x{}", width, height);
+                if ! ::env().console.lock().draw {
+                    ::env().send_event(DisplayEvent {
+                        index: index as u32,
+                        width: width,
+                        height: height
+                    }.to_event());
+                }
+            }
+        }
+    }
+}
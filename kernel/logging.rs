@@ -1,7 +1,8 @@
 use collections::borrow::ToOwned;
 use collections::String;
 
-#[derive(Copy, Clone)]
+/// The priority of a log message, from the most important to the least
+#[derive(Copy, Clone, PartialEq, PartialOrd)]
 pub enum LogLevel {
     Critical,
     Error,
@@ -10,6 +11,29 @@ pub enum LogLevel {
     Debug,
 }
 
+impl LogLevel {
+    pub fn parse(name: &str) -> Option<LogLevel> {
+        match name {
+            "critical" => Some(LogLevel::Critical),
+            "error" => Some(LogLevel::Error),
+            "warning" => Some(LogLevel::Warning),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            LogLevel::Critical => "critical",
+            LogLevel::Error => "error",
+            LogLevel::Warning => "warning",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+}
+
 /// Add message to kernel logs with format
 #[macro_export]
 macro_rules! syslog {
@@ -60,6 +84,10 @@ pub fn syslog(level: LogLevel, message: &str) {
 
 //TODO: Limit log message size
 pub fn syslog_inner(level: LogLevel, message: String) {
+    if level > ::env().tunables.lock().log_level {
+        return;
+    }
+
     let time = ::env().clock_monotonic.lock().clone();
     let mut logs = ::env().logs.lock();
     while logs.len() >= 1024 {
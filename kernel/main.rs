@@ -35,12 +35,16 @@ extern crate system;
 
 use acpi::Acpi;
 
+use alloc::arc::Arc;
 use alloc::boxed::Box;
 
-use arch::context::{context_switch, Context};
+use arch::context::{context_switch, Context, CONTEXT_IMAGE_ADDR};
+use arch::fpu;
 use arch::memory;
 use arch::paging::Page;
 use arch::regs::Regs;
+use arch::swap::Swap;
+use arch::timepage;
 use arch::tss::Tss;
 
 use collections::Vec;
@@ -49,6 +53,8 @@ use collections::string::ToString;
 use core::{mem, usize};
 use core::slice::SliceExt;
 
+use common::{random, symbols};
+use common::backtrace::Backtrace;
 use common::time::Duration;
 
 use drivers::pci;
@@ -63,6 +69,7 @@ use graphics::display;
 
 use network::schemes::{ArpScheme, EthernetScheme, IcmpScheme, IpScheme, TcpScheme, UdpScheme};
 
+use schemes::clock::ClockScheme;
 use schemes::context::ContextScheme;
 use schemes::debug::DebugScheme;
 use schemes::disk::DiskScheme;
@@ -72,12 +79,21 @@ use schemes::env::EnvScheme;
 use schemes::initfs::InitFsScheme;
 use schemes::interrupt::InterruptScheme;
 use schemes::memory::MemoryScheme;
+use schemes::pointer::PointerScheme;
+use schemes::sem::SemScheme;
+use schemes::stats::StatsScheme;
+use schemes::sys::SysScheme;
 use schemes::syslog::SyslogScheme;
 use schemes::test::TestScheme;
+use schemes::watch::WatchScheme;
+
+use sync::Intex;
 
 use syscall::execute::execute;
 use syscall::{do_sys_chdir, do_sys_exit, do_sys_open, syscall_handle};
 
+use system::syscall::{EXIT_SIGNAL, SIGBUS, SIGFPE, SIGILL, SIGKILL, SIGSEGV, SIGTRAP};
+
 pub use externs::*;
 
 /// Common std-like functionality.
@@ -257,6 +273,15 @@ extern {
     static mut __bss_end: u8;
 }
 
+/// The size of the stack double faults are handled on.
+#[cfg(target_arch = "x86_64")]
+const DOUBLE_FAULT_STACK_SIZE: usize = 16384;
+
+/// The stack double faults are handled on, so a kernel stack overflow is reported instead of
+/// faulting again while pushing the registers.
+#[cfg(target_arch = "x86_64")]
+static mut DOUBLE_FAULT_STACK: [u8; DOUBLE_FAULT_STACK_SIZE] = [0; DOUBLE_FAULT_STACK_SIZE];
+
 /// Test of zero values in BSS.
 static BSS_TEST_ZERO: usize = 0;
 /// Test of non-zero values in BSS.
@@ -287,9 +312,13 @@ unsafe fn init(tss_data: usize) {
         debug_assert_eq!(BSS_TEST_NONZERO, usize::MAX);
     }
 
+    // Find how the floating point registers are saved, before any context switch
+    fpu::init();
+
     // Setup paging, this allows for memory allocation
     Page::init();
     memory::cluster_init();
+    timepage::init();
 
     // Get the VBE information before unmapping the first megabyte
     display::vbe_init();
@@ -346,7 +375,25 @@ unsafe fn init(tss_data: usize) {
         }
     }
 
+    // Use large pages for the kernel heap and the framebuffer, to reduce TLB misses
+    {
+        Page::map_large(0, CONTEXT_IMAGE_ADDR);
+        if let Some(mode_info) = display::VBEMODEINFO {
+            let start_ptr = mode_info.physbaseptr as usize;
+            let end_ptr = start_ptr + mode_info.xresolution as usize * mode_info.yresolution as usize * 4;
+            Page::map_large(start_ptr, end_ptr);
+        }
+    }
+
     TSS_PTR = Some(&mut *(tss_data as *mut Tss));
+
+    // The first interrupt stack of the TSS is used by the double fault handler
+    #[cfg(target_arch = "x86_64")]
+    {
+        if let Some(ref mut tss) = TSS_PTR {
+            tss.ist1 = (DOUBLE_FAULT_STACK.as_ptr() as usize + DOUBLE_FAULT_STACK_SIZE) & !0xF;
+        }
+    }
     ENV_PTR = Some(&mut *Box::into_raw(Environment::new()));
 
     match ENV_PTR {
@@ -362,6 +409,7 @@ unsafe fn init(tss_data: usize) {
             debugln!("  * data={:X}:{:X} bss={:X}:{:X}",
                     & __data_start as *const u8 as usize, & __data_end as *const u8 as usize,
                     & __bss_start as *const u8 as usize, & __bss_end as *const u8 as usize);
+            debugln!("  * fpu={}", fpu::name());
 
             if let Some(acpi) = Acpi::new() {
                 env.schemes.lock().push(acpi);
@@ -369,6 +417,14 @@ unsafe fn init(tss_data: usize) {
 
             *(env.clock_realtime.lock()) = Rtc::new().time();
 
+            //Seed from the clock, unless a seed was given to reproduce a run
+            let seed = random::boot_seed().unwrap_or_else(|| {
+                let time = *env.clock_realtime.lock();
+                (time.secs as u64) ^ ((time.nanos as u64) << 32)
+            });
+            random::srand(seed);
+            debugln!("  * random seed={}", seed);
+
             env.schemes.lock().push(Ps2::new());
             env.schemes.lock().push(Serial::new(0x3F8, 0x4));
 
@@ -376,17 +432,36 @@ unsafe fn init(tss_data: usize) {
 
             env.schemes.lock().push(DebugScheme::new());
             env.schemes.lock().push(InitFsScheme::new());
+            env.schemes.lock().push(box ClockScheme);
+            env.schemes.lock().push(box PointerScheme);
             env.schemes.lock().push(box ContextScheme);
             env.schemes.lock().push(box DisplayScheme);
             env.schemes.lock().push(box EnvScheme);
             env.schemes.lock().push(box InterruptScheme);
             env.schemes.lock().push(box MemoryScheme);
+            env.schemes.lock().push(box SemScheme::new());
+            env.schemes.lock().push(box StatsScheme);
+            env.schemes.lock().push(box SysScheme);
             env.schemes.lock().push(box SyslogScheme);
             env.schemes.lock().push(box TestScheme);
+            env.schemes.lock().push(box WatchScheme);
 
             //TODO: Do not do this! Find a better way
             let mut disks = Vec::new();
-            disks.append(&mut env.disks.lock());
+            for disk in env.disks.lock().drain(..) {
+                disks.push(Arc::new(Intex::new(disk)));
+            }
+
+            //Use the first disk with a swap header for swap
+            //Swap goes to the driver directly, as it transfers while switching contexts, where the I/O scheduler can not wait
+            for disk in disks.iter() {
+                if let Some(swap) = Swap::open(disk.clone()) {
+                    debugln!("SWAP: {} KB on {}", swap.size() / 1024, disk.lock().name());
+                    *env.swap.lock() = Some(swap);
+                    break;
+                }
+            }
+
             env.schemes.lock().push(DiskScheme::new(disks));
 
             env.schemes.lock().push(box EthernetScheme);
@@ -417,9 +492,9 @@ unsafe fn init(tss_data: usize) {
                     do_sys_chdir(wd_c.as_ptr()).unwrap();
 
                     let stdio_c = "debug:\0";
-                    do_sys_open(stdio_c.as_ptr(), 0).unwrap();
-                    do_sys_open(stdio_c.as_ptr(), 0).unwrap();
-                    do_sys_open(stdio_c.as_ptr(), 0).unwrap();
+                    do_sys_open(stdio_c.as_ptr(), 0, 0).unwrap();
+                    do_sys_open(stdio_c.as_ptr(), 0, 0).unwrap();
+                    do_sys_open(stdio_c.as_ptr(), 0, 0).unwrap();
 
                     if let Some(ref display) = ::env().console.lock().display {
                         let mut contexts = ::env().contexts.lock();
@@ -439,6 +514,25 @@ unsafe fn init(tss_data: usize) {
     }
 }
 
+/// End the context that caused an exception, with the signal of the exception in its exit status.
+///
+/// Exceptions in programs are added to the kernel log with where they happened, so the program
+/// is known to have crashed. Exceptions in the kernel are only printed by the handler, as the log
+/// may be locked by the code that caused them.
+fn fault(name: &str, signal: usize, regs: &Regs) -> ! {
+    if regs.cs & 3 == 3 {
+        let context = {
+            let contexts = env().contexts.lock();
+            contexts.current().map(|context| (context.pid, context.name.clone()))
+        };
+        if let Ok((pid, context_name)) = context {
+            syslog_error!("PID {}: {}: {} at {:X}, exiting with signal {}", pid, context_name, name, regs.ip, signal);
+        }
+    }
+
+    do_sys_exit(EXIT_SIGNAL + signal);
+}
+
 #[cold]
 #[inline(never)]
 #[no_mangle]
@@ -455,9 +549,12 @@ pub extern "cdecl" fn kernel(interrupt: usize, mut regs: &mut Regs) {
 
             debugln!("  INT {:X}: {}", interrupt, $name);
             debugln!("    CS:  {:08X}    IP:  {:08X}    FLG: {:08X}", regs.cs, regs.ip, regs.flags);
+            if let Some((name, offset)) = symbols::lookup(regs.ip) {
+                debugln!("    IN:  {}+{:X}", name, offset);
+            }
             debugln!("    SS:  {:08X}    SP:  {:08X}    BP:  {:08X}", regs.ss, regs.sp, regs.bp);
             debugln!("    AX:  {:08X}    BX:  {:08X}    CX:  {:08X}    DX:  {:08X}", regs.ax, regs.bx, regs.cx, regs.dx);
-            debugln!("    DI:  {:08X}    SI:  {:08X}", regs.di, regs.di);
+            debugln!("    DI:  {:08X}    SI:  {:08X}", regs.di, regs.si);
 
             let cr0: usize;
             let cr2: usize;
@@ -479,6 +576,10 @@ pub extern "cdecl" fn kernel(interrupt: usize, mut regs: &mut Regs) {
             }
             debugln!("    FSW: {:08X}    FCW: {:08X}", fsw, fcw);
 
+            if let Some(backtrace) = Backtrace::from_regs(regs) {
+                backtrace.print();
+            }
+
             /* TODO: Stack dump
             {
                 let contexts = ::env().contexts.lock();
@@ -505,17 +606,15 @@ pub extern "cdecl" fn kernel(interrupt: usize, mut regs: &mut Regs) {
     };
 
     macro_rules! exception {
-        ($name:expr) => ({
+        ($name:expr, $signal:expr) => ({
             exception_inner!($name);
 
-            loop {
-                do_sys_exit(usize::MAX);
-            }
+            fault($name, $signal, regs);
         })
     };
 
     macro_rules! exception_error {
-        ($name:expr) => ({
+        ($name:expr, $signal:expr) => ({
             let error = regs.ip;
             regs.ip = regs.cs;
             regs.cs = regs.flags;
@@ -527,9 +626,7 @@ pub extern "cdecl" fn kernel(interrupt: usize, mut regs: &mut Regs) {
             exception_inner!($name);
             debugln!("    ERR: {:08X}", error);
 
-            loop {
-                do_sys_exit(usize::MAX);
-            }
+            fault($name, $signal, regs);
         })
     };
 
@@ -548,12 +645,19 @@ pub extern "cdecl" fn kernel(interrupt: usize, mut regs: &mut Regs) {
                 let mut clock_realtime = env().clock_realtime.lock();
                 *clock_realtime = *clock_realtime + PIT_DURATION;
             }
+            unsafe { timepage::update(*env().clock_monotonic.lock(), *env().clock_realtime.lock(), PIT_DURATION) };
 
+            //Switch contexts at the end of each timeslice
+            let timeslice = env().tunables.lock().timeslice;
+            let mut switch = true;
             if let Ok(mut current) = env().contexts.lock().current_mut() {
                 current.time += 1;
+                switch = current.time % timeslice == 0;
             }
 
-            unsafe { context_switch(); }
+            if switch {
+                unsafe { context_switch(); }
+            }
         }
         i @ 0x21 ... 0x2F => {
             env().on_irq(i as u8 - 0x20);
@@ -565,28 +669,28 @@ pub extern "cdecl" fn kernel(interrupt: usize, mut regs: &mut Regs) {
                 idle_loop();
             }
         },
-        0x0 => exception!("Divide by zero exception"),
-        0x1 => exception!("Debug exception"),
-        0x2 => exception!("Non-maskable interrupt"),
-        0x3 => exception!("Breakpoint exception"),
-        0x4 => exception!("Overflow exception"),
-        0x5 => exception!("Bound range exceeded exception"),
-        0x6 => exception!("Invalid opcode exception"),
-        0x7 => exception!("Device not available exception"),
-        0x8 => exception_error!("Double fault"),
-        0x9 => exception!("Coprocessor Segment Overrun"), // legacy
-        0xA => exception_error!("Invalid TSS exception"),
-        0xB => exception_error!("Segment not present exception"),
-        0xC => exception_error!("Stack-segment fault"),
-        0xD => exception_error!("General protection fault"),
-        0xE => exception_error!("Page fault"),
-        0x10 => exception!("x87 floating-point exception"),
-        0x11 => exception_error!("Alignment check exception"),
-        0x12 => exception!("Machine check exception"),
-        0x13 => exception!("SIMD floating-point exception"),
-        0x14 => exception!("Virtualization exception"),
-        0x1E => exception_error!("Security exception"),
-        _ => exception!("Unknown Interrupt"),
+        0x0 => exception!("Divide by zero exception", SIGFPE),
+        0x1 => exception!("Debug exception", SIGTRAP),
+        0x2 => exception!("Non-maskable interrupt", SIGBUS),
+        0x3 => exception!("Breakpoint exception", SIGTRAP),
+        0x4 => exception!("Overflow exception", SIGSEGV),
+        0x5 => exception!("Bound range exceeded exception", SIGSEGV),
+        0x6 => exception!("Invalid opcode exception", SIGILL),
+        0x7 => exception!("Device not available exception", SIGFPE),
+        0x8 => exception_error!("Double fault", SIGSEGV),
+        0x9 => exception!("Coprocessor Segment Overrun", SIGFPE), // legacy
+        0xA => exception_error!("Invalid TSS exception", SIGSEGV),
+        0xB => exception_error!("Segment not present exception", SIGBUS),
+        0xC => exception_error!("Stack-segment fault", SIGBUS),
+        0xD => exception_error!("General protection fault", SIGSEGV),
+        0xE => exception_error!("Page fault", SIGSEGV),
+        0x10 => exception!("x87 floating-point exception", SIGFPE),
+        0x11 => exception_error!("Alignment check exception", SIGBUS),
+        0x12 => exception!("Machine check exception", SIGBUS),
+        0x13 => exception!("SIMD floating-point exception", SIGFPE),
+        0x14 => exception!("Virtualization exception", SIGSEGV),
+        0x1E => exception_error!("Security exception", SIGSEGV),
+        _ => exception!("Unknown Interrupt", SIGKILL),
     }
 
     if interrupt >= 0x20 && interrupt < 0x30 {
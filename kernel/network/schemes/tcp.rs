@@ -113,7 +113,7 @@ impl TcpStream {
                                             sequence: n32::new(self.sequence),
                                             ack_num: n32::new(self.acknowledge),
                                             flags: n16::new(((mem::size_of::<TcpHeader>() << 10) & 0xF000) as u16 | TCP_ACK),
-                                            window_size: n16::new(65535),
+                                            window_size: n16::new(::env().tunables.lock().tcp_window),
                                             checksum: Checksum {
                                                 data: 0
                                             },
@@ -165,7 +165,7 @@ impl TcpStream {
                 ack_num: n32::new(self.acknowledge),
                 flags: n16::new((((mem::size_of::<TcpHeader>()) << 10) & 0xF000) as u16 | TCP_PSH |
                                 TCP_ACK),
-                window_size: n16::new(65535),
+                window_size: n16::new(::env().tunables.lock().tcp_window),
                 checksum: Checksum { data: 0 },
                 urgent_pointer: n16::new(0),
             },
@@ -233,7 +233,7 @@ impl TcpStream {
                 sequence: n32::new(self.sequence),
                 ack_num: n32::new(self.acknowledge),
                 flags: n16::new(((mem::size_of::<TcpHeader>() << 10) & 0xF000) as u16 | TCP_SYN),
-                window_size: n16::new(65535),
+                window_size: n16::new(::env().tunables.lock().tcp_window),
                 checksum: Checksum { data: 0 },
                 urgent_pointer: n16::new(0),
             },
@@ -283,7 +283,7 @@ impl TcpStream {
                                                     sequence: n32::new(self.sequence),
                                                     ack_num: n32::new(self.acknowledge),
                                                     flags: n16::new(((mem::size_of::<TcpHeader>() << 10) & 0xF000) as u16 | TCP_ACK),
-                                                    window_size: n16::new(65535),
+                                                    window_size: n16::new(::env().tunables.lock().tcp_window),
                                                     checksum: Checksum {
                                                         data: 0
                                                     },
@@ -336,7 +336,7 @@ impl TcpStream {
                 ack_num: n32::new(self.acknowledge),
                 flags: n16::new(((mem::size_of::<TcpHeader>() << 10) & 0xF000) as u16 | TCP_SYN |
                                 TCP_ACK),
-                window_size: n16::new(65535),
+                window_size: n16::new(::env().tunables.lock().tcp_window),
                 checksum: Checksum { data: 0 },
                 urgent_pointer: n16::new(0),
             },
@@ -403,7 +403,7 @@ impl Drop for TcpStream {
                 sequence: n32::new(self.sequence),
                 ack_num: n32::new(self.acknowledge),
                 flags: n16::new((((mem::size_of::<TcpHeader>()) << 10) & 0xF000) as u16 | TCP_FIN | TCP_ACK),
-                window_size: n16::new(65535),
+                window_size: n16::new(::env().tunables.lock().tcp_window),
                 checksum: Checksum { data: 0 },
                 urgent_pointer: n16::new(0),
             },
@@ -1,5 +1,6 @@
 use core::{fmt, result};
 
+use common::backtrace::Backtrace;
 use common::debug;
 
 struct DebugStream;
@@ -21,6 +22,8 @@ pub extern "C" fn panic_fmt(args: fmt::Arguments, file: &'static str, line: u32)
     let _ = fmt::write(&mut DebugStream, args);
     debug::dl();
 
+    Backtrace::current().print();
+
     unsafe {
         loop {
             asm!("sti");
@@ -0,0 +1,101 @@
+use alloc::boxed::Box;
+
+use collections::string::{String, ToString};
+
+use common::datetime::{self, DateTime};
+
+use fs::{KScheme, Resource, Url, VecResource};
+use fs::resource::ResourceSeek;
+
+use system::error::{Error, Result, EINVAL, ENOENT};
+
+/// A scheme for the time of day
+///
+/// `clock:` reads the local time in ISO-8601, `clock:utc` the time in UTC, and `clock:human`
+/// the local time in the style of `date`. `clock:offset` reads the offset of local time from UTC
+/// as `+HH:MM`, and changes it when written.
+pub struct ClockScheme;
+
+impl KScheme for ClockScheme {
+    fn scheme(&self) -> &str {
+        "clock"
+    }
+
+    fn open(&mut self, url: Url, _: usize) -> Result<Box<Resource>> {
+        let path = url.reference().trim_matches('/');
+        let string = match path {
+            "" => DateTime::local().iso8601(),
+            "utc" => DateTime::utc().iso8601(),
+            "human" => format!("{}", DateTime::local()),
+            "offset" => return Ok(box ClockOffsetResource {
+                pos: 0
+            }),
+            _ => return Err(Error::new(ENOENT))
+        };
+        Ok(box VecResource::new(format!("clock:{}", path), (string + "\n").into_bytes()))
+    }
+}
+
+/// The offset of local time from UTC
+pub struct ClockOffsetResource {
+    pos: usize
+}
+
+impl ClockOffsetResource {
+    fn get_offset_str(&self) -> String {
+        datetime::format_offset(*::env().utc_offset.lock(), false) + "\n"
+    }
+}
+
+impl Resource for ClockOffsetResource {
+    fn dup(&self) -> Result<Box<Resource>> {
+        Ok(box ClockOffsetResource { pos: self.pos })
+    }
+
+    fn path(&self, buf: &mut [u8]) -> Result<usize> {
+        let path = "clock:offset".to_string();
+
+        let mut i = 0;
+        for b in path.bytes() {
+            if i < buf.len() {
+                buf[i] = b;
+                i += 1;
+            } else {
+                break;
+            }
+        }
+
+        Ok(i)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let string = self.get_offset_str();
+        let mut i = 0;
+        while i < buf.len() && self.pos < string.len() {
+            buf[i] = string.as_bytes()[self.pos];
+            i += 1;
+            self.pos += 1;
+        }
+        Ok(i)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let string = String::from_utf8_lossy(buf);
+        match datetime::parse_offset(&string) {
+            Some(offset) => {
+                *::env().utc_offset.lock() = offset;
+                Ok(buf.len())
+            },
+            None => Err(Error::new(EINVAL))
+        }
+    }
+
+    fn seek(&mut self, pos: ResourceSeek) -> Result<usize> {
+        match pos {
+            ResourceSeek::Start(offset) => self.pos = offset,
+            ResourceSeek::Current(offset) => self.pos = (self.pos as isize + offset) as usize,
+            ResourceSeek::End(offset) => self.pos = (self.get_offset_str().len() as isize + offset) as usize
+        }
+        Ok(self.pos)
+    }
+}
@@ -4,9 +4,11 @@ use collections::string::{String, ToString};
 
 use arch::context;
 
+use common::backtrace::Backtrace;
+
 use fs::{KScheme, Resource, Url, VecResource};
 
-use system::error::Result;
+use system::error::{Error, Result, ENOENT};
 
 pub struct ContextScheme;
 
@@ -15,7 +17,15 @@ impl KScheme for ContextScheme {
         "context"
     }
 
-    fn open(&mut self, _: Url, _: usize) -> Result<Box<Resource>> {
+    fn open(&mut self, url: Url, _: usize) -> Result<Box<Resource>> {
+        let path = url.reference().trim_matches('/');
+        if path.starts_with("backtrace/") {
+            let pid = try!(path[10..].parse::<usize>().or(Err(Error::new(ENOENT))));
+            return Ok(box VecResource::new(format!("context:backtrace/{}", pid), try!(backtrace(pid)).into_bytes()));
+        } else if ! path.is_empty() {
+            return Err(Error::new(ENOENT));
+        }
+
         let mut string = format!("{:<6}{:<6}{:<8}{:<8}{:<8}{:<6}{:<6}{:<6}{}\n",
                                  "PID",
                                  "PPID",
@@ -88,3 +98,24 @@ impl KScheme for ContextScheme {
         Ok(box VecResource::new("context:".to_string(), string.into_bytes()))
     }
 }
+
+/// The functions a context is in, one frame on each line with its return address
+fn backtrace(pid: usize) -> Result<String> {
+    let contexts = ::env().contexts.lock();
+    let context = try!(contexts.find(pid));
+    let current = contexts.current().map(|current| current.pid == pid).unwrap_or(false);
+    let backtrace = if current {
+        Backtrace::current()
+    } else {
+        Backtrace::from_context(context)
+    };
+
+    let mut string = String::new();
+    for frame in backtrace {
+        match frame.symbol() {
+            Some((name, offset)) => string.push_str(&format!("{:08X} {}+{:X}\n", frame.address, name, offset)),
+            None => string.push_str(&format!("{:08X}\n", frame.address))
+        }
+    }
+    Ok(string)
+}
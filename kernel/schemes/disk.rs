@@ -6,17 +6,18 @@ use collections::{String, Vec};
 
 use core::cmp;
 use disk::Disk;
+use disk::queue::DiskQueue;
 use fs::{KScheme, Resource, ResourceSeek, Url, VecResource};
 use sync::Intex;
 
 use syscall::{MODE_DIR, MODE_FILE, Stat};
 
-use system::error::{Error, Result, ENOENT};
+use system::error::{Error, Result, ENOENT, ENOSYS};
 
 /// A disk resource
 pub struct DiskResource {
     pub path: String,
-    pub disk: Arc<Intex<Box<Disk>>>,
+    pub disk: Arc<DiskQueue>,
     pub seek: u64,
 }
 
@@ -39,19 +40,19 @@ impl Resource for DiskResource {
     }
 
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        let count = try!(self.disk.lock().read(self.seek/512, buf));
+        let count = try!(self.disk.read(self.seek/512, buf));
         self.seek += count as u64;
         Ok(count)
     }
 
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
-        let count = try!(self.disk.lock().write(self.seek/512, buf));
+        let count = try!(self.disk.write(self.seek/512, buf));
         self.seek += count as u64;
         Ok(count)
     }
 
     fn seek(&mut self, pos: ResourceSeek) -> Result<usize> {
-        let size = self.disk.lock().size();
+        let size = self.disk.size();
         match pos {
             ResourceSeek::Start(offset) => self.seek = cmp::min(size, offset as u64),
             ResourceSeek::Current(offset) => self.seek = cmp::min(size, cmp::max(0, self.seek as i64 + offset as i64) as u64),
@@ -61,7 +62,7 @@ impl Resource for DiskResource {
     }
 
     fn sync(&mut self) -> Result<()> {
-        Ok(())
+        self.disk.flush()
     }
 }
 
@@ -73,21 +74,15 @@ impl Drop for DiskResource {
 
 /// A disk scheme
 pub struct DiskScheme {
-    disks: Vec<Arc<Intex<Box<Disk>>>>,
+    disks: Vec<Arc<DiskQueue>>,
 }
 
 impl DiskScheme {
-    /// Create a new disk scheme from an array of Disks
-    pub fn new(mut disks: Vec<Box<Disk>>) -> Box<Self> {
-        let mut scheme = box DiskScheme {
-            disks: Vec::new()
-        };
-
-        for disk in disks.drain(..) {
-            scheme.disks.push(Arc::new(Intex::new(disk)));
+    /// Create a new disk scheme from an array of Disks, queueing the requests to each
+    pub fn new(disks: Vec<Arc<Intex<Box<Disk>>>>) -> Box<Self> {
+        box DiskScheme {
+            disks: disks.into_iter().map(|disk| Arc::new(DiskQueue::new(disk))).collect()
         }
-
-        scheme
     }
 }
 
@@ -113,6 +108,16 @@ impl KScheme for DiskScheme {
             }
 
             return Ok(box VecResource::new("disk:/".to_owned(), list.into_bytes()));
+        } else if path.ends_with("/smart") {
+            if let Ok(number) = path[.. path.len() - 6].parse::<usize>() {
+                if let Some(disk) = self.disks.get(number) {
+                    if ! disk.has_smart() {
+                        return Err(Error::new(ENOSYS));
+                    }
+                    let report = try!(disk.smart());
+                    return Ok(box VecResource::new(format!("disk:/{}/smart", number), report.into_bytes()));
+                }
+            }
         } else {
             if let Ok(number) = path.parse::<usize>() {
                 if let Some(disk) = self.disks.get(number) {
@@ -143,11 +148,23 @@ impl KScheme for DiskScheme {
             stat.st_mode = MODE_DIR;
             stat.st_size = list.len() as u64;
             return Ok(());
+        } else if path.ends_with("/smart") {
+            if let Ok(number) = path[.. path.len() - 6].parse::<usize>() {
+                if let Some(disk) = self.disks.get(number) {
+                    if ! disk.has_smart() {
+                        return Err(Error::new(ENOSYS));
+                    }
+                    //The report is made when it is opened
+                    stat.st_mode = MODE_FILE;
+                    stat.st_size = 0;
+                    return Ok(());
+                }
+            }
         } else {
             if let Ok(number) = path.parse::<usize>() {
                 if let Some(disk) = self.disks.get(number) {
                     stat.st_mode = MODE_FILE;
-                    stat.st_size = disk.lock().size();
+                    stat.st_size = disk.size();
                     return Ok(());
                 }
             }
@@ -1,6 +1,7 @@
 use alloc::boxed::Box;
 
 use collections::String;
+use collections::string::ToString;
 
 use common::event::Event;
 
@@ -9,20 +10,71 @@ use core::mem::size_of;
 
 use fs::{KScheme, Resource, ResourceSeek, Url};
 
+use graphics::display::Display;
+
 use system::error::{Error, Result, EACCES, EBADF, ENOENT, EINVAL};
 use system::graphics::fast_copy;
 
+/// Run `f` on the display at `index`, index 0 is the primary display owned by the console
+pub fn with_display<T, F: FnOnce(&Display) -> T>(index: usize, f: F) -> Option<T> {
+    if index == 0 {
+        let console = ::env().console.lock();
+        console.display.as_ref().map(|display| f(display))
+    } else {
+        let displays = ::env().displays.lock();
+        displays.get(index - 1).map(|display| f(display))
+    }
+}
+
+/// Displays are arranged left to right, this returns the horizontal offset of the display at `index`
+pub fn display_offset(index: usize) -> usize {
+    let mut offset = 0;
+    for i in 0..index {
+        offset += with_display(i, |display| display.width).unwrap_or(0);
+    }
+    offset
+}
+
+/// The size of the desktop spanning all displays
+pub fn desktop_size() -> (usize, usize) {
+    let mut width = 0;
+    let mut height = 0;
+    let mut i = 0;
+    while let Some((w, h)) = with_display(i, |display| (display.width, display.height)) {
+        width += w;
+        height = cmp::max(height, h);
+        i += 1;
+    }
+    (width, height)
+}
+
 /// A display resource
 pub struct DisplayResource {
+    /// Display index
+    index: usize,
     /// Path
     path: String,
     /// Seek
     seek: usize,
 }
 
+impl DisplayResource {
+    /// Open the display at `index`, the path contains its size and position on the desktop
+    fn new(index: usize) -> Option<Box<DisplayResource>> {
+        with_display(index, |display| (display.width, display.height)).map(|(width, height)| {
+            box DisplayResource {
+                index: index,
+                path: format!("display:{}/{}/{}/{}", width, height, display_offset(index), 0),
+                seek: 0,
+            }
+        })
+    }
+}
+
 impl Resource for DisplayResource {
     fn dup(&self) -> Result<Box<Resource>> {
         Ok(Box::new(DisplayResource {
+            index: self.index,
             path: self.path.clone(),
             seek: self.seek
         }))
@@ -61,35 +113,32 @@ impl Resource for DisplayResource {
     }
 
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
-        let console = ::env().console.lock();
-        if let Some(ref display) = console.display {
-            let size = cmp::max(0, cmp::min(display.size as isize - self.seek as isize, (buf.len()/4) as isize)) as usize;
+        let seek = self.seek;
+        with_display(self.index, |display| {
+            let size = cmp::max(0, cmp::min(display.size as isize - seek as isize, (buf.len()/4) as isize)) as usize;
 
             if size > 0 {
                 unsafe {
-                    fast_copy(display.onscreen.offset(self.seek as isize), buf.as_ptr() as *const u32, size);
+                    fast_copy(display.onscreen.offset(seek as isize), buf.as_ptr() as *const u32, size);
                 }
+                display.flush(seek, size);
             }
 
-            Ok(size)
-        } else {
-            Err(Error::new(EBADF))
-        }
+            size
+        }).ok_or(Error::new(EBADF))
     }
 
     fn seek(&mut self, pos: ResourceSeek) -> Result<usize> {
-        let console = ::env().console.lock();
-        if let Some(ref display) = console.display {
-            self.seek = match pos {
+        let seek = self.seek;
+        self.seek = try!(with_display(self.index, |display| {
+            match pos {
                 ResourceSeek::Start(offset) => cmp::min(display.size, cmp::max(0, offset)),
-                ResourceSeek::Current(offset) => cmp::min(display.size, cmp::max(0, self.seek as isize + offset) as usize),
+                ResourceSeek::Current(offset) => cmp::min(display.size, cmp::max(0, seek as isize + offset) as usize),
                 ResourceSeek::End(offset) => cmp::min(display.size, cmp::max(0, display.size as isize + offset) as usize),
-            };
+            }
+        }).ok_or(Error::new(EBADF)));
 
-            Ok(self.seek)
-        } else {
-            Err(Error::new(EBADF))
-        }
+        Ok(self.seek)
     }
 
     fn sync(&mut self) -> Result<()> {
@@ -97,6 +146,73 @@ impl Resource for DisplayResource {
     }
 }
 
+/// The backlight of the panel, read and written as a brightness in percent
+pub struct BacklightResource {
+    seek: usize,
+}
+
+impl BacklightResource {
+    fn get_percent_str(&self) -> Result<String> {
+        ::env().backlight.lock().as_ref().map(|backlight| format!("{}\n", backlight.percent())).ok_or(Error::new(ENOENT))
+    }
+}
+
+impl Resource for BacklightResource {
+    fn dup(&self) -> Result<Box<Resource>> {
+        Ok(box BacklightResource { seek: self.seek })
+    }
+
+    fn path(&self, buf: &mut [u8]) -> Result<usize> {
+        let path = "display:backlight".to_string();
+
+        let mut i = 0;
+        for b in path.bytes() {
+            if i < buf.len() {
+                buf[i] = b;
+                i += 1;
+            } else {
+                break;
+            }
+        }
+
+        Ok(i)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let string = try!(self.get_percent_str());
+        let mut i = 0;
+        while i < buf.len() && self.seek < string.len() {
+            buf[i] = string.as_bytes()[self.seek];
+            i += 1;
+            self.seek += 1;
+        }
+        Ok(i)
+    }
+
+    /// Set the brightness to a percent, such as `40`
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let percent = try!(String::from_utf8_lossy(buf).trim().parse::<u32>().map_err(|_| Error::new(EINVAL)));
+        if percent > 100 {
+            return Err(Error::new(EINVAL));
+        }
+
+        match *::env().backlight.lock() {
+            Some(ref mut backlight) => backlight.set_percent(percent),
+            None => return Err(Error::new(ENOENT))
+        }
+        Ok(buf.len())
+    }
+
+    fn seek(&mut self, pos: ResourceSeek) -> Result<usize> {
+        match pos {
+            ResourceSeek::Start(offset) => self.seek = offset,
+            ResourceSeek::Current(offset) => self.seek = cmp::max(0, self.seek as isize + offset) as usize,
+            ResourceSeek::End(offset) => self.seek = cmp::max(0, try!(self.get_percent_str()).len() as isize + offset) as usize
+        }
+        Ok(self.seek)
+    }
+}
+
 pub struct DisplayScheme;
 
 impl KScheme for DisplayScheme {
@@ -104,33 +220,34 @@ impl KScheme for DisplayScheme {
         "display"
     }
 
+    /// Open `display:manager` to take over the primary display from the console, and
+    /// `display:manager/N` to open further displays, which are numbered from 1. `display:backlight` is the
+    /// brightness of the panel of a laptop, in percent.
     fn open(&mut self, url: Url, _: usize) -> Result<Box<Resource>> {
-        if url.reference() == "manager" {
+        let reference = url.reference();
+        if reference == "backlight" {
+            if ::env().backlight.lock().is_some() {
+                Ok(box BacklightResource { seek: 0 })
+            } else {
+                Err(Error::new(ENOENT))
+            }
+        } else if reference == "manager" {
             let mut console = ::env().console.lock();
             if console.draw {
                 console.draw = false;
+                drop(console);
 
-                if let Some(ref display) = console.display {
-                    Ok(box DisplayResource {
-                        path: format!("display:{}/{}", display.width, display.height),
-                        seek: 0,
-                    })
-                } else {
-                    Err(Error::new(ENOENT))
-                }
+                DisplayResource::new(0).map(|resource| resource as Box<Resource>).ok_or(Error::new(ENOENT))
             } else {
                 Err(Error::new(EACCES))
             }
-        } else {
-            let console = ::env().console.lock();
-            if let Some(ref display) = console.display {
-                Ok(box DisplayResource {
-                    path: format!("display:{}/{}", display.width, display.height),
-                    seek: 0,
-                })
-            } else {
-                Err(Error::new(ENOENT))
+        } else if reference.starts_with("manager/") {
+            match reference[8..].parse::<usize>() {
+                Ok(index) if index > 0 => DisplayResource::new(index).map(|resource| resource as Box<Resource>).ok_or(Error::new(ENOENT)),
+                _ => Err(Error::new(ENOENT))
             }
+        } else {
+            DisplayResource::new(0).map(|resource| resource as Box<Resource>).ok_or(Error::new(ENOENT))
         }
     }
 }
@@ -17,9 +17,14 @@ impl KScheme for MemoryScheme {
     }
 
     fn open(&mut self, _: Url, _: usize) -> Result<Box<Resource>> {
-        let string = format!("Memory Used: {} KB\nMemory Free: {} KB\n",
-                             memory::memory_used() / 1024,
-                             memory::memory_free() / 1024);
+        let mut string = format!("Memory Used: {} KB\nMemory Free: {} KB\n",
+                                 memory::memory_used() / 1024,
+                                 memory::memory_free() / 1024);
+        if let Some(ref swap) = *::env().swap.lock() {
+            string.push_str(&format!("Swap Used: {} KB\nSwap Free: {} KB\n",
+                                     swap.used() / 1024,
+                                     (swap.size() - swap.used()) / 1024));
+        }
         Ok(box VecResource::new("memory:".to_string(), string.into_bytes()))
     }
 }
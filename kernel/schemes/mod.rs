@@ -1,3 +1,5 @@
+/// Time of day scheme
+pub mod clock;
 /// Context scheme
 pub mod context;
 /// Debug scheme
@@ -16,7 +18,17 @@ pub mod interrupt;
 pub mod memory;
 /// Pipes
 pub mod pipe;
+/// Pointer settings scheme
+pub mod pointer;
+/// Named semaphores
+pub mod sem;
+/// Kernel statistics scheme
+pub mod stats;
+/// System information and runtime settings scheme
+pub mod sys;
 /// Logging scheme
 pub mod syslog;
 /// Tests
 pub mod test;
+/// File change notifications
+pub mod watch;
@@ -0,0 +1,98 @@
+use alloc::boxed::Box;
+
+use collections::string::{String, ToString};
+
+use fs::{KScheme, Resource, Url};
+use fs::resource::ResourceSeek;
+
+use system::error::{Error, Result, EINVAL, ENOENT};
+
+/// A scheme for the pointer speed settings
+///
+/// `pointer:` reads the settings as `key=value` lines, `sensitivity` and `factor` in percent, `acceleration`
+/// as `flat`, `linear`, or `quadratic`, and `threshold` in counts per packet. Writing lines in the same form
+/// changes them.
+pub struct PointerScheme;
+
+impl KScheme for PointerScheme {
+    fn scheme(&self) -> &str {
+        "pointer"
+    }
+
+    fn open(&mut self, url: Url, _: usize) -> Result<Box<Resource>> {
+        if url.reference().trim_matches('/').is_empty() {
+            Ok(box PointerResource {
+                pos: 0
+            })
+        } else {
+            Err(Error::new(ENOENT))
+        }
+    }
+}
+
+/// The pointer speed settings
+pub struct PointerResource {
+    pos: usize
+}
+
+impl PointerResource {
+    fn get_settings_str(&self) -> String {
+        ::env().pointer.lock().to_string()
+    }
+}
+
+impl Resource for PointerResource {
+    fn dup(&self) -> Result<Box<Resource>> {
+        Ok(box PointerResource { pos: self.pos })
+    }
+
+    fn path(&self, buf: &mut [u8]) -> Result<usize> {
+        let path = "pointer:".to_string();
+
+        let mut i = 0;
+        for b in path.bytes() {
+            if i < buf.len() {
+                buf[i] = b;
+                i += 1;
+            } else {
+                break;
+            }
+        }
+
+        Ok(i)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let string = self.get_settings_str();
+        let mut i = 0;
+        while i < buf.len() && self.pos < string.len() {
+            buf[i] = string.as_bytes()[self.pos];
+            i += 1;
+            self.pos += 1;
+        }
+        Ok(i)
+    }
+
+    /// Settings are changed together, so none change if a line is invalid
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let string = String::from_utf8_lossy(buf);
+        let mut pointer = ::env().pointer.lock();
+        let mut settings = *pointer;
+        for line in string.lines() {
+            if ! line.trim().is_empty() && ! settings.set(line) {
+                return Err(Error::new(EINVAL));
+            }
+        }
+        *pointer = settings;
+        Ok(buf.len())
+    }
+
+    fn seek(&mut self, pos: ResourceSeek) -> Result<usize> {
+        match pos {
+            ResourceSeek::Start(offset) => self.pos = offset,
+            ResourceSeek::Current(offset) => self.pos = (self.pos as isize + offset) as usize,
+            ResourceSeek::End(offset) => self.pos = (self.get_settings_str().len() as isize + offset) as usize
+        }
+        Ok(self.pos)
+    }
+}
@@ -0,0 +1,139 @@
+use alloc::arc::{Arc, Weak};
+use alloc::boxed::Box;
+
+use collections::{BTreeMap, String, Vec};
+use collections::string::ToString;
+
+use common::time::Duration;
+
+use core::{cmp, str};
+
+use fs::{KScheme, Resource, Url};
+
+use sync::Semaphore;
+
+use system::error::{Error, Result, EAGAIN, EINVAL, ENOENT, ETIMEDOUT};
+
+/// A named semaphore resource
+///
+/// Writing `post` increments the semaphore, `wait` decrements it, blocking while it is zero, `trywait` fails with
+/// `EAGAIN` instead of blocking, and `timedwait 100` fails with `ETIMEDOUT` if it is still zero after 100
+/// milliseconds. Reading returns the count, such as `2`.
+pub struct SemResource {
+    name: String,
+    semaphore: Arc<Semaphore>,
+}
+
+impl Resource for SemResource {
+    fn dup(&self) -> Result<Box<Resource>> {
+        Ok(box SemResource {
+            name: self.name.clone(),
+            semaphore: self.semaphore.clone(),
+        })
+    }
+
+    fn path(&self, buf: &mut [u8]) -> Result<usize> {
+        let path_str = format!("sem:{}", self.name);
+        let path = path_str.as_bytes();
+
+        for (b, p) in buf.iter_mut().zip(path.iter()) {
+            *b = *p;
+        }
+
+        Ok(cmp::min(buf.len(), path.len()))
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let value = self.semaphore.value().to_string();
+        let value = value.as_bytes();
+
+        for (b, v) in buf.iter_mut().zip(value.iter()) {
+            *b = *v;
+        }
+
+        Ok(cmp::min(buf.len(), value.len()))
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let command = try!(str::from_utf8(buf).or(Err(Error::new(EINVAL)))).trim();
+        let mut args = command.split_whitespace();
+        match (args.next(), args.next(), args.next()) {
+            (Some("post"), None, None) => self.semaphore.post(),
+            (Some("wait"), None, None) => self.semaphore.wait(),
+            (Some("trywait"), None, None) => if ! self.semaphore.try_wait() {
+                return Err(Error::new(EAGAIN));
+            },
+            (Some("timedwait"), Some(millis), None) => {
+                let millis = try!(millis.parse::<i64>().or(Err(Error::new(EINVAL))));
+                let deadline = Duration::monotonic() + Duration::new(millis / 1000, (millis % 1000) as i32 * 1000000);
+                if ! self.semaphore.wait_until(deadline) {
+                    return Err(Error::new(ETIMEDOUT));
+                }
+            },
+            _ => return Err(Error::new(EINVAL))
+        }
+
+        Ok(buf.len())
+    }
+
+    fn sync(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The semaphore scheme, opened as `sem:name`, or `sem:name/count` to give the count of a new semaphore
+///
+/// Processes that open the same name share the semaphore, until every handle to it is closed. A semaphore created
+/// with a count of 1 is a mutex shared between processes, locked with `wait` and unlocked with `post`.
+pub struct SemScheme {
+    semaphores: BTreeMap<String, Weak<Semaphore>>,
+}
+
+impl SemScheme {
+    pub fn new() -> SemScheme {
+        SemScheme {
+            semaphores: BTreeMap::new(),
+        }
+    }
+}
+
+impl KScheme for SemScheme {
+    fn scheme(&self) -> &str {
+        "sem"
+    }
+
+    fn open(&mut self, url: Url, _: usize) -> Result<Box<Resource>> {
+        let path = url.reference();
+        let (name, count) = match path.find('/') {
+            Some(slash) => (&path[.. slash], try!(path[slash + 1 ..].parse::<usize>().or(Err(Error::new(EINVAL))))),
+            None => (path, 0)
+        };
+        if name.is_empty() {
+            return Err(Error::new(ENOENT));
+        }
+
+        //Forget the semaphores that are no longer open
+        let closed: Vec<String> = self.semaphores.iter()
+                                      .filter(|&(_, semaphore)| semaphore.upgrade().is_none())
+                                      .map(|(name, _)| name.clone())
+                                      .collect();
+        for name in closed.iter() {
+            self.semaphores.remove(name);
+        }
+
+        let existing = self.semaphores.get(name).and_then(|semaphore| semaphore.upgrade());
+        let semaphore = match existing {
+            Some(semaphore) => semaphore,
+            None => {
+                let semaphore = Arc::new(Semaphore::new(count));
+                self.semaphores.insert(name.to_string(), Arc::downgrade(&semaphore));
+                semaphore
+            }
+        };
+
+        Ok(box SemResource {
+            name: name.to_string(),
+            semaphore: semaphore,
+        })
+    }
+}
@@ -0,0 +1,59 @@
+use alloc::boxed::Box;
+
+use collections::string::{String, ToString};
+
+use fs::{KScheme, Resource, Url, VecResource};
+
+use system::error::{Error, Result, ENOENT};
+use system::syscall::O_TRUNC;
+
+/// Kernel statistics
+///
+/// `stats:schemes` lists the calls to each userspace scheme by operation, with their count and latency, opening it
+/// with `O_TRUNC` clears them first
+pub struct StatsScheme;
+
+/// Format nanoseconds with a unit
+fn format_nanos(nanos: u64) -> String {
+    if nanos >= 1000000000 {
+        format!("{} s", nanos / 1000000000)
+    } else if nanos >= 1000000 {
+        format!("{} ms", nanos / 1000000)
+    } else if nanos >= 1000 {
+        format!("{} us", nanos / 1000)
+    } else {
+        format!("{} ns", nanos)
+    }
+}
+
+impl KScheme for StatsScheme {
+    fn scheme(&self) -> &str {
+        "stats"
+    }
+
+    fn open(&mut self, url: Url, flags: usize) -> Result<Box<Resource>> {
+        match url.reference().trim_matches('/') {
+            "" => Ok(box VecResource::new("stats:".to_string(), b"schemes\n".to_vec())),
+            "schemes" => {
+                let mut latency = ::env().scheme_latency.lock();
+                if flags & O_TRUNC == O_TRUNC {
+                    latency.clear();
+                }
+
+                let mut string = format!("{:<16}{:<12}{:<12}{:<10}{:<10}{}\n", "SCHEME", "OPERATION", "COUNT", "P50", "P99", "MEAN");
+                for (scheme, operation, histogram) in latency.list() {
+                    string.push_str(&format!("{:<16}{:<12}{:<12}{:<10}{:<10}{}\n",
+                                             scheme,
+                                             operation,
+                                             histogram.count,
+                                             format_nanos(histogram.percentile(50)),
+                                             format_nanos(histogram.percentile(99)),
+                                             format_nanos(histogram.total / histogram.count)));
+                }
+
+                Ok(box VecResource::new("stats:schemes".to_string(), string.into_bytes()))
+            },
+            _ => Err(Error::new(ENOENT))
+        }
+    }
+}
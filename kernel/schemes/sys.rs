@@ -0,0 +1,207 @@
+use alloc::boxed::Box;
+
+use collections::{String, Vec};
+use collections::string::ToString;
+
+use core::{cmp, str};
+
+use env::tunables::MAX_TIMESLICE;
+
+use fs::{KScheme, Resource, Url, VecResource};
+use fs::resource::ResourceSeek;
+
+use logging::LogLevel;
+
+use schemes::watch::{watch_notify, WatchKind};
+
+use system::error::{Error, Result, EINVAL, ENOENT};
+
+/// The settings under `sys:/config`, by path
+const TUNABLES: [&'static str; 7] = [
+    "log/level",
+    "pointer/acceleration",
+    "pointer/factor",
+    "pointer/sensitivity",
+    "pointer/threshold",
+    "sched/timeslice",
+    "tcp/window",
+];
+
+/// The value of a setting
+fn get(name: &str) -> Option<String> {
+    match name {
+        "log/level" => Some(::env().tunables.lock().log_level.name().to_string()),
+        "pointer/acceleration" => Some(::env().pointer.lock().acceleration.name().to_string()),
+        "pointer/factor" => Some(::env().pointer.lock().factor.to_string()),
+        "pointer/sensitivity" => Some(::env().pointer.lock().sensitivity.to_string()),
+        "pointer/threshold" => Some(::env().pointer.lock().threshold.to_string()),
+        "sched/timeslice" => Some(::env().tunables.lock().timeslice.to_string()),
+        "tcp/window" => Some(::env().tunables.lock().tcp_window.to_string()),
+        _ => None
+    }
+}
+
+/// Change a setting, returning false if the value is invalid for it
+fn set(name: &str, value: &str) -> bool {
+    match name {
+        "log/level" => match LogLevel::parse(value) {
+            Some(level) => ::env().tunables.lock().log_level = level,
+            None => return false
+        },
+        "pointer/acceleration" | "pointer/factor" | "pointer/sensitivity" | "pointer/threshold" => {
+            return ::env().pointer.lock().set(&format!("{}={}", &name["pointer/".len()..], value));
+        },
+        "sched/timeslice" => match value.parse::<usize>() {
+            Ok(timeslice) if timeslice > 0 && timeslice <= MAX_TIMESLICE => ::env().tunables.lock().timeslice = timeslice,
+            _ => return false
+        },
+        "tcp/window" => match value.parse::<u16>() {
+            Ok(window) if window > 0 => ::env().tunables.lock().tcp_window = window,
+            _ => return false
+        },
+        _ => return false
+    }
+    true
+}
+
+/// The settings and directories directly inside a directory of `sys:/config`, directories ending in `/`
+fn list(dir: &str) -> Vec<String> {
+    let mut entries: Vec<String> = Vec::new();
+    for tunable in TUNABLES.iter() {
+        let rest = if dir.is_empty() {
+            *tunable
+        } else if tunable.starts_with(dir) && tunable[dir.len()..].starts_with('/') {
+            &tunable[dir.len() + 1..]
+        } else {
+            continue;
+        };
+
+        let entry = match rest.find('/') {
+            Some(slash) => rest[.. slash + 1].to_string(),
+            None => rest.to_string()
+        };
+        if ! entries.contains(&entry) {
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+/// A scheme for the system, and kernel settings that can be changed while running
+///
+/// `sys:/config` is a tree of settings, such as `sys:/config/sched/timeslice`. Reading a setting returns its value,
+/// writing a value changes it, failing with `EINVAL` if the value is invalid for the setting. Reading a directory
+/// lists the settings and directories in it. Each change is reported to watches of the setting and of its directory.
+///
+/// `sys:/cpu` describes the processor as `key=value` lines, with its caches and the features that can be used, see
+/// `CpuInfo`.
+pub struct SysScheme;
+
+impl KScheme for SysScheme {
+    fn scheme(&self) -> &str {
+        "sys"
+    }
+
+    fn open(&mut self, url: Url, _: usize) -> Result<Box<Resource>> {
+        let path = url.reference().trim_matches('/');
+        if path.is_empty() {
+            return Ok(box VecResource::new("sys:/".to_string(), b"config/\ncpu\n".to_vec()));
+        }
+        if path == "cpu" {
+            return Ok(box VecResource::new("sys:/cpu".to_string(), ::env().cpu.to_string().into_bytes()));
+        }
+
+        let name = if path == "config" {
+            ""
+        } else if path.starts_with("config/") {
+            &path["config/".len()..]
+        } else {
+            return Err(Error::new(ENOENT));
+        };
+
+        if let Some(value) = get(name) {
+            return Ok(box SysConfigResource {
+                name: name.to_string(),
+                data: (value + "\n").into_bytes(),
+                seek: 0,
+            });
+        }
+
+        let entries = list(name);
+        if entries.is_empty() {
+            Err(Error::new(ENOENT))
+        } else {
+            let mut data = String::new();
+            for entry in entries.iter() {
+                data.push_str(entry);
+                data.push('\n');
+            }
+            Ok(box VecResource::new(format!("sys:/{}/", path), data.into_bytes()))
+        }
+    }
+}
+
+/// A setting under `sys:/config`, read as its value when it was opened
+pub struct SysConfigResource {
+    name: String,
+    data: Vec<u8>,
+    seek: usize,
+}
+
+impl Resource for SysConfigResource {
+    fn dup(&self) -> Result<Box<Resource>> {
+        Ok(box SysConfigResource {
+            name: self.name.clone(),
+            data: self.data.clone(),
+            seek: self.seek,
+        })
+    }
+
+    fn path(&self, buf: &mut [u8]) -> Result<usize> {
+        let path_str = format!("sys:/config/{}", self.name);
+        let path = path_str.as_bytes();
+
+        for (b, p) in buf.iter_mut().zip(path.iter()) {
+            *b = *p;
+        }
+
+        Ok(cmp::min(buf.len(), path.len()))
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let mut i = 0;
+        while i < buf.len() && self.seek < self.data.len() {
+            buf[i] = self.data[self.seek];
+            i += 1;
+            self.seek += 1;
+        }
+        Ok(i)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let value = try!(str::from_utf8(buf).or(Err(Error::new(EINVAL)))).trim();
+        if ! set(&self.name, value) {
+            return Err(Error::new(EINVAL));
+        }
+
+        if let Some(value) = get(&self.name) {
+            self.data = (value + "\n").into_bytes();
+        }
+        watch_notify(WatchKind::Modify, &format!("sys:/config/{}", self.name));
+
+        Ok(buf.len())
+    }
+
+    fn seek(&mut self, pos: ResourceSeek) -> Result<usize> {
+        match pos {
+            ResourceSeek::Start(offset) => self.seek = offset,
+            ResourceSeek::Current(offset) => self.seek = (self.seek as isize + offset) as usize,
+            ResourceSeek::End(offset) => self.seek = (self.data.len() as isize + offset) as usize
+        }
+        Ok(self.seek)
+    }
+
+    fn sync(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
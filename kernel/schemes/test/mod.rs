@@ -32,6 +32,7 @@ macro_rules! fail {
 // Add your test here!
 pub mod get_slice;
 pub mod meta;
+pub mod random;
 
 pub struct TestScheme;
 
@@ -90,6 +91,7 @@ impl KScheme for TestScheme {
         reg_test!(meta::meta_test_woah, "Testing the testing (wut)");
         reg_test!(!meta::meta_test_woah_fail, "Testing the fail testing (wut)");
         reg_test!(get_slice::test, "GetSlice");
+        reg_test!(random::test, "Prng, seed {}", ::common::random::seed());
 
         Ok(box VecResource::new("test:".to_string(), string.into_bytes()))
     }
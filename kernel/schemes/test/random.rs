@@ -0,0 +1,26 @@
+pub fn test() -> bool {
+    use common::random::Prng;
+
+    //The first number from SplitMix64 with a zero seed
+    test!(Prng::new(0).next_u64() == 0xE220A8397B1DCDAF);
+
+    let mut a = Prng::new(1234);
+    let mut b = Prng::new(1234);
+    for _ in 0..64 {
+        test!(a.next_u64() == b.next_u64());
+    }
+
+    let mut c = Prng::new(4321);
+    test!(a.next_u64() != c.next_u64());
+
+    for _ in 0..64 {
+        let n = a.range(10, 20);
+        test!(n >= 10 && n < 20);
+    }
+    test!(a.range(5, 5) == 5);
+
+    let mut buf = [0; 13];
+    Prng::new(1).fill(&mut buf);
+    test!(buf.iter().any(|b| *b != 0));
+    succ!();
+}
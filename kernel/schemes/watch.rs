@@ -0,0 +1,145 @@
+use alloc::arc::Arc;
+use alloc::boxed::Box;
+
+use collections::{String, Vec};
+use collections::string::ToString;
+
+use core::cmp;
+
+use fs::{KScheme, Resource, Url};
+
+use sync::WaitQueue;
+
+use system::error::{Error, Result, ENOENT};
+
+/// The kind of change reported by a watch
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WatchKind {
+    /// A file or directory was created
+    Create,
+    /// A file was written to or truncated
+    Modify,
+    /// A file or directory was removed
+    Delete,
+}
+
+impl WatchKind {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            WatchKind::Create => "create",
+            WatchKind::Modify => "modify",
+            WatchKind::Delete => "delete",
+        }
+    }
+}
+
+/// A watched path, with its pending change notifications
+pub struct Watch {
+    path: String,
+    events: WaitQueue<String>,
+}
+
+impl Watch {
+    /// A watch matches its own path, and the direct children of its path if it is a directory
+    fn matches(&self, path: &str) -> bool {
+        let watched = self.path.trim_right_matches('/');
+        let path = path.trim_right_matches('/');
+        if path == watched {
+            true
+        } else if path.starts_with(watched) && path[watched.len()..].starts_with('/') {
+            ! path[watched.len() + 1..].contains('/')
+        } else {
+            false
+        }
+    }
+}
+
+/// Notify all watches matching `path` of a change
+pub fn watch_notify(kind: WatchKind, path: &str) {
+    let mut watches = ::env().watches.lock();
+    watches.retain(|watch| watch.upgrade().is_some());
+    for watch in watches.iter() {
+        if let Some(watch) = watch.upgrade() {
+            if watch.matches(path) {
+                watch.events.send(format!("{} {}\n", kind.as_str(), path));
+            }
+        }
+    }
+}
+
+/// A watch resource
+///
+/// Reading blocks until a change occurs, and returns one line per change, such as
+/// `modify file:/home/notes.txt`
+pub struct WatchResource {
+    watch: Arc<Watch>,
+    data: Vec<u8>,
+}
+
+impl Resource for WatchResource {
+    fn dup(&self) -> Result<Box<Resource>> {
+        Ok(box WatchResource {
+            watch: self.watch.clone(),
+            data: self.data.clone(),
+        })
+    }
+
+    fn path(&self, buf: &mut [u8]) -> Result<usize> {
+        let path_str = format!("watch:{}", self.watch.path);
+        let path = path_str.as_bytes();
+
+        for (b, p) in buf.iter_mut().zip(path.iter()) {
+            *b = *p;
+        }
+
+        Ok(cmp::min(buf.len(), path.len()))
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.data.is_empty() {
+            self.data.extend_from_slice(self.watch.events.receive().as_bytes());
+            while let Some(event) = self.watch.events.inner.lock().pop_front() {
+                self.data.extend_from_slice(event.as_bytes());
+            }
+        }
+
+        let count = cmp::min(buf.len(), self.data.len());
+        for (b, d) in buf.iter_mut().zip(self.data.drain(.. count)) {
+            *b = d;
+        }
+
+        Ok(count)
+    }
+
+    fn sync(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The watch scheme, opened as `watch:file:/path/to/directory`
+pub struct WatchScheme;
+
+impl KScheme for WatchScheme {
+    fn scheme(&self) -> &str {
+        "watch"
+    }
+
+    fn open(&mut self, url: Url, _: usize) -> Result<Box<Resource>> {
+        let path = url.reference();
+        if path.find(':').is_none() {
+            return Err(Error::new(ENOENT));
+        }
+
+        let watch = Arc::new(Watch {
+            path: path.to_string(),
+            events: WaitQueue::new(),
+        });
+
+        ::env().watches.lock().push(Arc::downgrade(&watch));
+
+        Ok(box WatchResource {
+            watch: watch,
+            data: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,3 @@
+; The code symbols of the kernel, listed by the Makefile from the first link, see common/symbols.rs
+section .symbols
+incbin "kernel.syms"
@@ -1,8 +1,10 @@
 pub use arch::intex::Intex;
+pub use self::semaphore::Semaphore;
 pub use self::wait_condition::WaitCondition;
 pub use self::wait_queue::WaitQueue;
 pub use self::wait_map::WaitMap;
 
+pub mod semaphore;
 pub mod wait_condition;
 pub mod wait_queue;
 pub mod wait_map;
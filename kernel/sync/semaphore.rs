@@ -0,0 +1,60 @@
+use common::time::Duration;
+
+use super::Intex;
+use super::WaitCondition;
+
+/// A counting semaphore, which blocks waiting contexts while its count is zero
+pub struct Semaphore {
+    count: Intex<usize>,
+    condition: WaitCondition,
+}
+
+impl Semaphore {
+    pub fn new(count: usize) -> Semaphore {
+        Semaphore {
+            count: Intex::new(count),
+            condition: WaitCondition::new()
+        }
+    }
+
+    pub fn value(&self) -> usize {
+        *self.count.lock()
+    }
+
+    /// Increment the count, waking the waiting contexts to take it
+    pub fn post(&self) {
+        *self.count.lock() += 1;
+        unsafe { self.condition.notify(); }
+    }
+
+    /// Decrement the count if it is not zero, returning false if it is
+    pub fn try_wait(&self) -> bool {
+        let mut count = self.count.lock();
+        if *count > 0 {
+            *count -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Decrement the count, waiting until it is not zero
+    pub fn wait(&self) {
+        while ! self.try_wait() {
+            unsafe { self.condition.wait(); }
+        }
+    }
+
+    /// Decrement the count, waiting until it is not zero or until a monotonic time, returning false if the time
+    /// passed first
+    pub fn wait_until(&self, deadline: Duration) -> bool {
+        loop {
+            if self.try_wait() {
+                return true;
+            }
+            if Duration::monotonic() >= deadline || ! unsafe { self.condition.wait_until(deadline) } {
+                return self.try_wait();
+            }
+        }
+    }
+}
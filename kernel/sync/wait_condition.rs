@@ -2,6 +2,8 @@ use arch::context::{context_switch, Context};
 
 use collections::Vec;
 
+use common::time::Duration;
+
 use core::mem;
 use core::ops::DerefMut;
 
@@ -34,6 +36,30 @@ impl WaitCondition {
         }
         context_switch();
     }
+
+    /// Wait until notified or until a monotonic time, returning false if the time passed first
+    pub unsafe fn wait_until(&self, deadline: Duration) -> bool {
+        let mut waiting = 0 as *mut Context;
+        if let Ok(mut context) = ::env().contexts.lock().current_mut() {
+            let mut contexts = self.contexts.lock();
+            waiting = context.deref_mut() as *mut Context;
+            contexts.push(waiting);
+            (*context).blocked = true;
+            (*context).wake = Some(deadline);
+        }
+        context_switch();
+
+        if waiting.is_null() {
+            return true;
+        }
+        (*waiting).wake = None;
+
+        //A context that timed out is still in the list, and must be removed before it can be freed
+        let mut contexts = self.contexts.lock();
+        let len = contexts.len();
+        contexts.retain(|&context| context != waiting);
+        contexts.len() == len
+    }
 }
 
 impl Drop for WaitCondition {
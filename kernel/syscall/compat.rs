@@ -0,0 +1,88 @@
+//! System calls of 32-bit programs on a 64-bit kernel
+//!
+//! Programs in compatibility mode use the Redox system calls, but only the lower half of each register is defined,
+//! and pointers and sizes in their memory are 32-bit. Calls that read or write those are converted here, the rest
+//! run as usual.
+
+use arch::regs::Regs;
+
+use collections::Vec;
+use collections::string::ToString;
+
+use core::ptr;
+
+use system::c_string_to_str;
+use system::error::{Error, Result, EFAULT};
+use system::syscall::{SYS_EXECVE, SYS_LSEEK, SYS_PIPE2, SYS_WAITPID};
+
+use super::execute::execute;
+use super::{do_sys_lseek, do_sys_pipe2, do_sys_waitpid, syscall};
+
+pub fn syscall_handle(regs: &mut Regs) {
+    regs.ax &= 0xFFFFFFFF;
+    regs.bx &= 0xFFFFFFFF;
+    regs.cx &= 0xFFFFFFFF;
+    regs.dx &= 0xFFFFFFFF;
+    regs.si &= 0xFFFFFFFF;
+    regs.di &= 0xFFFFFFFF;
+    regs.bp &= 0xFFFFFFFF;
+
+    let result = match regs.ax {
+        SYS_EXECVE => compat_execve(regs.bx as *const u8, regs.cx as *const u32),
+        SYS_LSEEK => do_sys_lseek(regs.bx, regs.cx as u32 as i32 as isize, regs.dx),
+        SYS_PIPE2 => compat_pipe2(regs.bx as *mut u32, regs.cx),
+        SYS_WAITPID => compat_waitpid(regs.bx as u32 as i32 as isize, regs.cx as *mut u32, regs.dx),
+        _ => syscall(regs)
+    };
+    regs.ax = Error::mux(result) & 0xFFFFFFFF;
+}
+
+/// Read an array of 32-bit pointers to strings, ending with a null pointer
+unsafe fn compat_array(ptr: *const u32) -> Vec<*const u8> {
+    let mut array = Vec::new();
+    if ptr as usize > 0 {
+        let mut i = 0;
+        loop {
+            let item = ptr::read(ptr.offset(i));
+            if item == 0 {
+                break;
+            }
+            array.push(item as usize as *const u8);
+            i += 1;
+        }
+    }
+    array
+}
+
+fn compat_execve(path: *const u8, args: *const u32) -> Result<usize> {
+    let mut args_vec = Vec::new();
+    args_vec.push(c_string_to_str(path).to_string());
+    for arg in unsafe { compat_array(args) }.iter() {
+        args_vec.push(c_string_to_str(*arg).to_string());
+    }
+
+    execute(args_vec)
+}
+
+fn compat_pipe2(fds: *mut u32, flags: usize) -> Result<usize> {
+    if fds as usize == 0 {
+        return Err(Error::new(EFAULT));
+    }
+
+    let mut native_fds = [0; 2];
+    try!(do_sys_pipe2(native_fds.as_mut_ptr(), flags));
+    unsafe {
+        ptr::write(fds, native_fds[0] as u32);
+        ptr::write(fds.offset(1), native_fds[1] as u32);
+    }
+    Ok(0)
+}
+
+fn compat_waitpid(pid: isize, status_ptr: *mut u32, options: usize) -> Result<usize> {
+    let mut status = 0;
+    let pid = try!(do_sys_waitpid(pid, &mut status, options));
+    if status_ptr as usize > 0 {
+        unsafe { ptr::write(status_ptr, status as u32) };
+    }
+    Ok(pid)
+}
@@ -2,9 +2,9 @@ use alloc::arc::Arc;
 
 use arch::context::{CONTEXT_IMAGE_ADDR, CONTEXT_IMAGE_SIZE, CONTEXT_HEAP_ADDR, CONTEXT_HEAP_SIZE,
                     CONTEXT_MMAP_ADDR, CONTEXT_MMAP_SIZE, CONTEXT_STACK_SIZE, CONTEXT_STACK_ADDR,
-                    context_switch, context_userspace, Context, ContextMemory, ContextZone};
+                    context_switch, context_userspace, Context, ContextMemory, ContextZone, Personality};
 use arch::elf::Elf;
-use arch::memory;
+use arch::swap;
 use arch::regs::Regs;
 
 use collections::string::{String, ToString};
@@ -20,11 +20,18 @@ use fs::Url;
 
 use system::error::{Error, Result, ENOEXEC, ENOMEM};
 
+/// The code segment of 32-bit programs on a 64-bit kernel, which runs them in compatibility mode
+const COMPAT_CODE_SEGMENT: usize = 0x38;
+
 pub fn execute_thread(context_ptr: *mut Context, entry: usize, mut args: Vec<String>) -> ! {
     Context::spawn("kexec".to_string(), box move || {
         let context = unsafe { &mut *context_ptr };
 
         let mut context_args: Vec<usize> = Vec::new();
+        if context.personality == Personality::Linux {
+            context_args.push(0); // AUXV AT_NULL
+            context_args.push(0);
+        }
         context_args.push(0); // ENVP
         context_args.push(0); // ARGV NULL
         let mut argc = 0;
@@ -50,6 +57,8 @@ pub fn execute_thread(context_ptr: *mut Context, entry: usize, mut args: Vec<Str
                     virtual_size: virtual_size,
                     writeable: false,
                     allocated: true,
+                    swap: None,
+                    locked: false,
                 });
             }
 
@@ -64,18 +73,26 @@ pub fn execute_thread(context_ptr: *mut Context, entry: usize, mut args: Vec<Str
         context.regs.sp = context.kernel_stack + CONTEXT_STACK_SIZE - 128;
 
         context.stack = Some(ContextMemory {
-            physical_address: unsafe { memory::alloc_aligned(CONTEXT_STACK_SIZE, 4096) },
+            physical_address: unsafe { swap::alloc_user(CONTEXT_STACK_SIZE) },
             virtual_address: CONTEXT_STACK_ADDR,
             virtual_size: CONTEXT_STACK_SIZE,
             writeable: true,
             allocated: true,
+            swap: None,
+            locked: false,
         });
 
         let user_sp = if let Some(ref stack) = context.stack {
             let mut sp = stack.physical_address + stack.virtual_size - 128;
             for arg in context_args.iter() {
-                sp -= mem::size_of::<usize>();
-                unsafe { ptr::write(sp as *mut usize, *arg) };
+                // Pointers are 32-bit in compatibility mode
+                if context.compat {
+                    sp -= mem::size_of::<u32>();
+                    unsafe { ptr::write(sp as *mut u32, *arg as u32) };
+                } else {
+                    sp -= mem::size_of::<usize>();
+                    unsafe { ptr::write(sp as *mut usize, *arg) };
+                }
             }
             sp - stack.physical_address + stack.virtual_address
         } else {
@@ -86,7 +103,7 @@ pub fn execute_thread(context_ptr: *mut Context, entry: usize, mut args: Vec<Str
             context.push(0x20 | 3);
             context.push(user_sp);
             context.push(1 << 9);
-            context.push(0x18 | 3);
+            context.push(if context.compat { COMPAT_CODE_SEGMENT } else { 0x18 } | 3);
             context.push(entry);
             context.push(context_userspace as usize);
         }
@@ -108,7 +125,7 @@ pub fn execute(mut args: Vec<String>) -> Result<usize> {
 
     let mut vec: Vec<u8> = Vec::new();
 
-    let path = current.canonicalize(args.get(0).map_or("", |p| &p));
+    let path = try!(current.canonicalize(args.get(0).map_or("", |p| &p)));
     let mut url = try!(Url::from_str(&path)).to_cow();
     {
         let mut resource = if let Ok(resource) = url.as_url().open() {
@@ -126,7 +143,7 @@ pub fn execute(mut args: Vec<String>) -> Result<usize> {
             let virtual_size = 65536;
             let virtual_address = heap.next_mem();
 
-            let physical_address = memory::alloc_aligned(virtual_size, 4096);
+            let physical_address = swap::alloc_user(virtual_size);
             if physical_address == 0 {
                 return Err(Error::new(ENOMEM));
             }
@@ -137,6 +154,8 @@ pub fn execute(mut args: Vec<String>) -> Result<usize> {
                 virtual_size: virtual_size,
                 writeable: true,
                 allocated: true,
+                swap: None,
+                locked: false,
             };
 
             memory.map();
@@ -178,6 +197,20 @@ pub fn execute(mut args: Vec<String>) -> Result<usize> {
     } else {
         match Elf::from(&vec) {
             Ok(executable) => {
+                // Linux system calls are only translated from the 32-bit interrupt interface
+                let personality = if unsafe { executable.linux() } {
+                    if cfg!(target_arch = "x86") {
+                        Personality::Linux
+                    } else {
+                        debugln!("execute: failed to exec '{:?}': Linux executables are only supported on x86", url);
+                        return Err(Error::new(ENOEXEC));
+                    }
+                } else {
+                    Personality::Redox
+                };
+
+                let compat = executable.compat();
+
                 let entry = unsafe { executable.entry() };
                 let mut memory = Vec::new();
                 unsafe {
@@ -187,7 +220,7 @@ pub fn execute(mut args: Vec<String>) -> Result<usize> {
 
                         let offset = virtual_address % 4096;
 
-                        let physical_address = memory::alloc_aligned(virtual_size + offset, 4096);
+                        let physical_address = swap::alloc_user(virtual_size + offset);
 
                         if physical_address > 0 {
                             //TODO: Use paging to fix collisions
@@ -202,6 +235,8 @@ pub fn execute(mut args: Vec<String>) -> Result<usize> {
                                 virtual_size: virtual_size + offset,
                                 writeable: segment.flags & 2 == 2,
                                 allocated: true,
+                                swap: None,
+                                locked: false,
                             });
                         }
                     }
@@ -214,7 +249,10 @@ pub fn execute(mut args: Vec<String>) -> Result<usize> {
                     //debugln!("{}: {}: execute {}", context.pid, context.name, url.string);
 
                     context.name = url.as_url().to_string();
+                    context.personality = personality;
+                    context.compat = compat;
                     context.cwd = Arc::new(UnsafeCell::new(unsafe { (*context.cwd.get()).clone() }));
+                    context.umask = Arc::new(UnsafeCell::new(unsafe { *context.umask.get() }));
 
                     unsafe { context.unmap() };
 
@@ -1,16 +1,19 @@
-use arch::context::ContextFile;
+use arch::context::{ContextFile, ContextMemory};
 
-use core::slice;
+use collections::Vec;
 
-use fs::{ResourceSeek, Url};
+use core::{slice, str};
+
+use fs::{Resource, ResourceSeek, Url};
 
 use schemes::pipe::{PipeRead, PipeWrite};
+use schemes::watch::{watch_notify, WatchKind};
 
 use system::c_string_to_str;
 
-use syscall::{Stat, SEEK_CUR, SEEK_END, SEEK_SET};
+use syscall::{Stat, O_CREAT, SEEK_CUR, SEEK_END, SEEK_SET};
 
-use system::error::{Error, Result, EBADF, EFAULT, EINVAL};
+use system::error::{Error, Result, EBADF, EFAULT, EINVAL, ENOMEM, EXDEV};
 
 /** <!-- @MANSTART{sys_chdir} -->
 NAME
@@ -53,11 +56,23 @@ pub fn do_sys_chdir(path: *const u8) -> Result<usize> {
     let contexts = ::env().contexts.lock();
     let current = try!(contexts.current());
     unsafe {
-        *current.cwd.get() = current.canonicalize(c_string_to_str(path));
+        *current.cwd.get() = try!(current.canonicalize(c_string_to_str(path)));
     }
     Ok(0)
 }
 
+/// Report a modification of a resource to any watches on its path
+fn notify_modify(resource: &Resource) {
+    if ! ::env().watches.lock().is_empty() {
+        let mut buf = [0; 4096];
+        if let Ok(count) = resource.kernel_path(&mut buf) {
+            if let Ok(path) = str::from_utf8(&buf[.. count]) {
+                watch_notify(WatchKind::Modify, path);
+            }
+        }
+    }
+}
+
 /** <!-- @MANSTART{sys_close} -->
 NAME
     sys_close - close a file descriptor
@@ -98,7 +113,10 @@ pub fn do_sys_close(fd: usize) -> Result<usize> {
 
         if remove {
             if i < unsafe { (*current.files.get()).len() } {
-                drop(unsafe { (*current.files.get()).remove(i) });
+                let mut file = unsafe { (*current.files.get()).remove(i) };
+                // Unmap the file before the scheme is told it is closed and frees the memory
+                unsafe { file.unmap(Some(&**current)) };
+                drop(file);
 
                 return Ok(0);
             }
@@ -142,11 +160,143 @@ pub fn do_sys_dup(fd: usize) -> Result<usize> {
         (*current.files.get()).push(ContextFile {
             fd: new_fd,
             resource: new_resource,
+            maps: Vec::new(),
         });
     }
     Ok(new_fd)
 }
 
+/** <!-- @MANSTART{sys_flink} -->
+NAME
+    sys_flink - give a file a new name
+
+SYNOPSIS
+    sys_flink(fd: usize, path: *const u8) -> Result<usize>;
+
+DESCRIPTION
+    sys_flink links the file referenced by fd to path. Combined with O_TMPFILE, this allows a file
+    to be written and synchronized before it becomes visible
+
+RETURN VALUE
+    On success, Ok(0) is returned. On error, Err(err) is returned where err is one of the following
+    errors
+
+ERRORS
+    EBADF
+        fd is not a valid open file decriptor
+
+    EEXIST
+        path already exists
+
+    EPERM
+        The resource does not support linking
+
+    EXDEV
+        path is not on the same scheme as the resource
+
+    ESRCH
+        Currently not running in a process context (rare, would only happen during kernel init)
+<!-- @MANEND --> */
+pub fn do_sys_flink(fd: usize, path: *const u8) -> Result<usize> {
+    let mut contexts = ::env().contexts.lock();
+    let mut current = try!(contexts.current_mut());
+    let path_string = try!(current.canonicalize(c_string_to_str(path)));
+    let url = try!(Url::from_str(&path_string));
+    let mut resource = try!(current.get_file_mut(fd));
+
+    //A resource without a path is left to report whether it supports linking
+    let mut buf = [0; 4096];
+    if let Ok(count) = resource.kernel_path(&mut buf) {
+        if let Ok(resource_path) = str::from_utf8(&buf[.. count]) {
+            if let Ok(resource_url) = Url::from_str(resource_path) {
+                if resource_url.scheme() != url.scheme() {
+                    return Err(Error::new(EXDEV));
+                }
+            }
+        }
+    }
+
+    let result = resource.link(url);
+    if result.is_ok() {
+        watch_notify(WatchKind::Create, &path_string);
+    }
+    result.and(Ok(0))
+}
+
+/** <!-- @MANSTART{sys_fmap} -->
+NAME
+    sys_fmap - map a file into memory
+
+SYNOPSIS
+    sys_fmap(fd: usize, offset: usize, size: usize) -> Result<usize>;
+
+DESCRIPTION
+    sys_fmap maps size bytes starting at offset in the file referenced by fd into the memory of the
+    calling process. The memory is shared with the scheme providing the file, so writes to it are
+    seen without copying. It can only be written if fd was opened for writing. It stays mapped
+    until sys_funmap is called with the returned address, or until fd is closed
+
+RETURN VALUE
+    On success, Ok(address) is returned. On error, Err(err) is returned where err is one of the
+    following errors
+
+ERRORS
+    EBADF
+        fd is not a valid open file decriptor
+
+    EFAULT
+        The scheme did not provide pinned memory for the range
+
+    EINVAL
+        size is zero, or the range is outside of the file
+
+    ENOMEM
+        There is no room left to map memory
+
+    EPERM
+        fd does not support mapping
+
+    ESRCH
+        Currently not running in a process context (rare, would only happen during kernel init)
+<!-- @MANEND --> */
+pub fn do_sys_fmap(fd: usize, offset: usize, size: usize) -> Result<usize> {
+    if size == 0 {
+        return Err(Error::new(EINVAL));
+    }
+
+    let mut contexts = ::env().contexts.lock();
+    let mut current = try!(contexts.current_mut());
+    let (physical_address, writeable) = try!(try!(current.get_file_mut(fd)).map(offset, size));
+    let page_offset = physical_address % 4096;
+
+    let mmap = unsafe { &mut *current.mmap.get() };
+    let virtual_address = mmap.next_mem();
+    match virtual_address.checked_add(page_offset).and_then(|address| address.checked_add(size)) {
+        Some(end) if end <= mmap.address + mmap.size => (),
+        _ => return Err(Error::new(ENOMEM))
+    }
+
+    let mut mem = ContextMemory {
+        physical_address: physical_address - page_offset,
+        virtual_address: virtual_address,
+        virtual_size: page_offset + size,
+        writeable: writeable,
+        allocated: false,
+        swap: None,
+        locked: false,
+    };
+    unsafe { mem.map() };
+    mmap.memory.push(mem);
+
+    for file in unsafe { (*current.files.get()).iter_mut() } {
+        if file.fd == fd {
+            file.maps.push((current.mmap.clone(), virtual_address));
+        }
+    }
+
+    Ok(virtual_address + page_offset)
+}
+
 pub fn do_sys_fpath(fd: usize, buf: *mut u8, count: usize) -> Result<usize> {
     let contexts = ::env().contexts.lock();
     let current = try!(contexts.current());
@@ -154,6 +304,40 @@ pub fn do_sys_fpath(fd: usize, buf: *mut u8, count: usize) -> Result<usize> {
     resource.path(unsafe { slice::from_raw_parts_mut(buf, count) })
 }
 
+/** <!-- @MANSTART{sys_fpunch} -->
+NAME
+    sys_fpunch - deallocate a range of a file
+
+SYNOPSIS
+    sys_fpunch(fd: usize, offset: usize, len: usize) -> Result<usize>;
+
+DESCRIPTION
+    sys_fpunch deallocates len bytes starting at offset in the file referenced by fd. The range
+    reads as zeros afterwards, and the size of the file is unchanged
+
+RETURN VALUE
+    On success, Ok(0) is returned. On error, Err(err) is returned where err is one of the following
+    errors
+
+ERRORS
+    EBADF
+        fd is not a valid open file decriptor
+
+    EPERM
+        fd does not support deallocation
+
+    ESRCH
+        Currently not running in a process context (rare, would only happen during kernel init)
+<!-- @MANEND --> */
+pub fn do_sys_fpunch(fd: usize, offset: usize, len: usize) -> Result<usize> {
+    let mut contexts = ::env().contexts.lock();
+    let mut current = try!(contexts.current_mut());
+    let mut resource = try!(current.get_file_mut(fd));
+    try!(resource.punch(offset, len));
+    notify_modify(&**resource);
+    Ok(0)
+}
+
 pub fn do_sys_fstat(fd: usize, stat: *mut Stat) -> Result<usize> {
     let contexts = ::env().contexts.lock();
     let current = try!(contexts.current());
@@ -232,7 +416,9 @@ pub fn do_sys_ftruncate(fd: usize, length: usize) -> Result<usize> {
     let mut contexts = ::env().contexts.lock();
     let mut current = try!(contexts.current_mut());
     let mut resource = try!(current.get_file_mut(fd));
-    resource.truncate(length).and(Ok(0))
+    try!(resource.truncate(length));
+    notify_modify(&**resource);
+    Ok(0)
 }
 
 //TODO: Link
@@ -290,10 +476,11 @@ NAME
     sys_mkdir - create a directory
 
 SYNOPSIS
-    sys_mkdir(path: *const u8, flags: usize) -> Result<usize>;
+    sys_mkdir(path: *const u8, mode: usize) -> Result<usize>;
 
 DESCRIPTION
-    sys_mkdir attempts to create a directory named path
+    sys_mkdir attempts to create a directory named path, with the permissions in mode that are not in
+    the umask of the process
 
 RETURN VALUE
     On success, Ok(0) is returned. On error, Err(err) is returned where err is one of the following
@@ -322,11 +509,11 @@ ERRORS
     ESRCH
         Currently not running in a process context (rare, would only happen during kernel init)
 <!-- @MANEND --> */
-pub fn do_sys_mkdir(path: *const u8, flags: usize) -> Result<usize> {
+pub fn do_sys_mkdir(path: *const u8, mode: usize) -> Result<usize> {
     let contexts = ::env().contexts.lock();
     let current = try!(contexts.current());
-    let path_string = current.canonicalize(c_string_to_str(path));
-    ::env().mkdir(try!(Url::from_str(&path_string)), flags).and(Ok(0))
+    let path_string = try!(current.canonicalize(c_string_to_str(path)));
+    ::env().mkdir(try!(Url::from_str(&path_string)), current.create_mode(mode)).and(Ok(0))
 }
 
 /** <!-- @MANSTART{sys_open} -->
@@ -334,10 +521,11 @@ NAME
     sys_open - open and possibly create a file
 
 SYNOPSIS
-    sys_open(path: *const u8, flags: usize) -> Result<usize>;
+    sys_open(path: *const u8, flags: usize, mode: usize) -> Result<usize>;
 
 DESCRIPTION
-    sys_open returns a file descriptor referencing path, creating path if O_CREAT is provided
+    sys_open returns a file descriptor referencing path, creating path if O_CREAT is provided, with
+    the permissions in mode that are not in the umask of the process
 
     TODO: Open is very complicated, and has a lot of flags
 
@@ -381,18 +569,20 @@ ERRORS
     ESRCH
         Currently not running in a process context (rare, would only happen during kernel init)
 <!-- @MANEND --> */
-pub fn do_sys_open(path_c: *const u8, flags: usize) -> Result<usize> {
+pub fn do_sys_open(path_c: *const u8, flags: usize, mode: usize) -> Result<usize> {
     let contexts = ::env().contexts.lock();
     let current = try!(contexts.current());
-    let path = current.canonicalize(c_string_to_str(path_c));
+    let path = try!(current.canonicalize(c_string_to_str(path_c)));
     //debugln!("{}: {}: open {}", current.pid, current.name, path);
     let url = try!(Url::from_str(&path));
-    let resource = try!(::env().open(url, flags));
+    let mode = if flags & O_CREAT == O_CREAT { current.create_mode(mode) } else { 0 };
+    let resource = try!(::env().open_mode(url, flags, mode));
     let fd = current.next_fd();
     unsafe {
         (*current.files.get()).push(ContextFile {
             fd: fd,
             resource: resource,
+            maps: Vec::new(),
         });
     }
     Ok(fd)
@@ -410,12 +600,14 @@ pub fn do_sys_pipe2(fds: *mut usize, _flags: usize) -> Result<usize> {
             (*current.files.get()).push(ContextFile {
                 fd: *fds.offset(0),
                 resource: read,
+                maps: Vec::new(),
             });
 
             *fds.offset(1) = current.next_fd();
             (*current.files.get()).push(ContextFile {
                 fd: *fds.offset(1),
                 resource: write,
+                maps: Vec::new(),
             });
         }
 
@@ -463,17 +655,52 @@ pub fn do_sys_read(fd: usize, buf: *mut u8, count: usize) -> Result<usize> {
     resource.read(unsafe { slice::from_raw_parts_mut(buf, count) })
 }
 
+/** <!-- @MANSTART{sys_rename} -->
+NAME
+    sys_rename - change the name of a file
+
+SYNOPSIS
+    sys_rename(old: *const u8, new: *const u8) -> Result<usize>;
+
+DESCRIPTION
+    sys_rename renames old to new, atomically replacing new if it already exists
+
+RETURN VALUE
+    On success, Ok(0) is returned. On error, Err(err) is returned where err is one of the following
+    errors
+
+ERRORS
+    ENOENT
+        old does not exist
+
+    EPERM
+        The scheme does not support renaming
+
+    EXDEV
+        old and new are not on the same scheme
+
+    ESRCH
+        Currently not running in a process context (rare, would only happen during kernel init)
+<!-- @MANEND --> */
+pub fn do_sys_rename(old: *const u8, new: *const u8) -> Result<usize> {
+    let contexts = ::env().contexts.lock();
+    let current = try!(contexts.current());
+    let old_string = try!(current.canonicalize(c_string_to_str(old)));
+    let new_string = try!(current.canonicalize(c_string_to_str(new)));
+    ::env().rename(try!(Url::from_str(&old_string)), try!(Url::from_str(&new_string))).and(Ok(0))
+}
+
 pub fn do_sys_rmdir(path: *const u8) -> Result<usize> {
     let contexts = ::env().contexts.lock();
     let current = try!(contexts.current());
-    let path_string = current.canonicalize(c_string_to_str(path));
+    let path_string = try!(current.canonicalize(c_string_to_str(path)));
     ::env().rmdir(try!(Url::from_str(&path_string))).and(Ok(0))
 }
 
 pub fn do_sys_stat(path: *const u8, stat: *mut Stat) -> Result<usize> {
     let contexts = ::env().contexts.lock();
     let current = try!(contexts.current());
-    let path = current.canonicalize(c_string_to_str(path));
+    let path = try!(current.canonicalize(c_string_to_str(path)));
     let url = try!(Url::from_str(&path));
     if stat as usize > 0 {
         ::env().stat(url, unsafe { &mut *stat }).and(Ok(0))
@@ -482,10 +709,40 @@ pub fn do_sys_stat(path: *const u8, stat: *mut Stat) -> Result<usize> {
     }
 }
 
+/** <!-- @MANSTART{sys_umask} -->
+NAME
+    sys_umask - set the permissions removed from created files
+
+SYNOPSIS
+    sys_umask(mask: usize) -> Result<usize>;
+
+DESCRIPTION
+    sys_umask sets the umask of the process to mask, the permissions that sys_open and sys_mkdir
+    remove from the mode of files and directories they create. It starts as 0o022. Threads share
+    the umask, and child processes get a copy of it
+
+RETURN VALUE
+    On success, Ok(old) is returned, where old is the previous umask. On error, Err(err) is returned
+    where err is one of the following errors
+
+ERRORS
+    ESRCH
+        Currently not running in a process context (rare, would only happen during kernel init)
+<!-- @MANEND --> */
+pub fn do_sys_umask(mask: usize) -> Result<usize> {
+    let contexts = ::env().contexts.lock();
+    let current = try!(contexts.current());
+    unsafe {
+        let old = *current.umask.get();
+        *current.umask.get() = mask & 0o777;
+        Ok(old)
+    }
+}
+
 pub fn do_sys_unlink(path: *const u8) -> Result<usize> {
     let contexts = ::env().contexts.lock();
     let current = try!(contexts.current());
-    let path_string = current.canonicalize(c_string_to_str(path));
+    let path_string = try!(current.canonicalize(c_string_to_str(path)));
     ::env().unlink(try!(Url::from_str(&path_string))).and(Ok(0))
 }
 
@@ -530,5 +787,9 @@ pub fn do_sys_write(fd: usize, buf: *const u8, count: usize) -> Result<usize> {
     let mut contexts = ::env().contexts.lock();
     let mut current = try!(contexts.current_mut());
     let mut resource = try!(current.get_file_mut(fd));
-    resource.write(unsafe { slice::from_raw_parts(buf, count) })
+    let count = try!(resource.write(unsafe { slice::from_raw_parts(buf, count) }));
+    if count > 0 {
+        notify_modify(&**resource);
+    }
+    Ok(count)
 }
@@ -0,0 +1,384 @@
+//! Linux system calls
+//!
+//! Contexts running a Linux executable use the 32-bit Linux interrupt interface: its call numbers, flags, and
+//! structures, with arguments in bx, cx, dx, si, di, and bp. The calls of simple statically linked programs are
+//! translated to the Redox ones, anything else fails with ENOSYS.
+
+use arch::context::{context_clone, ContextMemory};
+use arch::regs::Regs;
+use arch::swap;
+
+use collections::Vec;
+use collections::string::ToString;
+
+use core::{ptr, slice};
+
+use system::{c_array_to_slice, c_string_to_str};
+use system::error::{Error, Result, EFAULT, EINVAL, ENODEV, ENOMEM, ENOSYS, ENOTTY};
+use system::syscall::{Stat, TimeSpec, CLOCK_MONOTONIC, CLOCK_REALTIME, CLONE_FILES, CLONE_FS, CLONE_VFORK, CLONE_VM,
+                      O_APPEND, O_CREAT, O_EXCL, O_NONBLOCK, O_TRUNC};
+
+use super::execute::execute;
+use super::{do_sys_brk, do_sys_chdir, do_sys_clock_gettime, do_sys_close, do_sys_dup, do_sys_exit, do_sys_fsync,
+            do_sys_getpid, do_sys_lseek, do_sys_mkdir, do_sys_nanosleep, do_sys_open, do_sys_pipe2, do_sys_read,
+            do_sys_rename, do_sys_rmdir, do_sys_stat, do_sys_umask, do_sys_unlink, do_sys_waitpid, do_sys_write,
+            do_sys_yield};
+
+const SYS_EXIT: usize = 1;
+const SYS_FORK: usize = 2;
+const SYS_READ: usize = 3;
+const SYS_WRITE: usize = 4;
+const SYS_OPEN: usize = 5;
+    const O_WRONLY: usize = 0o1;
+    const O_RDWR: usize = 0o2;
+    const LINUX_O_CREAT: usize = 0o100;
+    const LINUX_O_EXCL: usize = 0o200;
+    const LINUX_O_TRUNC: usize = 0o1000;
+    const LINUX_O_APPEND: usize = 0o2000;
+    const LINUX_O_NONBLOCK: usize = 0o4000;
+const SYS_CLOSE: usize = 6;
+const SYS_WAITPID: usize = 7;
+const SYS_CREAT: usize = 8;
+const SYS_UNLINK: usize = 10;
+const SYS_EXECVE: usize = 11;
+const SYS_CHDIR: usize = 12;
+const SYS_TIME: usize = 13;
+const SYS_LSEEK: usize = 19;
+const SYS_GETPID: usize = 20;
+const SYS_GETUID: usize = 24;
+const SYS_ACCESS: usize = 33;
+const SYS_RENAME: usize = 38;
+const SYS_MKDIR: usize = 39;
+const SYS_RMDIR: usize = 40;
+const SYS_DUP: usize = 41;
+const SYS_PIPE: usize = 42;
+const SYS_BRK: usize = 45;
+const SYS_GETGID: usize = 47;
+const SYS_GETEUID: usize = 49;
+const SYS_GETEGID: usize = 50;
+const SYS_IOCTL: usize = 54;
+const SYS_UMASK: usize = 60;
+const SYS_GETPPID: usize = 64;
+const SYS_MMAP: usize = 90;
+const SYS_MUNMAP: usize = 91;
+const SYS_WAIT4: usize = 114;
+const SYS_FSYNC: usize = 118;
+const SYS_CLONE: usize = 120;
+const SYS_READV: usize = 145;
+const SYS_WRITEV: usize = 146;
+const SYS_SCHED_YIELD: usize = 158;
+const SYS_NANOSLEEP: usize = 162;
+const SYS_RT_SIGACTION: usize = 174;
+const SYS_RT_SIGPROCMASK: usize = 175;
+const SYS_VFORK: usize = 190;
+const SYS_MMAP2: usize = 192;
+    const PROT_WRITE: usize = 0x2;
+    const MAP_FIXED: usize = 0x10;
+    const MAP_ANONYMOUS: usize = 0x20;
+const SYS_GETUID32: usize = 199;
+const SYS_GETGID32: usize = 200;
+const SYS_GETEUID32: usize = 201;
+const SYS_GETEGID32: usize = 202;
+const SYS_EXIT_GROUP: usize = 252;
+const SYS_SET_TID_ADDRESS: usize = 258;
+const SYS_CLOCK_GETTIME: usize = 265;
+    const LINUX_CLOCK_REALTIME: usize = 0;
+    const LINUX_CLOCK_MONOTONIC: usize = 1;
+
+/// A time, with a `long` for each field
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(packed)]
+pub struct LinuxTimeSpec {
+    pub tv_sec: isize,
+    pub tv_nsec: isize,
+}
+
+/// A buffer of `readv` or `writev`
+#[repr(packed)]
+pub struct IoVec {
+    pub base: *mut u8,
+    pub len: usize,
+}
+
+/// The arguments of the old `mmap`, which are passed in memory
+#[repr(packed)]
+pub struct MmapArgs {
+    pub addr: usize,
+    pub len: usize,
+    pub prot: usize,
+    pub flags: usize,
+    pub fd: usize,
+    pub offset: usize,
+}
+
+pub fn syscall_handle(regs: &mut Regs) {
+    //debugln!("linux {:X}: {} {:X} {:X} {:X}", regs.ip, regs.ax, regs.bx, regs.cx, regs.dx);
+    regs.ax = Error::mux(match regs.ax {
+        SYS_ACCESS => linux_access(regs.bx as *const u8),
+        SYS_BRK => do_sys_brk(regs.bx),
+        SYS_CHDIR => do_sys_chdir(regs.bx as *const u8),
+        SYS_CLOCK_GETTIME => linux_clock_gettime(regs.bx, regs.cx as *mut LinuxTimeSpec),
+        SYS_CLONE => {
+            let (flags, stack) = (regs.bx, regs.cx);
+            linux_clone(regs, flags, stack)
+        },
+        SYS_CLOSE => do_sys_close(regs.bx),
+        SYS_CREAT => linux_open(regs.bx as *const u8, O_WRONLY | LINUX_O_CREAT | LINUX_O_TRUNC, regs.cx),
+        SYS_DUP => do_sys_dup(regs.bx),
+        SYS_EXECVE => linux_execve(regs.bx as *const u8, regs.cx as *const *const u8),
+        SYS_EXIT | SYS_EXIT_GROUP => do_sys_exit(regs.bx),
+        SYS_FORK => linux_clone(regs, 0, 0),
+        SYS_FSYNC => do_sys_fsync(regs.bx),
+        SYS_GETPID | SYS_SET_TID_ADDRESS => do_sys_getpid(),
+        SYS_GETPPID => linux_getppid(),
+        // There is only one user
+        SYS_GETUID | SYS_GETGID | SYS_GETEUID | SYS_GETEGID |
+        SYS_GETUID32 | SYS_GETGID32 | SYS_GETEUID32 | SYS_GETEGID32 => Ok(0),
+        // There are no terminal controls
+        SYS_IOCTL => Err(Error::new(ENOTTY)),
+        SYS_LSEEK => do_sys_lseek(regs.bx, regs.cx as isize, regs.dx),
+        SYS_MKDIR => do_sys_mkdir(regs.bx as *const u8, regs.cx),
+        SYS_MMAP => linux_mmap_args(regs.bx as *const MmapArgs),
+        SYS_MMAP2 => linux_mmap(regs.bx, regs.cx, regs.dx, regs.si, regs.di, regs.bp * 4096),
+        SYS_MUNMAP => linux_munmap(regs.bx),
+        SYS_NANOSLEEP => linux_nanosleep(regs.bx as *const LinuxTimeSpec, regs.cx as *mut LinuxTimeSpec),
+        SYS_OPEN => linux_open(regs.bx as *const u8, regs.cx, regs.dx),
+        SYS_PIPE => do_sys_pipe2(regs.bx as *mut usize, 0),
+        SYS_READ => do_sys_read(regs.bx, regs.cx as *mut u8, regs.dx),
+        SYS_READV => linux_readv(regs.bx, regs.cx as *const IoVec, regs.dx),
+        SYS_RENAME => do_sys_rename(regs.bx as *const u8, regs.cx as *const u8),
+        SYS_RMDIR => do_sys_rmdir(regs.bx as *const u8),
+        // There are no signals, so handlers and masks are never used
+        SYS_RT_SIGACTION | SYS_RT_SIGPROCMASK => Ok(0),
+        SYS_SCHED_YIELD => do_sys_yield(),
+        SYS_TIME => linux_time(regs.bx as *mut isize),
+        SYS_UMASK => do_sys_umask(regs.bx),
+        SYS_UNLINK => do_sys_unlink(regs.bx as *const u8),
+        SYS_VFORK => linux_clone(regs, CLONE_VM | CLONE_VFORK, 0),
+        SYS_WAITPID | SYS_WAIT4 => linux_waitpid(regs.bx as isize, regs.cx as *mut usize, regs.dx),
+        SYS_WRITE => do_sys_write(regs.bx, regs.cx as *mut u8, regs.dx),
+        SYS_WRITEV => linux_writev(regs.bx, regs.cx as *const IoVec, regs.dx),
+
+        _ => {
+            debugln!("linux: unsupported system call {}", regs.ax);
+            Err(Error::new(ENOSYS))
+        },
+    });
+    //debugln!("={:X}", regs.ax);
+}
+
+fn linux_access(path: *const u8) -> Result<usize> {
+    let mut stat = Stat::default();
+    do_sys_stat(path, &mut stat).and(Ok(0))
+}
+
+fn linux_clock_gettime(clock: usize, tp: *mut LinuxTimeSpec) -> Result<usize> {
+    if tp as usize == 0 {
+        return Err(Error::new(EFAULT));
+    }
+
+    let clock = match clock {
+        LINUX_CLOCK_REALTIME => CLOCK_REALTIME,
+        LINUX_CLOCK_MONOTONIC => CLOCK_MONOTONIC,
+        _ => return Err(Error::new(EINVAL))
+    };
+
+    let mut time = TimeSpec::default();
+    try!(do_sys_clock_gettime(clock, &mut time));
+    unsafe {
+        (*tp).tv_sec = time.tv_sec as isize;
+        (*tp).tv_nsec = time.tv_nsec as isize;
+    }
+    Ok(0)
+}
+
+/// Clone with the flags Redox shares, and start the child on its own stack if one is given
+fn linux_clone(regs: &mut Regs, flags: usize, stack: usize) -> Result<usize> {
+    // The low byte is the signal sent on exit, and the higher flags are for thread setup that is not supported
+    let (bx, sp) = (regs.bx, regs.sp);
+    regs.bx = flags & (CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_VFORK);
+    if stack > 0 {
+        regs.sp = stack;
+    }
+
+    let result = unsafe { context_clone(regs) };
+
+    regs.bx = bx;
+    regs.sp = sp;
+    result
+}
+
+/// Execute, with the program name as the first argument
+fn linux_execve(path: *const u8, args: *const *const u8) -> Result<usize> {
+    let mut args_vec = Vec::new();
+    args_vec.push(c_string_to_str(path).to_string());
+    for arg in c_array_to_slice(args).iter().skip(1) {
+        args_vec.push(c_string_to_str(*arg).to_string());
+    }
+
+    execute(args_vec)
+}
+
+fn linux_getppid() -> Result<usize> {
+    let contexts = ::env().contexts.lock();
+    let current = try!(contexts.current());
+    Ok(current.ppid)
+}
+
+/// Map zeroed memory, as file mappings are not supported
+fn linux_mmap(_addr: usize, len: usize, prot: usize, flags: usize, _fd: usize, _offset: usize) -> Result<usize> {
+    if flags & MAP_ANONYMOUS != MAP_ANONYMOUS {
+        return Err(Error::new(ENODEV));
+    }
+
+    // The address is only a hint, unless it is fixed
+    if flags & MAP_FIXED == MAP_FIXED || len == 0 {
+        return Err(Error::new(EINVAL));
+    }
+
+    let contexts = ::env().contexts.lock();
+    let current = try!(contexts.current());
+    let mmap = unsafe { &mut *current.mmap.get() };
+
+    let virtual_size = (len + 4095)/4096 * 4096;
+    let virtual_address = mmap.next_mem();
+    if virtual_address + virtual_size > mmap.address + mmap.size {
+        return Err(Error::new(ENOMEM));
+    }
+
+    let physical_address = unsafe { swap::alloc_user(virtual_size) };
+    if physical_address == 0 {
+        return Err(Error::new(ENOMEM));
+    }
+
+    let mut memory = ContextMemory {
+        physical_address: physical_address,
+        virtual_address: virtual_address,
+        virtual_size: virtual_size,
+        writeable: prot & PROT_WRITE == PROT_WRITE,
+        allocated: true,
+        swap: None,
+        locked: false,
+    };
+
+    unsafe {
+        ::memset(physical_address as *mut u8, 0, virtual_size);
+        memory.map();
+    }
+
+    mmap.memory.push(memory);
+
+    Ok(virtual_address)
+}
+
+fn linux_mmap_args(args: *const MmapArgs) -> Result<usize> {
+    if args as usize == 0 {
+        return Err(Error::new(EFAULT));
+    }
+
+    let args = unsafe { &*args };
+    linux_mmap(args.addr, args.len, args.prot, args.flags, args.fd, args.offset)
+}
+
+/// Unmap a whole mapping made by `mmap`
+fn linux_munmap(addr: usize) -> Result<usize> {
+    let contexts = ::env().contexts.lock();
+    let current = try!(contexts.current());
+    let mmap = unsafe { &mut *current.mmap.get() };
+
+    match mmap.memory.iter().position(|mem| mem.virtual_address == addr) {
+        Some(i) => {
+            let mut memory = mmap.memory.remove(i);
+            unsafe { memory.unmap() };
+            Ok(0)
+        },
+        None => Err(Error::new(EINVAL))
+    }
+}
+
+fn linux_nanosleep(req: *const LinuxTimeSpec, rem: *mut LinuxTimeSpec) -> Result<usize> {
+    if req as usize == 0 {
+        return Err(Error::new(EFAULT));
+    }
+
+    let redox_req = unsafe {
+        TimeSpec {
+            tv_sec: (*req).tv_sec as i64,
+            tv_nsec: (*req).tv_nsec as i32,
+        }
+    };
+    let mut redox_rem = TimeSpec::default();
+    try!(do_sys_nanosleep(&redox_req, &mut redox_rem));
+    if rem as usize > 0 {
+        unsafe {
+            (*rem).tv_sec = redox_rem.tv_sec as isize;
+            (*rem).tv_nsec = redox_rem.tv_nsec as isize;
+        }
+    }
+    Ok(0)
+}
+
+fn linux_open(path: *const u8, flags: usize, mode: usize) -> Result<usize> {
+    let mut redox_flags = flags & (O_WRONLY | O_RDWR);
+    for &(linux, redox) in [(LINUX_O_CREAT, O_CREAT),
+                            (LINUX_O_EXCL, O_EXCL),
+                            (LINUX_O_TRUNC, O_TRUNC),
+                            (LINUX_O_APPEND, O_APPEND),
+                            (LINUX_O_NONBLOCK, O_NONBLOCK)].iter() {
+        if flags & linux == linux {
+            redox_flags |= redox;
+        }
+    }
+
+    do_sys_open(path, redox_flags, mode)
+}
+
+fn linux_readv(fd: usize, iov: *const IoVec, count: usize) -> Result<usize> {
+    if iov as usize == 0 && count > 0 {
+        return Err(Error::new(EFAULT));
+    }
+
+    let mut total = 0;
+    for vec in unsafe { slice::from_raw_parts(iov, count) }.iter() {
+        let read = try!(do_sys_read(fd, vec.base, vec.len));
+        total += read;
+        if read < vec.len {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+fn linux_time(tloc: *mut isize) -> Result<usize> {
+    let secs = ::env().clock_realtime.lock().secs as isize;
+    if tloc as usize > 0 {
+        unsafe { ptr::write(tloc, secs) };
+    }
+    Ok(secs as usize)
+}
+
+/// Wait for a child, with the exit status in the second byte of the status
+fn linux_waitpid(pid: isize, status_ptr: *mut usize, options: usize) -> Result<usize> {
+    let mut status = 0;
+    let pid = try!(do_sys_waitpid(pid, &mut status, options));
+    if status_ptr as usize > 0 {
+        unsafe { ptr::write(status_ptr, (status & 0xFF) << 8) };
+    }
+    Ok(pid)
+}
+
+fn linux_writev(fd: usize, iov: *const IoVec, count: usize) -> Result<usize> {
+    if iov as usize == 0 && count > 0 {
+        return Err(Error::new(EFAULT));
+    }
+
+    let mut total = 0;
+    for vec in unsafe { slice::from_raw_parts(iov, count) }.iter() {
+        let written = try!(do_sys_write(fd, vec.base, vec.len));
+        total += written;
+        if written < vec.len {
+            break;
+        }
+    }
+    Ok(total)
+}
@@ -1,10 +1,18 @@
-use arch::context::ContextMemory;
-use arch::memory;
+use arch::context::{ContextManager, ContextMemory};
+use arch::swap;
 
-use system::error::Result;
+use system::error::{Error, Result, EINVAL};
 
 //TODO: Refactor file to propogate results
 
+/// Check if any context has mapped part of a segment with `sys_fmap`
+fn mapped(contexts: &ContextManager, mem: &ContextMemory) -> bool {
+    contexts.iter().any(|context| unsafe { (*context.mmap.get()).memory.iter() }.any(|other| {
+        ! other.allocated && other.physical_address < mem.physical_address + mem.virtual_size &&
+        mem.physical_address < other.physical_address + other.virtual_size
+    }))
+}
+
 pub fn do_sys_brk(addr: usize) -> Result<usize> {
     let mut ret = 0;
 
@@ -13,13 +21,15 @@ pub fn do_sys_brk(addr: usize) -> Result<usize> {
         ret = unsafe { (*current.heap.get()).next_mem() };
 
         // TODO: Make this smarter, currently it attempt to resize the entire data segment
-        if let Some(mut mem) = unsafe { (*current.heap.get()).memory.last_mut() } {
+        // Pinned or mapped memory cannot be moved, so the heap grows with a new segment after it instead
+        let locked = unsafe { (*current.heap.get()).memory.last().map_or(false, |mem| mem.locked || mapped(&contexts, mem)) };
+        if let Some(mut mem) = unsafe { (*current.heap.get()).memory.last_mut() }.and_then(|mem| if locked { None } else { Some(mem) }) {
             if mem.writeable && mem.allocated {
                 if addr >= mem.virtual_address {
                     unsafe { mem.unmap() };
 
                     let size = addr - mem.virtual_address;
-                    let physical_address = unsafe { memory::realloc_aligned(mem.physical_address, size, 4096) };
+                    let physical_address = unsafe { swap::realloc_user(mem.physical_address, size) };
                     if physical_address > 0 {
                         mem.physical_address = physical_address;
                         mem.virtual_size = size;
@@ -36,14 +46,16 @@ pub fn do_sys_brk(addr: usize) -> Result<usize> {
             }
         } else if addr >= ret {
             let size = addr - ret;
-            let physical_address = unsafe { memory::alloc_aligned(size, 4096) };
+            let physical_address = unsafe { swap::alloc_user(size) };
             if physical_address > 0 {
                 let mut mem = ContextMemory {
                     physical_address: physical_address,
                     virtual_address: ret,
                     virtual_size: size,
                     writeable: true,
-                    allocated: true
+                    allocated: true,
+                    swap: None,
+                    locked: false,
                 };
                 ret = mem.virtual_address + mem.virtual_size;
 
@@ -61,3 +73,47 @@ pub fn do_sys_brk(addr: usize) -> Result<usize> {
 
     Ok(ret)
 }
+
+/// Pin the memory containing a range, so it is not swapped out or moved, for audio and DMA buffers
+pub fn do_sys_mlock(addr: usize, len: usize) -> Result<usize> {
+    if len == 0 {
+        return Ok(0);
+    }
+
+    let mut contexts = ::env().contexts.lock();
+    let current = try!(contexts.current_mut());
+    unsafe { current.set_locked(addr, len, true) }.map(|_| 0)
+}
+
+/// Unpin the memory containing a range
+pub fn do_sys_munlock(addr: usize, len: usize) -> Result<usize> {
+    if len == 0 {
+        return Ok(0);
+    }
+
+    let mut contexts = ::env().contexts.lock();
+    let current = try!(contexts.current_mut());
+    unsafe { current.set_locked(addr, len, false) }.map(|_| 0)
+}
+
+/// Unmap memory mapped with `sys_fmap`, leaving the memory of the scheme that provided it
+pub fn do_sys_funmap(addr: usize) -> Result<usize> {
+    let contexts = ::env().contexts.lock();
+    let current = try!(contexts.current());
+    let mmap = unsafe { &mut *current.mmap.get() };
+
+    let virtual_address = addr - addr % 4096;
+    match mmap.memory.iter().position(|mem| mem.virtual_address == virtual_address && ! mem.allocated) {
+        Some(i) => {
+            let mut memory = mmap.memory.remove(i);
+            unsafe { memory.unmap() };
+            for file in unsafe { (*current.files.get()).iter_mut() } {
+                file.maps.retain(|&(ref zone, address)| {
+                    ! (zone.get() == current.mmap.get() && address == virtual_address)
+                });
+            }
+            Ok(0)
+        },
+        None => Err(Error::new(EINVAL))
+    }
+}
@@ -8,11 +8,13 @@ pub use self::process::*;
 pub use self::time::*;
 
 use arch::regs::Regs;
-use arch::context::context_switch;
+use arch::context::{context_switch, Personality};
 
+pub mod compat;
 pub mod debug;
 pub mod execute;
 pub mod file;
+pub mod linux;
 pub mod memory;
 pub mod process;
 pub mod time;
@@ -39,8 +41,22 @@ pub fn syscall_handle(regs: &mut Regs) {
         }
     }
 
+    let (personality, compat) = ::env().contexts.lock().current().map(|context| (context.personality, context.compat)).unwrap_or((Personality::Redox, false));
+    if personality == Personality::Linux {
+        return linux::syscall_handle(regs);
+    }
+    if compat {
+        return compat::syscall_handle(regs);
+    }
+
     //debugln!("{:X}: {} {:X} {:X} {:X}", regs.ip, regs.ax, regs.bx, regs.cx, regs.dx);
-    regs.ax = Error::mux(match regs.ax {
+    regs.ax = Error::mux(syscall(regs));
+    //debugln!("={:X}", regs.ax);
+}
+
+/// Run a Redox system call
+pub fn syscall(regs: &mut Regs) -> Result<usize> {
+    match regs.ax {
         // Redox
         SYS_DEBUG => do_sys_debug(regs.bx as *const u8, regs.cx),
         SYS_SUPERVISE => do_sys_supervise(regs.bx),
@@ -54,27 +70,34 @@ pub fn syscall_handle(regs: &mut Regs) {
         SYS_DUP => do_sys_dup(regs.bx),
         SYS_EXECVE => do_sys_execve(regs.bx as *const u8, regs.cx as *const *const u8),
         SYS_EXIT => do_sys_exit(regs.bx),
+        SYS_FLINK => do_sys_flink(regs.bx, regs.cx as *const u8),
+        SYS_FMAP => do_sys_fmap(regs.bx, regs.cx, regs.dx),
         SYS_FPATH => do_sys_fpath(regs.bx, regs.cx as *mut u8, regs.dx),
+        SYS_FPUNCH => do_sys_fpunch(regs.bx, regs.cx, regs.dx),
         SYS_FSTAT => do_sys_fstat(regs.bx, regs.cx as *mut Stat),
         SYS_FSYNC => do_sys_fsync(regs.bx),
         SYS_FTRUNCATE => do_sys_ftruncate(regs.bx, regs.cx),
+        SYS_FUNMAP => do_sys_funmap(regs.bx),
         SYS_GETPID => do_sys_getpid(),
         SYS_IOPL => do_sys_iopl(regs),
         // TODO: link
         SYS_LSEEK => do_sys_lseek(regs.bx, regs.cx as isize, regs.dx),
         SYS_MKDIR => do_sys_mkdir(regs.bx as *const u8, regs.cx),
+        SYS_MLOCK => do_sys_mlock(regs.bx, regs.cx),
+        SYS_MUNLOCK => do_sys_munlock(regs.bx, regs.cx),
         SYS_NANOSLEEP => do_sys_nanosleep(regs.bx as *const TimeSpec, regs.cx as *mut TimeSpec),
-        SYS_OPEN => do_sys_open(regs.bx as *const u8, regs.cx),
+        SYS_OPEN => do_sys_open(regs.bx as *const u8, regs.cx, regs.dx),
         SYS_PIPE2 => do_sys_pipe2(regs.bx as *mut usize, regs.cx),
         SYS_READ => do_sys_read(regs.bx, regs.cx as *mut u8, regs.dx),
+        SYS_RENAME => do_sys_rename(regs.bx as *const u8, regs.cx as *const u8),
         SYS_RMDIR => do_sys_rmdir(regs.bx as *const u8),
         SYS_STAT => do_sys_stat(regs.bx as *const u8, regs.cx as *mut Stat),
+        SYS_UMASK => do_sys_umask(regs.bx),
         SYS_UNLINK => do_sys_unlink(regs.bx as *const u8),
         SYS_WAITPID => do_sys_waitpid(regs.bx as isize, regs.cx as *mut usize, regs.dx),
         SYS_WRITE => do_sys_write(regs.bx, regs.cx as *mut u8, regs.dx),
         SYS_YIELD => do_sys_yield(),
 
         _ => Err(Error::new(ENOSYS)),
-    });
-    //debugln!("={:X}", regs.ax);
+    }
 }
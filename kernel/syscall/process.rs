@@ -172,6 +172,7 @@ pub fn do_sys_supervise(pid: usize) -> Result<usize> {
         (*current.files.get()).push(ContextFile {
             fd: fd,
             resource: box try!(SupervisorResource::new(procc)),
+            maps: Vec::new(),
         });
     }
 
@@ -162,7 +162,7 @@ pub trait Hci {
                                             if ::env().console.lock().draw {
                                                 //ignore mouse event
                                             } else {
-                                                ::env().events.send(mouse_event.to_event());
+                                                ::env().send_event(mouse_event.to_event());
                                             }
                                         }
 
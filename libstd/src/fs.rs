@@ -7,8 +7,9 @@ use string::String;
 use sys_common::AsInner;
 use vec::Vec;
 
-use system::syscall::{sys_open, sys_dup, sys_close, sys_fpath, sys_ftruncate, sys_read,
-              sys_write, sys_lseek, sys_fsync, sys_mkdir, sys_rmdir, sys_stat, sys_unlink};
+use system::error::{ENOSYS, EPERM, EXDEV};
+use system::syscall::{sys_open, sys_dup, sys_close, sys_flink, sys_fpath, sys_fpunch, sys_ftruncate, sys_read,
+              sys_write, sys_lseek, sys_fsync, sys_mkdir, sys_rename, sys_rmdir, sys_stat, sys_unlink};
 use system::syscall::{O_RDWR, O_RDONLY, O_WRONLY, O_APPEND, O_CREAT, O_TRUNC, MODE_DIR, MODE_FILE, SEEK_SET, SEEK_CUR, SEEK_END, Stat};
 
 /// A Unix-style file
@@ -35,7 +36,7 @@ impl File {
         let mut path_c = path_str.to_owned();
         path_c.push_str("\0");
         unsafe {
-            sys_open(path_c.as_ptr(), O_CREAT | O_RDWR | O_TRUNC, 0).map(|fd| File::from_raw_fd(fd) )
+            sys_open(path_c.as_ptr(), O_CREAT | O_RDWR | O_TRUNC, 0o666).map(|fd| File::from_raw_fd(fd) )
         }.map_err(|x| Error::from_sys(x))
     }
 
@@ -44,6 +45,16 @@ impl File {
         sys_dup(self.fd).map(|fd| unsafe { File::from_raw_fd(fd) }).map_err(|x| Error::from_sys(x))
     }
 
+    /// Give the file a new name, such as a file created with `O_TMPFILE`
+    pub fn link<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path_str = path.as_ref().as_os_str().as_inner();
+        let mut path_c = path_str.to_owned();
+        path_c.push_str("\0");
+        unsafe {
+            sys_flink(self.fd, path_c.as_ptr()).and(Ok(()))
+        }.map_err(|x| Error::from_sys(x))
+    }
+
     /// Get the canonical path of the file
     pub fn path(&self) -> Result<PathBuf> {
         let mut buf: [u8; 4096] = [0; 4096];
@@ -63,6 +74,11 @@ impl File {
         sys_fsync(self.fd).and(Ok(())).map_err(|x| Error::from_sys(x))
     }
 
+    /// Deallocate a range of the file, which reads as zeros afterwards
+    pub fn punch_hole(&mut self, offset: u64, len: u64) -> Result<()> {
+        sys_fpunch(self.fd, offset as usize, len as usize).and(Ok(())).map_err(|x| Error::from_sys(x))
+    }
+
     /// Truncates the file
     pub fn set_len(&mut self, size: u64) -> Result<()> {
         sys_ftruncate(self.fd, size as usize).and(Ok(())).map_err(|x| Error::from_sys(x))
@@ -217,7 +233,7 @@ impl OpenOptions {
         let mut path_c = path_str.to_owned();
         path_c.push_str("\0");
         unsafe {
-            sys_open(path_c.as_ptr(), flags, 0).map(|fd| File::from_raw_fd(fd))
+            sys_open(path_c.as_ptr(), flags, 0o666).map(|fd| File::from_raw_fd(fd))
         }.map_err(|x| Error::from_sys(x))
     }
 }
@@ -350,7 +366,7 @@ pub fn create_dir<P: AsRef<Path>>(path: P) -> Result<()> {
     let mut path_c = path_str.to_owned();
     path_c.push_str("\0");
     unsafe {
-        sys_mkdir(path_c.as_ptr(), 755).and(Ok(())).map_err(|x| Error::from_sys(x))
+        sys_mkdir(path_c.as_ptr(), 0o755).and(Ok(())).map_err(|x| Error::from_sys(x))
     }
 }
 
@@ -362,9 +378,22 @@ pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<u64> {
 }
 
 /// Rename a file or directory to a new name
+///
+/// This is atomic when the scheme supports renaming, otherwise the file is copied and removed
 pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<()> {
-    try!(copy(Path::new(from.as_ref()), to));
-    remove_file(from)
+    let mut from_c = from.as_ref().as_os_str().as_inner().to_owned();
+    from_c.push_str("\0");
+    let mut to_c = to.as_ref().as_os_str().as_inner().to_owned();
+    to_c.push_str("\0");
+    match unsafe { sys_rename(from_c.as_ptr(), to_c.as_ptr()) } {
+        Ok(_) => Ok(()),
+        Err(err) => if err.errno == ENOSYS || err.errno == EPERM || err.errno == EXDEV {
+            try!(copy(Path::new(from.as_ref()), to));
+            remove_file(from)
+        } else {
+            Err(Error::from_sys(err))
+        }
+    }
 }
 
 /// Return an iterator over the entries within a directory